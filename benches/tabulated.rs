@@ -0,0 +1,47 @@
+#![feature(test)]
+
+// compares searching a thousand point measured spectrum as `Tabulated` and as the `SplitTabulated` layout
+// built by `Curve::optimize`. run with `cargo bench --bench tabulated`.
+
+extern crate test;
+
+use math::prelude::*;
+use test::{black_box, Bencher};
+
+fn measured_spectrum() -> Curve {
+    // irregularly spaced samples, as from a spectrometer
+    let signal = (0..1000)
+        .map(|i| {
+            let x = 350.0 + i as f32 * 0.45 + (i as f32 * 0.7).sin() * 0.2;
+            (x, 0.5 + 0.4 * (x / 23.0).sin())
+        })
+        .collect();
+    Curve::Tabulated {
+        signal,
+        mode: InterpolationMode::Linear,
+    }
+}
+
+fn evaluate_sweep(b: &mut Bencher, curve: &Curve) {
+    // a stride coprime with the sample spacing, so that consecutive lookups don't share cache lines
+    let lambdas: Vec<f32> = (0..1024)
+        .map(|i| 350.0 + ((i * 389) % 1024) as f32 * 450.0 / 1024.0)
+        .collect();
+    b.iter(|| {
+        let mut sum = 0.0;
+        for &lambda in &lambdas {
+            sum += curve.evaluate(black_box(lambda));
+        }
+        sum
+    });
+}
+
+#[bench]
+fn tabulated(b: &mut Bencher) {
+    evaluate_sweep(b, &measured_spectrum());
+}
+
+#[bench]
+fn split_tabulated(b: &mut Bencher) {
+    evaluate_sweep(b, &measured_spectrum().optimize());
+}
@@ -0,0 +1,170 @@
+use crate::prelude::*;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// tables for energy compensated multiple scattering (Kulla and Conty 2017).
+// single scattering microfacet models lose energy at high roughness, as light that would scatter between microfacets
+// is discarded. the missing energy of a lobe is 1 - E(cos_theta_o), where E is its directional albedo,
+// and it can be added back with a separate lobe that depends only on the albedo tables.
+
+// base 2 radical inverse, for the second dimension of the hammersley point set
+fn radical_inverse(i: u32) -> f32 {
+    (i.reverse_bits() as f64 / (1u64 << 32) as f64) as f32
+}
+
+/// Directional albedo E(cos_theta_o, alpha) = integral of f(wo, wi, alpha) * cos_theta_i over the hemisphere,
+/// tabulated over a grid of cos_theta_o in [0, 1] (x) and roughness alpha in [0, 1] (y).
+/// values are stored at cell centers and interpolated bilinearly.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug)]
+pub struct AlbedoTable {
+    albedo: Grid2D<f32>,
+    /// the cosine weighted average of the albedo over cos_theta_o, for each row of the table
+    average: Vec<f32>,
+}
+
+impl AlbedoTable {
+    /// integrates the directional albedo of `bsdf` with `samples` quasi random cosine weighted directions per cell.
+    /// `bsdf(wo, wi, alpha)` is given local directions around the +z normal, and should return the bsdf value
+    /// without the cosine term. cosine weighted sampling is exact for diffuse lobes,
+    /// while sharp lobes at low roughness need many more samples to converge.
+    /// the rows are integrated in parallel with the `parallel` feature.
+    pub fn build<F>(
+        cos_resolution: usize,
+        roughness_resolution: usize,
+        samples: usize,
+        bsdf: F,
+    ) -> Self
+    where
+        F: Fn(Vec3, Vec3, f32) -> f32 + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        let rows = (0..roughness_resolution).into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let rows = 0..roughness_resolution;
+        let rows: Vec<Vec<f32>> = rows
+            .map(|y| {
+                let alpha = (y as f32 + 0.5) / roughness_resolution as f32;
+                (0..cos_resolution)
+                    .map(|x| {
+                        let cos_theta_o = (x as f32 + 0.5) / cos_resolution as f32;
+                        let wo =
+                            Vec3::new((1.0 - cos_theta_o * cos_theta_o).sqrt(), 0.0, cos_theta_o);
+                        let sum: f32 = (0..samples)
+                            .map(|i| {
+                                let sample = Sample2D::new(
+                                    i as f32 / samples as f32,
+                                    radical_inverse(i as u32),
+                                );
+                                let (wi, pdf) = concentric_hemisphere_direction(
+                                    sample,
+                                    HemisphereWeighting::Cosine,
+                                );
+                                if Tolerance::EXACT.rejects_pdf(pdf) {
+                                    return 0.0;
+                                }
+                                bsdf(wo, wi, alpha) * wi.z() / *pdf
+                            })
+                            .sum();
+                        sum / samples as f32
+                    })
+                    .collect()
+            })
+            .collect();
+        let albedo = Grid2D::from_vec(cos_resolution, roughness_resolution, rows.concat());
+        AlbedoTable::from_grid(albedo)
+    }
+
+    /// wraps a precomputed albedo grid, i.e. one that was loaded from disk
+    pub fn from_grid(albedo: Grid2D<f32>) -> Self {
+        let width = albedo.width();
+        // E_avg = 2 * integral of E(mu) * mu over [0, 1], with the midpoint rule
+        let average = (0..albedo.height())
+            .map(|y| {
+                albedo
+                    .row(y)
+                    .iter()
+                    .enumerate()
+                    .map(|(x, e)| 2.0 * e * (x as f32 + 0.5) / width as f32)
+                    .sum::<f32>()
+                    / width as f32
+            })
+            .collect();
+        AlbedoTable { albedo, average }
+    }
+
+    pub fn grid(&self) -> &Grid2D<f32> {
+        &self.albedo
+    }
+
+    /// the directional albedo E(cos_theta_o, alpha)
+    pub fn albedo(&self, cos_theta_o: f32, alpha: f32) -> f32 {
+        self.albedo.bilinear(cos_theta_o.abs(), alpha)
+    }
+
+    /// the average albedo E_avg(alpha), linearly interpolated between rows
+    pub fn average_albedo(&self, alpha: f32) -> f32 {
+        let height = self.average.len();
+        let y = (alpha * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let y0 = y as usize;
+        let y1 = (y0 + 1).min(height - 1);
+        let t = y - y0 as f32;
+        (1.0 - t) * self.average[y0] + t * self.average[y1]
+    }
+
+    /// the multiple scattering compensation lobe f_ms = (1 - E(mu_o)) (1 - E(mu_i)) / (pi (1 - E_avg)),
+    /// which is added to the single scattering lobe such that the sum reflects all of the incident energy.
+    pub fn compensation(&self, cos_theta_o: f32, cos_theta_i: f32, alpha: f32) -> f32 {
+        let missing_average = 1.0 - self.average_albedo(alpha);
+        if missing_average <= 0.0 {
+            return 0.0;
+        }
+        let missing_o = (1.0 - self.albedo(cos_theta_o, alpha)).max(0.0);
+        let missing_i = (1.0 - self.albedo(cos_theta_i, alpha)).max(0.0);
+        missing_o * missing_i / (PI * missing_average)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_albedo_table() {
+        // a lambertian lobe is integrated exactly
+        let lambertian = AlbedoTable::build(8, 4, 64, |_, _, _| 0.8 / PI);
+        assert!((lambertian.albedo(0.3, 0.5) - 0.8).abs() < 1e-4);
+        assert!((lambertian.average_albedo(0.1) - 0.8).abs() < 1e-4);
+
+        // a lobe that loses energy with roughness and at grazing angles
+        let lossy = AlbedoTable::build(32, 8, 256, |wo, _, alpha| {
+            (1.0 - alpha) * wo.z().sqrt() / PI
+        });
+        assert!((lossy.albedo(0.25, 0.5) - 0.5 * 0.5).abs() < 1e-2);
+        assert_eq!(lossy.grid().width(), 32);
+
+        // the compensation lobe restores the missing energy: integral of f_ms * cos_theta_i = 1 - E(mu_o)
+        let (alpha, cos_theta_o) = (0.7, 0.4);
+        let n = 4096;
+        let restored = (0..n)
+            .map(|i| {
+                let sample = Sample2D::new(i as f32 / n as f32, radical_inverse(i as u32));
+                let (wi, pdf) =
+                    concentric_hemisphere_direction(sample, HemisphereWeighting::Cosine);
+                lossy.compensation(cos_theta_o, wi.z(), alpha) * wi.z() / *pdf
+            })
+            .sum::<f32>()
+            / n as f32;
+        let missing = 1.0 - lossy.albedo(cos_theta_o, alpha);
+        assert!(
+            (restored - missing).abs() < 1e-2,
+            "{} {}",
+            restored,
+            missing
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::prelude::*;
+
+// anisotropic lobes in the local shading frame, with the normal along +z and the tangent along +x.
+// roughness is given as (alpha_x, alpha_y), the widths of the lobe along the tangent and bitangent.
+// sampled directions are returned with their pdfs wrt solid angle.
+
+/// converts a roughness alpha to the equivalent cosine power exponent, n = 2 / alpha^2 - 2 (Walter et al. 2007)
+pub fn roughness_to_exponent(alpha: f32) -> f32 {
+    (2.0 / (alpha * alpha).max(1e-7) - 2.0).max(0.0)
+}
+
+// the exponent of the elliptical lobe in the azimuthal direction of `w`
+fn elliptical_exponent(cos_phi_squared: f32, alpha_x: f32, alpha_y: f32) -> f32 {
+    let (nu, nv) = (
+        roughness_to_exponent(alpha_x),
+        roughness_to_exponent(alpha_y),
+    );
+    nu * cos_phi_squared + nv * (1.0 - cos_phi_squared)
+}
+
+/// samples the normalized elliptical cosine power lobe of Ashikhmin and Shirley around +z,
+/// proportional to cos_theta^(nu cos^2 phi + nv sin^2 phi), with nu and nv derived from `alpha_x` and `alpha_y`.
+pub fn elliptical_cosine_power_direction(
+    r: Sample2D,
+    alpha_x: f32,
+    alpha_y: f32,
+) -> (Vec3, PDF<f32, SolidAngle>) {
+    let (nu, nv) = (
+        roughness_to_exponent(alpha_x),
+        roughness_to_exponent(alpha_y),
+    );
+    // sample phi in the first quadrant, then mirror it into the quadrant selected by r.x
+    let quadrant = (r.x * 4.0).min(3.0) as usize;
+    let u = r.x * 4.0 - quadrant as f32;
+    let mut phi = (((nu + 1.0) / (nv + 1.0)).sqrt() * (PI / 2.0 * u).tan()).atan();
+    phi = match quadrant {
+        0 => phi,
+        1 => PI - phi,
+        2 => PI + phi,
+        _ => 2.0 * PI - phi,
+    };
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let exponent = elliptical_exponent(cos_phi * cos_phi, alpha_x, alpha_y);
+    let cos_theta = (1.0 - r.y).powf(1.0 / (exponent + 1.0));
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let direction = Vec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+    (
+        direction,
+        elliptical_cosine_power_pdf(direction, alpha_x, alpha_y),
+    )
+}
+
+/// the pdf wrt solid angle of `elliptical_cosine_power_direction`
+pub fn elliptical_cosine_power_pdf(
+    direction: Vec3,
+    alpha_x: f32,
+    alpha_y: f32,
+) -> PDF<f32, SolidAngle> {
+    let cos_theta = direction.z();
+    if cos_theta <= 0.0 {
+        return PDF::new(0.0);
+    }
+    let sin_theta_squared = 1.0 - cos_theta * cos_theta;
+    let cos_phi_squared = if sin_theta_squared > 0.0 {
+        direction.x() * direction.x() / sin_theta_squared
+    } else {
+        1.0
+    };
+    let (nu, nv) = (
+        roughness_to_exponent(alpha_x),
+        roughness_to_exponent(alpha_y),
+    );
+    let exponent = elliptical_exponent(cos_phi_squared, alpha_x, alpha_y);
+    PDF::new(((nu + 1.0) * (nv + 1.0)).sqrt() / (2.0 * PI) * cos_theta.powf(exponent))
+}
+
+/// the anisotropic GGX normal distribution D(wm)
+pub fn ggx_d(wm: Vec3, alpha_x: f32, alpha_y: f32) -> f32 {
+    if wm.z() <= 0.0 {
+        return 0.0;
+    }
+    let (x, y, z) = (wm.x() / alpha_x, wm.y() / alpha_y, wm.z());
+    let denominator = x * x + y * y + z * z;
+    1.0 / (PI * alpha_x * alpha_y * denominator * denominator)
+}
+
+/// the smith shadowing auxiliary function lambda(w) for anisotropic GGX
+pub fn ggx_lambda(w: Vec3, alpha_x: f32, alpha_y: f32) -> f32 {
+    let cos_theta_squared = w.z() * w.z();
+    if cos_theta_squared == 0.0 {
+        return f32::INFINITY;
+    }
+    let projected = (alpha_x * w.x()).powi(2) + (alpha_y * w.y()).powi(2);
+    ((1.0 + projected / cos_theta_squared).sqrt() - 1.0) / 2.0
+}
+
+/// the smith masking function G1(w) for anisotropic GGX
+pub fn ggx_g1(w: Vec3, alpha_x: f32, alpha_y: f32) -> f32 {
+    1.0 / (1.0 + ggx_lambda(w, alpha_x, alpha_y))
+}
+
+/// samples a microfacet normal from the distribution of normals visible from `wo` (Heitz 2018).
+/// `wo` must be in the upper hemisphere. returns the normal and its pdf wrt the solid angle of normals.
+pub fn ggx_visible_normal(
+    wo: Vec3,
+    r: Sample2D,
+    alpha_x: f32,
+    alpha_y: f32,
+) -> (Vec3, PDF<f32, HalfVector>) {
+    // stretch the view direction to the configuration with unit roughness
+    let wh = Vec3::new(alpha_x * wo.x(), alpha_y * wo.y(), wo.z()).normalized();
+    let length_squared = wh.x() * wh.x() + wh.y() * wh.y();
+    let t1 = if length_squared > 0.0 {
+        Vec3::new(-wh.y(), wh.x(), 0.0) / length_squared.sqrt()
+    } else {
+        Vec3::X
+    };
+    let t2 = wh.cross(t1);
+
+    // sample the projected hemisphere, a disk with its lower half compressed along t2
+    let radius = r.x.sqrt();
+    let (sin_phi, cos_phi) = (2.0 * PI * r.y).sin_cos();
+    let p1 = radius * cos_phi;
+    let s = 0.5 * (1.0 + wh.z());
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * radius * sin_phi;
+    let nh = t1 * p1 + t2 * p2 + wh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+    // unstretch
+    let wm = Vec3::new(alpha_x * nh.x(), alpha_y * nh.y(), nh.z().max(0.0)).normalized();
+    (wm, ggx_visible_normal_pdf(wo, wm, alpha_x, alpha_y))
+}
+
+/// the pdf of `ggx_visible_normal`, D_wo(wm) = G1(wo) max(0, wo . wm) D(wm) / cos_theta_o
+pub fn ggx_visible_normal_pdf(
+    wo: Vec3,
+    wm: Vec3,
+    alpha_x: f32,
+    alpha_y: f32,
+) -> PDF<f32, HalfVector> {
+    if wo.z() <= 0.0 {
+        return PDF::new(0.0);
+    }
+    PDF::new(
+        ggx_g1(wo, alpha_x, alpha_y) * (wo * wm).max(0.0) * ggx_d(wm, alpha_x, alpha_y) / wo.z(),
+    )
+}
+
+/// the pdf of the reflected direction `wi` when reflecting `wo` about a visible normal sampled with `ggx_visible_normal`
+pub fn ggx_reflection_pdf(wo: Vec3, wi: Vec3, alpha_x: f32, alpha_y: f32) -> PDF<f32, SolidAngle> {
+    let wm = half_vector_reflection(wo, wi);
+    let cos_om = wo * wm;
+    if wi.z() <= 0.0 || cos_om <= 0.0 {
+        return PDF::new(0.0);
+    }
+    ggx_visible_normal_pdf(wo, wm, alpha_x, alpha_y).convert_reflection_to_solid_angle(cos_om)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // integrates `f` over the sphere with stratified uniform directions
+    fn integrate_sphere(f: impl Fn(Vec3) -> f32) -> f32 {
+        let n = 256;
+        let mut sum = 0.0;
+        for j in 0..n {
+            for i in 0..n {
+                let sample =
+                    Sample2D::new((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                sum += f(random_on_unit_sphere(sample));
+            }
+        }
+        sum * 4.0 * PI / (n * n) as f32
+    }
+
+    #[test]
+    fn test_anisotropic_lobes() {
+        let (alpha_x, alpha_y) = (0.3, 0.6);
+        let wo = Vec3::new(0.5, -0.3, 0.8).normalized();
+
+        let total = integrate_sphere(|w| *elliptical_cosine_power_pdf(w, alpha_x, alpha_y));
+        assert!((total - 1.0).abs() < 1e-2, "{}", total);
+        // projected area of visible normals: integral of D(wm) cos_theta_m = 1
+        let total = integrate_sphere(|w| ggx_d(w, alpha_x, alpha_y) * w.z().max(0.0));
+        assert!((total - 1.0).abs() < 1e-2, "{}", total);
+        let total = integrate_sphere(|w| *ggx_visible_normal_pdf(wo, w, alpha_x, alpha_y));
+        assert!((total - 1.0).abs() < 1e-2, "{}", total);
+
+        // sampled directions follow their pdfs: the sample mean of x^2 matches its expectation under the pdf,
+        // and the lobes are wider along the axis with the larger roughness
+        let n = 20000;
+        let mut sampler = RandomSampler::new();
+        let mut cosine_power = (0.0, 0.0);
+        let mut visible = (0.0, 0.0);
+        let mut reflected = 0.0;
+        for _ in 0..n {
+            let (w, pdf) = elliptical_cosine_power_direction(sampler.draw_2d(), alpha_x, alpha_y);
+            assert!((*pdf - *elliptical_cosine_power_pdf(w, alpha_x, alpha_y)).abs() < 1e-3 * *pdf);
+            cosine_power.0 += w.x() * w.x() / n as f32;
+            cosine_power.1 += w.y() * w.y() / n as f32;
+            let (wm, pdf) = ggx_visible_normal(wo, sampler.draw_2d(), alpha_x, alpha_y);
+            assert!(*pdf > 0.0 && (wm.norm() - 1.0).abs() < 1e-4);
+            visible.0 += wm.x() * wm.x() / n as f32;
+            visible.1 += wm.y() * wm.y() / n as f32;
+            let wi = wm * (2.0 * (wo * wm)) - wo;
+            if wi.z() > 0.0 {
+                reflected += 1.0 / n as f32;
+            }
+        }
+        // reflections that end up below the horizon are lost, so the reflection pdf integrates to the fraction above it
+        let total = integrate_sphere(|w| *ggx_reflection_pdf(wo, w, alpha_x, alpha_y));
+        assert!((total - reflected).abs() < 2e-2, "{} {}", total, reflected);
+        let expected =
+            integrate_sphere(|w| w.x() * w.x() * *elliptical_cosine_power_pdf(w, alpha_x, alpha_y));
+        assert!(
+            (cosine_power.0 - expected).abs() < 0.05 * expected,
+            "{} {}",
+            cosine_power.0,
+            expected
+        );
+        assert!(cosine_power.0 < cosine_power.1);
+        let expected =
+            integrate_sphere(|w| w.y() * w.y() * *ggx_visible_normal_pdf(wo, w, alpha_x, alpha_y));
+        assert!(
+            (visible.1 - expected).abs() < 0.05 * expected,
+            "{} {}",
+            visible.1,
+            expected
+        );
+        assert!(visible.0 < visible.1);
+    }
+}
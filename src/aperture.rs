@@ -0,0 +1,204 @@
+use crate::prelude::*;
+
+// aperture sampling for thin lens camera models. points are returned in the plane of the aperture,
+// centered on the optical axis, along with their pdfs wrt area on that plane.
+
+/// samples a point uniformly on a circular aperture of the given radius
+pub fn sample_disk_aperture(r: Sample2D, radius: f32) -> ((f32, f32), PDF<f32, Area>) {
+    let (x, y) = concentric_sample_disk(r);
+    (
+        (x * radius, y * radius),
+        PDF::new(1.0 / (PI * radius * radius)),
+    )
+}
+
+/// A regular polygonal aperture formed by `blades` straight aperture blades, with a circumradius of `radius`.
+/// `roundness` blends the boundary between the straight edged polygon (0) and a circle (1), as with curved blades.
+#[derive(Clone, Debug)]
+pub struct BladedAperture {
+    pub blades: usize,
+    pub radius: f32,
+    pub rotation: f32,
+    pub roundness: f32,
+    // distribution of the local angle within one blade sector, proportional to the area swept by that angle
+    sector: Distribution1D,
+}
+
+impl BladedAperture {
+    pub fn new(blades: usize, radius: f32, rotation: f32, roundness: f32) -> Self {
+        assert!(blades >= 3);
+        debug_assert!((0.0..=1.0).contains(&roundness));
+        let half_sector = PI / blades as f32;
+        let mut aperture = BladedAperture {
+            blades,
+            radius,
+            rotation,
+            roundness,
+            sector: Distribution1D::new(&[1.0], Bounds1D::new(-half_sector, half_sector)),
+        };
+        let bins = 64;
+        let swept_area: Vec<f32> = (0..bins)
+            .map(|i| {
+                let local = aperture.sector.bounds.lerp((i as f32 + 0.5) / bins as f32);
+                aperture.local_boundary_radius(local).powi(2) / 2.0
+            })
+            .collect();
+        aperture.sector = Distribution1D::new(&swept_area, aperture.sector.bounds);
+        aperture
+    }
+
+    fn sector_angle(&self) -> f32 {
+        2.0 * PI / self.blades as f32
+    }
+
+    // boundary radius as a function of the angle relative to the middle of a blade
+    fn local_boundary_radius(&self, local: f32) -> f32 {
+        let apothem = self.radius * (PI / self.blades as f32).cos();
+        (1.0 - self.roundness) * apothem / local.cos() + self.roundness * self.radius
+    }
+
+    // splits an angle into the index of the blade sector it falls in and the angle relative to that sector's center
+    fn to_local(&self, phi: f32) -> (usize, f32) {
+        let sector_angle = self.sector_angle();
+        let relative = (phi - self.rotation + sector_angle / 2.0).rem_euclid(2.0 * PI);
+        let sector = ((relative / sector_angle) as usize).min(self.blades - 1);
+        (sector, relative - (sector as f32 + 0.5) * sector_angle)
+    }
+
+    /// distance from the center to the edge of the aperture in the direction `phi`
+    pub fn boundary_radius(&self, phi: f32) -> f32 {
+        self.local_boundary_radius(self.to_local(phi).1)
+    }
+
+    /// area of the aperture, approximated with the same tabulation that is used for sampling
+    pub fn area(&self) -> f32 {
+        self.blades as f32 * self.sector.func_integral
+    }
+
+    /// samples a point on the aperture, approximately uniformly wrt area. the returned pdf is exact.
+    pub fn sample(&self, r: Sample2D) -> ((f32, f32), PDF<f32, Area>) {
+        // the first dimension selects the blade sector, and is then reused to select the angle within that sector
+        let scaled = r.x * self.blades as f32;
+        let sector = (scaled as usize).min(self.blades - 1);
+        let remapped = (scaled - sector as f32).min(1.0 - f32::EPSILON);
+        let (local, _, _) = self.sector.sample(Sample1D::new(remapped));
+
+        let boundary = self.local_boundary_radius(local);
+        let phi = self.rotation + sector as f32 * self.sector_angle() + local;
+        let radius = boundary * r.y.sqrt();
+        let (sin, cos) = phi.sin_cos();
+        (
+            (radius * cos, radius * sin),
+            self.local_pdf(local, boundary),
+        )
+    }
+
+    // area density of sampling radius^2 proportionally, given the local angle and the boundary radius at that angle
+    fn local_pdf(&self, local: f32, boundary: f32) -> PDF<f32, Area> {
+        let angular_pdf = *self.sector.pdf(local) / self.blades as f32;
+        PDF::new(2.0 * angular_pdf / (boundary * boundary))
+    }
+
+    pub fn pdf(&self, point: (f32, f32)) -> PDF<f32, Area> {
+        let (_, local) = self.to_local(point.1.atan2(point.0));
+        let boundary = self.local_boundary_radius(local);
+        if point.0.hypot(point.1) > boundary {
+            return PDF::new(0.0);
+        }
+        self.local_pdf(local, boundary)
+    }
+
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        point.0.hypot(point.1) <= self.boundary_radius(point.1.atan2(point.0))
+    }
+}
+
+/// the fraction of a circular aperture of radius `aperture_radius` that remains unobstructed by a second circular opening,
+/// such as the lens barrel, of radius `barrel_radius` whose center is displaced by `offset` from the aperture's center.
+/// `offset` typically grows proportionally with the distance of the image point from the optical axis,
+/// producing the cat's eye shaped bokeh and the corresponding falloff in illumination towards the corners.
+pub fn cat_eye_vignetting(aperture_radius: f32, barrel_radius: f32, offset: f32) -> f32 {
+    let (a, b, d) = (aperture_radius, barrel_radius, offset.abs());
+    if d >= a + b {
+        return 0.0;
+    }
+    let aperture_area = PI * a * a;
+    if d <= (a - b).abs() {
+        let smaller = a.min(b);
+        return (PI * smaller * smaller / aperture_area).min(1.0);
+    }
+    // area of the lens shaped intersection of the two circles
+    let alpha = ((d * d + a * a - b * b) / (2.0 * d * a))
+        .clamp(-1.0, 1.0)
+        .acos();
+    let beta = ((d * d + b * b - a * a) / (2.0 * d * b))
+        .clamp(-1.0, 1.0)
+        .acos();
+    let kite = 0.5
+        * ((-d + a + b) * (d + a - b) * (d - a + b) * (d + a + b))
+            .max(0.0)
+            .sqrt();
+    (a * a * alpha + b * b * beta - kite) / aperture_area
+}
+
+/// whether an aperture sample at `point` passes through a barrel opening of radius `barrel_radius` centered at `barrel_center`
+pub fn cat_eye_visible(point: (f32, f32), barrel_center: (f32, f32), barrel_radius: f32) -> bool {
+    (point.0 - barrel_center.0).hypot(point.1 - barrel_center.1) <= barrel_radius
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disk_aperture() {
+        for _ in 0..1000 {
+            let ((x, y), pdf) = sample_disk_aperture(Sample2D::new_random_sample(), 2.0);
+            assert!(x.hypot(y) <= 2.0 + 1e-5);
+            assert!((*pdf - 1.0 / (4.0 * PI)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_bladed_aperture() {
+        let hexagon = BladedAperture::new(6, 1.0, 0.3, 0.0);
+        let exact_area = 0.5 * 6.0 * (2.0 * PI / 6.0).sin();
+        assert!(
+            (hexagon.area() - exact_area).abs() < 1e-3,
+            "{}",
+            hexagon.area()
+        );
+
+        let rounded = BladedAperture::new(5, 1.0, 0.0, 0.5);
+        // seeded, since a point sampled right at the edge of a tabulated angle bin can map back into its neighbour
+        crate::random::scoped_seed(2, || {
+            for aperture in [hexagon, rounded] {
+                // estimate the area through importance sampling, which should be exact up to floating point error
+                let n = 10000;
+                let mut estimate = 0.0;
+                for _ in 0..n {
+                    let (point, pdf) = aperture.sample(Sample2D::new_random_sample());
+                    assert!(aperture.contains((point.0 * 0.999, point.1 * 0.999)));
+                    assert!((*pdf - *aperture.pdf(point)).abs() < 1e-3 * *pdf);
+                    estimate += 1.0 / *pdf / n as f32;
+                }
+                assert!((estimate - aperture.area()).abs() < 1e-2, "{}", estimate);
+            }
+        });
+    }
+
+    #[test]
+    fn test_cat_eye_vignetting() {
+        assert_eq!(cat_eye_vignetting(1.0, 1.5, 0.0), 1.0);
+        assert_eq!(cat_eye_vignetting(1.0, 1.0, 2.0), 0.0);
+        assert_eq!(cat_eye_vignetting(1.0, 0.5, 0.2), 0.25);
+        // two unit circles with their centers one radius apart overlap by 2pi/3 - sqrt(3)/2
+        let expected = (2.0 * PI / 3.0 - 3.0f32.sqrt() / 2.0) / PI;
+        assert!((cat_eye_vignetting(1.0, 1.0, 1.0) - expected).abs() < 1e-5);
+        // falloff is monotonic with the offset
+        assert!(cat_eye_vignetting(1.0, 1.2, 0.5) > cat_eye_vignetting(1.0, 1.2, 0.8));
+
+        assert!(cat_eye_visible((0.5, 0.0), (0.3, 0.0), 1.0));
+        assert!(!cat_eye_visible((-0.9, 0.0), (0.3, 0.0), 1.0));
+    }
+}
@@ -1,9 +1,12 @@
+use crate::prelude::*;
+
 #[cfg(feature = "deepsize")]
 use deepsize::DeepSizeOf;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::simd::mask32x4;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
 pub struct Bounds1D {
@@ -69,6 +72,108 @@ impl Bounds2D {
     }
 }
 
+/// An interval on a periodic domain, such as angles or hue, which may cross the wrap point.
+/// the interval starts at `lower` and extends forward by `span`, wrapping around `domain` as needed.
+/// a span equal to the period of the domain covers the whole domain.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+pub struct WrappedBounds1D {
+    pub domain: Bounds1D,
+    pub lower: f32,
+    pub span: f32,
+}
+
+impl WrappedBounds1D {
+    /// periodic domain of angles in radians
+    pub const RADIANS: Bounds1D = Bounds1D::new(0.0, std::f32::consts::TAU);
+    /// periodic domain of hue, in turns
+    pub const HUE: Bounds1D = Bounds1D::new(0.0, 1.0);
+
+    /// the interval going forward from `lower` to `upper`, which crosses the wrap point if `upper` < `lower`.
+    /// intervals spanning at least a period cover the whole domain, starting from `lower`.
+    pub fn new(domain: Bounds1D, lower: f32, upper: f32) -> Self {
+        if upper - lower >= domain.span() {
+            return WrappedBounds1D {
+                domain,
+                lower: Self::wrap_into(domain, lower),
+                span: domain.span(),
+            };
+        }
+        let lower = Self::wrap_into(domain, lower);
+        let span = Self::wrap_into(domain, upper) - lower;
+        WrappedBounds1D {
+            domain,
+            lower,
+            span: if span < 0.0 {
+                span + domain.span()
+            } else {
+                span
+            },
+        }
+    }
+    pub fn full(domain: Bounds1D) -> Self {
+        WrappedBounds1D {
+            domain,
+            lower: domain.lower,
+            span: domain.span(),
+        }
+    }
+    pub fn period(&self) -> f32 {
+        self.domain.span()
+    }
+    pub fn upper(&self) -> f32 {
+        self.wrap(self.lower + self.span)
+    }
+
+    fn wrap_into(domain: Bounds1D, value: f32) -> f32 {
+        let wrapped = (value - domain.lower).rem_euclid(domain.span()) + domain.lower;
+        // rem_euclid can round up to the period for tiny negative inputs
+        if wrapped >= domain.upper {
+            domain.lower
+        } else {
+            wrapped
+        }
+    }
+    /// maps `value` into the domain
+    pub fn wrap(&self, value: f32) -> f32 {
+        Self::wrap_into(self.domain, value)
+    }
+    /// the signed offset to go from `a` to `b` the short way around the domain, in [-period / 2, period / 2]
+    pub fn shortest_difference(&self, a: f32, b: f32) -> f32 {
+        let d = Self::wrap_into(self.domain, b - a + self.domain.lower) - self.domain.lower;
+        if d > self.period() / 2.0 {
+            d - self.period()
+        } else {
+            d
+        }
+    }
+    pub fn distance(&self, a: f32, b: f32) -> f32 {
+        self.shortest_difference(a, b).abs()
+    }
+    /// interpolates from `a` to `b` the short way around the domain, crossing the wrap point if necessary
+    pub fn interpolate(&self, a: f32, b: f32, t: f32) -> f32 {
+        self.wrap(a + t * self.shortest_difference(a, b))
+    }
+
+    pub fn contains(&self, value: &f32) -> bool {
+        let offset = self.wrap(*value) - self.lower;
+        let offset = if offset < 0.0 {
+            offset + self.period()
+        } else {
+            offset
+        };
+        offset < self.span || self.span >= self.period()
+    }
+    /// lerps forward through the interval, wrapping the result into the domain
+    pub fn lerp(&self, t: f32) -> f32 {
+        self.wrap(self.lower + t * self.span)
+    }
+    pub fn sample(&self, x: f32) -> f32 {
+        self.lerp(x)
+    }
+}
+
 impl From<(f32, f32)> for Bounds1D {
     fn from(mut bounds: (f32, f32)) -> Self {
         // swap if in the wrong order
@@ -80,3 +185,218 @@ impl From<(f32, f32)> for Bounds1D {
         Self::new(bounds.0, bounds.1)
     }
 }
+
+/// An axis aligned box in 3d. boxes with any `min` component greater than the `max` component are empty.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds3D {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+// pads t_max of slab tests by 2 gamma(3) to make them conservative wrt rounding (Pharr et al., pbrt 3rd edition, 3.9.2)
+const SLAB_PADDING: f32 = 1.0 + 2.0 * (3.0 * f32::EPSILON * 0.5) / (1.0 - 3.0 * f32::EPSILON * 0.5);
+
+impl Bounds3D {
+    /// the empty box, which is the identity of `union`
+    pub const EMPTY: Bounds3D = Bounds3D {
+        min: Point3::INFINITY,
+        max: Point3::NEG_INFINITY,
+    };
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Bounds3D {
+            min: Point3(a.0.simd_min(b.0)),
+            max: Point3(a.0.simd_max(b.0)),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.min.0.simd_gt(self.max.0).any()
+    }
+    pub fn union(&self, other: Self) -> Self {
+        Bounds3D {
+            min: Point3(self.min.0.simd_min(other.min.0)),
+            max: Point3(self.max.0.simd_max(other.max.0)),
+        }
+    }
+    pub fn union_point(&self, p: Point3) -> Self {
+        self.union(Bounds3D { min: p, max: p })
+    }
+    pub fn contains(&self, p: Point3) -> bool {
+        (p.0.simd_ge(self.min.0) & p.0.simd_le(self.max.0)).all()
+    }
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+    pub fn centroid(&self) -> Point3 {
+        self.min + self.diagonal() * 0.5
+    }
+    pub fn surface_area(&self) -> f32 {
+        let d = self.diagonal();
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+    /// the parametric distances at which `ray` enters and exits the box, clipped to [0, ray.tmax], or None if it misses
+    pub fn intersect(&self, ray: &Ray) -> Option<(f32, f32)> {
+        if self.is_empty() {
+            return None;
+        }
+        let inverse_direction = f32x4::splat(1.0) / ray.direction.0;
+        let t0 = (self.min.0 - ray.origin.0) * inverse_direction;
+        let t1 = (self.max.0 - ray.origin.0) * inverse_direction;
+        // simd_min and simd_max skip the NaNs of rays that lie within a slab's plane
+        let (near, far) = (t0.simd_min(t1), t0.simd_max(t1));
+        let t_enter = near.to_array()[..3].iter().fold(0.0f32, |t, &n| t.max(n));
+        let t_exit = far.to_array()[..3]
+            .iter()
+            .fold(ray.tmax, |t, &f| t.min(f * SLAB_PADDING));
+        (t_enter <= t_exit).then_some((t_enter, t_exit))
+    }
+}
+
+/// Four axis aligned boxes in structure of arrays layout, for testing a ray against all four at once,
+/// i.e. against the children of a node in a 4 wide BVH.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds3D4 {
+    /// the x, y and z coordinates of the minimum corners, with one box per lane
+    pub min: [f32x4; 3],
+    pub max: [f32x4; 3],
+}
+
+impl Bounds3D4 {
+    pub fn new(boxes: [Bounds3D; 4]) -> Self {
+        let axis = |corner: fn(&Bounds3D) -> Point3, i: usize| {
+            f32x4::from_array(boxes.map(|b| corner(&b).0[i]))
+        };
+        Bounds3D4 {
+            min: [0, 1, 2].map(|i| axis(|b| b.min, i)),
+            max: [0, 1, 2].map(|i| axis(|b| b.max, i)),
+        }
+    }
+    /// packs up to four boxes, filling the remaining lanes with empty boxes that are never hit
+    pub fn from_slice(boxes: &[Bounds3D]) -> Self {
+        debug_assert!(boxes.len() <= 4);
+        let mut padded = [Bounds3D::EMPTY; 4];
+        padded[..boxes.len()].copy_from_slice(boxes);
+        Bounds3D4::new(padded)
+    }
+    pub fn get(&self, lane: usize) -> Bounds3D {
+        Bounds3D {
+            min: Point3::new(self.min[0][lane], self.min[1][lane], self.min[2][lane]),
+            max: Point3::new(self.max[0][lane], self.max[1][lane], self.max[2][lane]),
+        }
+    }
+    /// tests one ray against all four boxes, given the ray origin and the reciprocal of its direction,
+    /// which can be computed once per ray and reused for every node. returns the mask of boxes that are hit
+    /// within [t_min, t_max] and the distances at which the ray enters each of them, which are only meaningful for hit lanes.
+    pub fn intersect(
+        &self,
+        origin: Point3,
+        inverse_direction: Vec3,
+        t_min: f32,
+        t_max: f32,
+    ) -> (mask32x4, f32x4) {
+        let mut t_enter = f32x4::splat(t_min);
+        let mut t_exit = f32x4::splat(t_max);
+        // empty boxes would otherwise be hit by rays parallel to their slabs
+        let mut valid = mask32x4::splat(true);
+        for axis in 0..3 {
+            valid &= self.min[axis].simd_le(self.max[axis]);
+            let o = f32x4::splat(origin.0[axis]);
+            let inverse = f32x4::splat(inverse_direction.0[axis]);
+            let t0 = (self.min[axis] - o) * inverse;
+            let t1 = (self.max[axis] - o) * inverse;
+            t_enter = t_enter.simd_max(t0.simd_min(t1));
+            t_exit = t_exit.simd_min(t0.simd_max(t1) * f32x4::splat(SLAB_PADDING));
+        }
+        (valid & t_enter.simd_le(t_exit), t_enter)
+    }
+    /// tests `ray` against all four boxes within [0, ray.tmax]
+    pub fn intersect_ray(&self, ray: &Ray) -> (mask32x4, f32x4) {
+        let inverse_direction = Vec3(f32x4::splat(1.0) / ray.direction.0);
+        self.intersect(ray.origin, inverse_direction, 0.0, ray.tmax)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bounds3d4_slab_test() {
+        let boxes = [
+            Bounds3D::new(Point3::new(-1.0, -1.0, 4.0), Point3::new(1.0, 1.0, 6.0)),
+            Bounds3D::new(Point3::new(2.0, 2.0, 1.0), Point3::new(3.0, 3.0, 2.0)),
+            // the ray is parallel to the x and y slabs, and passes between them
+            Bounds3D::new(Point3::new(-0.5, -1.0, 1.0), Point3::new(1.0, 0.5, 2.0)),
+            Bounds3D::new(Point3::new(-1.0, -1.0, -3.0), Point3::new(1.0, 1.0, -2.0)),
+        ];
+        let packed = Bounds3D4::new(boxes);
+        assert_eq!(packed.get(1), boxes[1]);
+
+        let ray = Ray::new(Point3::ORIGIN, Vec3::Z);
+        let (mask, t) = packed.intersect_ray(&ray);
+        assert_eq!(mask.to_array(), [true, false, true, false]);
+        assert_eq!(t[0], 4.0);
+        assert_eq!(t[2], 1.0);
+        // the packed test agrees with the scalar one
+        for (i, b) in boxes.iter().enumerate() {
+            assert_eq!(b.intersect(&ray).is_some(), mask.test(i));
+        }
+        let (t_enter, t_exit) = boxes[0].intersect(&ray).unwrap();
+        assert!(t_enter == 4.0 && (t_exit - 6.0).abs() < 1e-5);
+
+        // tmax cuts off the far box, and an origin inside a box enters it at t = 0
+        let short = Ray { tmax: 3.0, ..ray };
+        assert_eq!(
+            packed.intersect_ray(&short).0.to_array(),
+            [false, false, true, false]
+        );
+        let inside = Ray::new(
+            Point3::new(0.0, 0.0, 5.0),
+            Vec3::new(1.0, 1.0, 1.0).normalized(),
+        );
+        let (mask, t) = packed.intersect_ray(&inside);
+        assert!(mask.test(0) && t[0] == 0.0);
+
+        // padding lanes never hit
+        let partial = Bounds3D4::from_slice(&boxes[..2]);
+        assert_eq!(
+            partial.intersect_ray(&ray).0.to_array(),
+            [true, false, false, false]
+        );
+
+        let union = boxes.iter().fold(Bounds3D::EMPTY, |u, b| u.union(*b));
+        assert!(Bounds3D::EMPTY.is_empty() && !union.is_empty());
+        assert_eq!(union.min, Point3::new(-1.0, -1.0, -3.0));
+        assert!(union.contains(Point3::new(2.5, 2.5, 1.5)));
+        assert_eq!(boxes[1].surface_area(), 6.0);
+        assert_eq!(boxes[1].centroid(), Point3::new(2.5, 2.5, 1.5));
+    }
+
+    #[test]
+    fn test_wrapped_bounds() {
+        let hue = WrappedBounds1D::new(WrappedBounds1D::HUE, 0.9, 0.1);
+        assert!((hue.span - 0.2).abs() < 1e-6);
+        assert!(hue.contains(&0.95));
+        assert!(hue.contains(&0.05));
+        assert!(hue.contains(&1.05));
+        assert!(!hue.contains(&0.5));
+        assert!((hue.lerp(0.75) - 0.05).abs() < 1e-6);
+        assert!((hue.upper() - 0.1).abs() < 1e-6);
+
+        assert!((hue.distance(0.95, 0.05) - 0.1).abs() < 1e-6);
+        assert!((hue.shortest_difference(0.05, 0.95) + 0.1).abs() < 1e-6);
+        // interpolating across the wrap point
+        assert!((hue.interpolate(0.9, 0.2, 0.5) - 0.05).abs() < 1e-6);
+
+        let angles = WrappedBounds1D::full(WrappedBounds1D::RADIANS);
+        assert!(angles.contains(&-1.0));
+        assert!(
+            (angles.wrap(-std::f32::consts::FRAC_PI_2) - 1.5 * std::f32::consts::PI).abs() < 1e-5
+        );
+        assert!(angles.wrap(-1e-9) < angles.domain.upper);
+        // a full period is the whole circle rather than an empty interval
+        let circle =
+            WrappedBounds1D::new(WrappedBounds1D::RADIANS, -1.0, std::f32::consts::TAU - 1.0);
+        assert_eq!(circle.span, std::f32::consts::TAU);
+        assert!(circle.contains(&0.5) && circle.contains(&5.0));
+    }
+}
@@ -0,0 +1,85 @@
+use super::color_space::apply_matrix;
+use super::{ColorSpace, RGBColor, SRGBColor, XYZColor};
+
+use std::simd::f32x4;
+use std::simd::num::SimdFloat;
+
+// Stephen Hill's fit of the ACES reference rendering transform (RRT) combined with the sRGB output device transform (ODT).
+// the input matrix maps linear sRGB to ACEScg, including the D65 to D60 adaptation and the saturation boost of the RRT,
+// and the output matrix maps back to linear sRGB, undoing the adaptation and the boost.
+
+const ACES_INPUT: [[f32; 3]; 3] = [
+    [0.59719, 0.35458, 0.04823],
+    [0.07600, 0.90834, 0.01566],
+    [0.02840, 0.13383, 0.83777],
+];
+const ACES_OUTPUT: [[f32; 3]; 3] = [
+    [1.60475, -0.53108, -0.07367],
+    [-0.10208, 1.10813, -0.00605],
+    [-0.00327, -0.07276, 1.07602],
+];
+
+// the combined rrt and odt tone curve, applied per channel in ACEScg
+fn rrt_and_odt_fit(v: f32x4) -> f32x4 {
+    let a = v * (v + f32x4::splat(0.0245786)) - f32x4::splat(0.000090537);
+    let b = v * (f32x4::splat(0.983729) * v + f32x4::splat(0.432951)) + f32x4::splat(0.238081);
+    a / b
+}
+
+/// tone maps scene referred linear sRGB to display referred linear sRGB in [0, 1], using the ACES filmic curve.
+/// exposure should be applied beforehand.
+pub fn aces_tonemap(color: RGBColor) -> RGBColor {
+    let mapped = apply_matrix(
+        &ACES_OUTPUT,
+        rrt_and_odt_fit(apply_matrix(&ACES_INPUT, color.0)),
+    );
+    // clear the unused fourth lane, which the tone curve maps to a small nonzero value
+    RGBColor::from_raw(
+        mapped.simd_clamp(f32x4::splat(0.0), f32x4::splat(1.0))
+            * f32x4::from_array([1.0, 1.0, 1.0, 0.0]),
+    )
+}
+
+/// tone maps scene referred XYZ to display ready, gamma encoded sRGB
+pub fn aces_tonemap_xyz(color: XYZColor) -> SRGBColor {
+    SRGBColor::from_linear(aces_tonemap(ColorSpace::SRGB.from_xyz(color)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_aces_tonemap() {
+        assert_eq!(aces_tonemap(RGBColor::BLACK), RGBColor::BLACK);
+        // greys stay neutral, increase monotonically and saturate at 1
+        let mut last = 0.0;
+        for i in 1..100 {
+            let v = 0.01 * 1.15f32.powi(i);
+            let mapped = aces_tonemap(RGBColor::new(v, v, v));
+            assert!(
+                (mapped.r() - mapped.g()).abs() < 1e-3 && (mapped.g() - mapped.b()).abs() < 1e-3
+            );
+            assert!(
+                mapped.g() >= last && mapped.g() <= 1.0,
+                "{} {:?}",
+                v,
+                mapped
+            );
+            last = mapped.g();
+        }
+        assert_eq!(last, 1.0);
+        // the toe darkens midtones, with 18% grey ending up near 10% linear on display
+        let grey = aces_tonemap(RGBColor::new(0.18, 0.18, 0.18)).g();
+        assert!((grey - 0.1056).abs() < 1e-3, "{}", grey);
+
+        // bright saturated colors desaturate towards white rather than clipping to the primary
+        let red = aces_tonemap(RGBColor::new(20.0, 0.0, 0.0));
+        assert!(red.r() > 0.99 && red.g() > 0.05, "{:?}", red);
+
+        // D65 white in XYZ maps to a neutral encoded value
+        let white = aces_tonemap_xyz(XYZColor::new(0.95047, 1.0, 1.08883) * 0.18);
+        let [r, g, b] = white.to_u8();
+        assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "{:?}", white);
+    }
+}
@@ -0,0 +1,175 @@
+use super::{Chromaticity, ColorMatrix3, XYZColor};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// von kries style chromatic adaptation: XYZ is mapped into a cone-like response space, each channel is scaled by
+// the ratio of the target and source white responses, and the result is mapped back to XYZ.
+// the methods differ only in the matrix that defines the response space.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum AdaptationMethod {
+    /// the sharpened response space of the Bradford transform, as used by ICC profiles
+    Bradford,
+    /// the response space of CIECAM02
+    CAT02,
+    /// the Hunt-Pointer-Estevez cone fundamentals
+    VonKries,
+    /// scales XYZ directly, which is simple but the least accurate
+    XYZScaling,
+}
+
+impl AdaptationMethod {
+    /// the matrix that maps XYZ to the response space of this method
+    pub fn cone_response_matrix(&self) -> [[f32; 3]; 3] {
+        match self {
+            AdaptationMethod::Bradford => [
+                [0.8951, 0.2664, -0.1614],
+                [-0.7502, 1.7135, 0.0367],
+                [0.0389, -0.0685, 1.0296],
+            ],
+            AdaptationMethod::CAT02 => [
+                [0.7328, 0.4296, -0.1624],
+                [-0.7036, 1.6975, 0.0061],
+                [0.0030, 0.0136, 0.9834],
+            ],
+            AdaptationMethod::VonKries => [
+                [0.40024, 0.7076, -0.08081],
+                [-0.2263, 1.16532, 0.0457],
+                [0.0, 0.0, 0.91822],
+            ],
+            AdaptationMethod::XYZScaling => [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        }
+    }
+}
+
+/// A linear transform that maps colors seen under one white point to the colors that appear the same under another,
+/// i.e. D65 to D50 when moving values between an sRGB working space and an ICC profile connection space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct ChromaticAdaptation {
+    pub method: AdaptationMethod,
+    pub source_white: Chromaticity,
+    pub target_white: Chromaticity,
+    matrix: [[f32; 3]; 3],
+}
+
+impl ChromaticAdaptation {
+    pub fn new(
+        method: AdaptationMethod,
+        source_white: Chromaticity,
+        target_white: Chromaticity,
+    ) -> Self {
+        let to_xyz = |(x, y): Chromaticity| {
+            let (x, y) = (x as f64, y as f64);
+            nalgebra::Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
+        };
+        let response = method.cone_response_matrix();
+        let cone = nalgebra::Matrix3::from_fn(|i, j| response[i][j] as f64);
+        let inverse = cone
+            .try_inverse()
+            .expect("cone response matrices are invertible");
+        let (source, target) = (cone * to_xyz(source_white), cone * to_xyz(target_white));
+        let scale = nalgebra::Matrix3::from_diagonal(&target.component_div(&source));
+        let combined = inverse * scale * cone;
+        let mut matrix = [[0.0; 3]; 3];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = combined[(i, j)] as f32;
+            }
+        }
+        ChromaticAdaptation {
+            method,
+            source_white,
+            target_white,
+            matrix,
+        }
+    }
+
+    /// the adaptation back from the target white to the source white
+    pub fn inverse(&self) -> Self {
+        ChromaticAdaptation::new(self.method, self.target_white, self.source_white)
+    }
+
+    /// the matrix that maps XYZ under the source white to XYZ under the target white
+    pub fn matrix(&self) -> &[[f32; 3]; 3] {
+        &self.matrix
+    }
+
+    /// `matrix` as a `ColorMatrix3`, for composing with other transforms
+    pub fn transform(&self) -> ColorMatrix3 {
+        ColorMatrix3::from_rows(self.matrix)
+    }
+
+    pub fn adapt(&self, color: XYZColor) -> XYZColor {
+        self.transform().apply_xyz(color)
+    }
+}
+
+impl XYZColor {
+    /// adapts self from `source_white` to `target_white`. prefer constructing a `ChromaticAdaptation` once when adapting many colors.
+    pub fn adapt(
+        &self,
+        method: AdaptationMethod,
+        source_white: Chromaticity,
+        target_white: Chromaticity,
+    ) -> XYZColor {
+        ChromaticAdaptation::new(method, source_white, target_white).adapt(*self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_chromatic_adaptation() {
+        let d65 = (0.3127, 0.3290);
+        let d50 = (0.3457, 0.3585);
+        let white = |(x, y): Chromaticity| XYZColor::new(x / y, 1.0, (1.0 - x - y) / y);
+
+        // the Bradford D65 to D50 matrix published by Lindbloom, whose whites are tabulated in XYZ rather than xy
+        let bradford = ChromaticAdaptation::new(AdaptationMethod::Bradford, d65, d50);
+        let expected = [
+            [1.0478112, 0.0228866, -0.0501270],
+            [0.0295424, 0.9904844, -0.0170491],
+            [-0.0092345, 0.0150436, 0.7521316],
+        ];
+        for (row, expected_row) in bradford.matrix().iter().zip(expected.iter()) {
+            for (value, expected_value) in row.iter().zip(expected_row.iter()) {
+                assert!(
+                    (value - expected_value).abs() < 5e-4,
+                    "{:?}",
+                    bradford.matrix()
+                );
+            }
+        }
+
+        for method in [
+            AdaptationMethod::Bradford,
+            AdaptationMethod::CAT02,
+            AdaptationMethod::VonKries,
+            AdaptationMethod::XYZScaling,
+        ] {
+            let adaptation = ChromaticAdaptation::new(method, d65, d50);
+            // the source white maps to the target white, and the inverse undoes the adaptation
+            let adapted = adaptation.adapt(white(d65));
+            let target = white(d50);
+            for (a, b) in adapted.0.to_array().iter().zip(target.0.to_array().iter()) {
+                assert!((a - b).abs() < 1e-5, "{:?} {:?}", method, adapted);
+            }
+            let color = XYZColor::new(0.3, 0.4, 0.2);
+            let round_trip = adaptation.inverse().adapt(adaptation.adapt(color));
+            for (a, b) in round_trip
+                .0
+                .to_array()
+                .iter()
+                .zip(color.0.to_array().iter())
+            {
+                assert!((a - b).abs() < 1e-5, "{:?}", method);
+            }
+            assert_eq!(color.adapt(method, d65, d50).0, adaptation.adapt(color).0);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use super::{ColorMatrix3, ColorSpace, RGBColor, XYZColor};
+
+use std::simd::{f32x4, simd_swizzle};
+
+// structure of arrays layouts for four colors at once, for loops that convert whole images such as film resolves.
+// a single color only fills three lanes of an f32x4 and its conversion needs three splats per matrix multiply,
+// whereas four colors with their components in separate vectors convert with nine multiply adds and no shuffles.
+// the transposition to and from `[XYZColor; 4]` is a 4x4 shuffle, as each color is already a full f32x4.
+
+// transposes four colors into their first three components, dropping the unused fourth lane
+#[inline(always)]
+fn transpose_in(colors: [f32x4; 4]) -> [f32x4; 3] {
+    let [a, b, c, d] = colors;
+    // [a0, b0, a1, b1] and [c0, d0, c1, d1], and likewise for the third and fourth components
+    let ab01 = simd_swizzle!(a, b, [0, 4, 1, 5]);
+    let cd01 = simd_swizzle!(c, d, [0, 4, 1, 5]);
+    let ab23 = simd_swizzle!(a, b, [2, 6, 3, 7]);
+    let cd23 = simd_swizzle!(c, d, [2, 6, 3, 7]);
+    [
+        simd_swizzle!(ab01, cd01, [0, 1, 4, 5]),
+        simd_swizzle!(ab01, cd01, [2, 3, 6, 7]),
+        simd_swizzle!(ab23, cd23, [0, 1, 4, 5]),
+    ]
+}
+
+#[inline(always)]
+fn transpose_out(components: [f32x4; 3]) -> [f32x4; 4] {
+    let [x, y, z] = components;
+    let w = f32x4::splat(0.0);
+    let xy01 = simd_swizzle!(x, y, [0, 4, 1, 5]);
+    let xy23 = simd_swizzle!(x, y, [2, 6, 3, 7]);
+    let zw01 = simd_swizzle!(z, w, [0, 4, 1, 5]);
+    let zw23 = simd_swizzle!(z, w, [2, 6, 3, 7]);
+    [
+        simd_swizzle!(xy01, zw01, [0, 1, 4, 5]),
+        simd_swizzle!(xy01, zw01, [2, 3, 6, 7]),
+        simd_swizzle!(xy23, zw23, [0, 1, 4, 5]),
+        simd_swizzle!(xy23, zw23, [2, 3, 6, 7]),
+    ]
+}
+
+/// Four `XYZColor`s stored as structure of arrays, lane i of each component belonging to the i'th color
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct XYZColorx4 {
+    pub x: f32x4,
+    pub y: f32x4,
+    pub z: f32x4,
+}
+
+impl XYZColorx4 {
+    pub const fn new(x: f32x4, y: f32x4, z: f32x4) -> Self {
+        XYZColorx4 { x, y, z }
+    }
+    pub fn splat(color: XYZColor) -> Self {
+        XYZColorx4::new(
+            f32x4::splat(color.x()),
+            f32x4::splat(color.y()),
+            f32x4::splat(color.z()),
+        )
+    }
+    pub fn from_colors(colors: [XYZColor; 4]) -> Self {
+        let [x, y, z] = transpose_in(colors.map(|c| c.0));
+        XYZColorx4 { x, y, z }
+    }
+    pub fn to_colors(&self) -> [XYZColor; 4] {
+        transpose_out([self.x, self.y, self.z]).map(XYZColor::from_raw)
+    }
+}
+
+/// Four `RGBColor`s stored as structure of arrays, lane i of each component belonging to the i'th color
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RGBColorx4 {
+    pub r: f32x4,
+    pub g: f32x4,
+    pub b: f32x4,
+}
+
+impl RGBColorx4 {
+    pub const fn new(r: f32x4, g: f32x4, b: f32x4) -> Self {
+        RGBColorx4 { r, g, b }
+    }
+    pub fn splat(color: RGBColor) -> Self {
+        RGBColorx4::new(
+            f32x4::splat(color.r()),
+            f32x4::splat(color.g()),
+            f32x4::splat(color.b()),
+        )
+    }
+    pub fn from_colors(colors: [RGBColor; 4]) -> Self {
+        let [r, g, b] = transpose_in(colors.map(|c| c.0));
+        RGBColorx4 { r, g, b }
+    }
+    pub fn to_colors(&self) -> [RGBColor; 4] {
+        transpose_out([self.r, self.g, self.b]).map(RGBColor::from_raw)
+    }
+}
+
+impl ColorMatrix3 {
+    /// applies self to four vectors at once, given as their x, y and z components
+    #[inline(always)]
+    pub fn apply_x4(&self, v: [f32x4; 3]) -> [f32x4; 3] {
+        let rows = self.rows();
+        rows.map(|[m0, m1, m2]| {
+            f32x4::splat(m0) * v[0] + f32x4::splat(m1) * v[1] + f32x4::splat(m2) * v[2]
+        })
+    }
+}
+
+impl ColorSpace {
+    pub fn from_xyz_x4(&self, color: XYZColorx4) -> RGBColorx4 {
+        let [r, g, b] = self
+            .xyz_to_rgb_transform()
+            .apply_x4([color.x, color.y, color.z]);
+        RGBColorx4 { r, g, b }
+    }
+    pub fn to_xyz_x4(&self, color: RGBColorx4) -> XYZColorx4 {
+        let [x, y, z] = self
+            .rgb_to_xyz_transform()
+            .apply_x4([color.r, color.g, color.b]);
+        XYZColorx4 { x, y, z }
+    }
+
+    /// converts a buffer of XYZ values to linear RGB in this space, four colors at a time.
+    /// equivalent to mapping `from_xyz` over `colors`.
+    pub fn from_xyz_batch(&self, colors: &[XYZColor]) -> Vec<RGBColor> {
+        let transform = self.xyz_to_rgb_transform();
+        let mut out = Vec::with_capacity(colors.len());
+        let mut chunks = colors.chunks_exact(4);
+        for chunk in &mut chunks {
+            let colors = [chunk[0], chunk[1], chunk[2], chunk[3]];
+            let rgb = transform.apply_x4(transpose_in(colors.map(|c| c.0)));
+            out.extend(transpose_out(rgb).iter().map(|v| RGBColor::from_raw(*v)));
+        }
+        out.extend(chunks.remainder().iter().map(|c| self.from_xyz(*c)));
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_batched_conversion() {
+        let colors: Vec<XYZColor> = (0..11)
+            .map(|i| {
+                let t = i as f32;
+                XYZColor::new(0.1 + 0.05 * t, 0.3 + 0.02 * t, 0.9 - 0.07 * t)
+            })
+            .collect();
+        let batch = XYZColorx4::from_colors([colors[0], colors[1], colors[2], colors[3]]);
+        assert_eq!(
+            batch.y,
+            f32x4::from_array([0, 1, 2, 3].map(|i| colors[i].y()))
+        );
+        for (a, b) in batch.to_colors().iter().zip(&colors) {
+            assert_eq!(a.0, b.0);
+        }
+
+        for space in [ColorSpace::SRGB, ColorSpace::ACEScg] {
+            let converted = space.from_xyz_batch(&colors);
+            assert_eq!(converted.len(), colors.len());
+            for (rgb, xyz) in converted.iter().zip(&colors) {
+                let expected = space.from_xyz(*xyz);
+                assert!(
+                    (rgb.0 - expected.0)
+                        .to_array()
+                        .iter()
+                        .all(|v| v.abs() < 1e-6),
+                    "{:?} {:?}",
+                    rgb,
+                    expected
+                );
+                // the unused lane stays zeroed, as from the scalar conversion
+                assert_eq!(rgb.0[3], 0.0);
+            }
+            // and back
+            let rgb =
+                RGBColorx4::from_colors([converted[4], converted[5], converted[6], converted[7]]);
+            for (a, b) in space.to_xyz_x4(rgb).to_colors().iter().zip(&colors[4..8]) {
+                assert!((a.0 - b.0).to_array().iter().all(|v| v.abs() < 1e-5));
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use super::XYZColor;
+use crate::misc::blackbody;
+use crate::spectral::{x_bar, y_bar, z_bar, BOUNDED_VISIBLE_RANGE};
+
+use std::collections::VecDeque;
+use std::simd::f32x4;
+use std::sync::OnceLock;
+
+// the planckian locus is tabulated uniformly in mireds (1e6 / kelvin),
+// in which the chromaticity of a blackbody varies much more evenly than it does in kelvin.
+const MIN_TEMPERATURE: f32 = 1000.0;
+const MAX_TEMPERATURE: f32 = 40000.0;
+const TABLE_SIZE: usize = 256;
+
+pub(super) const MAX_MIRED: f32 = 1e6 / MIN_TEMPERATURE;
+pub(super) const MIN_MIRED: f32 = 1e6 / MAX_TEMPERATURE;
+
+static PLANCKIAN_LOCUS: OnceLock<Vec<f32x4>> = OnceLock::new();
+
+/// integrates the color matching functions against a blackbody at `temperature` kelvin,
+/// normalized such that Y = 1. this is the slow path that the table and cache are built from.
+pub fn blackbody_xyz_integrated(temperature: f32) -> XYZColor {
+    let step_size = 1.0;
+    let iterations = (BOUNDED_VISIBLE_RANGE.span() / step_size) as usize;
+    let mut sum = f32x4::splat(0.0);
+    for i in 0..=iterations {
+        let lambda = BOUNDED_VISIBLE_RANGE.lower + i as f32 * step_size;
+        let angstroms = lambda * 10.0;
+        sum += f32x4::splat(blackbody(temperature, lambda))
+            * f32x4::from_array([x_bar(angstroms), y_bar(angstroms), z_bar(angstroms), 0.0]);
+    }
+    XYZColor::from_raw(sum / f32x4::splat(sum[1]))
+}
+
+pub(super) fn planckian_locus() -> &'static [f32x4] {
+    PLANCKIAN_LOCUS.get_or_init(|| {
+        (0..TABLE_SIZE)
+            .map(|i| {
+                let mired =
+                    MIN_MIRED + (MAX_MIRED - MIN_MIRED) * i as f32 / (TABLE_SIZE - 1) as f32;
+                blackbody_xyz_integrated(1e6 / mired).0
+            })
+            .collect()
+    })
+}
+
+/// the color of a blackbody at `temperature` kelvin, normalized such that Y = 1,
+/// interpolated from a precomputed table of the planckian locus.
+/// temperatures are clamped to [1000, 40000] kelvin. much cheaper than integrating a `Curve::Blackbody`,
+/// and suitable for interactive color previews.
+pub fn blackbody_xyz(temperature: f32) -> XYZColor {
+    let table = planckian_locus();
+    let mired = 1e6 / temperature.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE);
+    let t = (mired - MIN_MIRED) / (MAX_MIRED - MIN_MIRED) * (TABLE_SIZE - 1) as f32;
+    let index = (t as usize).min(TABLE_SIZE - 2);
+    let t = t - index as f32;
+    XYZColor::from_raw(table[index] + f32x4::splat(t) * (table[index + 1] - table[index]))
+}
+
+/// A small least recently used cache of exactly integrated blackbody colors,
+/// for when a handful of temperatures are evaluated repeatedly and the table interpolation error is undesirable.
+#[derive(Clone, Debug)]
+pub struct BlackbodyCache {
+    capacity: usize,
+    // most recently used entries are at the front
+    entries: VecDeque<(u32, XYZColor)>,
+}
+
+impl BlackbodyCache {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        BlackbodyCache {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+    /// returns the color of a blackbody at `temperature` kelvin, normalized such that Y = 1
+    pub fn get(&mut self, temperature: f32) -> XYZColor {
+        let key = temperature.to_bits();
+        if let Some(position) = self.entries.iter().position(|(k, _)| *k == key) {
+            let entry = self.entries.remove(position).unwrap();
+            self.entries.push_front(entry);
+            return entry.1;
+        }
+        let color = blackbody_xyz_integrated(temperature);
+        if self.entries.len() == self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front((key, color));
+        color
+    }
+}
+
+impl Default for BlackbodyCache {
+    fn default() -> Self {
+        BlackbodyCache::new(16)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_blackbody_xyz_matches_integration() {
+        for temperature in [1000.0, 1850.0, 2700.0, 5500.0, 6504.0, 12000.0, 40000.0] {
+            let fast = blackbody_xyz(temperature);
+            let exact = blackbody_xyz_integrated(temperature);
+            assert_eq!(fast.y(), 1.0);
+            assert!(
+                (fast.x() - exact.x()).abs() < 1e-3,
+                "{:?} {:?}",
+                fast,
+                exact
+            );
+            assert!(
+                (fast.z() - exact.z()).abs() < 1e-3,
+                "{:?} {:?}",
+                fast,
+                exact
+            );
+        }
+        // hotter blackbodies are bluer
+        assert!(blackbody_xyz(9000.0).z() > blackbody_xyz(3000.0).z());
+    }
+
+    #[test]
+    fn test_blackbody_cache() {
+        let mut cache = BlackbodyCache::new(2);
+        let a = cache.get(3000.0);
+        cache.get(4000.0);
+        // touching 3000 makes 4000 the least recently used entry
+        assert_eq!(cache.get(3000.0).0, a.0);
+        cache.get(5000.0);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.entries.iter().all(|(k, _)| *k != 4000.0f32.to_bits()));
+    }
+}
@@ -0,0 +1,189 @@
+use super::blackbody::{planckian_locus, MAX_MIRED, MIN_MIRED};
+use super::{blackbody_xyz, Chromaticity, XYZColor};
+
+// CIE 1960 uv and CIE 1976 u'v' are projective transforms of xy in which distances correspond more closely to perceived
+// color differences. u' = u and v' = 1.5 v, and Duv is conventionally measured in the 1960 uv diagram.
+
+/// converts CIE xy chromaticity to CIE 1976 u'v'
+pub fn xy_to_uv_prime((x, y): Chromaticity) -> Chromaticity {
+    let denominator = -2.0 * x + 12.0 * y + 3.0;
+    (4.0 * x / denominator, 9.0 * y / denominator)
+}
+
+/// converts CIE 1976 u'v' chromaticity to CIE xy
+pub fn uv_prime_to_xy((u, v): Chromaticity) -> Chromaticity {
+    let denominator = 6.0 * u - 16.0 * v + 12.0;
+    (9.0 * u / denominator, 4.0 * v / denominator)
+}
+
+impl XYZColor {
+    /// CIE xy chromaticity. black maps to (0, 0)
+    pub fn xy(&self) -> Chromaticity {
+        let sum = self.x() + self.y() + self.z();
+        if sum == 0.0 {
+            return (0.0, 0.0);
+        }
+        (self.x() / sum, self.y() / sum)
+    }
+    /// CIE 1976 u'v' chromaticity. black maps to (0, 0)
+    pub fn uv_prime(&self) -> Chromaticity {
+        let denominator = self.x() + 15.0 * self.y() + 3.0 * self.z();
+        if denominator == 0.0 {
+            return (0.0, 0.0);
+        }
+        (4.0 * self.x() / denominator, 9.0 * self.y() / denominator)
+    }
+}
+
+/// CIE 1960 uv chromaticity of a blackbody at `temperature` kelvin, interpolated from the tabulated planckian locus
+pub fn planckian_uv(temperature: f32) -> Chromaticity {
+    let (u, v) = blackbody_xyz(temperature).uv_prime();
+    (u, v / 1.5)
+}
+
+// finds the point on the tabulated planckian locus that is closest to `uv` (in CIE 1960 uv),
+// returning its temperature and the signed distance to it, positive above the locus (towards green).
+pub(crate) fn nearest_planckian((u, v): Chromaticity) -> (f32, f32) {
+    let table = planckian_locus();
+    let locus_uv = |i: usize| {
+        let (u, v) = XYZColor::from_raw(table[i]).uv_prime();
+        (u, v / 1.5)
+    };
+    let distance_squared = |(lu, lv): Chromaticity| (u - lu).powi(2) + (v - lv).powi(2);
+
+    let closest = (0..table.len())
+        .min_by(|a, b| distance_squared(locus_uv(*a)).total_cmp(&distance_squared(locus_uv(*b))))
+        .unwrap();
+
+    // project onto the segments adjacent to the closest entry, and keep the nearer projection
+    let mut best = (f32::INFINITY, 0.0, (0.0, 0.0));
+    for start in [closest.saturating_sub(1), closest.min(table.len() - 2)] {
+        let (u0, v0) = locus_uv(start);
+        let (u1, v1) = locus_uv(start + 1);
+        let (du, dv) = (u1 - u0, v1 - v0);
+        let t = (((u - u0) * du + (v - v0) * dv) / (du * du + dv * dv)).clamp(0.0, 1.0);
+        let projected = (u0 + t * du, v0 + t * dv);
+        let distance = distance_squared(projected);
+        if distance < best.0 {
+            best = (distance, start as f32 + t, projected);
+        }
+    }
+    let (distance_squared, index, projected) = best;
+    let mired = MIN_MIRED + (MAX_MIRED - MIN_MIRED) * index / (table.len() - 1) as f32;
+    let duv = distance_squared.sqrt().copysign(v - projected.1);
+    (1e6 / mired, duv)
+}
+
+/// signed distance of `color` from the planckian locus in the CIE 1960 uv diagram.
+/// positive values lie above the locus (greenish), negative values below it (pinkish).
+/// only meaningful for colors near the locus, with the locus tabulated over [1000, 40000] kelvin.
+pub fn duv(color: XYZColor) -> f32 {
+    cct_duv(color).1
+}
+
+/// the correlated color temperature of `color` in kelvin, i.e. the temperature of the blackbody whose chromaticity
+/// is nearest to it in the CIE 1960 uv diagram, along with its Duv.
+/// like Robertson's method, this measures distance perpendicular to the tabulated planckian locus,
+/// and temperatures are limited to [1000, 40000] kelvin.
+pub fn cct_duv(color: XYZColor) -> (f32, f32) {
+    let (u, v) = color.uv_prime();
+    nearest_planckian((u, v / 1.5))
+}
+
+/// the correlated color temperature of `color` in kelvin. see `cct_duv`
+pub fn cct_from_xyz(color: XYZColor) -> f32 {
+    cct_duv(color).0
+}
+
+/// McCamy's cubic approximation of the correlated color temperature from xy chromaticity.
+/// cheap, and within a couple of percent between roughly 2000 and 12500 kelvin near the locus,
+/// but it diverges quickly outside of that range.
+pub fn cct_mccamy(color: XYZColor) -> f32 {
+    let (x, y) = color.xy();
+    let n = (x - 0.3320) / (0.1858 - y);
+    ((449.0 * n + 3525.0) * n + 6823.3) * n + 5520.33
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_uv_prime() {
+        let d65 = (0.3127, 0.3290);
+        let (u, v) = xy_to_uv_prime(d65);
+        assert!(
+            (u - 0.1978).abs() < 1e-4 && (v - 0.4683).abs() < 1e-4,
+            "{} {}",
+            u,
+            v
+        );
+        let (x, y) = uv_prime_to_xy((u, v));
+        assert!((x - d65.0).abs() < 1e-6 && (y - d65.1).abs() < 1e-6);
+
+        let color = XYZColor::new(0.5, 0.4, 0.3);
+        let (u, v) = color.uv_prime();
+        let (expected_u, expected_v) = xy_to_uv_prime(color.xy());
+        assert!((u - expected_u).abs() < 1e-6 && (v - expected_v).abs() < 1e-6);
+        assert_eq!(XYZColor::ZERO.uv_prime(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_duv() {
+        // blackbodies lie on the locus
+        for temperature in [1500.0, 2856.0, 5000.0, 10000.0] {
+            let (found, distance) = nearest_planckian(planckian_uv(temperature));
+            assert!(distance.abs() < 1e-5, "{}", distance);
+            assert!(
+                (found - temperature).abs() < 1e-2 * temperature,
+                "{}",
+                found
+            );
+        }
+
+        // D65 is slightly above the locus, with a CCT of roughly 6504K
+        let d65 = XYZColor::new(0.3127 / 0.3290, 1.0, (1.0 - 0.3127 - 0.3290) / 0.3290);
+        let (temperature, distance) = nearest_planckian({
+            let (u, v) = d65.uv_prime();
+            (u, v / 1.5)
+        });
+        assert!((duv(d65) - 0.0032).abs() < 3e-4, "{}", duv(d65));
+        assert_eq!(distance, duv(d65));
+        assert!((temperature - 6504.0).abs() < 50.0, "{}", temperature);
+
+        // a magenta tinted white lies below the locus
+        let pinkish = XYZColor::new(1.0, 0.9, 1.0);
+        assert!(duv(pinkish) < 0.0);
+    }
+
+    #[test]
+    fn test_cct() {
+        // blackbody curves integrated at full resolution are recovered, and McCamy drifts by up to 2% at low temperatures
+        for temperature in [2000.0, 2856.0, 4000.0, 6500.0, 10000.0] {
+            let color = crate::curves::Curve::Blackbody {
+                temperature,
+                boost: 1.0,
+            }
+            .convert_to_xyz(crate::spectral::BOUNDED_VISIBLE_RANGE, 1.0, false);
+            let (cct, distance) = cct_duv(color);
+            assert!((cct - temperature).abs() < 5e-3 * temperature, "{}", cct);
+            assert!(distance.abs() < 5e-4, "{}", distance);
+            assert_eq!(cct_from_xyz(color), cct);
+            let approximate = cct_mccamy(color);
+            assert!(
+                (approximate - temperature).abs() < 2e-2 * temperature,
+                "{} {}",
+                approximate,
+                temperature
+            );
+        }
+        // D65 has a CCT of about 6504K, and both methods agree for it
+        let d65 = crate::color::white_point::to_xyz(crate::color::white_point::D65);
+        assert!((cct_from_xyz(d65) - 6504.0).abs() < 50.0);
+        assert!(
+            (cct_mccamy(d65) - 6504.0).abs() < 50.0,
+            "{}",
+            cct_mccamy(d65)
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use super::white_point::{D50, D65};
+use super::{AdaptationMethod, ChromaticAdaptation, ColorMatrix3, RGBColor, XYZColor};
+
+use std::simd::f32x4;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// CIE xy chromaticity coordinates
+pub type Chromaticity = (f32, f32);
+
+/// RGB working spaces, defined by the chromaticities of their primaries and white point.
+/// conversions are linear, i.e. no transfer function is applied.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ColorSpace {
+    /// ITU-R BT.709 primaries with a D65 white point, shared with sRGB
+    Rec709,
+    /// ITU-R BT.2020 primaries with a D65 white point
+    Rec2020,
+    /// DCI-P3 primaries with a D65 white point
+    DisplayP3,
+    /// DCI-P3 primaries with the greenish DCI white point, as used for digital cinema projection
+    DCIP3,
+    /// ROMM RGB, with primaries that cover nearly all surface colors and a D50 white point, as used for photo editing.
+    /// the blue and green primaries lie outside of the spectral locus
+    ProPhoto,
+    /// ACES AP1 primaries with the ACES white point (approximately D60)
+    ACEScg,
+    /// ACES2065-1, the ACES interchange space, with AP0 primaries that enclose the whole spectral locus and the ACES white point
+    ACES2065,
+}
+
+// matrices derived from the primaries and white points below, computed in exact arithmetic and rounded to f32.
+// rows of each matrix map to the rows of the output.
+
+const REC709_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4123908, 0.35758433, 0.1804808],
+    [0.212639, 0.71516865, 0.07219232],
+    [0.019330818, 0.11919478, 0.95053214],
+];
+const XYZ_TO_REC709: [[f32; 3]; 3] = [
+    [3.24097, -1.5373832, -0.49861076],
+    [-0.96924365, 1.8759675, 0.04155506],
+    [0.05563008, -0.20397696, 1.0569715],
+];
+const REC2020_TO_XYZ: [[f32; 3]; 3] = [
+    [0.63695806, 0.1446169, 0.16888097],
+    [0.2627002, 0.67799807, 0.059301715],
+    [0.0, 0.028072692, 1.0609851],
+];
+const XYZ_TO_REC2020: [[f32; 3]; 3] = [
+    [1.7166512, -0.35567078, -0.2533663],
+    [-0.6666843, 1.6164812, 0.015768547],
+    [0.017639857, -0.042770613, 0.94210315],
+];
+const DISPLAY_P3_TO_XYZ: [[f32; 3]; 3] = [
+    [0.48657095, 0.2656677, 0.19821729],
+    [0.22897457, 0.69173855, 0.07928691],
+    [0.0, 0.04511338, 1.0439444],
+];
+const XYZ_TO_DISPLAY_P3: [[f32; 3]; 3] = [
+    [2.493497, -0.9313836, -0.4027108],
+    [-0.829489, 1.7626641, 0.023624687],
+    [0.03584583, -0.07617239, 0.9568845],
+];
+const DCI_P3_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4451698, 0.27713442, 0.17228267],
+    [0.20949169, 0.7215952, 0.068913065],
+    [0.0, 0.04706056, 0.90735537],
+];
+const XYZ_TO_DCI_P3: [[f32; 3]; 3] = [
+    [2.725394, -1.018003, -0.4401632],
+    [-0.79516804, 1.6897321, 0.02264719],
+    [0.04124189, -0.08763902, 1.1009294],
+];
+const PROPHOTO_TO_XYZ: [[f32; 3]; 3] = [
+    [0.7977605, 0.13518584, 0.03134935],
+    [0.28807113, 0.7118432, 8.565396e-5],
+    [0.0, 0.0, 0.8251046],
+];
+const XYZ_TO_PROPHOTO: [[f32; 3]; 3] = [
+    [1.345799, -0.2555801, -0.051106285],
+    [-0.5446225, 1.5082327, 0.020536032],
+    [0.0, 0.0, 1.2119676],
+];
+const ACESCG_TO_XYZ: [[f32; 3]; 3] = [
+    [0.6624542, 0.1340042, 0.15618768],
+    [0.27222872, 0.67408174, 0.053689517],
+    [-0.0055746497, 0.0040607336, 1.0103391],
+];
+const XYZ_TO_ACESCG: [[f32; 3]; 3] = [
+    [1.6410234, -0.3248033, -0.2364247],
+    [-0.66366285, 1.6153315, 0.016756348],
+    [0.011721894, -0.008284442, 0.98839486],
+];
+
+const ACES2065_TO_XYZ: [[f32; 3]; 3] = [
+    [0.9525524, 0.0, 9.367863e-5],
+    [0.34396645, 0.7281661, -0.07213254],
+    [0.0, 0.0, 1.0088252],
+];
+const XYZ_TO_ACES2065: [[f32; 3]; 3] = [
+    [1.049811, 0.0, -9.748454e-5],
+    [-0.495903, 1.3733131, 0.09824003],
+    [0.0, 0.0, 0.991252],
+];
+
+// computes m * v, treating the first 3 lanes of v as a column vector
+#[inline(always)]
+pub(crate) fn apply_matrix(m: &[[f32; 3]; 3], v: f32x4) -> f32x4 {
+    ColorMatrix3::from_rows(*m).apply(v)
+}
+
+/// computes the matrix that maps linear RGB to XYZ, given the chromaticities of the red, green and blue primaries
+/// and of the white point. the white point is mapped to Y = 1.
+pub fn rgb_to_xyz_from_primaries(
+    primaries: [Chromaticity; 3],
+    white: Chromaticity,
+) -> [[f32; 3]; 3] {
+    let to_xyz = |(x, y): Chromaticity| {
+        let (x, y) = (x as f64, y as f64);
+        nalgebra::Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
+    };
+    let p = nalgebra::Matrix3::from_columns(&[
+        to_xyz(primaries[0]),
+        to_xyz(primaries[1]),
+        to_xyz(primaries[2]),
+    ]);
+    // the primaries are scaled such that they add up to the white point
+    let scale = p.try_inverse().expect("primaries must not be collinear") * to_xyz(white);
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (p[(i, j)] * scale[j]) as f32;
+        }
+    }
+    m
+}
+
+impl ColorSpace {
+    /// sRGB uses the Rec709 primaries and white point. see `SRGBColor` for its transfer function.
+    pub const SRGB: ColorSpace = ColorSpace::Rec709;
+    pub const ALL: [ColorSpace; 7] = [
+        ColorSpace::Rec709,
+        ColorSpace::Rec2020,
+        ColorSpace::DisplayP3,
+        ColorSpace::DCIP3,
+        ColorSpace::ProPhoto,
+        ColorSpace::ACEScg,
+        ColorSpace::ACES2065,
+    ];
+
+    /// chromaticities of the red, green and blue primaries
+    pub fn primaries(&self) -> [Chromaticity; 3] {
+        match self {
+            ColorSpace::Rec709 => [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06)],
+            ColorSpace::Rec2020 => [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+            ColorSpace::DisplayP3 | ColorSpace::DCIP3 => {
+                [(0.680, 0.320), (0.265, 0.690), (0.150, 0.060)]
+            }
+            ColorSpace::ProPhoto => [(0.7347, 0.2653), (0.1596, 0.8404), (0.0366, 0.0001)],
+            ColorSpace::ACEScg => [(0.713, 0.293), (0.165, 0.830), (0.128, 0.044)],
+            ColorSpace::ACES2065 => [(0.7347, 0.2653), (0.0, 1.0), (0.0001, -0.0770)],
+        }
+    }
+    pub fn white_point(&self) -> Chromaticity {
+        match self {
+            ColorSpace::Rec709 | ColorSpace::Rec2020 | ColorSpace::DisplayP3 => D65,
+            ColorSpace::DCIP3 => (0.314, 0.351),
+            ColorSpace::ProPhoto => D50,
+            ColorSpace::ACEScg | ColorSpace::ACES2065 => (0.32168, 0.33767),
+        }
+    }
+    pub fn rgb_to_xyz_matrix(&self) -> &'static [[f32; 3]; 3] {
+        match self {
+            ColorSpace::Rec709 => &REC709_TO_XYZ,
+            ColorSpace::Rec2020 => &REC2020_TO_XYZ,
+            ColorSpace::DisplayP3 => &DISPLAY_P3_TO_XYZ,
+            ColorSpace::DCIP3 => &DCI_P3_TO_XYZ,
+            ColorSpace::ProPhoto => &PROPHOTO_TO_XYZ,
+            ColorSpace::ACEScg => &ACESCG_TO_XYZ,
+            ColorSpace::ACES2065 => &ACES2065_TO_XYZ,
+        }
+    }
+    pub fn xyz_to_rgb_matrix(&self) -> &'static [[f32; 3]; 3] {
+        match self {
+            ColorSpace::Rec709 => &XYZ_TO_REC709,
+            ColorSpace::Rec2020 => &XYZ_TO_REC2020,
+            ColorSpace::DisplayP3 => &XYZ_TO_DISPLAY_P3,
+            ColorSpace::DCIP3 => &XYZ_TO_DCI_P3,
+            ColorSpace::ProPhoto => &XYZ_TO_PROPHOTO,
+            ColorSpace::ACEScg => &XYZ_TO_ACESCG,
+            ColorSpace::ACES2065 => &XYZ_TO_ACES2065,
+        }
+    }
+    /// `rgb_to_xyz_matrix` as a `ColorMatrix3`, for composing with other transforms
+    pub fn rgb_to_xyz_transform(&self) -> ColorMatrix3 {
+        ColorMatrix3::from_rows(*self.rgb_to_xyz_matrix())
+    }
+    pub fn xyz_to_rgb_transform(&self) -> ColorMatrix3 {
+        ColorMatrix3::from_rows(*self.xyz_to_rgb_matrix())
+    }
+    pub fn from_xyz(&self, color: XYZColor) -> RGBColor {
+        RGBColor::from_raw(apply_matrix(self.xyz_to_rgb_matrix(), color.0))
+    }
+    pub fn to_xyz(&self, color: RGBColor) -> XYZColor {
+        XYZColor::from_raw(apply_matrix(self.rgb_to_xyz_matrix(), color.0))
+    }
+    /// converts XYZ values relative to the white point `white` to linear RGB in this space,
+    /// adapting from `white` to the white point of this space with `method`
+    pub fn from_xyz_adapted(
+        &self,
+        color: XYZColor,
+        white: Chromaticity,
+        method: AdaptationMethod,
+    ) -> RGBColor {
+        self.from_xyz(ChromaticAdaptation::new(method, white, self.white_point()).adapt(color))
+    }
+    /// converts linear RGB in this space to XYZ values relative to the white point `white`,
+    /// adapting from the white point of this space to `white` with `method`
+    pub fn to_xyz_adapted(
+        &self,
+        color: RGBColor,
+        white: Chromaticity,
+        method: AdaptationMethod,
+    ) -> XYZColor {
+        ChromaticAdaptation::new(method, self.white_point(), white).adapt(self.to_xyz(color))
+    }
+    /// converts linear RGB values in this space to linear RGB values in `other`, through XYZ.
+    /// note that no chromatic adaptation is applied between differing white points.
+    pub fn convert(&self, color: RGBColor, other: ColorSpace) -> RGBColor {
+        other.from_xyz(self.to_xyz(color))
+    }
+    /// converts linear RGB values in this space to linear RGB values in `other`, adapting between their white points with `method`,
+    /// such that white in this space maps to white in `other`
+    pub fn convert_adapted(
+        &self,
+        color: RGBColor,
+        other: ColorSpace,
+        method: AdaptationMethod,
+    ) -> RGBColor {
+        let adaptation = ChromaticAdaptation::new(method, self.white_point(), other.white_point());
+        other.from_xyz(adaptation.adapt(self.to_xyz(color)))
+    }
+}
+
+impl XYZColor {
+    pub fn to_rgb(&self, space: ColorSpace) -> RGBColor {
+        space.from_xyz(*self)
+    }
+}
+
+impl RGBColor {
+    pub fn to_xyz(&self, space: ColorSpace) -> XYZColor {
+        space.to_xyz(*self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn max_difference(a: f32x4, b: f32x4) -> f32 {
+        (a - b)
+            .to_array()
+            .iter()
+            .fold(0.0, |acc, v| acc.max(v.abs()))
+    }
+
+    #[test]
+    fn test_matrices_match_primaries() {
+        for space in ColorSpace::ALL {
+            let derived = rgb_to_xyz_from_primaries(space.primaries(), space.white_point());
+            let table = space.rgb_to_xyz_matrix();
+            for i in 0..3 {
+                for j in 0..3 {
+                    assert!((derived[i][j] - table[i][j]).abs() < 1e-5, "{:?}", space);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_color_space_round_trip() {
+        let xyz = XYZColor::new(0.3, 0.5, 0.2);
+        for space in ColorSpace::ALL {
+            let round_trip = space.to_xyz(xyz.to_rgb(space));
+            assert!(max_difference(round_trip.0, xyz.0) < 1e-5, "{:?}", space);
+
+            // white maps to (1, 1, 1)
+            let (x, y) = space.white_point();
+            let white = XYZColor::new(x / y, 1.0, (1.0 - x - y) / y).to_rgb(space);
+            assert!(max_difference(white.0, f32x4::from_array([1.0, 1.0, 1.0, 0.0])) < 1e-5);
+        }
+        // white stays white between spaces with different white points only when adapting
+        let white = RGBColor::new(1.0, 1.0, 1.0);
+        let adapted = ColorSpace::Rec709.convert_adapted(
+            white,
+            ColorSpace::ACEScg,
+            AdaptationMethod::Bradford,
+        );
+        assert!(max_difference(adapted.0, white.0) < 1e-4, "{:?}", adapted);
+        let unadapted = ColorSpace::Rec709.convert(white, ColorSpace::ACEScg);
+        assert!(max_difference(unadapted.0, white.0) > 1e-2);
+        // rec709 green is inside of the rec2020 gamut
+        let green = ColorSpace::Rec709.convert(RGBColor::new(0.0, 1.0, 0.0), ColorSpace::Rec2020);
+        assert!(green.r() > 0.0 && green.g() > 0.0 && green.b() > 0.0);
+        // and every rec709 primary is inside of prophoto, while display p3 and dci p3 differ only in white point
+        for primary in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]] {
+            let [r, g, b] = primary;
+            let color = ColorSpace::Rec709.convert_adapted(
+                RGBColor::new(r, g, b),
+                ColorSpace::ProPhoto,
+                AdaptationMethod::Bradford,
+            );
+            assert!(
+                color.0.to_array()[..3].iter().all(|v| *v > -1e-4),
+                "{:?}",
+                color
+            );
+        }
+        assert_eq!(
+            ColorSpace::DCIP3.primaries(),
+            ColorSpace::DisplayP3.primaries()
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use super::{RGBColor, XYZColor};
+
+// exposure arithmetic for scene referred colors, in stops: each exposure value doubles or halves the light.
+// auto exposure follows Reinhard et al. (2002), "Photographic Tone Reproduction for Digital Images",
+// scaling the scene such that its log-average luminance lands on a key value, middle gray by default.
+
+/// the key that auto exposure maps the log-average luminance to, the reflectance of an 18% gray card
+pub const MIDDLE_GRAY: f32 = 0.18;
+// keeps the logarithm of black pixels finite
+const LOG_AVERAGE_DELTA: f32 = 1e-4;
+
+/// the factor 2^ev that exposes by `ev` stops
+pub fn exposure_scale(ev: f32) -> f32 {
+    ev.exp2()
+}
+
+impl XYZColor {
+    /// self scaled by 2^ev, i.e. brighter by `ev` stops
+    pub fn exposed(&self, ev: f32) -> XYZColor {
+        *self * exposure_scale(ev)
+    }
+}
+
+impl RGBColor {
+    /// self scaled by 2^ev, i.e. brighter by `ev` stops
+    pub fn exposed(&self, ev: f32) -> RGBColor {
+        *self * exposure_scale(ev)
+    }
+}
+
+/// the geometric mean of the luminance Y of `colors`, exp(mean(ln(delta + Y))) with a small delta for black pixels.
+/// less sensitive to small bright highlights than the arithmetic mean. returns 0 for an empty iterator.
+pub fn log_average_luminance(colors: impl IntoIterator<Item = XYZColor>) -> f32 {
+    let (sum, count) = colors
+        .into_iter()
+        .fold((0.0f64, 0usize), |(sum, count), color| {
+            let y = color.y().max(0.0);
+            (sum + ((LOG_AVERAGE_DELTA + y) as f64).ln(), count + 1)
+        });
+    if count == 0 {
+        return 0.0;
+    }
+    ((sum / count as f64).exp() as f32 - LOG_AVERAGE_DELTA).max(0.0)
+}
+
+/// the exposure in stops that maps the log-average luminance of `colors` to `key`, such as `MIDDLE_GRAY`.
+/// brighter scenes are typically given keys up to about 0.36, and darker scenes keys down to 0.09.
+/// returns 0 for empty or black images, leaving them unchanged.
+pub fn auto_exposure(colors: impl IntoIterator<Item = XYZColor>, key: f32) -> f32 {
+    let average = log_average_luminance(colors);
+    if average > 0.0 {
+        (key / average).log2()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exposure() {
+        let color = XYZColor::new(0.2, 0.4, 0.1);
+        assert_eq!(color.exposed(1.0).y(), 0.8);
+        assert_eq!(color.exposed(-2.0).x(), 0.05);
+        assert_eq!(
+            RGBColor::new(1.0, 0.5, 0.25).exposed(1.0),
+            RGBColor::new(2.0, 1.0, 0.5)
+        );
+
+        // a uniform image has its own luminance as the log-average
+        let uniform = vec![XYZColor::new(0.5, 0.6, 0.7); 16];
+        assert!((log_average_luminance(uniform.iter().copied()) - 0.6).abs() < 1e-5);
+        // the log-average is the geometric mean, which a single highlight barely moves
+        let mut image = vec![XYZColor::new(0.1, 0.1, 0.1); 99];
+        image.push(XYZColor::new(1000.0, 1000.0, 1000.0));
+        let average = log_average_luminance(image.iter().copied());
+        assert!(average < 0.2, "{}", average);
+
+        // auto exposure brings the average to the key, and is invariant to the overall brightness of the scene
+        let ev = auto_exposure(image.iter().copied(), MIDDLE_GRAY);
+        let exposed: Vec<XYZColor> = image.iter().map(|c| c.exposed(ev)).collect();
+        assert!((log_average_luminance(exposed.iter().copied()) - MIDDLE_GRAY).abs() < 1e-3);
+        let brighter = auto_exposure(image.iter().map(|c| *c * 8.0), MIDDLE_GRAY);
+        assert!((ev - brighter - 3.0).abs() < 1e-3);
+        assert_eq!(auto_exposure(std::iter::empty(), MIDDLE_GRAY), 0.0);
+        assert_eq!(auto_exposure(vec![XYZColor::BLACK; 4], MIDDLE_GRAY), 0.0);
+    }
+}
@@ -0,0 +1,165 @@
+use super::{ColorSpace, RGBColor};
+
+// mapping of out of gamut linear RGB, i.e. from saturated spectral renders, back into the [0, 1] cube of a color space.
+// negative components are colors more saturated than the primaries can produce,
+// and components above 1 are brighter than the display can show.
+
+/// whether every component of `color` lies within [0, 1]
+pub fn is_in_gamut(color: RGBColor) -> bool {
+    [color.r(), color.g(), color.b()]
+        .iter()
+        .all(|v| (0.0..=1.0).contains(v))
+}
+
+/// Strategies for bringing out of gamut colors into gamut
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GamutMapping {
+    /// clamps each component to [0, 1], which shifts the hue of saturated colors
+    Clip,
+    /// moves the color towards the gray of the same luminance until it fits, which keeps its hue.
+    /// colors brighter than white are desaturated towards white instead.
+    Desaturate,
+    /// smoothly compresses the distance from the gray axis beyond `threshold`, as in the ACES reference gamut
+    /// compression, then rolls off values above `threshold` towards 1. colors within the gamut but beyond the
+    /// threshold are compressed as well, which avoids the hard edges that clipping leaves in gradients.
+    SoftClip { threshold: f32 },
+}
+
+// the identity below `threshold`, and a curve with matching slope above it that approaches 1
+fn soft_clip(x: f32, threshold: f32) -> f32 {
+    if x <= threshold {
+        return x;
+    }
+    let headroom = 1.0 - threshold;
+    threshold + headroom * ((x - threshold) / headroom).tanh()
+}
+
+/// maps `color`, given in linear RGB in `space`, into gamut with `mapping`.
+/// returns the mapped color and whether `color` was out of gamut.
+pub fn map_to_gamut(color: RGBColor, space: ColorSpace, mapping: GamutMapping) -> (RGBColor, bool) {
+    let out_of_gamut = !is_in_gamut(color);
+    let mapped = match mapping {
+        GamutMapping::Clip => RGBColor::new(
+            color.r().clamp(0.0, 1.0),
+            color.g().clamp(0.0, 1.0),
+            color.b().clamp(0.0, 1.0),
+        ),
+        GamutMapping::Desaturate if out_of_gamut => {
+            let [kr, kg, kb] = space.rgb_to_xyz_matrix()[1];
+            let luminance = (kr * color.r() + kg * color.g() + kb * color.b()).clamp(0.0, 1.0);
+            // the largest fraction of the distance from gray that keeps every component within [0, 1]
+            let mut t = 1.0f32;
+            for v in [color.r(), color.g(), color.b()] {
+                let offset = v - luminance;
+                if v > 1.0 {
+                    t = t.min((1.0 - luminance) / offset);
+                } else if v < 0.0 {
+                    t = t.min(-luminance / offset);
+                }
+            }
+            let t = t.max(0.0);
+            let gray = RGBColor::new(luminance, luminance, luminance);
+            let mapped = gray + (color + gray * -1.0) * t;
+            // guards against rounding just outside of the cube
+            RGBColor::new(
+                mapped.r().clamp(0.0, 1.0),
+                mapped.g().clamp(0.0, 1.0),
+                mapped.b().clamp(0.0, 1.0),
+            )
+        }
+        GamutMapping::Desaturate => color,
+        GamutMapping::SoftClip { threshold } => {
+            let threshold = threshold.clamp(0.0, 0.999);
+            let achromatic = color.r().max(color.g()).max(color.b());
+            if achromatic <= 0.0 {
+                RGBColor::BLACK
+            } else {
+                // the relative distance of each component from the achromatic axis is 1 at 0 and larger for negatives,
+                // and compressing it below 1 makes every component positive
+                let compress = |v: f32| {
+                    let distance = (achromatic - v) / achromatic;
+                    achromatic * (1.0 - soft_clip(distance, threshold))
+                };
+                let compressed = RGBColor::new(
+                    compress(color.r()),
+                    compress(color.g()),
+                    compress(color.b()),
+                );
+                // then the brightness is rolled off, scaling all components to keep their ratios
+                compressed * (soft_clip(achromatic, threshold) / achromatic)
+            }
+        }
+    };
+    (mapped, out_of_gamut)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::HSVColor;
+
+    #[test]
+    fn test_gamut_mapping() {
+        let space = ColorSpace::SRGB;
+        let inside = RGBColor::new(0.2, 0.5, 0.3);
+        for mapping in [GamutMapping::Clip, GamutMapping::Desaturate] {
+            assert_eq!(map_to_gamut(inside, space, mapping), (inside, false));
+        }
+        let (soft, flag) = map_to_gamut(inside, space, GamutMapping::SoftClip { threshold: 0.8 });
+        assert!(!flag);
+        assert!((soft.0 - inside.0)
+            .to_array()
+            .iter()
+            .all(|v| v.abs() < 1e-6));
+
+        // saturated colors, as rec2020 primaries converted to srgb, and overexposed ones
+        let outside = [
+            ColorSpace::Rec2020.convert(RGBColor::new(0.0, 0.9, 0.1), space),
+            ColorSpace::Rec2020.convert(RGBColor::new(0.8, 0.05, 0.0), space),
+            RGBColor::new(1.4, 0.6, 0.2),
+            RGBColor::new(3.0, 3.0, 2.5),
+            RGBColor::new(-0.2, -0.1, 0.3),
+        ];
+        for color in outside {
+            assert!(!is_in_gamut(color));
+            for mapping in [
+                GamutMapping::Clip,
+                GamutMapping::Desaturate,
+                GamutMapping::SoftClip { threshold: 0.8 },
+            ] {
+                let (mapped, flag) = map_to_gamut(color, space, mapping);
+                assert!(flag);
+                assert!(
+                    is_in_gamut(mapped),
+                    "{:?} {:?} {:?}",
+                    mapping,
+                    color,
+                    mapped
+                );
+            }
+        }
+
+        // desaturation keeps the hue and, within the range of the display, the luminance
+        let color = outside[0];
+        let (mapped, _) = map_to_gamut(color, space, GamutMapping::Desaturate);
+        let luminance = |c: RGBColor| {
+            let [kr, kg, kb] = space.rgb_to_xyz_matrix()[1];
+            kr * c.r() + kg * c.g() + kb * c.b()
+        };
+        assert!((luminance(mapped) - luminance(color)).abs() < 1e-5);
+        // the distances from gray shrink in proportion, which keeps the hue, unlike clipping
+        let hue = HSVColor::from(color).hue;
+        assert!((HSVColor::from(mapped).hue - hue).abs() < 1e-2);
+        let (clipped, _) = map_to_gamut(color, space, GamutMapping::Clip);
+        assert!((HSVColor::from(clipped).hue - hue).abs() > 1.0);
+
+        // the soft clip is continuous at the threshold, and monotonic up to 1 above it
+        assert!((soft_clip(0.8 + 1e-4, 0.8) - 0.8).abs() < 2e-4);
+        let mut previous = 0.0;
+        for i in 0..100 {
+            let v = soft_clip(i as f32 * 0.1, 0.8);
+            assert!(v >= previous && v <= 1.0);
+            previous = v;
+        }
+    }
+}
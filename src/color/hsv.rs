@@ -0,0 +1,209 @@
+use super::RGBColor;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// cylindrical remappings of the RGB cube, for picking and generating colors by hue.
+// they are applied to the RGB values as given, without any transfer function, and aren't perceptually uniform.
+// hue is in degrees, in [0, 360). values above 1 are allowed and scale value and lightness accordingly,
+// but negative (out of gamut) components don't have a meaningful hue.
+
+// the hue of an rgb color in degrees, along with its largest and smallest components
+fn hue_max_min(color: RGBColor) -> (f32, f32, f32) {
+    let (r, g, b) = (color.r(), color.g(), color.b());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    if chroma <= 0.0 {
+        return (0.0, max, min);
+    }
+    let sector = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    ((60.0 * sector).rem_euclid(360.0), max, min)
+}
+
+// the fully saturated color of the given chroma at `hue`, offset by `min`
+fn from_hue_chroma(hue: f32, chroma: f32, min: f32) -> RGBColor {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    RGBColor::new(r + min, g + min, b + min)
+}
+
+/// Hue, saturation and value, where value is the largest RGB component
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct HSVColor {
+    pub hue: f32,
+    pub saturation: f32,
+    pub value: f32,
+}
+
+impl HSVColor {
+    pub const fn new(hue: f32, saturation: f32, value: f32) -> Self {
+        HSVColor {
+            hue,
+            saturation,
+            value,
+        }
+    }
+}
+
+impl From<RGBColor> for HSVColor {
+    fn from(color: RGBColor) -> Self {
+        let (hue, max, min) = hue_max_min(color);
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        HSVColor::new(hue, saturation, max)
+    }
+}
+
+impl From<HSVColor> for RGBColor {
+    fn from(color: HSVColor) -> Self {
+        let chroma = color.value * color.saturation;
+        from_hue_chroma(color.hue, chroma, color.value - chroma)
+    }
+}
+
+/// Hue, saturation and lightness, where lightness is the mean of the largest and smallest RGB components
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct HSLColor {
+    pub hue: f32,
+    pub saturation: f32,
+    pub lightness: f32,
+}
+
+impl HSLColor {
+    pub const fn new(hue: f32, saturation: f32, lightness: f32) -> Self {
+        HSLColor {
+            hue,
+            saturation,
+            lightness,
+        }
+    }
+}
+
+impl From<RGBColor> for HSLColor {
+    fn from(color: RGBColor) -> Self {
+        let (hue, max, min) = hue_max_min(color);
+        let lightness = (max + min) / 2.0;
+        let denominator = 1.0 - (2.0 * lightness - 1.0).abs();
+        let saturation = if denominator > 0.0 {
+            (max - min) / denominator
+        } else {
+            0.0
+        };
+        HSLColor::new(hue, saturation, lightness)
+    }
+}
+
+impl From<HSLColor> for RGBColor {
+    fn from(color: HSLColor) -> Self {
+        let chroma = (1.0 - (2.0 * color.lightness - 1.0).abs()) * color.saturation;
+        from_hue_chroma(color.hue, chroma, color.lightness - chroma / 2.0)
+    }
+}
+
+impl From<HSVColor> for HSLColor {
+    fn from(color: HSVColor) -> Self {
+        HSLColor::from(RGBColor::from(color))
+    }
+}
+
+impl From<HSLColor> for HSVColor {
+    fn from(color: HSLColor) -> Self {
+        HSVColor::from(RGBColor::from(color))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::simd::num::SimdFloat;
+
+    #[test]
+    fn test_hsv_hsl() {
+        let cases = [
+            (
+                RGBColor::new(1.0, 0.0, 0.0),
+                (0.0, 1.0, 1.0),
+                (0.0, 1.0, 0.5),
+            ),
+            (
+                RGBColor::new(0.0, 0.5, 0.0),
+                (120.0, 1.0, 0.5),
+                (120.0, 1.0, 0.25),
+            ),
+            (
+                RGBColor::new(0.5, 0.5, 1.0),
+                (240.0, 0.5, 1.0),
+                (240.0, 1.0, 0.75),
+            ),
+            (
+                RGBColor::new(1.0, 0.0, 1.0),
+                (300.0, 1.0, 1.0),
+                (300.0, 1.0, 0.5),
+            ),
+            (
+                RGBColor::new(0.75, 0.75, 0.75),
+                (0.0, 0.0, 0.75),
+                (0.0, 0.0, 0.75),
+            ),
+            (RGBColor::BLACK, (0.0, 0.0, 0.0), (0.0, 0.0, 0.0)),
+        ];
+        for (rgb, (h, s, v), (hl, sl, l)) in cases {
+            assert_eq!(HSVColor::from(rgb), HSVColor::new(h, s, v));
+            assert_eq!(HSLColor::from(rgb), HSLColor::new(hl, sl, l));
+            assert_eq!(RGBColor::from(HSVColor::new(h, s, v)), rgb);
+            assert_eq!(RGBColor::from(HSLColor::new(hl, sl, l)), rgb);
+        }
+
+        // round trips over a grid of colors, including hdr values above 1 for HSV
+        for i in 0..125 {
+            let rgb = RGBColor::new(
+                (i % 5) as f32 / 4.0,
+                (i / 5 % 5) as f32 / 4.0,
+                (i / 25) as f32 / 4.0,
+            );
+            for (color, scale) in [(rgb, 1.0), (rgb * 3.0, 3.0)] {
+                let back = RGBColor::from(HSVColor::from(color));
+                assert!(
+                    (back.0 - color.0)
+                        .abs()
+                        .to_array()
+                        .iter()
+                        .all(|e| *e < 1e-5 * scale),
+                    "{:?}",
+                    color
+                );
+            }
+            let back = RGBColor::from(HSLColor::from(rgb));
+            assert!(
+                (back.0 - rgb.0).abs().to_array().iter().all(|e| *e < 1e-5),
+                "{:?}",
+                rgb
+            );
+            let hsv = HSVColor::from(rgb);
+            let through_hsl = HSVColor::from(HSLColor::from(hsv));
+            assert!((through_hsl.value - hsv.value).abs() < 1e-5);
+        }
+        // hues wrap around
+        assert_eq!(
+            RGBColor::from(HSVColor::new(-120.0, 1.0, 1.0)),
+            RGBColor::new(0.0, 0.0, 1.0)
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use super::white_point::to_xyz as white_xyz;
+use super::{Chromaticity, ColorSpace, XYZColor};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// CIE 1976 L*a*b* and L*u*v*, which are approximately perceptually uniform relative to a reference white.
+// L* is 0 for black and 100 for the reference white. XYZ is expected on the scale where the reference white has Y = 1.
+// the From impls use D65, which is the white point of the sRGB and Rec2020 color spaces.
+
+const EPSILON: f32 = 216.0 / 24389.0;
+const KAPPA: f32 = 24389.0 / 27.0;
+
+// the cube root, with a linear segment near zero
+fn lab_f(t: f32) -> f32 {
+    if t > EPSILON {
+        t.cbrt()
+    } else {
+        (KAPPA * t + 16.0) / 116.0
+    }
+}
+
+fn lab_f_inverse(f: f32) -> f32 {
+    let t = f * f * f;
+    if t > EPSILON {
+        t
+    } else {
+        (116.0 * f - 16.0) / KAPPA
+    }
+}
+
+fn lightness(relative_y: f32) -> f32 {
+    116.0 * lab_f(relative_y) - 16.0
+}
+
+fn relative_luminance(lightness: f32) -> f32 {
+    lab_f_inverse((lightness + 16.0) / 116.0)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LabColor {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+impl LabColor {
+    pub const fn new(l: f32, a: f32, b: f32) -> Self {
+        LabColor { l, a, b }
+    }
+
+    pub fn from_xyz(color: XYZColor, white: Chromaticity) -> Self {
+        let white = white_xyz(white);
+        let (fx, fy, fz) = (
+            lab_f(color.x() / white.x()),
+            lab_f(color.y() / white.y()),
+            lab_f(color.z() / white.z()),
+        );
+        LabColor::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    pub fn to_xyz(&self, white: Chromaticity) -> XYZColor {
+        let white = white_xyz(white);
+        let fy = (self.l + 16.0) / 116.0;
+        let (fx, fz) = (fy + self.a / 500.0, fy - self.b / 200.0);
+        XYZColor::new(
+            white.x() * lab_f_inverse(fx),
+            white.y() * relative_luminance(self.l),
+            white.z() * lab_f_inverse(fz),
+        )
+    }
+
+    pub fn chroma(&self) -> f32 {
+        self.a.hypot(self.b)
+    }
+
+    /// hue angle in radians, in [-pi, pi]
+    pub fn hue(&self) -> f32 {
+        self.b.atan2(self.a)
+    }
+
+    /// the CIE 1976 color difference, which is the euclidean distance in L*a*b*
+    pub fn delta_e_76(&self, other: &LabColor) -> f32 {
+        ((self.l - other.l).powi(2) + (self.a - other.a).powi(2) + (self.b - other.b).powi(2))
+            .sqrt()
+    }
+}
+
+impl From<XYZColor> for LabColor {
+    fn from(color: XYZColor) -> Self {
+        LabColor::from_xyz(color, ColorSpace::SRGB.white_point())
+    }
+}
+
+impl From<LabColor> for XYZColor {
+    fn from(color: LabColor) -> Self {
+        color.to_xyz(ColorSpace::SRGB.white_point())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LuvColor {
+    pub l: f32,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl LuvColor {
+    pub const fn new(l: f32, u: f32, v: f32) -> Self {
+        LuvColor { l, u, v }
+    }
+
+    pub fn from_xyz(color: XYZColor, white: Chromaticity) -> Self {
+        let (white_u, white_v) = white_xyz(white).uv_prime();
+        let l = lightness(color.y());
+        if l <= 0.0 {
+            return LuvColor::new(0.0, 0.0, 0.0);
+        }
+        let (u, v) = color.uv_prime();
+        LuvColor::new(l, 13.0 * l * (u - white_u), 13.0 * l * (v - white_v))
+    }
+
+    pub fn to_xyz(&self, white: Chromaticity) -> XYZColor {
+        if self.l <= 0.0 {
+            return XYZColor::BLACK;
+        }
+        let (white_u, white_v) = white_xyz(white).uv_prime();
+        let u = self.u / (13.0 * self.l) + white_u;
+        let v = self.v / (13.0 * self.l) + white_v;
+        let y = relative_luminance(self.l);
+        XYZColor::new(
+            y * 9.0 * u / (4.0 * v),
+            y,
+            y * (12.0 - 3.0 * u - 20.0 * v) / (4.0 * v),
+        )
+    }
+
+    pub fn chroma(&self) -> f32 {
+        self.u.hypot(self.v)
+    }
+
+    /// hue angle in radians, in [-pi, pi]
+    pub fn hue(&self) -> f32 {
+        self.v.atan2(self.u)
+    }
+}
+
+impl From<XYZColor> for LuvColor {
+    fn from(color: XYZColor) -> Self {
+        LuvColor::from_xyz(color, ColorSpace::SRGB.white_point())
+    }
+}
+
+impl From<LuvColor> for XYZColor {
+    fn from(color: LuvColor) -> Self {
+        color.to_xyz(ColorSpace::SRGB.white_point())
+    }
+}
+
+/// the CIEDE2000 color difference between two L*a*b* colors, with unit weights.
+/// a difference of about 1 is the smallest that is noticeable side by side.
+pub fn delta_e_2000(first: LabColor, second: LabColor) -> f32 {
+    // following Sharma, Wu and Dalal, "The CIEDE2000 Color-Difference Formula: Implementation Notes"
+    let (l1, a1, b1) = (first.l as f64, first.a as f64, first.b as f64);
+    let (l2, a2, b2) = (second.l as f64, second.a as f64, second.b as f64);
+    let pow7 = |c: f64| c.powi(7) / (c.powi(7) + 25f64.powi(7));
+
+    let mean_chroma = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - pow7(mean_chroma).sqrt());
+    let (a1, a2) = ((1.0 + g) * a1, (1.0 + g) * a2);
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let hue = |a: f64, b: f64| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let (h1, h2) = (hue(a1, b1), hue(a2, b2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let achromatic = c1 * c2 == 0.0;
+    let delta_h = if achromatic {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h.to_radians() / 2.0).sin();
+
+    let mean_l = (l1 + l2) / 2.0;
+    let mean_c = (c1 + c2) / 2.0;
+    let mean_h = if achromatic {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t =
+        1.0 - 0.17 * cos(mean_h - 30.0) + 0.24 * cos(2.0 * mean_h) + 0.32 * cos(3.0 * mean_h + 6.0)
+            - 0.20 * cos(4.0 * mean_h - 63.0);
+    let delta_theta = 30.0 * (-((mean_h - 275.0) / 25.0).powi(2)).exp();
+    let rc = 2.0 * pow7(mean_c).sqrt();
+    let sl = 1.0 + 0.015 * (mean_l - 50.0).powi(2) / (20.0 + (mean_l - 50.0).powi(2)).sqrt();
+    let sc = 1.0 + 0.045 * mean_c;
+    let sh = 1.0 + 0.015 * mean_c * t;
+    let rt = -(2.0 * delta_theta).to_radians().sin() * rc;
+
+    let (l, c, h) = (delta_l / sl, delta_c / sc, delta_h / sh);
+    (l * l + c * c + h * h + rt * c * h).sqrt() as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lab_luv_and_delta_e() {
+        let d65 = ColorSpace::SRGB.white_point();
+        let d50 = crate::color::white_point::D50;
+
+        // the reference white has L* = 100 and no chroma, whatever it is
+        for white in [d65, d50] {
+            let lab = LabColor::from_xyz(white_xyz(white), white);
+            assert!(
+                (lab.l - 100.0).abs() < 1e-3 && lab.chroma() < 1e-3,
+                "{:?}",
+                lab
+            );
+            let luv = LuvColor::from_xyz(white_xyz(white), white);
+            assert!(
+                (luv.l - 100.0).abs() < 1e-3 && luv.chroma() < 1e-3,
+                "{:?}",
+                luv
+            );
+        }
+        // 18% grey is close to L* = 50, and the linear segment near black is continuous with the cube root
+        assert!((LabColor::from(XYZColor::new(0.18, 0.18, 0.18)).l - 49.496).abs() < 1e-2);
+        assert!((lightness(EPSILON) - lightness(EPSILON * (1.0 + 1e-5))).abs() < 1e-3);
+        assert_eq!(
+            LuvColor::from(XYZColor::BLACK),
+            LuvColor::new(0.0, 0.0, 0.0)
+        );
+
+        // sRGB red in L*a*b*, and round trips through both spaces
+        let red = ColorSpace::SRGB.to_xyz(crate::color::RGBColor::new(1.0, 0.0, 0.0));
+        let lab = LabColor::from(red);
+        for (value, expected) in [(lab.l, 53.24), (lab.a, 80.09), (lab.b, 67.20)] {
+            assert!((value - expected).abs() < 0.05, "{:?}", lab);
+        }
+        for color in [
+            red,
+            XYZColor::new(0.2, 0.3, 0.4),
+            XYZColor::new(0.001, 0.002, 0.0005),
+        ] {
+            for white in [d65, d50] {
+                let lab = LabColor::from_xyz(color, white).to_xyz(white);
+                let luv = LuvColor::from_xyz(color, white).to_xyz(white);
+                for round_trip in [lab, luv] {
+                    let error = (round_trip.0 - color.0).to_array();
+                    assert!(error.iter().all(|e| e.abs() < 1e-5), "{:?}", round_trip);
+                }
+            }
+        }
+
+        // pairs from the test data of Sharma, Wu and Dalal
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.49, -0.001), (50.0, -2.49, 0.0011), 7.2195),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            (
+                (60.2574, -34.0099, 36.2677),
+                (60.4626, -34.1751, 39.4387),
+                1.2644,
+            ),
+            (
+                (22.7233, 20.0904, -46.694),
+                (23.0331, 14.973, -42.5619),
+                2.0373,
+            ),
+            ((2.0776, 0.0795, -1.135), (0.9033, -0.0636, -0.5514), 0.9082),
+        ];
+        for ((l1, a1, b1), (l2, a2, b2), expected) in pairs {
+            let (first, second) = (LabColor::new(l1, a1, b1), LabColor::new(l2, a2, b2));
+            let difference = delta_e_2000(first, second);
+            assert!(
+                (difference - expected).abs() < 1e-3,
+                "{} {}",
+                difference,
+                expected
+            );
+            assert!((delta_e_2000(second, first) - expected).abs() < 1e-3);
+        }
+        assert_eq!(delta_e_2000(lab, lab), 0.0);
+        assert!(
+            (LabColor::new(50.0, 3.0, 4.0).delta_e_76(&LabColor::new(50.0, 0.0, 0.0)) - 5.0).abs()
+                < 1e-6
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use super::white_point::to_xyz as white_xyz;
+use super::{AdaptationMethod, Chromaticity, ColorMatrix3, XYZColor};
+
+use std::simd::f32x4;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// LMS is the response of the long, medium and short wavelength cones, or of sharpened variants of them.
+// each `AdaptationMethod` defines one through its cone response matrix: `VonKries` is the Hunt-Pointer-Estevez
+// space of physiological cone fundamentals, while `CAT02` and `Bradford` are spectrally sharpened spaces,
+// whose narrower responses make von Kries scaling in them a better model of adaptation.
+
+impl AdaptationMethod {
+    /// the transform from XYZ to the LMS space of this method
+    pub fn xyz_to_lms(&self) -> ColorMatrix3 {
+        ColorMatrix3::from_rows(self.cone_response_matrix())
+    }
+
+    pub fn lms_to_xyz(&self) -> ColorMatrix3 {
+        self.xyz_to_lms()
+            .inverse()
+            .expect("cone response matrices are invertible")
+    }
+}
+
+/// A color as the responses of the long, medium and short wavelength cones, in the space of an `AdaptationMethod`.
+/// the space isn't stored, so colors should be converted back with the same method they were converted with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct LMSColor {
+    pub l: f32,
+    pub m: f32,
+    pub s: f32,
+}
+
+impl LMSColor {
+    pub const fn new(l: f32, m: f32, s: f32) -> Self {
+        LMSColor { l, m, s }
+    }
+
+    pub fn from_xyz(color: XYZColor, method: AdaptationMethod) -> Self {
+        let [l, m, s, _] = method.xyz_to_lms().apply(color.0).to_array();
+        LMSColor::new(l, m, s)
+    }
+
+    /// `from_xyz` with the Hunt-Pointer-Estevez cone fundamentals
+    pub fn from_xyz_hpe(color: XYZColor) -> Self {
+        LMSColor::from_xyz(color, AdaptationMethod::VonKries)
+    }
+
+    /// `from_xyz` with the sharpened space of CIECAM02
+    pub fn from_xyz_cat02(color: XYZColor) -> Self {
+        LMSColor::from_xyz(color, AdaptationMethod::CAT02)
+    }
+
+    /// the cone response to the white with chromaticity `white` and Y = 1
+    pub fn from_white(white: Chromaticity, method: AdaptationMethod) -> Self {
+        LMSColor::from_xyz(white_xyz(white), method)
+    }
+
+    pub fn to_xyz(&self, method: AdaptationMethod) -> XYZColor {
+        XYZColor::from_raw(
+            method
+                .lms_to_xyz()
+                .apply(f32x4::from_array([self.l, self.m, self.s, 0.0])),
+        )
+    }
+
+    /// von Kries adaptation, scaling each response by the ratio of the target and source white responses
+    pub fn adapted(&self, source_white: LMSColor, target_white: LMSColor) -> Self {
+        LMSColor::new(
+            self.l * target_white.l / source_white.l,
+            self.m * target_white.m / source_white.m,
+            self.s * target_white.s / source_white.s,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::white_point;
+    use crate::color::ChromaticAdaptation;
+
+    #[test]
+    fn test_lms() {
+        let color = XYZColor::new(0.3, 0.4, 0.2);
+        // the rows of the CAT02 matrix sum to 1, so the equal energy white has equal responses
+        let equal_energy = LMSColor::from_xyz_cat02(XYZColor::new(1.0, 1.0, 1.0));
+        for v in [equal_energy.l, equal_energy.m, equal_energy.s] {
+            assert!((v - 1.0).abs() < 1e-6);
+        }
+        // hunt-pointer-estevez has no contribution of X and Y to the s cone
+        let hpe = LMSColor::from_xyz_hpe(color);
+        assert!((hpe.s - 0.91822 * 0.2).abs() < 1e-6);
+        assert!((hpe.l - (0.40024 * 0.3 + 0.7076 * 0.4 - 0.08081 * 0.2)).abs() < 1e-6);
+
+        for method in [AdaptationMethod::VonKries, AdaptationMethod::CAT02] {
+            let round_trip = LMSColor::from_xyz(color, method).to_xyz(method);
+            assert!((round_trip.0 - color.0)
+                .to_array()
+                .iter()
+                .all(|v| v.abs() < 1e-6));
+
+            // scaling in LMS is the chromatic adaptation of that method
+            let (d65, d50) = (white_point::D65, white_point::D50);
+            let adapted = LMSColor::from_xyz(color, method)
+                .adapted(
+                    LMSColor::from_white(d65, method),
+                    LMSColor::from_white(d50, method),
+                )
+                .to_xyz(method);
+            let expected = ChromaticAdaptation::new(method, d65, d50).adapt(color);
+            assert!((adapted.0 - expected.0)
+                .to_array()
+                .iter()
+                .all(|v| v.abs() < 1e-5));
+        }
+    }
+}
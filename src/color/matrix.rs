@@ -0,0 +1,175 @@
+use super::{RGBColor, XYZColor};
+use crate::error::Result;
+use crate::transform::matrix3::Matrix3x3;
+use crate::vec::Vec3;
+
+use std::ops::Mul;
+use std::simd::f32x4;
+
+/// A 3x3 matrix acting on tristimulus values, i.e. conversions between color spaces, chromatic adaptations,
+/// or camera and display transforms. a newtype over `Matrix3x3`, which it delegates its arithmetic to,
+/// built from the rows of color matrices as they're usually published and applied to colors rather than vectors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorMatrix3(pub Matrix3x3);
+
+impl ColorMatrix3 {
+    pub const IDENTITY: ColorMatrix3 = ColorMatrix3(Matrix3x3::IDENTITY);
+
+    pub const fn from_rows(rows: [[f32; 3]; 3]) -> Self {
+        ColorMatrix3::from_columns([
+            [rows[0][0], rows[1][0], rows[2][0]],
+            [rows[0][1], rows[1][1], rows[2][1]],
+            [rows[0][2], rows[1][2], rows[2][2]],
+        ])
+    }
+
+    /// the matrix with `columns` as its columns, i.e. the images of the three unit vectors
+    pub const fn from_columns(columns: [[f32; 3]; 3]) -> Self {
+        let [c0, c1, c2] = columns;
+        ColorMatrix3(Matrix3x3::from_columns(
+            Vec3::new(c0[0], c0[1], c0[2]),
+            Vec3::new(c1[0], c1[1], c1[2]),
+            Vec3::new(c2[0], c2[1], c2[2]),
+        ))
+    }
+
+    /// scales each component independently, i.e. for white balance gains
+    pub const fn from_diagonal(diagonal: [f32; 3]) -> Self {
+        ColorMatrix3::from_rows([
+            [diagonal[0], 0.0, 0.0],
+            [0.0, diagonal[1], 0.0],
+            [0.0, 0.0, diagonal[2]],
+        ])
+    }
+
+    pub fn rows(&self) -> [[f32; 3]; 3] {
+        [0, 1, 2].map(|i| {
+            let row = self.0.row(i);
+            [row.x(), row.y(), row.z()]
+        })
+    }
+
+    pub fn transpose(&self) -> Self {
+        ColorMatrix3(self.0.transpose())
+    }
+
+    /// computes self * v, treating the first 3 lanes of v as a column vector. the fourth lane of the result is 0.
+    #[inline(always)]
+    pub fn apply(&self, v: f32x4) -> f32x4 {
+        (self.0 * Vec3(v)).0
+    }
+
+    pub fn apply_xyz(&self, color: XYZColor) -> XYZColor {
+        XYZColor::from_raw(self.apply(color.0))
+    }
+
+    pub fn apply_rgb(&self, color: RGBColor) -> RGBColor {
+        RGBColor::from_raw(self.apply(color.0))
+    }
+
+    /// the transform that applies `self` and then `next`, i.e. the product next * self
+    pub fn then(&self, next: ColorMatrix3) -> Self {
+        next * *self
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.0.determinant()
+    }
+
+    /// the inverse transform, see `Matrix3x3::inverse`
+    pub fn inverse(&self) -> Result<Self> {
+        self.0.inverse().map(ColorMatrix3)
+    }
+}
+
+impl From<Matrix3x3> for ColorMatrix3 {
+    fn from(m: Matrix3x3) -> Self {
+        ColorMatrix3(m)
+    }
+}
+
+impl From<[[f32; 3]; 3]> for ColorMatrix3 {
+    fn from(rows: [[f32; 3]; 3]) -> Self {
+        ColorMatrix3::from_rows(rows)
+    }
+}
+
+impl From<ColorMatrix3> for [[f32; 3]; 3] {
+    fn from(m: ColorMatrix3) -> Self {
+        m.rows()
+    }
+}
+
+impl Mul for ColorMatrix3 {
+    type Output = ColorMatrix3;
+    fn mul(self, other: ColorMatrix3) -> ColorMatrix3 {
+        ColorMatrix3(self.0 * other.0)
+    }
+}
+
+impl Mul<XYZColor> for ColorMatrix3 {
+    type Output = XYZColor;
+    fn mul(self, color: XYZColor) -> XYZColor {
+        self.apply_xyz(color)
+    }
+}
+
+impl Mul<RGBColor> for ColorMatrix3 {
+    type Output = RGBColor;
+    fn mul(self, color: RGBColor) -> RGBColor {
+        self.apply_rgb(color)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::ColorSpace;
+    use crate::error::MathError;
+
+    #[test]
+    fn test_color_matrix() {
+        let to_xyz = ColorMatrix3::from(*ColorSpace::Rec2020.rgb_to_xyz_matrix());
+        let to_rgb = to_xyz.inverse().unwrap();
+        let color = RGBColor::new(0.2, 0.7, 0.4);
+        let xyz = to_xyz * color;
+        assert_eq!(xyz.0, ColorSpace::Rec2020.to_xyz(color).0);
+        for (a, b) in (to_rgb * xyz).0.to_array().iter().zip(color.0.to_array()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+        // the inverse agrees with the tabulated matrix
+        for (a, b) in to_rgb
+            .rows()
+            .iter()
+            .flatten()
+            .zip(ColorSpace::Rec2020.xyz_to_rgb_matrix().iter().flatten())
+        {
+            assert!((a - b).abs() < 1e-5, "{} {}", a, b);
+        }
+
+        // composition applies the first transform first, and a pipeline collapses to a single matrix
+        let gains = ColorMatrix3::from_diagonal([2.0, 1.0, 0.5]);
+        let pipeline = to_xyz
+            .then(ColorSpace::Rec709.xyz_to_rgb_transform())
+            .then(gains);
+        let expected = gains * ColorSpace::Rec709.from_xyz(ColorSpace::Rec2020.to_xyz(color));
+        for (a, b) in (pipeline * color)
+            .0
+            .to_array()
+            .iter()
+            .zip(expected.0.to_array())
+        {
+            assert!((a - b).abs() < 1e-6);
+        }
+        assert_eq!((to_xyz * ColorMatrix3::IDENTITY), to_xyz);
+        assert_eq!(to_xyz.transpose().transpose(), to_xyz);
+        assert!((gains.determinant() - 1.0).abs() < 1e-7);
+
+        let singular = ColorMatrix3::from_rows([[1.0, 2.0, 3.0], [2.0, 4.0, 6.0], [0.0, 1.0, 1.0]]);
+        assert_eq!(singular.inverse(), Err(MathError::SingularMatrix));
+        assert_eq!(
+            ColorMatrix3::from(Matrix3x3::IDENTITY),
+            ColorMatrix3::IDENTITY
+        );
+    }
+}
@@ -1,5 +1,43 @@
 // use std::simd::f32x4;
 
 
+mod aces;
+mod adaptation;
+mod batch;
+mod blackbody;
+mod chromaticity;
+mod color_space;
+mod exposure;
+mod gamut;
+mod hsv;
+mod lab;
+mod lms;
+mod matrix;
+mod rgb;
+mod spectral_locus;
+mod srgb;
+pub mod white_point;
+mod xyy;
 mod xyz;
+pub use aces::{aces_tonemap, aces_tonemap_xyz};
+pub use adaptation::{AdaptationMethod, ChromaticAdaptation};
+pub use batch::{RGBColorx4, XYZColorx4};
+pub use blackbody::{blackbody_xyz, blackbody_xyz_integrated, BlackbodyCache};
+pub use chromaticity::{
+    cct_duv, cct_from_xyz, cct_mccamy, duv, planckian_uv, uv_prime_to_xy, xy_to_uv_prime,
+};
+pub use color_space::{rgb_to_xyz_from_primaries, Chromaticity, ColorSpace};
+pub use exposure::{auto_exposure, exposure_scale, log_average_luminance, MIDDLE_GRAY};
+pub use gamut::{is_in_gamut, map_to_gamut, GamutMapping};
+pub use hsv::{HSLColor, HSVColor};
+pub use lab::{delta_e_2000, LabColor, LuvColor};
+pub use lms::LMSColor;
+pub use matrix::ColorMatrix3;
+pub use rgb::RGBColor;
+pub use spectral_locus::{
+    complementary_wavelength, dominant_wavelength, is_inside_spectral_locus, spectral_locus,
+    spectral_locus_xy, DominantWavelength,
+};
+pub use srgb::{srgb_decode, srgb_encode, SRGBColor};
+pub use xyy::XYYColor;
 pub use xyz::XYZColor;
@@ -0,0 +1,81 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul};
+use std::simd::f32x4;
+
+use crate::traits::Field;
+
+/// Linear RGB tristimulus values. the primaries and white point that these values are relative to
+/// are determined by the `ColorSpace` that they were converted with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RGBColor(pub f32x4);
+
+impl RGBColor {
+    pub const fn new(r: f32, g: f32, b: f32) -> RGBColor {
+        RGBColor(f32x4::from_array([r, g, b, 0.0]))
+    }
+    pub const fn from_raw(v: f32x4) -> RGBColor {
+        RGBColor(v)
+    }
+    pub const BLACK: RGBColor = RGBColor::from_raw(f32x4::ZERO);
+    pub const ZERO: RGBColor = RGBColor::from_raw(f32x4::ZERO);
+}
+
+impl RGBColor {
+    #[inline(always)]
+    pub fn r(&self) -> f32 {
+        self.0[0]
+    }
+    #[inline(always)]
+    pub fn g(&self) -> f32 {
+        self.0[1]
+    }
+    #[inline(always)]
+    pub fn b(&self) -> f32 {
+        self.0[2]
+    }
+}
+
+impl Mul<f32> for RGBColor {
+    type Output = RGBColor;
+    fn mul(self, other: f32) -> RGBColor {
+        RGBColor::from_raw(self.0 * f32x4::splat(other))
+    }
+}
+
+impl Mul<RGBColor> for f32 {
+    type Output = RGBColor;
+    fn mul(self, other: RGBColor) -> RGBColor {
+        RGBColor::from_raw(other.0 * f32x4::splat(self))
+    }
+}
+
+impl Div<f32> for RGBColor {
+    type Output = RGBColor;
+    fn div(self, other: f32) -> RGBColor {
+        RGBColor::from_raw(self.0 / f32x4::splat(other))
+    }
+}
+
+impl DivAssign<f32> for RGBColor {
+    fn div_assign(&mut self, other: f32) {
+        self.0 = self.0 / f32x4::splat(other);
+    }
+}
+
+impl Add for RGBColor {
+    type Output = RGBColor;
+    fn add(self, other: RGBColor) -> RGBColor {
+        RGBColor::from_raw(self.0 + other.0)
+    }
+}
+
+impl AddAssign for RGBColor {
+    fn add_assign(&mut self, other: RGBColor) {
+        self.0 = self.0 + other.0
+    }
+}
+
+impl From<RGBColor> for f32x4 {
+    fn from(v: RGBColor) -> f32x4 {
+        v.0
+    }
+}
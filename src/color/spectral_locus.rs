@@ -0,0 +1,188 @@
+use super::{Chromaticity, XYZColor};
+use crate::spectral::cmf::{cie_1931_tabulated, CIE_1931_TABLE, CIE_1931_TABLE_RANGE};
+use crate::tolerance::Tolerance;
+
+// the spectral locus is the horseshoe traced out in the xy diagram by monochromatic light.
+// together with the line of purples connecting its ends, it bounds the chromaticities of all physically realizable colors.
+// it is tabulated here at the 5nm spacing of the CIE 1931 color matching functions.
+
+fn table_wavelength(index: usize) -> f32 {
+    let step = CIE_1931_TABLE_RANGE.span() / (CIE_1931_TABLE.len() - 1) as f32;
+    CIE_1931_TABLE_RANGE.lower + step * index as f32
+}
+
+fn table_xy(index: usize) -> Chromaticity {
+    let [x, y, z] = CIE_1931_TABLE[index];
+    (x / (x + y + z), y / (x + y + z))
+}
+
+/// the chromaticity of monochromatic light at `lambda` nanometers, or None outside of the tabulated range
+pub fn spectral_locus_xy(lambda: f32) -> Option<Chromaticity> {
+    if !CIE_1931_TABLE_RANGE.contains(&lambda) && lambda != CIE_1931_TABLE_RANGE.upper {
+        return None;
+    }
+    let [x, y, z] = cie_1931_tabulated(lambda);
+    Some((x / (x + y + z), y / (x + y + z)))
+}
+
+/// the tabulated spectral locus, as (wavelength, xy chromaticity) pairs from 380 to 780 nm
+pub fn spectral_locus() -> impl Iterator<Item = (f32, Chromaticity)> {
+    (0..CIE_1931_TABLE.len()).map(|i| (table_wavelength(i), table_xy(i)))
+}
+
+/// whether `xy` lies within the area bounded by the spectral locus and the line of purples,
+/// i.e. whether it is the chromaticity of some physically realizable color
+pub fn is_inside_spectral_locus((x, y): Chromaticity) -> bool {
+    // even-odd rule, as the ends of the locus are not quite convex
+    let n = CIE_1931_TABLE.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (x0, y0) = table_xy(i);
+        let (x1, y1) = table_xy((i + 1) % n);
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) / (y1 - y0) * (x1 - x0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// the smallest positive t at which the ray white + t * direction crosses the boundary, along with the crossed segment,
+// where the segment index is that of its starting table entry, and n - 1 is the line of purples.
+fn intersect_boundary(white: Chromaticity, direction: Chromaticity) -> Option<(f32, usize, f32)> {
+    let n = CIE_1931_TABLE.len();
+    let mut nearest: Option<(f32, usize, f32)> = None;
+    for i in 0..n {
+        let (x0, y0) = table_xy(i);
+        let (x1, y1) = table_xy((i + 1) % n);
+        let (ex, ey) = (x1 - x0, y1 - y0);
+        let denominator = direction.0 * ey - direction.1 * ex;
+        if denominator == 0.0 {
+            continue;
+        }
+        let (wx, wy) = (x0 - white.0, y0 - white.1);
+        let t = (wx * ey - wy * ex) / denominator;
+        let s = (wx * direction.1 - wy * direction.0) / denominator;
+        if t > 0.0 && (0.0..=1.0).contains(&s) && nearest.is_none_or(|(best, _, _)| t < best) {
+            nearest = Some((t, i, s));
+        }
+    }
+    nearest
+}
+
+fn at_white_point((dx, dy): Chromaticity) -> bool {
+    Tolerance::DEFAULT.is_near_zero(dx.hypot(dy))
+}
+
+/// The dominant wavelength and excitation purity of a color relative to a white point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DominantWavelength {
+    /// the wavelength where the ray from the white point through the color meets the spectral locus.
+    /// for purples, the ray meets the line of purples instead, and this is the complementary wavelength,
+    /// found by extending the ray through the white point in the opposite direction.
+    pub wavelength: f32,
+    pub complementary: bool,
+    /// the distance of the color from the white point, relative to the distance of the boundary point
+    /// (on either the spectral locus or the line of purples) in the same direction. 0 for white and 1 on the boundary.
+    pub excitation_purity: f32,
+}
+
+/// computes the dominant wavelength and excitation purity of `color` relative to the white point `white` (in xy).
+/// returns None for black, colors at the white point, and white points outside the spectral locus.
+pub fn dominant_wavelength(color: XYZColor, white: Chromaticity) -> Option<DominantWavelength> {
+    let (x, y) = color.xy();
+    let direction = (x - white.0, y - white.1);
+    if color.x() + color.y() + color.z() <= 0.0 || at_white_point(direction) {
+        return None;
+    }
+    let (t, segment, s) = intersect_boundary(white, direction)?;
+    let purple_line = CIE_1931_TABLE.len() - 1;
+    if segment != purple_line {
+        let step = table_wavelength(1) - table_wavelength(0);
+        return Some(DominantWavelength {
+            wavelength: table_wavelength(segment) + s * step,
+            complementary: false,
+            excitation_purity: 1.0 / t,
+        });
+    }
+    let wavelength = complementary_wavelength(color, white)?;
+    Some(DominantWavelength {
+        wavelength,
+        complementary: true,
+        excitation_purity: 1.0 / t,
+    })
+}
+
+/// the wavelength where the ray from `color` through the white point `white` meets the spectral locus.
+/// returns None if it meets the line of purples instead, i.e. for greens, or if `color` is at the white point.
+pub fn complementary_wavelength(color: XYZColor, white: Chromaticity) -> Option<f32> {
+    let (x, y) = color.xy();
+    let direction = (white.0 - x, white.1 - y);
+    if at_white_point(direction) {
+        return None;
+    }
+    let (_, segment, s) = intersect_boundary(white, direction)?;
+    if segment == CIE_1931_TABLE.len() - 1 {
+        return None;
+    }
+    let step = table_wavelength(1) - table_wavelength(0);
+    Some(table_wavelength(segment) + s * step)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from_xy((x, y): Chromaticity) -> XYZColor {
+        XYZColor::new(x / y, 1.0, (1.0 - x - y) / y)
+    }
+
+    #[test]
+    fn test_dominant_wavelength() {
+        let d65 = (0.3127, 0.3290);
+        assert!(is_inside_spectral_locus(d65));
+        assert!(!is_inside_spectral_locus((0.3, 0.8)));
+        assert!(!is_inside_spectral_locus((0.6, 0.1)));
+        assert!(!is_inside_spectral_locus((0.3, -0.1)));
+        assert!(spectral_locus_xy(300.0).is_none());
+        assert_eq!(spectral_locus().count(), CIE_1931_TABLE.len());
+
+        // monochromatic light has itself as its dominant wavelength and full purity
+        for lambda in [470.0, 520.0, 577.5, 610.0] {
+            let color = XYZColor::from_raw(std::simd::f32x4::from_array({
+                let [x, y, z] = cie_1931_tabulated(lambda);
+                [x, y, z, 0.0]
+            }));
+            let dominant = dominant_wavelength(color, d65).unwrap();
+            assert!((dominant.wavelength - lambda).abs() < 0.5, "{:?}", dominant);
+            assert!(!dominant.complementary);
+            assert!((dominant.excitation_purity - 1.0).abs() < 1e-3);
+        }
+
+        // halfway between white and the locus
+        let (lx, ly) = spectral_locus_xy(600.0).unwrap();
+        let halfway = from_xy(((lx + d65.0) / 2.0, (ly + d65.1) / 2.0));
+        let dominant = dominant_wavelength(halfway, d65).unwrap();
+        assert!((dominant.wavelength - 600.0).abs() < 0.5);
+        assert!((dominant.excitation_purity - 0.5).abs() < 1e-3);
+        let complementary = complementary_wavelength(halfway, d65).unwrap();
+        assert!(
+            complementary > 480.0 && complementary < 500.0,
+            "{}",
+            complementary
+        );
+
+        // purples are described by their complementary wavelength
+        let magenta = from_xy((0.35, 0.15));
+        let dominant = dominant_wavelength(magenta, d65).unwrap();
+        assert!(dominant.complementary);
+        assert!(
+            dominant.wavelength > 490.0 && dominant.wavelength < 570.0,
+            "{:?}",
+            dominant
+        );
+        assert!(dominant.excitation_purity > 0.0 && dominant.excitation_purity < 1.0);
+
+        assert!(dominant_wavelength(from_xy(d65), d65).is_none());
+        assert!(dominant_wavelength(XYZColor::ZERO, d65).is_none());
+    }
+}
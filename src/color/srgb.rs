@@ -0,0 +1,131 @@
+use super::{ColorSpace, RGBColor, XYZColor};
+
+use std::simd::f32x4;
+
+// the piecewise sRGB transfer function from IEC 61966-2-1, which is linear near black and a 2.4 power curve elsewhere.
+// negative values are mirrored, as in extended sRGB, so that out of gamut colors survive a round trip.
+
+/// encodes a linear sRGB value for display
+pub fn srgb_encode(linear: f32) -> f32 {
+    let magnitude = linear.abs();
+    let encoded = if magnitude <= 0.0031308 {
+        12.92 * magnitude
+    } else {
+        1.055 * magnitude.powf(1.0 / 2.4) - 0.055
+    };
+    encoded.copysign(linear)
+}
+
+/// decodes a display encoded sRGB value to linear
+pub fn srgb_decode(encoded: f32) -> f32 {
+    let magnitude = encoded.abs();
+    let linear = if magnitude <= 0.04045 {
+        magnitude / 12.92
+    } else {
+        ((magnitude + 0.055) / 1.055).powf(2.4)
+    };
+    linear.copysign(encoded)
+}
+
+/// Gamma encoded sRGB values, as stored in 8 bit images and expected by displays.
+/// the linear values these encode are in the `Rec709` color space, which shares the sRGB primaries and D65 white point.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SRGBColor(pub f32x4);
+
+impl SRGBColor {
+    pub const fn new(r: f32, g: f32, b: f32) -> SRGBColor {
+        SRGBColor(f32x4::from_array([r, g, b, 0.0]))
+    }
+    pub fn r(&self) -> f32 {
+        self.0[0]
+    }
+    pub fn g(&self) -> f32 {
+        self.0[1]
+    }
+    pub fn b(&self) -> f32 {
+        self.0[2]
+    }
+
+    pub fn from_linear(color: RGBColor) -> SRGBColor {
+        SRGBColor::new(
+            srgb_encode(color.r()),
+            srgb_encode(color.g()),
+            srgb_encode(color.b()),
+        )
+    }
+    pub fn to_linear(&self) -> RGBColor {
+        RGBColor::new(
+            srgb_decode(self.r()),
+            srgb_decode(self.g()),
+            srgb_decode(self.b()),
+        )
+    }
+    pub fn from_xyz(color: XYZColor) -> SRGBColor {
+        SRGBColor::from_linear(ColorSpace::SRGB.from_xyz(color))
+    }
+    pub fn to_xyz(&self) -> XYZColor {
+        ColorSpace::SRGB.to_xyz(self.to_linear())
+    }
+
+    /// quantizes to 8 bits per channel, clamping to [0, 1] and rounding to nearest
+    pub fn to_u8(&self) -> [u8; 3] {
+        [self.r(), self.g(), self.b()].map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+    pub fn from_u8(rgb: [u8; 3]) -> SRGBColor {
+        let [r, g, b] = rgb.map(|v| v as f32 / 255.0);
+        SRGBColor::new(r, g, b)
+    }
+}
+
+impl From<RGBColor> for SRGBColor {
+    fn from(color: RGBColor) -> Self {
+        SRGBColor::from_linear(color)
+    }
+}
+
+impl From<SRGBColor> for RGBColor {
+    fn from(color: SRGBColor) -> Self {
+        color.to_linear()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_srgb() {
+        // the two pieces of the transfer function meet at the threshold
+        assert!((srgb_encode(0.0031308) - 0.04045).abs() < 1e-5);
+        assert!((srgb_encode(0.5) - 0.735357).abs() < 1e-5);
+        assert!((srgb_encode(1.0) - 1.0).abs() < 1e-6);
+        for v in [-0.5, 0.0, 0.001, 0.0031308, 0.2, 0.5, 1.0, 4.0] {
+            assert!(
+                (srgb_decode(srgb_encode(v)) - v).abs() < 1e-5 * v.abs().max(1.0),
+                "{}",
+                v
+            );
+        }
+
+        // 18% grey is encoded as 118 in 8 bits, and each 8 bit value survives a round trip through linear
+        assert_eq!(
+            SRGBColor::from_linear(RGBColor::new(0.18, 0.18, 0.18)).to_u8(),
+            [118; 3]
+        );
+        for v in 0..=255u8 {
+            let color = SRGBColor::from_u8([v, 0, 255 - v]);
+            assert_eq!(
+                SRGBColor::from_linear(color.to_linear()).to_u8(),
+                [v, 0, 255 - v]
+            );
+        }
+
+        // D65 white is (1, 1, 1), and the red primary has a luminance of 0.2126
+        let white = SRGBColor::from_xyz(XYZColor::new(0.95047, 1.0, 1.08883));
+        for v in [white.r(), white.g(), white.b()] {
+            assert!((v - 1.0).abs() < 1e-3, "{:?}", white);
+        }
+        let red = SRGBColor::new(1.0, 0.0, 0.0).to_xyz();
+        assert!((red.y() - 0.212639).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,71 @@
+use super::{Chromaticity, XYZColor};
+
+// chromaticities of the CIE standard illuminants for the 2 degree observer, to use as reference whites
+
+/// average daylight, the white point of sRGB, Rec2020 and Display P3
+pub const D65: Chromaticity = (0.3127, 0.3290);
+/// horizon daylight, the white point of ICC profile connection spaces and ProPhoto
+pub const D50: Chromaticity = (0.3457, 0.3585);
+/// incandescent tungsten light, a 2856K blackbody
+pub const A: Chromaticity = (0.44757, 0.40745);
+/// the equal energy illuminant, which is white in CIE XYZ and CIE RGB
+pub const E: Chromaticity = (1.0 / 3.0, 1.0 / 3.0);
+
+/// the XYZ tristimulus values of a white point, scaled to Y = 1
+pub fn to_xyz((x, y): Chromaticity) -> XYZColor {
+    XYZColor::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::{AdaptationMethod, ColorSpace, RGBColor};
+
+    #[test]
+    fn test_white_points() {
+        assert_eq!(ColorSpace::SRGB.white_point(), D65);
+        assert_eq!(ColorSpace::ProPhoto.white_point(), D50);
+        for v in &to_xyz(E).0.to_array()[..3] {
+            assert!((v - 1.0).abs() < 1e-6);
+        }
+        // illuminant A is a 2856K blackbody, up to the interpolation of the tabulated locus
+        let (x, y) = crate::color::blackbody_xyz(2856.0).xy();
+        assert!(
+            (x - A.0).abs() < 2e-3 && (y - A.1).abs() < 2e-3,
+            "{} {}",
+            x,
+            y
+        );
+
+        // XYZ measured under each white maps to rgb white in any space, whatever the white point of the space
+        for white in [D65, D50, A, E] {
+            for space in ColorSpace::ALL {
+                let rgb = space.from_xyz_adapted(to_xyz(white), white, AdaptationMethod::Bradford);
+                for v in &rgb.0.to_array()[..3] {
+                    assert!((v - 1.0).abs() < 1e-4, "{:?} {:?}", space, rgb);
+                }
+            }
+        }
+        // and colors round trip between spaces that are adapted to different whites
+        let color = RGBColor::new(0.2, 0.5, 0.3);
+        let xyz = ColorSpace::ProPhoto.to_xyz_adapted(color, D65, AdaptationMethod::CAT02);
+        let back = ColorSpace::ProPhoto.from_xyz_adapted(xyz, D65, AdaptationMethod::CAT02);
+        for (a, b) in back.0.to_array().iter().zip(color.0.to_array().iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+        let converted = ColorSpace::Rec709.from_xyz_adapted(xyz, D65, AdaptationMethod::CAT02);
+        let expected = ColorSpace::ProPhoto.convert_adapted(
+            color,
+            ColorSpace::Rec709,
+            AdaptationMethod::CAT02,
+        );
+        for (a, b) in converted
+            .0
+            .to_array()
+            .iter()
+            .zip(expected.0.to_array().iter())
+        {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::{Chromaticity, ColorSpace, XYZColor};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A color as CIE xy chromaticity and luminance Y.
+/// xyY can't represent colors with Y = 0 but nonzero X or Z, which only exist outside of the spectral locus
+/// and convert back as black. black itself has no chromaticity, and is given the chromaticity of D65 by default.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct XYYColor {
+    pub x: f32,
+    pub y: f32,
+    pub luminance: f32,
+}
+
+impl XYYColor {
+    pub const fn new(x: f32, y: f32, luminance: f32) -> Self {
+        XYYColor { x, y, luminance }
+    }
+    pub const fn from_chromaticity((x, y): Chromaticity, luminance: f32) -> Self {
+        XYYColor::new(x, y, luminance)
+    }
+
+    pub fn chromaticity(&self) -> Chromaticity {
+        (self.x, self.y)
+    }
+
+    /// the same chromaticity at another luminance
+    pub fn with_luminance(&self, luminance: f32) -> Self {
+        XYYColor::new(self.x, self.y, luminance)
+    }
+
+    /// converts `color` to xyY, assigning `black_chromaticity` to black
+    pub fn from_xyz_or(color: XYZColor, black_chromaticity: Chromaticity) -> Self {
+        let sum = color.x() + color.y() + color.z();
+        if sum == 0.0 {
+            return XYYColor::from_chromaticity(black_chromaticity, color.y());
+        }
+        XYYColor::new(color.x() / sum, color.y() / sum, color.y())
+    }
+
+    pub fn to_xyz(&self) -> XYZColor {
+        // y = 0 only arises from colors with Y = 0, so it has no luminance to scale
+        if self.y == 0.0 || self.luminance == 0.0 {
+            return XYZColor::BLACK;
+        }
+        let scale = self.luminance / self.y;
+        XYZColor::new(
+            self.x * scale,
+            self.luminance,
+            (1.0 - self.x - self.y) * scale,
+        )
+    }
+}
+
+impl From<XYZColor> for XYYColor {
+    fn from(color: XYZColor) -> Self {
+        XYYColor::from_xyz_or(color, ColorSpace::SRGB.white_point())
+    }
+}
+
+impl From<XYYColor> for XYZColor {
+    fn from(color: XYYColor) -> Self {
+        color.to_xyz()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_xyy() {
+        for color in [
+            XYZColor::new(0.5, 0.4, 0.3),
+            XYZColor::new(0.95047, 1.0, 1.08883),
+            XYZColor::new(1e-6, 2e-6, 0.0),
+            XYZColor::new(20.0, 30.0, 10.0),
+        ] {
+            let xyy = XYYColor::from(color);
+            assert_eq!(xyy.chromaticity(), color.xy());
+            assert_eq!(xyy.luminance, color.y());
+            let round_trip = XYZColor::from(xyy);
+            for (a, b) in round_trip
+                .0
+                .to_array()
+                .iter()
+                .zip(color.0.to_array().iter())
+            {
+                assert!((a - b).abs() <= 1e-6 * b.abs().max(1.0), "{:?}", round_trip);
+            }
+        }
+
+        // black takes the fallback chromaticity, and converts back to black
+        let black = XYYColor::from(XYZColor::BLACK);
+        assert_eq!(
+            black,
+            XYYColor::from_chromaticity(ColorSpace::SRGB.white_point(), 0.0)
+        );
+        assert_eq!(
+            XYYColor::from_xyz_or(XYZColor::BLACK, (0.3457, 0.3585)).chromaticity(),
+            (0.3457, 0.3585)
+        );
+        assert_eq!(black.to_xyz().0, XYZColor::BLACK.0);
+        // scaling luminance keeps the chromaticity
+        let scaled = XYYColor::from(XYZColor::new(0.5, 0.4, 0.3))
+            .with_luminance(0.8)
+            .to_xyz();
+        for (a, b) in scaled.0.to_array().iter().zip([1.0, 0.8, 0.6].iter()) {
+            assert!((a - b).abs() < 1e-6);
+        }
+        assert_eq!(XYYColor::new(0.3, 0.0, 1.0).to_xyz().0, XYZColor::BLACK.0);
+    }
+}
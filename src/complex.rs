@@ -0,0 +1,186 @@
+use crate::prelude::*;
+
+use std::ops::Sub;
+use std::simd::cmp::SimdPartialEq;
+
+/// Four complex numbers in structure of arrays layout, one per wavelength of a hero wavelength packet.
+/// intended for evaluating complex valued optics, such as conductor and thin film fresnel equations, for all lanes at once.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Complexx4 {
+    pub re: f32x4,
+    pub im: f32x4,
+}
+
+impl Complexx4 {
+    pub const fn new(re: f32x4, im: f32x4) -> Self {
+        Complexx4 { re, im }
+    }
+    pub const fn from_real(re: f32x4) -> Self {
+        Complexx4 {
+            re,
+            im: f32x4::from_array([0.0; 4]),
+        }
+    }
+    pub fn splat(re: f32, im: f32) -> Self {
+        Complexx4::new(f32x4::splat(re), f32x4::splat(im))
+    }
+    pub const ZERO: Complexx4 = Complexx4::from_real(f32x4::from_array([0.0; 4]));
+    pub const ONE: Complexx4 = Complexx4::from_real(f32x4::from_array([1.0; 4]));
+    pub const I: Complexx4 =
+        Complexx4::new(f32x4::from_array([0.0; 4]), f32x4::from_array([1.0; 4]));
+
+    /// the real and imaginary parts of lane `i`
+    pub fn lane(&self, i: usize) -> (f32, f32) {
+        (self.re[i], self.im[i])
+    }
+    pub fn conj(self) -> Self {
+        Complexx4::new(self.re, -self.im)
+    }
+    pub fn norm_squared(self) -> f32x4 {
+        self.re * self.re + self.im * self.im
+    }
+    /// the modulus |z|
+    pub fn abs(self) -> f32x4 {
+        // scale by the larger component to avoid overflow and underflow in the squares
+        let a = SimdFloat::abs(self.re);
+        let b = SimdFloat::abs(self.im);
+        let max = a.simd_max(b);
+        let min = a.simd_min(b);
+        let zero = max.simd_eq(f32x4::splat(0.0));
+        let ratio = zero.select(f32x4::splat(0.0), min / max);
+        max * (f32x4::splat(1.0) + ratio * ratio).sqrt()
+    }
+    /// the argument, in (-pi, pi]
+    pub fn arg(self) -> f32x4 {
+        let (re, im) = (self.re.to_array(), self.im.to_array());
+        f32x4::from_array([0, 1, 2, 3].map(|i| im[i].atan2(re[i])))
+    }
+    pub fn recip(self) -> Self {
+        Complexx4::ONE / self
+    }
+    pub fn exp(self) -> Self {
+        let magnitude = self.re.exp();
+        Complexx4::new(magnitude * self.im.cos(), magnitude * self.im.sin())
+    }
+    /// the principal square root, with a nonnegative real part
+    pub fn sqrt(self) -> Self {
+        let r = self.abs();
+        let half = f32x4::splat(0.5);
+        let re = (half * (r + self.re)).simd_max(f32x4::splat(0.0)).sqrt();
+        let im = (half * (r - self.re)).simd_max(f32x4::splat(0.0)).sqrt();
+        Complexx4::new(re, im.copysign(self.im))
+    }
+}
+
+impl From<f32x4> for Complexx4 {
+    fn from(re: f32x4) -> Self {
+        Complexx4::from_real(re)
+    }
+}
+
+impl Add for Complexx4 {
+    type Output = Complexx4;
+    fn add(self, rhs: Complexx4) -> Self::Output {
+        Complexx4::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complexx4 {
+    type Output = Complexx4;
+    fn sub(self, rhs: Complexx4) -> Self::Output {
+        Complexx4::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Neg for Complexx4 {
+    type Output = Complexx4;
+    fn neg(self) -> Self::Output {
+        Complexx4::new(-self.re, -self.im)
+    }
+}
+
+impl Mul for Complexx4 {
+    type Output = Complexx4;
+    fn mul(self, rhs: Complexx4) -> Self::Output {
+        Complexx4::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Mul<f32x4> for Complexx4 {
+    type Output = Complexx4;
+    fn mul(self, rhs: f32x4) -> Self::Output {
+        Complexx4::new(self.re * rhs, self.im * rhs)
+    }
+}
+
+impl Div for Complexx4 {
+    type Output = Complexx4;
+    fn div(self, rhs: Complexx4) -> Self::Output {
+        let denominator = rhs.norm_squared();
+        Complexx4::new(
+            (self.re * rhs.re + self.im * rhs.im) / denominator,
+            (self.im * rhs.re - self.re * rhs.im) / denominator,
+        )
+    }
+}
+
+impl Div<f32x4> for Complexx4 {
+    type Output = Complexx4;
+    fn div(self, rhs: f32x4) -> Self::Output {
+        Complexx4::new(self.re / rhs, self.im / rhs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: Complexx4, b: Complexx4) {
+        for i in 0..4 {
+            let (ar, ai) = a.lane(i);
+            let (br, bi) = b.lane(i);
+            assert!(
+                (ar - br).abs() < 1e-5 && (ai - bi).abs() < 1e-5,
+                "{:?} {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn test_complex_arithmetic() {
+        let z = Complexx4::new(
+            f32x4::from_array([1.0, -2.0, 0.5, -0.3]),
+            f32x4::from_array([2.0, 0.5, -1.5, -0.7]),
+        );
+        let w = Complexx4::new(
+            f32x4::from_array([0.2, 1.0, -3.0, 0.9]),
+            f32x4::from_array([-1.0, 0.0, 0.4, 2.0]),
+        );
+
+        assert_close(z * w / w, z);
+        assert_close(z * z.recip(), Complexx4::ONE);
+        assert_close(z.sqrt() * z.sqrt(), z);
+        assert!(z.sqrt().re.simd_ge(f32x4::splat(0.0)).all());
+        assert_close(Complexx4::I * Complexx4::I, -Complexx4::ONE);
+        assert_eq!(z.norm_squared(), (z * z.conj()).re);
+        assert!((z.abs() - z.norm_squared().sqrt())
+            .to_array()
+            .iter()
+            .all(|d| d.abs() < 1e-6));
+
+        // euler's identity, and exp(a + b) = exp(a) exp(b)
+        let i_pi = Complexx4::splat(0.0, PI);
+        assert_close(i_pi.exp(), -Complexx4::ONE);
+        assert_close((z + w).exp(), z.exp() * w.exp());
+
+        // the modulus does not overflow for large components
+        assert_eq!(Complexx4::splat(3e30, 4e30).abs()[0], 5e30);
+        assert_eq!(Complexx4::ZERO.abs()[0], 0.0);
+        assert!((Complexx4::splat(-1.0, 0.0).arg()[0] - PI).abs() < 1e-6);
+    }
+}
@@ -1,25 +1,173 @@
+use crate::error::Result;
 use crate::prelude::*;
 
-use crate::spectral::{x_bar, y_bar, z_bar};
+use crate::spectral::cmf::cie_1931_tabulated;
+use crate::spectral::{x_bar_bounded, y_bar_bounded, z_bar_bounded, CMF_SUPPORT};
 
 #[cfg(feature = "deepsize")]
 use deepsize::DeepSizeOf;
 use ordered_float::OrderedFloat;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::simd::cmp::{SimdOrd, SimdPartialEq, SimdPartialOrd};
 use std::simd::num::SimdUint;
 use std::simd::usizex4;
 
+pub mod atmosphere;
+pub mod batch;
+pub mod canonical;
+pub mod derivative;
+pub mod downsample;
+pub mod filters;
+pub mod fit;
+pub mod integrals;
+pub mod optimize;
+pub mod refine;
+pub mod registry;
+pub mod sparse;
+pub mod upsample;
+
+pub use optimize::SplitTabulated;
+
 const ONE_SUB_EPSILON: f32 = 1.0 - std::f32::EPSILON;
 
 // structs
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(from = "VersionedOp", into = "VersionedOp"))]
 #[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+// ops are serialized tagged with "op", i.e. {"op": "Clamp", "min": 0.0, "max": 1.0}.
+// machines written before ops were tagged hold the bare names of `Add` and `Mul`, which still deserialize.
 pub enum Op {
     Add,
     Mul,
+    Sub,
+    /// division that evaluates to 0 wherever the divisor is 0
+    Div,
+    Min,
+    Max,
+    /// raises the value to the power of the curve. negative values are clamped to 0 first, having no real powers,
+    /// and 0 raised to a negative power evaluates to 0 rather than infinity, like division by 0
+    Pow,
+    /// clamps the value to lie within [`min`, `max`]. the curve paired with it is ignored
+    Clamp {
+        min: f32,
+        max: f32,
+    },
+}
+
+// the serialized forms of `Op`
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum VersionedOp {
+    Tagged(TaggedOp),
+    // the ops that existed before tagging, serialized by their names
+    Untagged(UntaggedOp),
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "op")]
+enum TaggedOp {
+    Add,
+    Mul,
+    Sub,
+    Div,
+    Min,
+    Max,
+    Pow,
+    Clamp { min: f32, max: f32 },
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+enum UntaggedOp {
+    Add,
+    Mul,
+}
+
+#[cfg(feature = "serde")]
+impl From<VersionedOp> for Op {
+    fn from(op: VersionedOp) -> Self {
+        match op {
+            VersionedOp::Tagged(op) => match op {
+                TaggedOp::Add => Op::Add,
+                TaggedOp::Mul => Op::Mul,
+                TaggedOp::Sub => Op::Sub,
+                TaggedOp::Div => Op::Div,
+                TaggedOp::Min => Op::Min,
+                TaggedOp::Max => Op::Max,
+                TaggedOp::Pow => Op::Pow,
+                TaggedOp::Clamp { min, max } => Op::Clamp { min, max },
+            },
+            VersionedOp::Untagged(UntaggedOp::Add) => Op::Add,
+            VersionedOp::Untagged(UntaggedOp::Mul) => Op::Mul,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Op> for VersionedOp {
+    fn from(op: Op) -> Self {
+        VersionedOp::Tagged(match op {
+            Op::Add => TaggedOp::Add,
+            Op::Mul => TaggedOp::Mul,
+            Op::Sub => TaggedOp::Sub,
+            Op::Div => TaggedOp::Div,
+            Op::Min => TaggedOp::Min,
+            Op::Max => TaggedOp::Max,
+            Op::Pow => TaggedOp::Pow,
+            Op::Clamp { min, max } => TaggedOp::Clamp { min, max },
+        })
+    }
+}
+
+impl Op {
+    /// applies `self` to the running value `a` of a machine and the evaluated operand `b`
+    pub fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            Op::Add => a + b,
+            Op::Mul => a * b,
+            Op::Sub => a - b,
+            Op::Div => {
+                if b == 0.0 {
+                    0.0
+                } else {
+                    a / b
+                }
+            }
+            Op::Min => a.min(b),
+            Op::Max => a.max(b),
+            Op::Pow => {
+                let a = a.max(0.0);
+                if a == 0.0 && b < 0.0 {
+                    0.0
+                } else {
+                    a.powf(b)
+                }
+            }
+            Op::Clamp { min, max } => a.clamp(min, max),
+        }
+    }
+
+    #[cfg(feature = "simdfloat_patch")]
+    pub fn apply_f32x4(self, a: f32x4, b: f32x4) -> f32x4 {
+        match self {
+            Op::Add => a + b,
+            Op::Mul => a * b,
+            Op::Sub => a - b,
+            Op::Div => b.simd_eq(f32x4::ZERO).select(f32x4::ZERO, a / b),
+            Op::Min => a.simd_min(b),
+            Op::Max => a.simd_max(b),
+            Op::Pow => {
+                let a = a.simd_max(f32x4::ZERO);
+                (a.simd_eq(f32x4::ZERO) & b.simd_lt(f32x4::ZERO)).select(f32x4::ZERO, a.powf(b))
+            }
+            Op::Clamp { min, max } => a.simd_clamp(f32x4::splat(min), f32x4::splat(max)),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -44,7 +192,7 @@ pub trait SpectralPowerDistributionFunction<T: Field> {
     ) -> (WavelengthEnergy<T, T>, PDF<T, Uniform01>);
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
 pub enum Curve {
@@ -62,6 +210,9 @@ pub enum Curve {
         signal: Vec<(f32, f32)>,
         mode: InterpolationMode,
     },
+    /// Tabulated samples in a layout that evaluates like `Tabulated` but searches faster.
+    /// built from `Tabulated` curves with `Curve::optimize`, or with `SplitTabulated::new`.
+    SplitTabulated(SplitTabulated),
     /// An 8th degree polynomial, with the const offset term stored in `domain_range_mapping`.
     /// A good value for the x_offset and x_scale for an input x value of light wavelength in the visible range in nanometers
     /// is roughly 600 offset, 200 scale. thus, 400 is mapped to -1 and 800 is mapped to 1
@@ -74,7 +225,7 @@ pub enum Curve {
     Cauchy { a: f32, b: f32 },
     /// Each entry of signal is (offset, sigma1, sigma2, mult) which represents a nonsymmetric bell curve
     /// centered at `offset`, with `sigma1` as the left std deviation, `sigma2` as the right standard deviation, and `mult` as the multiplier
-    /// in pseudocode, f(x) = 1.0 - sum_i^n bell_curve[i].eval(x)
+    /// in pseudocode, f(x) = sum_i^n bell_curve[i].eval(x)
     Exponential { signal: Vec<(f32, f32, f32, f32)> },
     /// Each entry of signal is (offset, sigma1, sigma2, mult) which represents a nonsymmetric bell curve
     /// centered at `offset`, with `sigma1` as the left std deviation, `sigma2` as the right standard deviation, and `mult` as the multiplier
@@ -84,10 +235,23 @@ pub enum Curve {
     /// Represents a blackbody curve at a specific `temperature`, boosted by `boost`. if `boost` is 1.0, the curve is normalized to be 1.0 at the peak energy emitting wavelength in nm.
     Blackbody { temperature: f32, boost: f32 },
     /// Represents a ordered list of operations applied to a seed value,
-    /// with Op being either Add or Mul of some other `Curve`,
-    /// where Op::Mul is elementwise multiplication and Op::Add is elementwise addition
+    /// with Op being an elementwise arithmetic op (Add, Sub, Mul, Div, Min, Max or Pow) with some other `Curve`,
+    /// or a Clamp of the running value. the result is clamped to be nonnegative at the end.
     /// Note that any of the member `Curve`s can themselves be another Machine,
     Machine { seed: f32, list: Vec<(Op, Curve)> },
+    /// Wraps another `Curve`, clamping its values to lie within [`min`, `max`].
+    /// Evaluated lazily, so the clamping policy travels with the curve rather than with each call site.
+    Clamped {
+        curve: Box<Curve>,
+        min: f32,
+        max: f32,
+    },
+    /// Wraps another `Curve`, evaluating to f(x) = `scale` * curve(x) + `offset`
+    Affine {
+        curve: Box<Curve>,
+        scale: f32,
+        offset: f32,
+    },
 }
 
 impl Default for Curve {
@@ -113,6 +277,28 @@ impl Default for Curve {
 //     }
 // }
 
+// interpolates between the tabulated samples `left` and `right` at `x`, which lies between them
+fn interpolate(mode: InterpolationMode, left: (f32, f32), right: (f32, f32), x: f32) -> f32 {
+    let t = (x - left.0) / (right.0 - left.0);
+    match mode {
+        InterpolationMode::Linear => (1.0 - t) * left.1 + t * right.1,
+        InterpolationMode::Nearest => {
+            if t < 0.5 {
+                left.1
+            } else {
+                right.1
+            }
+        }
+        InterpolationMode::Cubic => {
+            let t2 = 2.0 * t;
+            let one_sub_t = 1.0 - t;
+            let h00 = (1.0 + t2) * one_sub_t * one_sub_t;
+            let h01 = t * t * (3.0 - t2);
+            h00 * left.1 + h01 * right.1
+        }
+    }
+}
+
 impl Curve {
     pub fn y_bar() -> Curve {
         Curve::Exponential {
@@ -120,6 +306,67 @@ impl Curve {
         }
     }
 
+    /// wraps `self` such that it evaluates to values clamped within [min, max]
+    pub fn clamped(self, min: f32, max: f32) -> Curve {
+        debug_assert!(min <= max);
+        Curve::Clamped {
+            curve: Box::new(self),
+            min,
+            max,
+        }
+    }
+
+    /// wraps `self` such that it evaluates to `scale` * self(x) + `offset`
+    pub fn affine(self, scale: f32, offset: f32) -> Curve {
+        Curve::Affine {
+            curve: Box::new(self),
+            scale,
+            offset,
+        }
+    }
+
+    /// constructs a `Linear` curve, validating that the signal is nonempty and finite and that the bounds are nonempty
+    pub fn new_linear(
+        signal: Vec<f32>,
+        bounds: Bounds1D,
+        mode: InterpolationMode,
+    ) -> Result<Curve> {
+        if signal.is_empty() {
+            return Err(MathError::InvalidCurve("signal is empty".to_string()));
+        }
+        if !signal.iter().all(|v| v.is_finite()) {
+            return Err(MathError::InvalidCurve(
+                "signal contains non-finite values".to_string(),
+            ));
+        }
+        if bounds.span().is_nan() || bounds.span() <= 0.0 {
+            return Err(MathError::EmptyBounds);
+        }
+        Ok(Curve::Linear {
+            signal,
+            bounds,
+            mode,
+        })
+    }
+
+    /// constructs a `Tabulated` curve, validating that the signal is nonempty, finite, and sorted by x
+    pub fn new_tabulated(signal: Vec<(f32, f32)>, mode: InterpolationMode) -> Result<Curve> {
+        if signal.is_empty() {
+            return Err(MathError::InvalidCurve("signal is empty".to_string()));
+        }
+        if !signal.iter().all(|(x, y)| x.is_finite() && y.is_finite()) {
+            return Err(MathError::InvalidCurve(
+                "signal contains non-finite values".to_string(),
+            ));
+        }
+        if !signal.windows(2).all(|w| w[0].0 <= w[1].0) {
+            return Err(MathError::InvalidCurve(
+                "signal is not sorted by x".to_string(),
+            ));
+        }
+        Ok(Curve::Tabulated { signal, mode })
+    }
+
     pub fn from_function<F>(
         mut func: F,
         samples: usize,
@@ -212,32 +459,12 @@ impl Curve {
                     let left = signal[index - 1];
                     return left.1;
                 }
-                let right = signal[index];
-                let t;
                 if index == 0 {
-                    return right.1;
-                }
-                let left = signal[index - 1];
-                t = (x - left.0) / (right.0 - left.0);
-
-                match mode {
-                    InterpolationMode::Linear => (1.0 - t) * left.1 + t * right.1,
-                    InterpolationMode::Nearest => {
-                        if t < 0.5 {
-                            left.1
-                        } else {
-                            right.1
-                        }
-                    }
-                    InterpolationMode::Cubic => {
-                        let t2 = 2.0 * t;
-                        let one_sub_t = 1.0 - t;
-                        let h00 = (1.0 + t2) * one_sub_t * one_sub_t;
-                        let h01 = t * t * (3.0 - t2);
-                        h00 * left.1 + h01 * right.1
-                    }
+                    return signal[0].1;
                 }
+                interpolate(*mode, signal[index - 1], signal[index], x)
             }
+            Curve::SplitTabulated(split) => split.evaluate(x),
             Curve::Cauchy { a, b } => *a + *b / (x * x),
             Curve::Exponential { signal } => {
                 let mut val = 0.0f32;
@@ -256,10 +483,10 @@ impl Curve {
             Curve::Machine { seed, list } => {
                 let mut val = *seed;
                 for (op, spd) in list {
-                    let eval = spd.evaluate(x);
                     val = match op {
-                        Op::Add => val + eval,
-                        Op::Mul => val * eval,
+                        // clamping doesn't depend on the curve, so skip evaluating it
+                        Op::Clamp { .. } => op.apply(val, 0.0),
+                        _ => op.apply(val, spd.evaluate(x)),
                     };
                 }
                 val.max(0.0)
@@ -272,73 +499,96 @@ impl Curve {
                         / blackbody(*temperature, max_blackbody_lambda(*temperature))
                 }
             }
+            Curve::Clamped { curve, min, max } => curve.evaluate(x).clamp(*min, *max),
+            Curve::Affine {
+                curve,
+                scale,
+                offset,
+            } => scale * curve.evaluate(x) + offset,
         }
     }
 
-    pub fn to_cdf(&self, bounds: Bounds1D, resolution: usize) -> CurveWithCDF {
-        // resolution is ignored if Curve variant is `Linear`
-        match &self {
-            Curve::Linear {
-                signal,
-                bounds,
-                mode,
-            } => {
-                // converting linear curve to CDF, easy enough since you have the raw signal
-                let mut cdf_signal = signal.clone();
-                let mut s = 0.0;
-                let step_size = bounds.span() / (signal.len() as f32);
-                for (i, v) in signal.iter().enumerate() {
-                    cdf_signal[i] = s;
-                    s += v * step_size;
+    /// `bounds` may be a `Bounds1D` or a `WavelengthRange`.
+    /// fails with `InvalidCurve` if `self` has no mass within the bounds, as the cdf can't be normalized.
+    pub fn to_cdf(&self, bounds: impl Into<Bounds1D>, resolution: usize) -> Result<CurveWithCDF> {
+        let bounds = bounds.into();
+        // resolution and bounds are ignored if Curve variant is `Linear`, the signal's own bins are used instead.
+        let (bounds, bins) = match &self {
+            Curve::Linear { signal, bounds, .. } => (*bounds, signal.len()),
+            _ => (bounds, resolution),
+        };
+        // the cdf is stored as N+1 explicit edges (x, cdf(x)) enclosing N bins,
+        // where the mass of each bin is computed using the trapezoidal rule.
+        // `Linear` curves are integrated exactly over their own bins.
+        let step_size = bounds.span() / (bins as f32);
+        let mut edges = Vec::with_capacity(bins + 1);
+        let mut s = 0.0;
+        match self {
+            // sums of nonnegative gaussians have closed form integrals, which make the cdf exact at the edges
+            Curve::Exponential { signal } if signal.iter().all(|lobe| lobe.3 >= 0.0) => {
+                for i in 0..=bins {
+                    let x = bounds.lower + (i as f32) * step_size;
+                    s = exponential_integral(signal, bounds.lower, x);
+                    edges.push((x, s));
                 }
-                cdf_signal.push(s);
-
-                // divide each entry in the cdf by the integral so that it ends at 1.0
-                cdf_signal.iter_mut().for_each(|e| *e /= s);
-                // println!("integral is {}, step_size was {}", s, step_size);
-                CurveWithCDF {
-                    pdf: self.clone(),
-                    cdf: Curve::Linear {
-                        signal: cdf_signal,
-                        bounds: *bounds,
-                        mode: *mode,
-                    },
-                    pdf_integral: s,
+            }
+            // the interpolants of all modes integrate to the mean of the two entries over each bin, as the cubic
+            // hermite basis functions do for zero tangents. the last bin holds the last entry.
+            Curve::Linear { signal, .. } => {
+                edges.push((bounds.lower, 0.0));
+                for (i, left) in signal.iter().enumerate() {
+                    let right = signal.get(i + 1).unwrap_or(left);
+                    s += 0.5 * step_size * (left.max(0.0) + right.max(0.0));
+                    edges.push((bounds.lower + ((i + 1) as f32) * step_size, s));
                 }
             }
             _ => {
-                // converting arbitrary curve to CDF, need to sample to compute the integral.
-                // TODO: convert riemann sum to trapezoidal rule or something more accurate.
-                let mut cdf_signal = Vec::new();
-                let mut s = 0.0;
-                let step_size = bounds.span() / (resolution as f32);
-                for i in 0..resolution {
-                    let lambda = bounds.lower + (i as f32) * step_size;
-                    s += self.evaluate(lambda);
-                    cdf_signal.push(s);
-                }
-
-                cdf_signal.iter_mut().for_each(|e| *e /= s);
-
-                CurveWithCDF {
-                    pdf: self.clone(),
-                    cdf: Curve::Linear {
-                        signal: cdf_signal,
-                        mode: InterpolationMode::Cubic,
-                        bounds,
-                    },
-                    pdf_integral: s,
+                let mut last_f = self.evaluate_power(bounds.lower);
+                edges.push((bounds.lower, 0.0));
+                for i in 1..=bins {
+                    let x = bounds.lower + (i as f32) * step_size;
+                    let f_x = self.evaluate_power(x);
+                    s += 0.5 * step_size * (last_f + f_x);
+                    edges.push((x, s));
+                    last_f = f_x;
                 }
             }
         }
+
+        if s.is_nan() || s <= 0.0 {
+            return Err(MathError::InvalidCurve(
+                "curve has no mass within the bounds of the cdf".to_string(),
+            ));
+        }
+        // divide each entry in the cdf by the integral so that it ends at 1.0
+        edges.iter_mut().for_each(|e| e.1 /= s);
+        Ok(CurveWithCDF {
+            pdf: self.clone(),
+            cdf: Curve::Tabulated {
+                signal: edges,
+                mode: InterpolationMode::Linear,
+            },
+            pdf_integral: s,
+            cubic_inversion: false,
+        })
     }
 
+    /// integrates `self` over `integration_bounds` using the trapezoidal rule.
+    /// `clamped` clamps values to [0, 1), equivalent to integrating `self.clone().clamped(0.0, 1.0 - f32::EPSILON)`
+    /// unclamped `Exponential` curves are integrated exactly, and `samples` is ignored for them.
     pub fn evaluate_integral(
         &self,
         integration_bounds: Bounds1D,
         samples: usize,
         clamped: bool,
     ) -> f32 {
+        if let (Curve::Exponential { signal }, false) = (self, clamped) {
+            return exponential_integral(
+                signal,
+                integration_bounds.lower,
+                integration_bounds.upper,
+            );
+        }
         // trapezoidal rule
         let step_size = integration_bounds.span() / samples as f32;
         let mut sum = 0.0;
@@ -360,31 +610,126 @@ impl Curve {
         }
         sum
     }
+    /// computes the inner product of `self` and `other` over `integration_bounds`, i.e. the integral of f(x) * g(x) dx,
+    /// using the trapezoidal rule on a grid of `samples + 1` points shared by both curves.
+    /// avoids building and integrating a `Machine` curve, which would evaluate both curves twice per step.
+    pub fn inner_product(
+        &self,
+        other: &Curve,
+        integration_bounds: Bounds1D,
+        samples: usize,
+    ) -> f32 {
+        let step_size = integration_bounds.span() / samples as f32;
+        let points = samples + 1;
+
+        #[cfg(feature = "simdfloat_patch")]
+        let (mut sum, start) = {
+            let offsets = f32x4::from_array([0.0, 1.0, 2.0, 3.0]);
+            let mut acc = f32x4::ZERO;
+            let mut i = 0;
+            while i + 4 <= points {
+                let x = f32x4::splat(integration_bounds.lower)
+                    + (f32x4::splat(i as f32) + offsets) * f32x4::splat(step_size);
+                let f: f32x4 = self.evaluate_power(x);
+                let g: f32x4 = other.evaluate_power(x);
+                acc += f * g;
+                i += 4;
+            }
+            (acc.reduce_sum(), i)
+        };
+        #[cfg(not(feature = "simdfloat_patch"))]
+        let (mut sum, start) = (0.0, 0);
+
+        // remainder that didn't fit in a packet
+        for i in start..points {
+            let x = integration_bounds.lower + (i as f32) * step_size;
+            sum += self.evaluate_power(x) * other.evaluate_power(x);
+        }
+
+        // the endpoints only carry half weight under the trapezoidal rule
+        let (lower, upper) = (integration_bounds.lower, integration_bounds.upper);
+        sum -= 0.5 * self.evaluate_power(lower) * other.evaluate_power(lower);
+        sum -= 0.5 * self.evaluate_power(upper) * other.evaluate_power(upper);
+        sum * step_size
+    }
+
+    /// integrates the curve against the color matching functions, which are treated as 0 outside of `CMF_SUPPORT`
     pub fn convert_to_xyz(
         &self,
         integration_bounds: Bounds1D,
         step_size: f32,
         clamped: bool,
+    ) -> XYZColor {
+        self.convert_to_xyz_with_support(integration_bounds, step_size, clamped, CMF_SUPPORT)
+    }
+
+    /// integrates the curve against the color matching functions, which are treated as 0 outside of `support`
+    pub fn convert_to_xyz_with_support(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        clamped: bool,
+        support: Bounds1D,
+    ) -> XYZColor {
+        self.integrate_xyz(integration_bounds, step_size, clamped, |lambda| {
+            let angstroms = lambda * 10.0;
+            [
+                x_bar_bounded(angstroms, support),
+                y_bar_bounded(angstroms, support),
+                z_bar_bounded(angstroms, support),
+            ]
+        })
+    }
+
+    /// integrates the curve against the chosen representation of the color matching functions.
+    /// `CMF::Fitted` is equivalent to `convert_to_xyz`.
+    pub fn convert_to_xyz_with_cmf(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        clamped: bool,
+        cmf: CMF,
+    ) -> XYZColor {
+        match cmf {
+            CMF::Fitted => self.convert_to_xyz(integration_bounds, step_size, clamped),
+            CMF::Tabulated => {
+                self.integrate_xyz(integration_bounds, step_size, clamped, cie_1931_tabulated)
+            }
+        }
+    }
+
+    fn integrate_xyz(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        clamped: bool,
+        cmf: impl Fn(f32) -> [f32; 3],
     ) -> XYZColor {
         let iterations = (integration_bounds.span() / step_size) as usize;
         let mut sum: XYZColor = XYZColor::ZERO;
         for i in 0..iterations {
             let lambda = integration_bounds.lower + (i as f32) * step_size;
-            let angstroms = lambda * 10.0;
             let val = if clamped {
                 self.evaluate_clamped(lambda)
             } else {
                 self.evaluate_power(lambda)
             };
-            sum.0 += f32x4::from_array([
-                val * x_bar(angstroms),
-                val * y_bar(angstroms),
-                val * z_bar(angstroms),
-                0.0,
-            ]) * f32x4::splat(step_size);
+            let [x, y, z] = cmf(lambda);
+            sum.0 += f32x4::from_array([val * x, val * y, val * z, 0.0]) * f32x4::splat(step_size);
         }
         sum
     }
+
+    /// integrates the curve against the color matching functions, then converts to linear RGB in `space`
+    pub fn convert_to_rgb(
+        &self,
+        space: ColorSpace,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        clamped: bool,
+    ) -> RGBColor {
+        space.from_xyz(self.convert_to_xyz(integration_bounds, step_size, clamped))
+    }
 }
 
 impl SpectralPowerDistributionFunction<f32> for Curve {
@@ -422,8 +767,12 @@ impl SpectralPowerDistributionFunction<f32x4> for Curve {
                 mode,
             } => {
                 let splatted_step_size = f32x4::splat(bounds.span() / (signal.len() as f32));
-                let index =
-                    ((lambda - f32x4::splat(bounds.lower)) / splatted_step_size).cast::<usize>();
+                // match the scalar path, which clamps to the first and last entries outside of bounds
+                let lambda =
+                    lambda.simd_clamp(f32x4::splat(bounds.lower), f32x4::splat(bounds.upper));
+                let index = ((lambda - f32x4::splat(bounds.lower)) / splatted_step_size)
+                    .cast::<usize>()
+                    .simd_min(usizex4::splat(signal.len() - 1));
 
                 let left = f32x4::gather_or_default(&signal, index);
 
@@ -496,6 +845,28 @@ impl SpectralPowerDistributionFunction<f32x4> for Curve {
                         / f32x4::splat(blackbody(*temperature, max_blackbody_lambda(*temperature)))
                 }
             }
+            Curve::Clamped { curve, min, max } => {
+                let inner: f32x4 = curve.evaluate_power(lambda);
+                inner.simd_clamp(f32x4::splat(*min), f32x4::splat(*max))
+            }
+            Curve::Affine {
+                curve,
+                scale,
+                offset,
+            } => {
+                let inner: f32x4 = curve.evaluate_power(lambda);
+                f32x4::splat(*scale) * inner + f32x4::splat(*offset)
+            }
+            Curve::Machine { seed, list } => {
+                let mut val = f32x4::splat(*seed);
+                for (op, spd) in list {
+                    val = match op {
+                        Op::Clamp { .. } => op.apply_f32x4(val, f32x4::ZERO),
+                        _ => op.apply_f32x4(val, spd.evaluate_power(lambda)),
+                    };
+                }
+                val.simd_max(f32x4::ZERO)
+            }
             _ => f32x4::from_array([
                 self.evaluate(lambda[0]),
                 self.evaluate(lambda[1]),
@@ -537,6 +908,186 @@ pub struct CurveWithCDF {
     pub cdf: Curve,
     // store pdf integral so that we don't have to normalize the `pdf` curve beforehand. instead, all samplings of the pdf when taken through the cdf should be normalized by dividing by pdf_integral.
     pub pdf_integral: f32,
+    // whether `invert` goes through a monotone cubic interpolant of the cdf edges rather than linearly.
+    // kept apart from the interpolation mode of `cdf`, so that evaluating the cdf is unaffected.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cubic_inversion: bool,
+}
+
+impl CurveWithCDF {
+    /// the domain covered by the cdf edges, if the cdf is stored as edges (see [`Curve::to_cdf`])
+    pub fn cdf_bounds(&self) -> Option<Bounds1D> {
+        match &self.cdf {
+            Curve::Tabulated { signal, .. } if signal.len() >= 2 => Some(Bounds1D::new(
+                signal.first().unwrap().0,
+                signal.last().unwrap().0,
+            )),
+            _ => None,
+        }
+    }
+
+    /// inverts the cdf edges through a monotone cubic interpolant, such that the sampled density is continuous
+    /// rather than constant within each bin. `cdf` keeps evaluating with its own interpolation mode.
+    /// has no effect if the cdf is not stored as edges.
+    pub fn with_cubic_inversion(mut self) -> Self {
+        self.cubic_inversion = true;
+        self
+    }
+
+    /// inverts the cdf at `u` in [0, 1], restricted to `wavelength_range`.
+    /// returns the sampled x value and the density (wrt x) that it was sampled with.
+    /// returns None if the cdf is not stored as edges or if `wavelength_range` has no overlap with it.
+    /// with `cubic_inversion`, the edges are inverted through a monotone cubic interpolant (see `invert_cdf_edges_cubic`),
+    /// and otherwise linearly.
+    pub fn invert(&self, wavelength_range: Bounds1D, u: f32) -> Option<(f32, f32)> {
+        let Curve::Tabulated { signal, .. } = &self.cdf else {
+            return None;
+        };
+        let cubic = self.cubic_inversion;
+        let restricted_bounds = self.cdf_bounds()?.intersection(wavelength_range);
+        // remap u to lie between the values that correspond to restricted_bounds.lower and restricted_bounds.upper
+        let cdf_at = |x: f32| {
+            if cubic {
+                evaluate_cdf_edges_cubic(signal, x).0
+            } else {
+                self.cdf.evaluate(x)
+            }
+        };
+        let lower_cdf_value = cdf_at(restricted_bounds.lower);
+        let upper_cdf_value = cdf_at(restricted_bounds.upper);
+        let restricted_mass = upper_cdf_value - lower_cdf_value;
+        if restricted_bounds.span() <= 0.0 || restricted_mass <= 0.0 {
+            return None;
+        }
+        let target = lower_cdf_value + u * restricted_mass;
+        let (x, density) = if cubic {
+            invert_cdf_edges_cubic(signal, target)
+        } else {
+            invert_cdf_edges(signal, target)
+        };
+        Some((
+            x.clamp(restricted_bounds.lower, restricted_bounds.upper),
+            density / restricted_mass,
+        ))
+    }
+}
+
+// the exact integral of the sum of the bell curves of an `Exponential` curve from `a` to `b`
+fn exponential_integral(signal: &[(f32, f32, f32, f32)], a: f32, b: f32) -> f32 {
+    signal
+        .iter()
+        .map(|&(offset, sigma1, sigma2, multiplier)| {
+            gaussian_integral(a, b, multiplier, offset, sigma1, sigma2)
+        })
+        .sum()
+}
+
+/// inverts a normalized, monotonically nondecreasing cdf stored as (x, cdf(x)) edges at `u`.
+/// the pdf is treated as constant within each bin, thus the inversion is linear within a bin.
+/// returns the sampled x and the density of that bin.
+pub(crate) fn invert_cdf_edges(edges: &[(f32, f32)], u: f32) -> (f32, f32) {
+    debug_assert!(edges.len() >= 2);
+    // index of the first edge whose cdf value is strictly greater than u,
+    // which also skips over any bins that have zero mass.
+    let index = edges
+        .partition_point(|&(_, c)| c <= u)
+        .clamp(1, edges.len() - 1);
+    let (x0, c0) = edges[index - 1];
+    let (x1, c1) = edges[index];
+    let mass = c1 - c0;
+    if mass <= 0.0 {
+        return (x0, 0.0);
+    }
+    let t = ((u - c0) / mass).clamp(0.0, 1.0);
+    (x0 + t * (x1 - x0), mass / (x1 - x0))
+}
+
+// the slope of the monotone cubic interpolant at edge `i`, i.e. the sampled density there.
+// interior slopes are the weighted harmonic mean of the neighboring secants (Fritsch and Butland 1984, as in PCHIP),
+// which is zero at local extrema and never large enough to overshoot, so the interpolant stays monotone.
+fn cdf_edge_slope(edges: &[(f32, f32)], i: usize) -> f32 {
+    let secant = |k: usize| {
+        let ((x0, c0), (x1, c1)) = (edges[k], edges[k + 1]);
+        ((c1 - c0) / (x1 - x0), x1 - x0)
+    };
+    if i == 0 {
+        return secant(0).0;
+    }
+    if i == edges.len() - 1 {
+        return secant(i - 1).0;
+    }
+    let ((left, h0), (right, h1)) = (secant(i - 1), secant(i));
+    if left <= 0.0 || right <= 0.0 {
+        return 0.0;
+    }
+    let (w0, w1) = (2.0 * h1 + h0, h1 + 2.0 * h0);
+    (w0 + w1) / (w0 / left + w1 / right)
+}
+
+// the cubic hermite interpolant of bin `index` (between edges index - 1 and index) and its derivative wrt x, at t in [0, 1]
+fn cdf_bin_cubic(edges: &[(f32, f32)], index: usize, t: f32) -> (f32, f32) {
+    let ((x0, c0), (x1, c1)) = (edges[index - 1], edges[index]);
+    let h = x1 - x0;
+    let (d0, d1) = (
+        cdf_edge_slope(edges, index - 1) * h,
+        cdf_edge_slope(edges, index) * h,
+    );
+    let (t2, t3) = (t * t, t * t * t);
+    let value = (2.0 * t3 - 3.0 * t2 + 1.0) * c0
+        + (t3 - 2.0 * t2 + t) * d0
+        + (-2.0 * t3 + 3.0 * t2) * c1
+        + (t3 - t2) * d1;
+    let derivative = (6.0 * t2 - 6.0 * t) * c0
+        + (3.0 * t2 - 4.0 * t + 1.0) * d0
+        + (6.0 * t - 6.0 * t2) * c1
+        + (3.0 * t2 - 2.0 * t) * d1;
+    (value, derivative / h)
+}
+
+/// evaluates the monotone cubic interpolant of cdf edges at `x`, returning the cdf value and the density
+pub(crate) fn evaluate_cdf_edges_cubic(edges: &[(f32, f32)], x: f32) -> (f32, f32) {
+    debug_assert!(edges.len() >= 2);
+    let index = edges
+        .partition_point(|&(e, _)| e <= x)
+        .clamp(1, edges.len() - 1);
+    let (x0, x1) = (edges[index - 1].0, edges[index].0);
+    cdf_bin_cubic(edges, index, ((x - x0) / (x1 - x0)).clamp(0.0, 1.0))
+}
+
+/// inverts the monotone cubic interpolant of cdf edges at `u`, which unlike linear inversion
+/// samples a density that is continuous across bins. returns the sampled x and its density.
+pub(crate) fn invert_cdf_edges_cubic(edges: &[(f32, f32)], u: f32) -> (f32, f32) {
+    debug_assert!(edges.len() >= 2);
+    let index = edges
+        .partition_point(|&(_, c)| c <= u)
+        .clamp(1, edges.len() - 1);
+    let ((x0, c0), (x1, c1)) = (edges[index - 1], edges[index]);
+    if c1 - c0 <= 0.0 {
+        return (x0, 0.0);
+    }
+    // newton's method, falling back to bisection whenever a step leaves the bracket
+    let (mut lower, mut upper) = (0.0f32, 1.0f32);
+    let mut t = ((u - c0) / (c1 - c0)).clamp(0.0, 1.0);
+    for _ in 0..32 {
+        let (value, density) = cdf_bin_cubic(edges, index, t);
+        let error = value - u;
+        if error.abs() <= 1e-7 {
+            break;
+        }
+        if error > 0.0 {
+            upper = t;
+        } else {
+            lower = t;
+        }
+        let step = t - error / (density * (x1 - x0));
+        t = if density > 0.0 && step > lower && step < upper {
+            step
+        } else {
+            0.5 * (lower + upper)
+        };
+    }
+    let (_, density) = cdf_bin_cubic(edges, index, t);
+    (x0 + t * (x1 - x0), density)
 }
 
 impl SpectralPowerDistributionFunction<f32> for CurveWithCDF {
@@ -549,90 +1100,31 @@ impl SpectralPowerDistributionFunction<f32> for CurveWithCDF {
     fn sample_power_and_pdf(
         &self,
         wavelength_range: Bounds1D,
-        mut sample: Sample1D,
+        sample: Sample1D,
     ) -> (SingleWavelength, PDF<f32, Uniform01>) {
         match &self.cdf {
             Curve::Const(v) => (
-                SingleWavelength::new(wavelength_range.sample(sample.x), (*v).into()),
+                SingleWavelength::new(wavelength_range.sample(sample.x), *v),
                 (1.0 / self.pdf_integral).into(),
             ),
-            Curve::Linear {
-                signal,
-                bounds,
-                mode,
-            } => {
-                let restricted_bounds = bounds.intersection(wavelength_range);
-                // remap sample.x to lie between the values that correspond to restricted_bounds.lower and restricted_bounds.upper
-                let lower_cdf_value = self.cdf.evaluate(restricted_bounds.lower);
-                let upper_cdf_value = self.cdf.evaluate(restricted_bounds.upper);
-                sample.x = lower_cdf_value + sample.x * (upper_cdf_value - lower_cdf_value);
-                // println!("{:?}", self.cdf);
-                // println!(
-                //     "remapped sample value to be {:?} which is between {:?} and {:?}",
-                //     sample.x, lower_cdf_value, upper_cdf_value
-                // );
-                let maybe_index = signal
-                    .binary_search_by_key(&OrderedFloat::<f32>(sample.x), |&a| {
-                        OrderedFloat::<f32>(a)
-                    });
-                let lambda = match maybe_index {
-                    Ok(index) | Err(index) => {
-                        if index == 0 {
-                            // index is at end, so return lambda that corresponds to index
-                            bounds.lower
-                        } else {
-                            let left = bounds.lower
-                                + (index as f32 - 1.0) * (bounds.upper - bounds.lower)
-                                    / (signal.len() as f32);
-                            let right = bounds.lower
-                                + (index as f32) * (bounds.upper - bounds.lower)
-                                    / (signal.len() as f32);
-                            let v0 = signal[index - 1];
-                            let v1 = signal[index];
-                            let t = if v0 != v1 {
-                                (sample.x - v0) / (v1 - v0)
-                            } else {
-                                0.0
-                            };
-
-                            assert!(0.0 <= t && t <= 1.0, "{}, {}, {}, {}", t, sample.x, v0, v1);
-                            match mode {
-                                InterpolationMode::Linear => (1.0 - t) * left + t * right,
-                                InterpolationMode::Nearest => {
-                                    if t < 0.5 {
-                                        left
-                                    } else {
-                                        right
-                                    }
-                                }
-                                InterpolationMode::Cubic => {
-                                    let t2 = 2.0 * t;
-                                    let one_sub_t = 1.0 - t;
-                                    let h00 = (1.0 + t2) * one_sub_t * one_sub_t;
-                                    let h01 = t * t * (3.0 - t2);
-                                    h00 * left + h01 * right
-                                }
-                            }
-                            .clamp(bounds.lower, bounds.upper)
-                        }
-                    }
-                };
-                // println!("lambda was {}", lambda);
-                let power = self.pdf.evaluate(lambda);
-
-                // println!("power was {}", power);
-                (
-                    SingleWavelength::new(lambda, power.into()),
-                    PDF::from(power / self.pdf_integral),
-                )
-            }
+            Curve::Tabulated { .. } => match self.invert(wavelength_range, sample.x) {
+                Some((lambda, density)) => {
+                    let power = self.pdf.evaluate(lambda);
+                    (SingleWavelength::new(lambda, power), PDF::from(density))
+                }
+                None => (
+                    SingleWavelength::new(wavelength_range.sample(sample.x), 0.0),
+                    PDF::from(0.0),
+                ),
+            },
             // should this be self.pdf.sample_power_and_pdf?
             _ => self.cdf.sample_power_and_pdf(wavelength_range, sample),
         }
     }
 }
 
-// TODO: figure out how to use SMIS/CMIS for these sample functions, especially with CurveWithCDF
+// TODO: figure out how to use SMIS/CMIS for these sample functions, especially with CurveWithCDF.
+// the weights themselves are implemented in crate::mis
 
 #[cfg(feature = "simdfloat_patch")]
 impl SpectralPowerDistributionFunction<f32x4> for CurveWithCDF {
@@ -645,7 +1137,7 @@ impl SpectralPowerDistributionFunction<f32x4> for CurveWithCDF {
     fn sample_power_and_pdf(
         &self,
         wavelength_range: Bounds1D,
-        mut sample: Sample1D,
+        sample: Sample1D,
     ) -> (HeroWavelength, PDF<f32x4, Uniform01>) {
         match &self.cdf {
             Curve::Const(v) => (
@@ -653,77 +1145,23 @@ impl SpectralPowerDistributionFunction<f32x4> for CurveWithCDF {
                     .replace_energy(f32x4::splat(*v)),
                 f32x4::splat(1.0 / self.pdf_integral).into(),
             ),
-            Curve::Linear {
-                signal,
-                bounds,
-                mode,
-            } => {
-                let restricted_bounds = bounds.intersection(wavelength_range);
-                // remap sample.x to lie between the values that correspond to restricted_bounds.lower and restricted_bounds.upper
-                let lower_cdf_value = self.cdf.evaluate(restricted_bounds.lower);
-                let upper_cdf_value = self.cdf.evaluate(restricted_bounds.upper);
-                sample.x = lower_cdf_value + sample.x * (upper_cdf_value - lower_cdf_value);
-                // println!("{:?}", self.cdf);
-                // println!(
-                //     "remapped sample value to be {:?} which is between {:?} and {:?}",
-                //     sample.x, lower_cdf_value, upper_cdf_value
-                // );
-                let maybe_index = signal
-                    .binary_search_by_key(&OrderedFloat::<f32>(sample.x), |&a| {
-                        OrderedFloat::<f32>(a)
-                    });
-                let hero_lambda = match maybe_index {
-                    Ok(index) | Err(index) => {
-                        if index == 0 {
-                            // index is at end, so return lambda that corresponds to index
-                            bounds.lower
-                        } else {
-                            let left = bounds.lower
-                                + (index as f32 - 1.0) * (bounds.upper - bounds.lower)
-                                    / (signal.len() as f32);
-                            let right = bounds.lower
-                                + (index as f32) * (bounds.upper - bounds.lower)
-                                    / (signal.len() as f32);
-                            let v0 = signal[index - 1];
-                            let v1 = signal[index];
-                            let t = if v0 != v1 {
-                                (sample.x - v0) / (v1 - v0)
-                            } else {
-                                0.0
-                            };
-
-                            assert!(0.0 <= t && t <= 1.0, "{}, {}, {}, {}", t, sample.x, v0, v1);
-                            match mode {
-                                InterpolationMode::Linear => (1.0 - t) * left + t * right,
-                                InterpolationMode::Nearest => {
-                                    if t < 0.5 {
-                                        left
-                                    } else {
-                                        right
-                                    }
-                                }
-                                InterpolationMode::Cubic => {
-                                    let t2 = 2.0 * t;
-                                    let one_sub_t = 1.0 - t;
-                                    let h00 = (1.0 + t2) * one_sub_t * one_sub_t;
-                                    let h01 = t * t * (3.0 - t2);
-                                    h00 * left + h01 * right
-                                }
-                            }
-                            .clamp(bounds.lower, bounds.upper)
-                        }
+            Curve::Tabulated { .. } => {
+                // invert only succeeds for cdfs with bounds, but degenerate cdfs fall through to the zero pdf
+                match (self.invert(wavelength_range, sample.x), self.cdf_bounds()) {
+                    (Some((hero_lambda, density)), Some(bounds)) => {
+                        // the other lanes are spaced evenly across the cdf domain, relative to the hero lambda
+                        let correlated_sample_x =
+                            ((hero_lambda - bounds.lower) / bounds.span()).min(ONE_SUB_EPSILON);
+                        let out_we = HeroWavelength::new_from_range(correlated_sample_x, bounds);
+                        let power: f32x4 = self.pdf.evaluate_power(out_we.lambda);
+
+                        (out_we.replace_energy(power), f32x4::splat(density).into())
                     }
-                };
-                // println!("lambda was {}", lambda);
-                let correlated_sample_x = (hero_lambda - bounds.lower) / bounds.span();
-                let out_we = HeroWavelength::new_from_range(correlated_sample_x, *bounds);
-                let power: f32x4 = self.pdf.evaluate_power(out_we.lambda);
-
-                // println!("power was {}", power);
-                (
-                    out_we.replace_energy(power),
-                    f32x4::splat(power[0] / self.pdf_integral).into(),
-                )
+                    _ => (
+                        HeroWavelength::new_from_range(sample.x, wavelength_range),
+                        f32x4::ZERO.into(),
+                    ),
+                }
             }
             // should this be self.pdf.sample_power_and_pdf?
             _ => self.cdf.sample_power_and_pdf(wavelength_range, sample),
@@ -834,7 +1272,227 @@ mod test {
         println!("{:?}", result);
     }
     #[test]
-    fn test_curve_machine() {}
+    fn test_inner_product() {
+        let a = Curve::Const(0.5);
+        let b = Curve::Const(2.0);
+        let result = a.inner_product(&b, Bounds1D::new(100.0, 200.0), 37);
+        assert!((result - 100.0).abs() < 1e-3, "{}", result);
+
+        let reflectance = Curve::Linear {
+            signal: vec![0.1, 0.4, 0.9, 0.5, 0.2, 0.7, 0.8, 0.3],
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Linear,
+        };
+        let illuminant = Curve::Blackbody {
+            temperature: 5500.0,
+            boost: 1.0,
+        };
+        let product = Curve::Machine {
+            seed: 1.0,
+            list: vec![
+                (Op::Mul, reflectance.clone()),
+                (Op::Mul, illuminant.clone()),
+            ],
+        };
+        // include a bound outside of the linear curve's domain to exercise the clamped edges
+        let bounds = Bounds1D::new(370.0, 790.0);
+        for samples in [10, 101, 400] {
+            let expected = product.evaluate_integral(bounds, samples, false);
+            let result = reflectance.inner_product(&illuminant, bounds, samples);
+            assert!(
+                (result - expected).abs() / expected < 1e-4,
+                "{} {}",
+                result,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_curve_clamped_and_affine() {
+        let base = Curve::Linear {
+            signal: vec![-0.5, 0.2, 0.9, 1.5],
+            bounds: Bounds1D::new(400.0, 800.0),
+            mode: InterpolationMode::Linear,
+        };
+        let clamped = base.clone().clamped(0.0, 1.0);
+        assert_eq!(clamped.evaluate(400.0), 0.0);
+        assert_eq!(clamped.evaluate(600.0), 0.9);
+        assert_eq!(clamped.evaluate(700.0), 1.0);
+
+        let affine = base.clone().affine(2.0, 0.5);
+        assert_eq!(affine.evaluate(600.0), 2.3);
+
+        // the clamped flag and the wrapper agree
+        let bounds = Bounds1D::new(400.0, 800.0);
+        assert_eq!(
+            base.evaluate_integral(bounds, 100, true),
+            base.clone()
+                .clamped(0.0, ONE_SUB_EPSILON)
+                .evaluate_integral(bounds, 100, false)
+        );
+
+        // wrappers compose
+        let composed = base.affine(0.5, 0.0).clamped(0.0, 0.5);
+        assert_eq!(composed.evaluate(750.0), 0.5);
+    }
+
+    #[test]
+    #[cfg(feature = "simdfloat_patch")]
+    fn test_curve_clamped_and_affine_hwss() {
+        let curve = Curve::Exponential {
+            signal: vec![(500.0, 40.0, 40.0, 2.0)],
+        }
+        .affine(1.5, -0.25)
+        .clamped(0.0, 1.0);
+        let lambda = f32x4::from_array([400.0, 480.0, 500.0, 650.0]);
+        let result: f32x4 = curve.evaluate_power(lambda);
+        for i in 0..4 {
+            assert!((result[i] - curve.evaluate(lambda[i])).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_validated_constructors() {
+        let bounds = Bounds1D::new(400.0, 800.0);
+        assert!(Curve::new_linear(vec![0.1, 0.5], bounds, InterpolationMode::Linear).is_ok());
+        assert!(matches!(
+            Curve::new_linear(vec![], bounds, InterpolationMode::Linear),
+            Err(MathError::InvalidCurve(_))
+        ));
+        assert_eq!(
+            Curve::new_linear(
+                vec![0.1],
+                Bounds1D::new(400.0, 400.0),
+                InterpolationMode::Linear
+            )
+            .unwrap_err(),
+            MathError::EmptyBounds
+        );
+        assert!(Curve::new_tabulated(
+            vec![(400.0, 0.1), (500.0, f32::NAN)],
+            InterpolationMode::Linear
+        )
+        .is_err());
+        assert!(
+            Curve::new_tabulated(vec![(500.0, 0.1), (400.0, 0.2)], InterpolationMode::Linear)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_curve_machine() {
+        let absorption = Curve::Linear {
+            signal: vec![0.1, 0.4, 0.9, 0.0, 0.2],
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Linear,
+        };
+        let ramp = Curve::Linear {
+            signal: vec![0.0, 0.5, 1.0, 1.5, 2.0],
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Linear,
+        };
+        // transmittance as 1 - absorption, and a ratio that is 0 where the divisor is
+        let transmittance = Curve::Machine {
+            seed: 1.0,
+            list: vec![(Op::Sub, absorption.clone())],
+        };
+        let ratio = Curve::Machine {
+            seed: 1.0,
+            list: vec![(Op::Mul, ramp.clone()), (Op::Div, absorption.clone())],
+        };
+        let mixed = Curve::Machine {
+            seed: 0.0,
+            list: vec![
+                (Op::Add, ramp.clone()),
+                (Op::Pow, Curve::Const(2.0)),
+                (Op::Max, absorption.clone()),
+                (Op::Min, Curve::Const(1.5)),
+                (Op::Clamp { min: 0.2, max: 1.2 }, Curve::Const(0.0)),
+            ],
+        };
+        let bounds = BOUNDED_VISIBLE_RANGE;
+        for i in 0..100 {
+            let lambda = bounds.lower + bounds.span() * i as f32 / 100.0;
+            let (a, r) = (absorption.evaluate(lambda), ramp.evaluate(lambda));
+            assert!((transmittance.evaluate(lambda) - (1.0 - a)).abs() < 1e-6);
+            let expected = if a == 0.0 { 0.0 } else { r / a };
+            assert!((ratio.evaluate(lambda) - expected).abs() < 1e-5 * expected.max(1.0));
+            let expected = (r * r).max(a).min(1.5).clamp(0.2, 1.2);
+            assert!((mixed.evaluate(lambda) - expected).abs() < 1e-5);
+
+            // simd evaluation and canonicalization agree with scalar evaluation
+            let lambdas = f32x4::splat(lambda) + f32x4::from_array([0.0, 0.7, 1.3, 2.9]);
+            for curve in [&transmittance, &ratio, &mixed] {
+                let canonical = curve.canonicalize();
+                for l in lambdas.to_array().iter() {
+                    let scalar = curve.evaluate_power(*l);
+                    assert!((canonical.evaluate_power(*l) - scalar).abs() < 1e-5 * scalar.max(1.0));
+                }
+                #[cfg(feature = "simdfloat_patch")]
+                {
+                    let simd = curve.evaluate_power(lambdas).to_array();
+                    for (j, l) in lambdas.to_array().iter().enumerate() {
+                        let scalar = curve.evaluate_power(*l);
+                        assert!((simd[j] - scalar).abs() < 1e-5 * scalar.max(1.0));
+                    }
+                }
+            }
+        }
+        // the divisor falls to exactly 0 at the fourth sample
+        assert_eq!(ratio.evaluate(bounds.lower + bounds.span() * 0.6), 0.0);
+    }
+
+    #[test]
+    fn test_pow_of_zero() {
+        // 0 to a negative power is 0 rather than infinite, like division by 0
+        assert_eq!(Op::Pow.apply(0.0, -2.0), 0.0);
+        assert_eq!(Op::Pow.apply(-1.0, -0.5), 0.0);
+        assert_eq!(Op::Pow.apply(0.0, 0.0), 1.0);
+        assert_eq!(Op::Pow.apply(4.0, -0.5), 0.5);
+        #[cfg(feature = "simdfloat_patch")]
+        {
+            let powers = Op::Pow.apply_f32x4(
+                f32x4::from_array([0.0, -1.0, 0.0, 4.0]),
+                f32x4::from_array([-2.0, -0.5, 0.0, -0.5]),
+            );
+            assert_eq!(powers.to_array(), [0.0, 0.0, 1.0, 0.5]);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_op_serde() {
+        let ops = [
+            Op::Add,
+            Op::Mul,
+            Op::Sub,
+            Op::Div,
+            Op::Min,
+            Op::Max,
+            Op::Pow,
+            Op::Clamp { min: 0.2, max: 1.2 },
+        ];
+        for op in ops {
+            let json = serde_json::to_string(&op).unwrap();
+            assert_eq!(serde_json::from_str::<Op>(&json).unwrap(), op, "{}", json);
+        }
+        assert_eq!(
+            serde_json::to_string(&Op::Clamp { min: 0.0, max: 1.0 }).unwrap(),
+            r#"{"op":"Clamp","min":0.0,"max":1.0}"#
+        );
+        // machines written before ops were tagged
+        let old =
+            r#"{"Machine":{"seed":1.0,"list":[["Add",{"Const":0.5}],["Mul",{"Const":2.0}]]}}"#;
+        assert_eq!(
+            serde_json::from_str::<Curve>(old).unwrap(),
+            Curve::Machine {
+                seed: 1.0,
+                list: vec![(Op::Add, Curve::Const(0.5)), (Op::Mul, Curve::Const(2.0))],
+            }
+        );
+        assert!(serde_json::from_str::<Op>(r#""Pow""#).is_err());
+    }
 
     #[test]
     fn test_cdf1() {
@@ -846,7 +1504,8 @@ mod test {
             bounds: BOUNDED_VISIBLE_RANGE,
             mode: InterpolationMode::Cubic,
         }
-        .to_cdf(BOUNDED_VISIBLE_RANGE, 100);
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
 
         let mut s = 0.0;
         for _ in 0..100 {
@@ -858,12 +1517,50 @@ mod test {
         println!("{}", s);
     }
 
+    #[test]
+    fn test_cdf_linear() {
+        let bounds = Bounds1D::new(400.0, 480.0);
+        for mode in [
+            InterpolationMode::Linear,
+            InterpolationMode::Nearest,
+            InterpolationMode::Cubic,
+        ] {
+            let curve = Curve::Linear {
+                signal: vec![1.0, 3.0, 0.0, 2.0],
+                bounds,
+                mode,
+            };
+            // the cdf uses the bins of the curve, whose masses are exact for every mode
+            let cdf = curve.to_cdf(BOUNDED_VISIBLE_RANGE, 100).unwrap();
+            assert_eq!(cdf.pdf_integral, 130.0);
+            assert!((curve.evaluate_integral(bounds, 8000, false) - 130.0).abs() < 1e-2);
+            let Curve::Tabulated { signal, .. } = &cdf.cdf else {
+                panic!()
+            };
+            let expected = [0.0, 40.0, 70.0, 90.0, 130.0];
+            assert_eq!(signal.len(), expected.len());
+            for (i, (x, c)) in signal.iter().enumerate() {
+                assert_eq!(*x, 400.0 + 20.0 * i as f32);
+                assert!((c - expected[i] / 130.0).abs() < 1e-6);
+            }
+        }
+        // curves without mass can't be normalized
+        assert!(Curve::Const(0.0).to_cdf(bounds, 10).is_err());
+        let empty = Curve::Linear {
+            signal: vec![],
+            bounds,
+            mode: InterpolationMode::Linear,
+        };
+        assert!(empty.to_cdf(bounds, 10).is_err());
+    }
+
     #[test]
     fn test_cdf2() {
         let cdf: CurveWithCDF = Curve::Exponential {
             signal: vec![(400.0, 200.0, 200.0, 0.9), (600.0, 200.0, 300.0, 1.0)],
         }
-        .to_cdf(BOUNDED_VISIBLE_RANGE, 100);
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
 
         let mut s = 0.0;
         for _ in 0..100 {
@@ -886,7 +1583,8 @@ mod test {
             bounds: BOUNDED_VISIBLE_RANGE,
             mode: InterpolationMode::Cubic,
         }
-        .to_cdf(BOUNDED_VISIBLE_RANGE, 100);
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
 
         let narrowed_bounds = Bounds1D::new(500.0, 600.0);
         let mut s = 0.0;
@@ -907,7 +1605,8 @@ mod test {
         let cdf: CurveWithCDF = Curve::Exponential {
             signal: vec![(400.0, 200.0, 200.0, 0.9), (600.0, 200.0, 300.0, 1.0)],
         }
-        .to_cdf(narrowed_bounds, 100);
+        .to_cdf(narrowed_bounds, 100)
+        .unwrap();
 
         let mut s = 0.0;
         for _ in 0..100 {
@@ -919,12 +1618,228 @@ mod test {
         println!("{}", s);
     }
 
+    #[test]
+    fn test_cdf_edges() {
+        let signal = vec![0.1, 0.4, 0.9, 1.5, 0.9, 2.0, 1.0, 0.4];
+        let cdf = Curve::Linear {
+            signal: signal.clone(),
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Linear,
+        }
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
+        let Curve::Tabulated { signal: edges, .. } = &cdf.cdf else {
+            panic!("expected cdf to be stored as edges, got {:?}", cdf.cdf);
+        };
+        // N bins, N+1 edges
+        assert_eq!(edges.len(), signal.len() + 1);
+        assert_eq!(edges[0], (BOUNDED_VISIBLE_RANGE.lower, 0.0));
+        assert_eq!(*edges.last().unwrap(), (BOUNDED_VISIBLE_RANGE.upper, 1.0));
+        assert!(edges.windows(2).all(|w| w[0].1 <= w[1].1));
+
+        // linear interpolation makes the trapezoidal rule exact.
+        let step_size = BOUNDED_VISIBLE_RANGE.span() / signal.len() as f32;
+        let expected = signal.windows(2).map(|w| 0.5 * (w[0] + w[1])).sum::<f32>() * step_size
+            + signal.last().unwrap() * step_size;
+        assert!((cdf.pdf_integral - expected).abs() / expected < 1e-5);
+    }
+
+    #[test]
+    fn test_cdf_inversion_round_trip() {
+        let cdf = Curve::Exponential {
+            signal: vec![(450.0, 30.0, 40.0, 0.9), (600.0, 20.0, 60.0, 1.0)],
+        }
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 200)
+        .unwrap();
+
+        for i in 0..1000 {
+            let u = i as f32 / 1000.0;
+            let (lambda, density) = cdf.invert(BOUNDED_VISIBLE_RANGE, u).unwrap();
+            assert!((BOUNDED_VISIBLE_RANGE.lower..=BOUNDED_VISIBLE_RANGE.upper).contains(&lambda));
+            assert!(density > 0.0);
+            let error = (cdf.cdf.evaluate(lambda) - u).abs();
+            assert!(
+                error < 1e-5,
+                "u = {}, lambda = {}, error = {}",
+                u,
+                lambda,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_cdf_cubic_inversion() {
+        // a spiky curve with a gap, so that the cdf has flat regions and abrupt changes in slope
+        let curve = Curve::Tabulated {
+            signal: vec![
+                (380.0, 0.2),
+                (450.0, 3.0),
+                (470.0, 0.0),
+                (560.0, 0.0),
+                (580.0, 8.0),
+                (600.0, 0.5),
+                (780.0, 0.1),
+            ],
+            mode: InterpolationMode::Linear,
+        };
+        let bounds = BOUNDED_VISIBLE_RANGE;
+        let cdf = curve.to_cdf(bounds, 40).unwrap().with_cubic_inversion();
+        let Curve::Tabulated { signal: edges, .. } = &cdf.cdf else {
+            panic!()
+        };
+        // evaluating the cdf is unaffected by the inversion mode
+        let linear = curve.to_cdf(bounds, 40).unwrap();
+        for lambda in [400.0, 455.5, 575.0, 700.0] {
+            assert_eq!(cdf.cdf.evaluate(lambda), linear.cdf.evaluate(lambda));
+        }
+
+        // the interpolant passes through the edges, and the inversion is monotone and consistent with it
+        for &(x, c) in edges.iter() {
+            assert!((evaluate_cdf_edges_cubic(edges, x).0 - c).abs() < 1e-6);
+        }
+        let mut last = bounds.lower;
+        for i in 0..=2000 {
+            let u = i as f32 / 2000.0;
+            let (lambda, density) = cdf.invert(bounds, u).unwrap();
+            assert!(lambda >= last, "{} {} {}", u, lambda, last);
+            last = lambda;
+            if density > 0.0 {
+                assert!((evaluate_cdf_edges_cubic(edges, lambda).0 - u).abs() < 1e-4);
+            }
+            // the density is the derivative of the cdf
+            let h = 0.05;
+            let (below, above) = (
+                evaluate_cdf_edges_cubic(edges, lambda - h).0,
+                evaluate_cdf_edges_cubic(edges, lambda + h).0,
+            );
+            let lambda_in_interior = lambda - h > bounds.lower && lambda + h < bounds.upper;
+            if lambda_in_interior {
+                assert!(((above - below) / (2.0 * h) - density).abs() < 1e-3);
+            }
+        }
+
+        // chi squared test of the sampled histogram against the interpolated cdf
+        let bins = 50;
+        let n = 100000;
+        let mut histogram = vec![0usize; bins];
+        crate::random::scoped_seed(13, || {
+            let mut sampler = RandomSampler::new();
+            for _ in 0..n {
+                let (lambda, _) = cdf.invert(bounds, sampler.draw_1d().x).unwrap();
+                let bin = ((lambda - bounds.lower) / bounds.span() * bins as f32) as usize;
+                histogram[bin.min(bins - 1)] += 1;
+            }
+        });
+        let mut chi_squared = 0.0;
+        let mut degrees_of_freedom = 0;
+        for (i, &count) in histogram.iter().enumerate() {
+            let edge = |k: usize| bounds.lower + bounds.span() * k as f32 / bins as f32;
+            let expected = n as f32
+                * (evaluate_cdf_edges_cubic(edges, edge(i + 1)).0
+                    - evaluate_cdf_edges_cubic(edges, edge(i)).0);
+            if expected < 5.0 {
+                assert!((count as f32) < 5.0 + 5.0 * expected);
+                continue;
+            }
+            chi_squared += (count as f32 - expected).powi(2) / expected;
+            degrees_of_freedom += 1;
+        }
+        // well above the 99.9th percentile for ~40 degrees of freedom
+        assert!(
+            chi_squared < 2.0 * degrees_of_freedom as f32 + 20.0,
+            "{} {}",
+            chi_squared,
+            degrees_of_freedom
+        );
+    }
+
+    #[test]
+    fn test_cdf_sampled_histogram() {
+        let signal = vec![
+            0.1, 0.4, 0.9, 1.5, 0.9, 2.0, 1.0, 0.4, 0.6, 0.9, 0.4, 1.4, 1.9, 2.0, 5.0, 9.0, 6.0,
+            3.0, 1.0, 0.4,
+        ];
+        let bounds = BOUNDED_VISIBLE_RANGE;
+        let curve = Curve::Linear {
+            signal,
+            bounds,
+            mode: InterpolationMode::Linear,
+        };
+        let cdf = curve.to_cdf(bounds, 100).unwrap();
+
+        // the sampled density is constant within each bin of the cdf, so the histogram shares its bins.
+        let bins = 20;
+        let n = 200000;
+        let mut histogram = vec![0usize; bins];
+        let mut estimate = 0.0;
+        for _ in 0..n {
+            let (we, pdf): (_, PDF<f32, _>) =
+                cdf.sample_power_and_pdf(bounds, Sample1D::new_random_sample());
+            let bin = (((we.lambda - bounds.lower) / bounds.span()) * bins as f32) as usize;
+            histogram[bin.min(bins - 1)] += 1;
+            estimate += we.energy / *pdf / n as f32;
+        }
+
+        let bin_width = bounds.span() / bins as f32;
+        for (i, &count) in histogram.iter().enumerate() {
+            let (lower, upper) = (
+                bounds.lower + i as f32 * bin_width,
+                bounds.lower + (i + 1) as f32 * bin_width,
+            );
+            let expected =
+                curve.evaluate_integral(Bounds1D::new(lower, upper), 100, false) / cdf.pdf_integral;
+            let observed = count as f32 / n as f32;
+            // allow 5 standard deviations of binomial noise
+            let tolerance = 5.0 * (expected * (1.0 - expected) / n as f32).sqrt() + 1e-4;
+            assert!(
+                (observed - expected).abs() < tolerance,
+                "bin {}: observed {}, expected {}",
+                i,
+                observed,
+                expected
+            );
+        }
+        assert!(
+            (estimate - cdf.pdf_integral).abs() / cdf.pdf_integral < 0.01,
+            "{} {}",
+            estimate,
+            cdf.pdf_integral
+        );
+    }
+
+    #[test]
+    fn test_cdf_narrowed_bounds_estimate() {
+        let curve = Curve::Exponential {
+            signal: vec![(400.0, 200.0, 200.0, 0.9), (600.0, 200.0, 300.0, 1.0)],
+        };
+        let cdf = curve.to_cdf(BOUNDED_VISIBLE_RANGE, 400).unwrap();
+        let narrowed_bounds = Bounds1D::new(500.0, 600.0);
+        let true_integral = curve.evaluate_integral(narrowed_bounds, 1000, false);
+
+        let n = 10000;
+        let mut estimate = 0.0;
+        for _ in 0..n {
+            let (we, pdf): (_, PDF<f32, _>) =
+                cdf.sample_power_and_pdf(narrowed_bounds, Sample1D::new_random_sample());
+            assert!((narrowed_bounds.lower..=narrowed_bounds.upper).contains(&we.lambda));
+            estimate += we.energy / *pdf / n as f32;
+        }
+        assert!(
+            (estimate - true_integral).abs() / true_integral < 0.01,
+            "{} {}",
+            estimate,
+            true_integral
+        );
+    }
+
     #[test]
     fn test_cdf_addition() {
         let cdf1: CurveWithCDF = Curve::Exponential {
             signal: vec![(400.0, 100.0, 100.0, 0.9), (600.0, 100.0, 100.0, 1.0)],
         }
-        .to_cdf(BOUNDED_VISIBLE_RANGE, 100);
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
 
         for i in 0..100 {
             let lambda = BOUNDED_VISIBLE_RANGE.lerp(i as f32 / 100.0);
@@ -944,7 +1859,8 @@ mod test {
             bounds: BOUNDED_VISIBLE_RANGE,
             mode: InterpolationMode::Cubic,
         }
-        .to_cdf(BOUNDED_VISIBLE_RANGE, 100);
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
 
         for i in 0..100 {
             let lambda = BOUNDED_VISIBLE_RANGE.lerp(i as f32 / 100.0);
@@ -974,6 +1890,7 @@ mod test {
             pdf: combined_spd,
             cdf: combined_cdf_curve,
             pdf_integral: integral1 + integral2,
+            cubic_inversion: false,
         };
         for i in 0..100 {
             let lambda = BOUNDED_VISIBLE_RANGE.lerp(i as f32 / 100.0);
@@ -1015,7 +1932,7 @@ mod test {
 
         let true_integral = |x: f32| x * x * x / 3.0;
         let true_integral = true_integral(1.0) - true_integral(0.0);
-        let cdf = curve.to_cdf(bounds, 100);
+        let cdf = curve.to_cdf(bounds, 100).unwrap();
 
         println!("pdf integral is {}", cdf.pdf_integral);
 
@@ -1058,7 +1975,8 @@ mod test {
             bounds: BOUNDED_VISIBLE_RANGE,
             mode: InterpolationMode::Cubic,
         }
-        .to_cdf(BOUNDED_VISIBLE_RANGE, 100);
+        .to_cdf(BOUNDED_VISIBLE_RANGE, 100)
+        .unwrap();
 
         let mut s = f32x4::ZERO;
         for _ in 0..100 {
@@ -1068,5 +1986,32 @@ mod test {
             s += we.energy / *pdf;
         }
         println!("{:?}", s);
+
+        // a cdf with a single edge has no bounds, and samples with a zero pdf rather than panicking
+        let degenerate = CurveWithCDF {
+            pdf: Curve::Const(1.0),
+            cdf: Curve::Tabulated {
+                signal: vec![(500.0, 1.0)],
+                mode: InterpolationMode::Linear,
+            },
+            pdf_integral: 1.0,
+            cubic_inversion: false,
+        };
+        let (_, pdf): (_, PDF<f32x4, _>) =
+            degenerate.sample_power_and_pdf(BOUNDED_VISIBLE_RANGE, Sample1D::new(0.5));
+        assert_eq!(*pdf, f32x4::ZERO);
+    }
+
+    #[test]
+    fn test_convert_to_xyz_with_cmf() {
+        let flat = Curve::Const(1.0);
+        let bounds = Bounds1D::new(380.0, 781.0);
+        let tabulated = flat.convert_to_xyz_with_cmf(bounds, 1.0, false, CMF::Tabulated);
+        let fitted = flat.convert_to_xyz_with_cmf(bounds, 1.0, false, CMF::Fitted);
+        assert_eq!(fitted.0, flat.convert_to_xyz(bounds, 1.0, false).0);
+        // illuminant E integrates to equal tristimulus values with the tabulated data
+        assert!((tabulated.x() - tabulated.y()).abs() < 0.01 * tabulated.y());
+        assert!((tabulated.z() - tabulated.y()).abs() < 0.01 * tabulated.y());
+        assert!((fitted.y() - tabulated.y()).abs() < 0.02 * tabulated.y());
     }
 }
@@ -0,0 +1,134 @@
+use crate::prelude::*;
+
+// scattering and absorption coefficients of the earth's atmosphere, in 1/m, for volume rendering and sky models.
+// all curves are tabulated over `ATMOSPHERE_RANGE` and hold their end values outside of it.
+// they describe the atmosphere at a given density, which falls off with altitude roughly as exp(-h / H),
+// with scale heights H of about 8km for air and 1.2km for aerosols.
+
+/// wavelength range over which the atmospheric coefficients are tabulated
+pub const ATMOSPHERE_RANGE: Bounds1D = Bounds1D::new(360.0, 830.0);
+const STEP: f32 = 10.0;
+
+/// number of molecules per m^2 in a column of 1 dobson unit, the unit in which ozone columns are measured
+pub const DOBSON_UNIT: f32 = 2.687e20;
+
+// number density of standard air at 15C and 101325Pa, in molecules per m^3
+const AIR_NUMBER_DENSITY: f64 = 2.546899e25;
+// depolarization factor of air, which corrects for the anisotropy of its molecules
+const AIR_DEPOLARIZATION: f64 = 0.0279;
+
+// absorption cross section of ozone in m^2 per molecule, from 360nm to 830nm in 10nm increments,
+// from the measurements of Serdyuchenko et al. (2014) at 293K, as resampled by Bruneton (2017).
+const OZONE_CROSS_SECTION: [f32; 48] = [
+    1.18e-27, 2.182e-28, 2.818e-28, 6.636e-28, 1.527e-27, 2.763e-27, 5.52e-27, 8.451e-27,
+    1.582e-26, 2.316e-26, 3.669e-26, 4.924e-26, 7.752e-26, 9.016e-26, 1.48e-25, 1.602e-25,
+    2.139e-25, 2.755e-25, 3.091e-25, 3.5e-25, 4.266e-25, 4.672e-25, 4.398e-25, 4.701e-25,
+    5.019e-25, 4.305e-25, 3.74e-25, 3.215e-25, 2.662e-25, 2.238e-25, 1.852e-25, 1.473e-25,
+    1.209e-25, 9.423e-26, 7.455e-26, 6.566e-26, 5.105e-26, 4.15e-26, 4.228e-26, 3.237e-26,
+    2.451e-26, 2.801e-26, 2.534e-26, 1.624e-26, 1.465e-26, 2.078e-26, 1.383e-26, 7.105e-27,
+];
+
+fn tabulate(func: impl Fn(f32) -> f32) -> Curve {
+    let samples = (ATMOSPHERE_RANGE.span() / STEP) as usize + 1;
+    Curve::Tabulated {
+        signal: (0..samples)
+            .map(|i| {
+                let lambda = ATMOSPHERE_RANGE.lower + STEP * i as f32;
+                (lambda, func(lambda))
+            })
+            .collect(),
+        mode: InterpolationMode::Linear,
+    }
+}
+
+// refractive index of standard air minus 1, following Peck and Reeder (1972)
+fn air_refractivity(lambda: f64) -> f64 {
+    let sigma_squared = (1000.0 / lambda).powi(2);
+    (8060.51 + 2480990.0 / (132.274 - sigma_squared) + 17455.7 / (39.32957 - sigma_squared)) * 1e-8
+}
+
+/// the Angstrom turbidity coefficient beta, the aerosol optical depth at 1um,
+/// for a Linke turbidity as used by the Preetham sky model
+pub fn angstrom_beta(turbidity: f32) -> f32 {
+    0.04608 * turbidity - 0.04586
+}
+
+impl Curve {
+    /// the Rayleigh scattering coefficient of air at `relative_density` times the density at sea level.
+    /// computed from the refractive index of air and scales with roughly lambda^-4.
+    pub fn rayleigh_scattering(relative_density: f32) -> Curve {
+        let king_factor = (6.0 + 3.0 * AIR_DEPOLARIZATION) / (6.0 - 7.0 * AIR_DEPOLARIZATION);
+        tabulate(|lambda| {
+            let lambda = lambda as f64;
+            let n_squared = (1.0 + air_refractivity(lambda)).powi(2);
+            let polarizability = (n_squared - 1.0) / (n_squared + 2.0);
+            let meters = lambda * 1e-9;
+            let cross_section = 24.0 * std::f64::consts::PI.powi(3) * polarizability.powi(2)
+                / (meters.powi(4) * AIR_NUMBER_DENSITY.powi(2))
+                * king_factor;
+            (cross_section * AIR_NUMBER_DENSITY * relative_density as f64) as f32
+        })
+    }
+
+    /// the absorption coefficient of an ozone column of `dobson_units` spread evenly over a layer of
+    /// `layer_thickness` meters. the earth has about 300 dobson units of ozone, mostly between 10km and 40km.
+    /// ozone barely scatters, but its absorption in the Chappuis band around 600nm turns the twilight sky blue.
+    pub fn ozone_absorption(dobson_units: f32, layer_thickness: f32) -> Curve {
+        let number_density = dobson_units * DOBSON_UNIT / layer_thickness;
+        tabulate(|lambda| {
+            let index = ((lambda - ATMOSPHERE_RANGE.lower) / STEP).round() as usize;
+            OZONE_CROSS_SECTION[index.min(OZONE_CROSS_SECTION.len() - 1)] * number_density
+        })
+    }
+
+    /// the extinction coefficient of aerosols following Angstrom's turbidity formula, beta (lambda / 1um)^-alpha,
+    /// for an aerosol optical depth `beta` at 1um distributed with `scale_height` meters.
+    /// alpha is about 1.3 for continental aerosols, and lower for larger particles such as haze and sea salt.
+    /// aerosols mostly scatter, with a single scattering albedo of about 0.9.
+    pub fn mie_extinction(beta: f32, alpha: f32, scale_height: f32) -> Curve {
+        tabulate(|lambda| beta * (lambda / 1000.0).powf(-alpha) / scale_height)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_atmosphere() {
+        // the rayleigh optical depth of the whole atmosphere, whose column is as much air as 8.44km at sea level,
+        // is within a few percent of the empirical fit used by the sky model
+        let rayleigh = Curve::rayleigh_scattering(1.0);
+        let at_550 = rayleigh.evaluate_power(550.0);
+        assert!((at_550 - 1.15e-5).abs() < 0.02e-5, "{}", at_550);
+        for lambda in [400.0, 450.0, 550.0, 650.0, 700.0] {
+            let depth = rayleigh.evaluate_power(lambda) * 8440.0;
+            let fit = 0.008735 * (lambda / 1000.0f32).powf(-4.08);
+            assert!(
+                (depth - fit).abs() < 0.05 * fit,
+                "{} {} {}",
+                lambda,
+                depth,
+                fit
+            );
+        }
+        // half the density scatters half as much
+        assert!(
+            (Curve::rayleigh_scattering(0.5).evaluate_power(550.0) - at_550 / 2.0).abs() < 1e-9
+        );
+
+        // 300 dobson units absorb at most a few percent, peaking in the Chappuis band
+        let ozone = Curve::ozone_absorption(300.0, 25000.0);
+        let depth = |lambda: f32| ozone.evaluate_power(lambda) * 25000.0;
+        assert!((depth(600.0) - 0.0405).abs() < 1e-3, "{}", depth(600.0));
+        assert!(depth(600.0) > depth(450.0) && depth(600.0) > depth(750.0));
+
+        // aerosols scatter less selectively than air, and the optical depth of the column is beta at 1um
+        let beta = angstrom_beta(3.0);
+        let mie = Curve::mie_extinction(beta, 1.3, 1200.0);
+        assert!((mie.evaluate_power(830.0) * 1200.0 - beta * 0.83f32.powf(-1.3)).abs() < 1e-6);
+        let mie_ratio = mie.evaluate_power(400.0) / mie.evaluate_power(700.0);
+        let rayleigh_ratio = rayleigh.evaluate_power(400.0) / rayleigh.evaluate_power(700.0);
+        assert!(mie_ratio > 1.0 && mie_ratio < rayleigh_ratio);
+    }
+}
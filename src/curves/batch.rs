@@ -0,0 +1,127 @@
+use crate::prelude::*;
+use crate::spectral::{x_bar_bounded, y_bar_bounded, z_bar_bounded, CMF_SUPPORT};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// conversion of many curves at once, i.e. for previews of palettes and material libraries.
+// the color matching functions are the same for every curve, so they're tabulated once at the integration wavelengths
+// and shared, while the curves themselves are evaluated four wavelengths at a time,
+// and spread across threads with the `parallel` feature.
+
+// the color matching functions at each wavelength, premultiplied by the step size
+fn tabulate_cmf(lambdas: impl Iterator<Item = f32>, step_size: f32) -> Vec<[f32; 3]> {
+    lambdas
+        .map(|lambda| {
+            let angstroms = lambda * 10.0;
+            [
+                x_bar_bounded(angstroms, CMF_SUPPORT) * step_size,
+                y_bar_bounded(angstroms, CMF_SUPPORT) * step_size,
+                z_bar_bounded(angstroms, CMF_SUPPORT) * step_size,
+            ]
+        })
+        .collect()
+}
+
+/// integrates every curve against the color matching functions, in parallel across curves with the `parallel` feature.
+/// equivalent to calling `convert_to_xyz(integration_bounds, step_size, false)` on each curve,
+/// up to the order of summation.
+pub fn convert_many_to_xyz(
+    curves: &[Curve],
+    integration_bounds: Bounds1D,
+    step_size: f32,
+) -> Vec<XYZColor> {
+    let iterations = (integration_bounds.span() / step_size) as usize;
+    let lambda = |i: usize| integration_bounds.lower + (i as f32) * step_size;
+    let cmf = tabulate_cmf((0..iterations).map(lambda), step_size);
+
+    #[cfg(feature = "simdfloat_patch")]
+    let (packets, start) = {
+        let packets: Vec<(f32x4, [f32x4; 3])> = cmf
+            .chunks_exact(4)
+            .enumerate()
+            .map(|(chunk, weights)| {
+                let lambdas = f32x4::from_array(std::array::from_fn(|j| lambda(4 * chunk + j)));
+                let channel = |c: usize| f32x4::from_array(std::array::from_fn(|j| weights[j][c]));
+                (lambdas, [channel(0), channel(1), channel(2)])
+            })
+            .collect();
+        let start = 4 * packets.len();
+        (packets, start)
+    };
+    #[cfg(not(feature = "simdfloat_patch"))]
+    let start = 0;
+
+    #[cfg(feature = "parallel")]
+    let curves = curves.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let curves = curves.iter();
+
+    curves
+        .map(|curve| {
+            #[cfg(feature = "simdfloat_patch")]
+            let mut sum = {
+                let mut acc = [f32x4::ZERO; 3];
+                for (lambdas, [x, y, z]) in &packets {
+                    let val: f32x4 = curve.evaluate_power(*lambdas);
+                    acc[0] += val * x;
+                    acc[1] += val * y;
+                    acc[2] += val * z;
+                }
+                XYZColor::new(
+                    acc[0].reduce_sum(),
+                    acc[1].reduce_sum(),
+                    acc[2].reduce_sum(),
+                )
+            };
+            #[cfg(not(feature = "simdfloat_patch"))]
+            let mut sum = XYZColor::ZERO;
+
+            // remainder that didn't fit in a packet
+            for (i, [x, y, z]) in cmf.iter().enumerate().skip(start) {
+                let val = curve.evaluate_power(lambda(i));
+                sum.0 += f32x4::from_array([val * x, val * y, val * z, 0.0]);
+            }
+            sum
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+    #[test]
+    fn test_convert_many_to_xyz() {
+        let mut curves: Vec<Curve> = (0..40)
+            .map(|i| Curve::Blackbody {
+                temperature: 2000.0 + 200.0 * i as f32,
+                boost: 1.0,
+            })
+            .collect();
+        curves.push(Curve::Linear {
+            signal: vec![0.1, 0.4, 0.9, 0.5, 0.2, 0.7, 0.8, 0.3],
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Cubic,
+        });
+        curves.push(Curve::Const(0.0));
+        // a step size that leaves a remainder outside of the packets
+        for step_size in [1.0, 0.7] {
+            let colors = convert_many_to_xyz(&curves, BOUNDED_VISIBLE_RANGE, step_size);
+            assert_eq!(colors.len(), curves.len());
+            for (curve, color) in curves.iter().zip(colors.iter()) {
+                let expected = curve.convert_to_xyz(BOUNDED_VISIBLE_RANGE, step_size, false);
+                for (a, b) in color.0.to_array().iter().zip(expected.0.to_array().iter()) {
+                    assert!(
+                        (a - b).abs() <= 1e-4 * b.abs().max(1.0),
+                        "{:?} {:?}",
+                        color,
+                        expected
+                    );
+                }
+            }
+        }
+        assert!(convert_many_to_xyz(&[], BOUNDED_VISIBLE_RANGE, 1.0).is_empty());
+    }
+}
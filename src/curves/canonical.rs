@@ -0,0 +1,355 @@
+use crate::prelude::*;
+use crate::spectral::CMF_SUPPORT;
+
+use super::Op;
+
+// canonical forms and approximate hashes, so that asset pipelines can deduplicate and cache spectra.
+// `canonicalize` only rewrites curves into forms that evaluate identically, so it can be applied unconditionally,
+// while `content_hash` compares what curves evaluate to, regardless of how they're built.
+
+// curves that never evaluate to negative values, which makes some rewrites exact that would otherwise change clamping
+fn is_nonnegative(curve: &Curve) -> bool {
+    match curve {
+        Curve::Const(_)
+        | Curve::Polynomial { .. }
+        | Curve::InverseExponential { .. }
+        | Curve::Machine { .. } => true,
+        Curve::Blackbody { boost, .. } => *boost >= 0.0,
+        Curve::Linear { signal, .. } => signal.iter().all(|v| *v >= 0.0),
+        Curve::Tabulated { signal, .. } => signal.iter().all(|(_, y)| *y >= 0.0),
+        Curve::SplitTabulated(split) => split.ys().iter().all(|y| *y >= 0.0),
+        Curve::Exponential { signal } => signal.iter().all(|lobe| lobe.3 >= 0.0),
+        Curve::Clamped { min, .. } => *min >= 0.0,
+        Curve::Affine {
+            curve,
+            scale,
+            offset,
+        } => *scale >= 0.0 && *offset >= 0.0 && is_nonnegative(curve),
+        Curve::Cauchy { a, b } => *a >= 0.0 && *b >= 0.0,
+    }
+}
+
+// the right identity of `op`, i.e. the operand that leaves any running value unchanged, if there is one
+fn identity(op: Op) -> Option<f32> {
+    match op {
+        Op::Add | Op::Sub => Some(0.0),
+        Op::Mul | Op::Div => Some(1.0),
+        Op::Min | Op::Max | Op::Pow | Op::Clamp { .. } => None,
+    }
+}
+
+// ops whose consecutive applications can be reordered. this excludes Pow, where the clamp of negative bases
+// depends on the order, and Clamp, whose operands aren't curves
+fn is_commutative(op: Op) -> bool {
+    matches!(
+        op,
+        Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Min | Op::Max
+    )
+}
+
+// an arbitrary but deterministic total order on canonical curves, for sorting commutative operands
+fn sort_key(curve: &Curve) -> String {
+    format!("{:?}", curve)
+}
+
+// normalizes negative zero, so that curves that compare equal also print the same
+fn zero_sign(v: f32) -> f32 {
+    if v == 0.0 {
+        0.0
+    } else {
+        v
+    }
+}
+
+fn canonicalize_machine(seed: f32, list: Vec<(Op, Curve)>) -> Curve {
+    let mut seed = seed;
+    let mut flattened: Vec<(Op, Curve)> = Vec::new();
+    for (op, curve) in list {
+        match curve.canonicalize() {
+            Curve::Const(c) if Some(c) == identity(op) => {}
+            // constants at the start of the list fold into the seed
+            Curve::Const(c) if flattened.is_empty() => seed = op.apply(seed, c),
+            // a nested machine that only applies `op` to nonnegative curves starting from its identity
+            // can't go below 0 before its final clamp, so its operands can be spliced into the outer list
+            Curve::Machine {
+                seed: inner_seed,
+                list: inner,
+            } if matches!(op, Op::Add | Op::Mul)
+                && Some(inner_seed) == identity(op)
+                && inner
+                    .iter()
+                    .all(|(inner_op, c)| *inner_op == op && is_nonnegative(c)) =>
+            {
+                flattened.extend(inner);
+            }
+            curve => flattened.push((op, curve)),
+        }
+    }
+    // consecutive operands of the same op commute, as long as nothing goes negative in between,
+    // at which point the order of the clamp no longer matters either
+    let mut start = 0;
+    while start < flattened.len() {
+        let op = flattened[start].0;
+        let mut end = start;
+        while end < flattened.len() && flattened[end].0 == op {
+            end += 1;
+        }
+        if is_commutative(op) && flattened[start..end].iter().all(|(_, c)| is_nonnegative(c)) {
+            flattened[start..end].sort_by_cached_key(|(_, c)| sort_key(c));
+        }
+        start = end;
+    }
+    match flattened.as_slice() {
+        [] => Curve::Const(zero_sign(seed.max(0.0))),
+        // only addition and multiplication have left identities, e.g. 1 / x is not x
+        [(op, curve)]
+            if matches!(op, Op::Add | Op::Mul)
+                && Some(seed) == identity(*op)
+                && is_nonnegative(curve) =>
+        {
+            curve.clone()
+        }
+        _ => Curve::Machine {
+            seed: zero_sign(seed),
+            list: flattened,
+        },
+    }
+}
+
+impl Curve {
+    /// rewrites `self` into a canonical form that evaluates identically, such that curves that are built differently
+    /// but are structurally equivalent compare equal afterwards. this flattens nested machines,
+    /// folds constants, sorts commutative operands and lobes, merges nested `Clamped` and `Affine` wrappers,
+    /// and replaces flat nonnegative signals with `Const`.
+    /// it doesn't detect every pair of equal curves, see `content_hash` for a comparison by value.
+    pub fn canonicalize(&self) -> Curve {
+        match self {
+            Curve::Const(c) => Curve::Const(zero_sign(c.max(0.0))),
+            // signals don't clamp but `Const` does, so only nonnegative flat signals can become constants
+            Curve::Linear { signal, .. }
+                if !signal.is_empty()
+                    && signal[0] >= 0.0
+                    && signal.windows(2).all(|w| w[0] == w[1]) =>
+            {
+                Curve::Const(zero_sign(signal[0]))
+            }
+            // the split layout is an optimization, and canonicalizes to the tabulated curve it was built from
+            Curve::SplitTabulated(split) => Curve::Tabulated {
+                signal: split.signal(),
+                mode: split.mode(),
+            }
+            .canonicalize(),
+            Curve::Tabulated { signal, .. }
+                if !signal.is_empty()
+                    && signal[0].1 >= 0.0
+                    && signal.windows(2).all(|w| w[0].1 == w[1].1) =>
+            {
+                Curve::Const(zero_sign(signal[0].1))
+            }
+            Curve::Exponential { signal } | Curve::InverseExponential { signal } => {
+                // the lobes are summed, so their order doesn't matter
+                let mut lobes: Vec<_> = signal.iter().copied().filter(|l| l.3 != 0.0).collect();
+                lobes.sort_by(|a, b| {
+                    let a = [a.0, a.1, a.2, a.3];
+                    let b = [b.0, b.1, b.2, b.3];
+                    a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+                });
+                match (self, lobes.is_empty()) {
+                    (Curve::Exponential { .. }, true) => Curve::Const(0.0),
+                    (Curve::Exponential { .. }, false) => Curve::Exponential { signal: lobes },
+                    (_, true) => Curve::Const(1.0),
+                    (_, false) => Curve::InverseExponential { signal: lobes },
+                }
+            }
+            Curve::Machine { seed, list } => canonicalize_machine(*seed, list.clone()),
+            Curve::Clamped { curve, min, max } => match curve.canonicalize() {
+                Curve::Const(c) => Curve::Const(zero_sign(c.clamp(*min, *max).max(0.0))),
+                // clamp(clamp(x, a, b), c, d) is clamp(x, max(a, c), min(b, d)) when the ranges overlap,
+                // and otherwise the constant at the nearest end of [c, d]
+                Curve::Clamped {
+                    curve,
+                    min: inner_min,
+                    max: inner_max,
+                } => Curve::Clamped {
+                    curve,
+                    min: inner_min.max(*min).min(*max),
+                    max: inner_max.min(*max).max(*min),
+                },
+                curve => curve.clamped(*min, *max),
+            },
+            Curve::Affine {
+                curve,
+                scale,
+                offset,
+            } => match curve.canonicalize() {
+                _ if *scale == 1.0 && *offset == 0.0 => curve.canonicalize(),
+                Curve::Affine {
+                    curve,
+                    scale: inner_scale,
+                    offset: inner_offset,
+                } => Curve::Affine {
+                    curve,
+                    scale: scale * inner_scale,
+                    offset: scale * inner_offset + offset,
+                }
+                .canonicalize(),
+                curve => curve.affine(*scale, *offset),
+            },
+            curve => curve.clone(),
+        }
+    }
+
+    /// an approximate hash of the values of `self`, sampled at `resolution` points across `CMF_SUPPORT`
+    /// and rounded to about 4 significant digits, so that curves that evaluate to nearly the same values
+    /// usually hash the same, however they're represented.
+    /// values that happen to straddle a rounding boundary can still hash differently,
+    /// so equal hashes should be treated as candidates for deduplication, to be confirmed by comparing values.
+    /// the hash is FNV-1a, so it is stable across platforms and builds, and can be stored in caches on disk.
+    pub fn content_hash(&self, resolution: usize) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+        // keep the top 13 bits of the mantissa
+        const MANTISSA_MASK: u32 = !((1 << 10) - 1);
+        let step = CMF_SUPPORT.span() / resolution as f32;
+        let mut hash = FNV_OFFSET;
+        for i in 0..resolution {
+            let lambda = CMF_SUPPORT.lower + (i as f32 + 0.5) * step;
+            let value = self.evaluate(lambda);
+            let bits = if value == 0.0 || value.is_nan() {
+                0
+            } else {
+                // round to nearest by adding half of the dropped range before masking
+                (value.to_bits().wrapping_add(1 << 9)) & MANTISSA_MASK
+            };
+            for byte in bits.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let blackbody = Curve::Blackbody {
+            temperature: 5000.0,
+            boost: 1.0,
+        };
+        let lobes = Curve::Exponential {
+            signal: vec![(450.0, 20.0, 30.0, 0.5), (600.0, 40.0, 10.0, 1.0)],
+        };
+        let a = Curve::Machine {
+            seed: 1.0,
+            list: vec![
+                (Op::Mul, blackbody.clone()),
+                (
+                    Op::Mul,
+                    Curve::Machine {
+                        seed: 1.0,
+                        list: vec![(Op::Mul, lobes.clone()), (Op::Mul, Curve::Const(1.0))],
+                    },
+                ),
+            ],
+        };
+        let b = Curve::Machine {
+            seed: 2.0,
+            list: vec![
+                (Op::Mul, Curve::Const(0.5)),
+                (
+                    Op::Mul,
+                    Curve::Exponential {
+                        signal: vec![(600.0, 40.0, 10.0, 1.0), (450.0, 20.0, 30.0, 0.5)],
+                    },
+                ),
+                (Op::Mul, blackbody.clone().affine(1.0, 0.0)),
+            ],
+        };
+        assert_ne!(a, b);
+        let (ca, cb) = (a.canonicalize(), b.canonicalize());
+        assert_eq!(ca, cb);
+        assert_eq!(ca.canonicalize(), ca);
+        for lambda in [400.0, 500.0, 555.0, 700.0] {
+            assert!((ca.evaluate(lambda) - a.evaluate(lambda)).abs() < 1e-6);
+        }
+
+        // wrappers merge, and flat or empty curves become constants
+        assert_eq!(
+            blackbody
+                .clone()
+                .affine(2.0, 1.0)
+                .affine(0.5, -0.5)
+                .canonicalize(),
+            blackbody.clone()
+        );
+        assert_eq!(
+            lobes
+                .clone()
+                .clamped(0.0, 0.5)
+                .clamped(0.2, 1.0)
+                .canonicalize(),
+            lobes.clone().clamped(0.2, 0.5)
+        );
+        assert_eq!(
+            lobes
+                .clone()
+                .clamped(0.0, 0.1)
+                .clamped(0.2, 1.0)
+                .evaluate(450.0),
+            lobes
+                .clamped(0.0, 0.1)
+                .clamped(0.2, 1.0)
+                .canonicalize()
+                .evaluate(450.0)
+        );
+        assert_eq!(
+            Curve::Linear {
+                signal: vec![0.3; 8],
+                bounds: Bounds1D::new(400.0, 700.0),
+                mode: InterpolationMode::Cubic,
+            }
+            .canonicalize(),
+            Curve::Const(0.3)
+        );
+        // negative flat signals evaluate unclamped, and empty signals have no value to fold
+        let negative = Curve::Tabulated {
+            signal: vec![(400.0, -0.2), (700.0, -0.2)],
+            mode: InterpolationMode::Linear,
+        };
+        assert_eq!(negative.canonicalize(), negative);
+        let empty = Curve::Linear {
+            signal: vec![],
+            bounds: Bounds1D::new(400.0, 700.0),
+            mode: InterpolationMode::Linear,
+        };
+        assert_eq!(empty.canonicalize(), empty);
+        assert_eq!(
+            Curve::InverseExponential { signal: vec![] }.canonicalize(),
+            Curve::Const(1.0)
+        );
+
+        // content hashes compare values rather than representations
+        assert_eq!(a.content_hash(64), b.content_hash(64));
+        assert_eq!(
+            Curve::Const(0.0).content_hash(64),
+            Curve::Const(-1.0).content_hash(64)
+        );
+        let matching = [0.3, 0.5, 1.0, 2.0]
+            .iter()
+            .filter(|&&boost| {
+                let scaled = Curve::Blackbody {
+                    temperature: 5000.0,
+                    boost,
+                };
+                scaled.content_hash(32) == blackbody.clone().affine(boost, 0.0).content_hash(32)
+            })
+            .count();
+        // nearly equal curves usually, but not always, hash the same
+        assert!(matching >= 3, "{}", matching);
+        assert_ne!(a.content_hash(64), blackbody.content_hash(64));
+    }
+}
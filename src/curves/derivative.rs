@@ -0,0 +1,246 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+// evaluation of curves on dual numbers, giving the derivative wrt wavelength alongside the value.
+// the analytic variants and the sampled and tabulated curves are differentiated exactly, which is what dispersion sampling
+// and curve fitting need. the remaining variants are rejected rather than silently differentiated numerically.
+
+// the asymmetric gaussian of `gaussianf32`, on duals
+fn gaussian_dual(x: Dual<f32>, alpha: f32, mu: f32, sigma1: f32, sigma2: f32) -> Dual<f32> {
+    let u = (x - mu) / (if x.value < mu { sigma1 } else { sigma2 });
+    (u * u * -0.5).exp() * alpha
+}
+
+// `interpolate` on duals, between the tabulated samples `left` and `right`
+fn interpolate_dual(
+    mode: InterpolationMode,
+    left: (f32, f32),
+    right: (f32, f32),
+    x: Dual<f32>,
+) -> Dual<f32> {
+    let t = (x - left.0) / (right.0 - left.0);
+    match mode {
+        InterpolationMode::Linear => (-t + 1.0) * left.1 + t * right.1,
+        InterpolationMode::Nearest => Dual::constant(if t.value < 0.5 { left.1 } else { right.1 }),
+        InterpolationMode::Cubic => {
+            let one_sub_t = -t + 1.0;
+            let h00 = (t * 2.0 + 1.0) * one_sub_t * one_sub_t;
+            let h01 = t * t * (-(t * 2.0) + 3.0);
+            h00 * left.1 + h01 * right.1
+        }
+    }
+}
+
+// evaluates sorted samples at x, constant beyond the first and last samples
+fn tabulated_dual(
+    len: usize,
+    sample: impl Fn(usize) -> (f32, f32),
+    mode: InterpolationMode,
+    x: Dual<f32>,
+) -> Dual<f32> {
+    // the number of samples strictly below x, as found by the binary searches of `evaluate`
+    let (mut lower, mut upper) = (0, len);
+    while lower < upper {
+        let middle = (lower + upper) / 2;
+        if sample(middle).0 < x.value {
+            lower = middle + 1;
+        } else {
+            upper = middle;
+        }
+    }
+    if lower == len {
+        return Dual::constant(sample(len - 1).1);
+    }
+    if lower == 0 {
+        return Dual::constant(sample(0).1);
+    }
+    interpolate_dual(mode, sample(lower - 1), sample(lower), x)
+}
+
+impl Curve {
+    /// evaluates self at `x`, propagating the derivative of x through the curve by the chain rule.
+    /// seed x with `Dual::variable(lambda)` to get the derivative wrt lambda.
+    /// supports `Const`, `Linear`, `Tabulated`, `SplitTabulated`, `Polynomial`, `Cauchy`, `Exponential`
+    /// and `InverseExponential`,
+    /// and `Clamped` and `Affine` wrappers around them. other variants fail with `InvalidCurve`.
+    /// the values agree with `evaluate`, and where it clamps, the derivative is 0.
+    pub fn evaluate_dual(&self, x: Dual<f32>) -> Result<Dual<f32>> {
+        let zero = Dual::constant(0.0);
+        Ok(match self {
+            Curve::Const(v) => Dual::constant(v.max(0.0)),
+            Curve::Linear {
+                signal,
+                bounds,
+                mode,
+            } => {
+                if x.value <= bounds.lower {
+                    return Ok(Dual::constant(*signal.first().unwrap()));
+                } else if x.value >= bounds.upper {
+                    return Ok(Dual::constant(*signal.last().unwrap()));
+                }
+                let step_size = bounds.span() / (signal.len() as f32);
+                let index = ((x.value - bounds.lower) / step_size) as usize;
+                let left = signal[index];
+                let right = if index + 1 < signal.len() {
+                    signal[index + 1]
+                } else {
+                    return Ok(Dual::constant(left));
+                };
+                let t = (x - (bounds.lower + index as f32 * step_size)) / step_size;
+                match mode {
+                    InterpolationMode::Linear => (-t + 1.0) * left + t * right,
+                    InterpolationMode::Nearest => {
+                        Dual::constant(if t.value < 0.5 { left } else { right })
+                    }
+                    InterpolationMode::Cubic => {
+                        let one_sub_t = -t + 1.0;
+                        let h00 = (t * 2.0 + 1.0) * one_sub_t * one_sub_t;
+                        let h01 = t * t * (-(t * 2.0) + 3.0);
+                        h00 * left + h01 * right
+                    }
+                }
+            }
+            Curve::Tabulated { signal, mode } => {
+                tabulated_dual(signal.len(), |i| signal[i], *mode, x)
+            }
+            Curve::SplitTabulated(split) => {
+                let (xs, ys) = (split.xs(), split.ys());
+                tabulated_dual(xs.len(), |i| (xs[i], ys[i]), split.mode(), x)
+            }
+            Curve::Polynomial {
+                domain_range_mapping,
+                coefficients,
+            } => {
+                let [x0, xs, y0, ys]: [f32; 4] = *domain_range_mapping;
+                let x = (x - x0) / xs;
+                let mut val = Dual::constant(y0);
+                for (i, &coef) in coefficients.iter().enumerate() {
+                    val += x.powi(i as i32 + 1) * (ys * coef);
+                }
+                Field::max(&val, zero)
+            }
+            Curve::Cauchy { a, b } => Dual::constant(*b) / (x * x) + *a,
+            Curve::Exponential { signal } => {
+                let mut val = zero;
+                for &(offset, sigma1, sigma2, multiplier) in signal {
+                    val += gaussian_dual(x, multiplier, offset, sigma1, sigma2);
+                }
+                val
+            }
+            Curve::InverseExponential { signal } => {
+                let mut val = Dual::constant(1.0);
+                for &(offset, sigma1, sigma2, multiplier) in signal {
+                    val = val - gaussian_dual(x, multiplier, offset, sigma1, sigma2);
+                }
+                Field::max(&val, zero)
+            }
+            Curve::Clamped { curve, min, max } => curve.evaluate_dual(x)?.clamp(*min, *max),
+            Curve::Affine {
+                curve,
+                scale,
+                offset,
+            } => curve.evaluate_dual(x)? * *scale + *offset,
+            _ => {
+                return Err(MathError::InvalidCurve(
+                    "curve variant doesn't support dual evaluation".to_string(),
+                ))
+            }
+        })
+    }
+
+    /// the derivative of self wrt x, d/dx f(x). see `evaluate_dual` for the supported variants.
+    pub fn derivative(&self, x: f32) -> Result<f32> {
+        Ok(self.evaluate_dual(Dual::variable(x))?.derivative)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_curve_derivative() {
+        let curves = [
+            Curve::Const(0.5),
+            Curve::Cauchy { a: 1.5, b: 4200.0 },
+            Curve::y_bar(),
+            Curve::InverseExponential {
+                signal: vec![(550.0, 30.0, 50.0, 0.6)],
+            },
+            Curve::Polynomial {
+                domain_range_mapping: [600.0, 200.0, 0.5, 0.3],
+                coefficients: [0.2, -0.4, 0.1, 0.0, 0.0, 0.0, 0.0, 0.0],
+            },
+            Curve::Linear {
+                signal: vec![0.1, 0.4, 0.3, 0.8, 0.6],
+                bounds: Bounds1D::new(400.0, 700.0),
+                mode: InterpolationMode::Cubic,
+            },
+            Curve::Linear {
+                signal: vec![0.1, 0.4, 0.3, 0.8, 0.6],
+                bounds: Bounds1D::new(400.0, 700.0),
+                mode: InterpolationMode::Linear,
+            },
+            Curve::y_bar().affine(2.0, 0.1).clamped(0.0, 1.5),
+            Curve::Tabulated {
+                signal: vec![
+                    (400.0, 0.2),
+                    (450.0, 0.5),
+                    (520.0, 0.4),
+                    (600.0, 0.9),
+                    (680.0, 0.3),
+                ],
+                mode: InterpolationMode::Cubic,
+            },
+            Curve::Tabulated {
+                signal: vec![
+                    (400.0, 0.2),
+                    (450.0, 0.5),
+                    (520.0, 0.4),
+                    (600.0, 0.9),
+                    (680.0, 0.3),
+                ],
+                mode: InterpolationMode::Linear,
+            }
+            .optimize(),
+        ];
+        // against central differences in f64 of the f32 evaluation, away from the knots of the linear curves
+        let h = 1e-2f32;
+        for curve in &curves {
+            for &lambda in &[430.0f32, 487.0, 555.5, 621.0] {
+                let dual = curve.evaluate_dual(Dual::variable(lambda)).unwrap();
+                assert_eq!(dual.value, curve.evaluate(lambda), "{:?}", curve);
+                let difference = (curve.evaluate(lambda + h) as f64
+                    - curve.evaluate(lambda - h) as f64)
+                    / (2.0 * h as f64);
+                assert!(
+                    (dual.derivative as f64 - difference).abs() < 1e-3 * (1.0 + difference.abs()),
+                    "{:?} at {}: {} {}",
+                    curve,
+                    lambda,
+                    dual.derivative,
+                    difference
+                );
+            }
+        }
+        // cauchy's equation has n'(lambda) = -2 b / lambda^3
+        assert!(
+            (curves[1].derivative(500.0).unwrap() + 2.0 * 4200.0 / 500.0f32.powi(3)).abs() < 1e-9
+        );
+        // derivatives chain through a dual input, d/dt f(2t) = 2 f'(2t)
+        let chained = curves[2].evaluate_dual(Dual::new(500.0, 2.0)).unwrap();
+        assert_eq!(
+            chained.derivative,
+            2.0 * curves[2].derivative(500.0).unwrap()
+        );
+
+        let blackbody = Curve::Blackbody {
+            temperature: 5000.0,
+            boost: 1.0,
+        };
+        assert!(matches!(
+            blackbody.derivative(500.0),
+            Err(MathError::InvalidCurve(_))
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+use crate::spectral::cmf::cie_1931_tabulated;
+
+// reduction of tabulated curves to fewer samples, bounded by the perceptual color error that the reduction introduces.
+// colors are computed by integrating reflectance * illuminant against the tabulated CIE 1931 color matching functions
+// at 1nm steps, and compared in CIELAB relative to the illuminant as the reference white.
+
+const STEP: f32 = 1.0;
+
+// CIELAB coordinates of `xyz` relative to `white`, which may be on any common scale
+fn lab(xyz: [f32; 3], white: [f32; 3]) -> LabColor {
+    let [x, y, z] = xyz.map(|c| c / white[1]);
+    LabColor::from_xyz(
+        XYZColor::new(x, y, z),
+        XYZColor::new(white[0], white[1], white[2]).xy(),
+    )
+}
+
+impl Curve {
+    /// greedily removes samples from a `Tabulated` or `Linear` curve, as long as the color of the curve as a reflectance
+    /// under `illuminant` stays within `max_delta_e` (CIE76) of the color of the original curve.
+    /// in each step the sample whose removal changes the color the least is removed.
+    /// returns a `Tabulated` curve with linear interpolation. samples outside of `bounds` are kept as they are.
+    pub fn downsample_bounded_de(
+        &self,
+        bounds: Bounds1D,
+        illuminant: &Curve,
+        max_delta_e: f32,
+    ) -> Result<Curve> {
+        let mut points: Vec<(f32, f32)> = match self {
+            Curve::Tabulated { signal, .. } => signal.clone(),
+            Curve::SplitTabulated(split) => split.signal(),
+            Curve::Linear {
+                signal,
+                bounds: domain,
+                ..
+            } => {
+                let step = domain.span() / signal.len() as f32;
+                signal
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &y)| (domain.lower + i as f32 * step, y))
+                    .collect()
+            }
+            _ => {
+                return Err(MathError::InvalidCurve(
+                    "only Tabulated and Linear curves can be downsampled".to_string(),
+                ))
+            }
+        };
+        if bounds.span().is_nan() || bounds.span() <= 0.0 {
+            return Err(MathError::EmptyBounds);
+        }
+
+        // the xyz contribution of each wavelength on the integration grid, per unit of reflectance
+        let grid: Vec<f32> = (0..(bounds.span() / STEP) as usize)
+            .map(|k| bounds.lower + k as f32 * STEP)
+            .collect();
+        let weights: Vec<[f32; 3]> = grid
+            .iter()
+            .map(|&lambda| {
+                cie_1931_tabulated(lambda).map(|c| c * illuminant.evaluate(lambda) * STEP)
+            })
+            .collect();
+        let integrate = |values: &mut dyn Iterator<Item = (usize, f32)>| {
+            values.fold([0.0f32; 3], |mut sum, (k, value)| {
+                for (s, w) in sum.iter_mut().zip(weights[k]) {
+                    *s += value * w;
+                }
+                sum
+            })
+        };
+        let white = integrate(&mut (0..grid.len()).map(|k| (k, 1.0)));
+        if white[1] <= 0.0 {
+            return Err(MathError::InvalidCurve(
+                "illuminant has no luminance within bounds".to_string(),
+            ));
+        }
+        let target = lab(
+            integrate(&mut grid.iter().map(|&l| self.evaluate(l)).enumerate()),
+            white,
+        );
+
+        // the current curve is the linear interpolation of `points`, sampled on the grid
+        let linear = |points: &[(f32, f32)], lambda: f32| {
+            Curve::Tabulated {
+                signal: points.to_vec(),
+                mode: InterpolationMode::Linear,
+            }
+            .evaluate(lambda)
+        };
+        let mut values: Vec<f32> = grid.iter().map(|&l| linear(&points, l)).collect();
+        let mut current = integrate(&mut values.iter().copied().enumerate());
+
+        // the grid indices within the open interval (a, b)
+        let grid_range = |a: f32, b: f32| {
+            let start = grid.partition_point(|&l| l <= a);
+            let end = grid.partition_point(|&l| l < b);
+            start..end.max(start)
+        };
+        loop {
+            // the change in xyz from removing each interior sample within bounds, which only affects the span between its neighbors
+            let mut best: Option<(usize, f32, [f32; 3])> = None;
+            for i in 1..points.len().saturating_sub(1) {
+                let ((x0, y0), (x, _), (x1, y1)) = (points[i - 1], points[i], points[i + 1]);
+                if !bounds.contains(&x) {
+                    continue;
+                }
+                let range = grid_range(x0, x1);
+                let change = integrate(&mut range.map(|k| {
+                    let t = (grid[k] - x0) / (x1 - x0);
+                    (k, y0 + t * (y1 - y0) - values[k])
+                }));
+                let xyz = [0, 1, 2].map(|c| current[c] + change[c]);
+                let error = lab(xyz, white).delta_e_76(&target);
+                if error <= max_delta_e && best.is_none_or(|(_, best_error, _)| error < best_error)
+                {
+                    best = Some((i, error, xyz));
+                }
+            }
+            let Some((i, _, xyz)) = best else {
+                break;
+            };
+            let (x0, x1) = (points[i - 1].0, points[i + 1].0);
+            points.remove(i);
+            for k in grid_range(x0, x1) {
+                values[k] = linear(&points, grid[k]);
+            }
+            current = xyz;
+        }
+        Ok(Curve::Tabulated {
+            signal: points,
+            mode: InterpolationMode::Linear,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::curves::Op;
+
+    #[test]
+    fn test_downsample_bounded_de() {
+        let bounds = Bounds1D::new(380.0, 780.0);
+        // a smooth reflectance with a bump and a step, sampled every 2nm
+        let reflectance = Curve::from_function(
+            |x| {
+                0.2 + gaussianf32(x, 0.5, 520.0, 30.0, 50.0)
+                    + 0.3 / (1.0 + ((620.0 - x) / 8.0).exp())
+            },
+            200,
+            bounds,
+            InterpolationMode::Linear,
+        );
+        let illuminant = Curve::Blackbody {
+            temperature: 6500.0,
+            boost: 1.0,
+        };
+        let max_delta_e = 0.5;
+        let reduced = reflectance
+            .downsample_bounded_de(bounds, &illuminant, max_delta_e)
+            .unwrap();
+        let Curve::Tabulated { signal, .. } = &reduced else {
+            panic!()
+        };
+        assert!(signal.len() < 40, "{}", signal.len());
+
+        // check the color difference with an independent integration
+        let color = |curve: &Curve| {
+            let lit = Curve::Machine {
+                seed: 1.0,
+                list: vec![(Op::Mul, curve.clone()), (Op::Mul, illuminant.clone())],
+            };
+            let xyz = lit.convert_to_xyz_with_cmf(bounds, 1.0, false, CMF::Tabulated);
+            [xyz.x(), xyz.y(), xyz.z()]
+        };
+        let white = color(&Curve::Const(1.0));
+        let error = lab(color(&reduced), white).delta_e_76(&lab(color(&reflectance), white));
+        assert!(error <= max_delta_e + 1e-3, "{}", error);
+
+        // a tighter bound keeps more samples
+        let tight = reflectance
+            .downsample_bounded_de(bounds, &illuminant, 0.05)
+            .unwrap();
+        let Curve::Tabulated { signal: tight, .. } = &tight else {
+            panic!()
+        };
+        assert!(tight.len() > signal.len());
+
+        assert!(Curve::Const(0.5)
+            .downsample_bounded_de(bounds, &illuminant, 1.0)
+            .is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::prelude::*;
+
+use super::Op;
+
+// transmission curves of common photographic and lighting filters, to be multiplied onto other curves,
+// i.e. with `Curve::Machine { seed: 1.0, list: vec![(Op::Mul, spd), (Op::Mul, filter)] }`.
+
+// the second radiation constant hc / k, in nm K
+const SECOND_RADIATION_CONSTANT: f32 = 1.4388e7;
+
+// color correction gels are tabulated at this spacing over the visible range
+const GEL_STEP: f32 = 5.0;
+const GEL_RANGE: Bounds1D = Bounds1D::new(380.0, 780.0);
+
+// edges are tabulated over this many multiples of the logistic scale on either side, where they are within 0.01% of flat
+const EDGE_EXTENT: f32 = 10.0;
+const EDGE_SAMPLES: usize = 96;
+
+// a smooth step from 0 to 1 at `edge`, rising from 10% to 90% transmission over `slope` nanometers
+fn edge(edge: f32, slope: f32, rising: bool) -> Curve {
+    debug_assert!(slope > 0.0);
+    // the 10% to 90% width of a logistic function with scale s is 2 ln(9) s
+    let scale = slope / (2.0 * 9.0f32.ln());
+    let lower = edge - EDGE_EXTENT * scale;
+    let step = 2.0 * EDGE_EXTENT * scale / (EDGE_SAMPLES - 1) as f32;
+    let signal = (0..EDGE_SAMPLES)
+        .map(|i| {
+            let x = lower + step * i as f32;
+            let sign = if rising { -1.0 } else { 1.0 };
+            (x, 1.0 / (1.0 + (sign * (x - edge) / scale).exp()))
+        })
+        .collect();
+    Curve::Tabulated {
+        signal,
+        mode: InterpolationMode::Cubic,
+    }
+}
+
+impl Curve {
+    /// a neutral density filter attenuating by `stops`, i.e. transmitting 2^-stops at all wavelengths
+    pub fn neutral_density(stops: f32) -> Curve {
+        Curve::Const((-stops).exp2())
+    }
+
+    /// a neutral density filter given by its optical density, transmitting 10^-density at all wavelengths.
+    /// ND filters are commonly labeled this way, with 0.3 being about 1 stop.
+    pub fn neutral_density_optical(density: f32) -> Curve {
+        Curve::Const(10.0f32.powf(-density))
+    }
+
+    /// a filter transmitting wavelengths above `edge`, with transmission rising from 10% to 90% over `slope` nm
+    pub fn longpass(edge_wavelength: f32, slope: f32) -> Curve {
+        edge(edge_wavelength, slope, true)
+    }
+
+    /// a filter transmitting wavelengths below `edge`, with transmission falling from 90% to 10% over `slope` nm
+    pub fn shortpass(edge_wavelength: f32, slope: f32) -> Curve {
+        edge(edge_wavelength, slope, false)
+    }
+
+    /// a filter transmitting wavelengths between the half transmission points `lower` and `upper`,
+    /// as the product of a longpass and a shortpass filter with the same `slope`
+    pub fn bandpass(lower: f32, upper: f32, slope: f32) -> Curve {
+        debug_assert!(lower < upper);
+        Curve::Machine {
+            seed: 1.0,
+            list: vec![
+                (Op::Mul, Curve::longpass(lower, slope)),
+                (Op::Mul, Curve::shortpass(upper, slope)),
+            ],
+        }
+    }
+
+    /// an idealized color correction gel that shifts the color temperature of blackbody light by `mired_shift`,
+    /// in micro reciprocal degrees, 10^6 / T_out - 10^6 / T_in.
+    /// positive shifts warm the light, and negative shifts cool it.
+    /// under the Wien approximation, the ratio of the two blackbody spectra is exp(-c2 * shift / lambda)
+    /// independently of the input temperature, so the same gel works for any source.
+    /// tabulated every 5nm from 380 to 780 nm and normalized to a peak transmission of 1 there.
+    pub fn mired_shift_filter(mired_shift: f32) -> Curve {
+        let shift = mired_shift * 1e-6;
+        let transmission = |lambda: f32| (-SECOND_RADIATION_CONSTANT * shift / lambda).exp();
+        let peak = transmission(GEL_RANGE.lower).max(transmission(GEL_RANGE.upper));
+        let n = (GEL_RANGE.span() / GEL_STEP) as usize + 1;
+        let signal = (0..n)
+            .map(|i| {
+                let lambda = GEL_RANGE.lower + GEL_STEP * i as f32;
+                (lambda, transmission(lambda) / peak)
+            })
+            .collect();
+        Curve::Tabulated {
+            signal,
+            mode: InterpolationMode::Linear,
+        }
+    }
+
+    /// a color temperature orange gel, converting daylight to tungsten light. `strength` is 1 for a full CTO,
+    /// which shifts by +159 mireds, i.e. from 5500K to about 2900K, and 0.5 or 0.25 for half and quarter CTO.
+    pub fn cto(strength: f32) -> Curve {
+        Curve::mired_shift_filter(159.0 * strength)
+    }
+
+    /// a color temperature blue gel, converting tungsten light to daylight. `strength` is 1 for a full CTB,
+    /// which shifts by -131 mireds, i.e. from 3200K to about 5500K, and 0.5 or 0.25 for half and quarter CTB.
+    pub fn ctb(strength: f32) -> Curve {
+        Curve::mired_shift_filter(-131.0 * strength)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(Curve::neutral_density(3.0).evaluate(550.0), 0.125);
+        assert!((Curve::neutral_density_optical(0.9).evaluate(550.0) - 0.125).abs() < 3e-3);
+
+        let longpass = Curve::longpass(500.0, 20.0);
+        assert!((longpass.evaluate(500.0) - 0.5).abs() < 1e-3);
+        assert!((longpass.evaluate(510.0) - 0.9).abs() < 2e-3);
+        assert!((longpass.evaluate(490.0) - 0.1).abs() < 2e-3);
+        assert!(longpass.evaluate(400.0) < 1e-3 && longpass.evaluate(700.0) > 0.999);
+        let shortpass = Curve::shortpass(500.0, 20.0);
+        assert!((shortpass.evaluate(480.0) + longpass.evaluate(480.0) - 1.0).abs() < 1e-3);
+
+        let bandpass = Curve::bandpass(500.0, 600.0, 10.0);
+        assert!(bandpass.evaluate(550.0) > 0.999);
+        assert!((bandpass.evaluate(600.0) - 0.5).abs() < 1e-3);
+        assert!(bandpass.evaluate(450.0) < 1e-3 && bandpass.evaluate(650.0) < 1e-3);
+        // the integral is about the width between the half transmission points
+        let width = bandpass.evaluate_integral(Bounds1D::new(400.0, 700.0), 3000, false);
+        assert!((width - 100.0).abs() < 0.5, "{}", width);
+
+        // a full CTO turns 5500K light into about 2934K light, and a full CTB turns 3200K light into about 5623K light
+        let filtered_xy = |temperature: f32, filter: Curve| {
+            Curve::Machine {
+                seed: 1.0,
+                list: vec![
+                    (
+                        Op::Mul,
+                        Curve::Blackbody {
+                            temperature,
+                            boost: 1.0,
+                        },
+                    ),
+                    (Op::Mul, filter),
+                ],
+            }
+            .convert_to_xyz(GEL_RANGE, 1.0, false)
+            .xy()
+        };
+        for (input, filter, output) in [
+            (5500.0, Curve::cto(1.0), 1e6 / (1e6 / 5500.0 + 159.0)),
+            (3200.0, Curve::ctb(1.0), 1e6 / (1e6 / 3200.0 - 131.0)),
+        ] {
+            let (x, y) = filtered_xy(input, filter);
+            let (ex, ey) = filtered_xy(output, Curve::Const(1.0));
+            assert!(
+                (x - ex).abs() < 2e-3 && (y - ey).abs() < 2e-3,
+                "{:?} {:?}",
+                (x, y),
+                (ex, ey)
+            );
+        }
+        let cto = Curve::cto(0.5);
+        assert!(cto.evaluate(450.0) < cto.evaluate(650.0));
+        assert!((cto.evaluate(780.0) - 1.0).abs() < 1e-6);
+    }
+}
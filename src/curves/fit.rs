@@ -0,0 +1,259 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+use crate::numeric;
+
+// fitting of compact analytic curves to measured or tabulated spectra.
+// a handful of asymmetric gaussian lobes in an `Exponential` curve is often enough for emitters like LEDs and fluorescents,
+// and evaluates much faster than a large tabulated curve.
+
+const FIT_SAMPLES: usize = 256;
+const MAX_ITERATIONS: usize = 200;
+
+// evaluates the mixture described by `parameters`, packed as (offset, sigma1, sigma2, multiplier) per lobe,
+// and optionally its jacobian wrt the parameters
+fn evaluate_mixture(parameters: &[f64], x: f64, mut jacobian: Option<&mut [f64]>) -> f64 {
+    let mut value = 0.0;
+    for (i, lobe) in parameters.chunks_exact(4).enumerate() {
+        let (offset, sigma1, sigma2, multiplier) = (lobe[0], lobe[1], lobe[2], lobe[3]);
+        let left = x < offset;
+        let sigma = if left { sigma1 } else { sigma2 };
+        let d = x - offset;
+        let e = (-(d * d) / (2.0 * sigma * sigma)).exp();
+        value += multiplier * e;
+        if let Some(jacobian) = jacobian.as_deref_mut() {
+            let row = &mut jacobian[4 * i..4 * i + 4];
+            row[0] = multiplier * e * d / (sigma * sigma);
+            let d_sigma = multiplier * e * d * d / (sigma * sigma * sigma);
+            row[1] = if left { d_sigma } else { 0.0 };
+            row[2] = if left { 0.0 } else { d_sigma };
+            row[3] = e;
+        }
+    }
+    value
+}
+
+impl Curve {
+    /// fits an `Exponential` curve with `n_lobes` lobes to `target` within `bounds`, minimizing the squared error
+    /// at evenly spaced points. see `fit_gaussian_mixture_weighted` for details.
+    pub fn fit_gaussian_mixture(target: &Curve, bounds: Bounds1D, n_lobes: usize) -> Result<Curve> {
+        Curve::fit_gaussian_mixture_weighted(target, bounds, n_lobes, |_| 1.0)
+    }
+
+    /// fits an `Exponential` curve with `n_lobes` lobes to `target` within `bounds`, minimizing the squared error
+    /// weighted by `weight(lambda)` at evenly spaced points, i.e. to emphasize where a color matching function is large.
+    /// lobes are placed greedily at the peaks of the remaining residual, then refined jointly with levenberg-marquardt,
+    /// solving each damped step as a least squares problem with `numeric::lstsq_f64`.
+    /// the result is a local optimum, so more lobes don't always give a better fit for complex spectra.
+    /// the multipliers of the lobes are kept nonnegative, such that the fit is a valid spectrum with an exact cdf,
+    /// and negative parts of `target` are fit as 0.
+    pub fn fit_gaussian_mixture_weighted<F>(
+        target: &Curve,
+        bounds: Bounds1D,
+        n_lobes: usize,
+        weight: F,
+    ) -> Result<Curve>
+    where
+        F: Fn(f32) -> f32,
+    {
+        if n_lobes == 0 {
+            return Err(MathError::InvalidCurve(
+                "a gaussian mixture needs at least one lobe".to_string(),
+            ));
+        }
+        if bounds.span().is_nan() || bounds.span() <= 0.0 {
+            return Err(MathError::EmptyBounds);
+        }
+        let step = bounds.span() as f64 / FIT_SAMPLES as f64;
+        let xs: Vec<f64> = (0..FIT_SAMPLES)
+            .map(|i| bounds.lower as f64 + (i as f64 + 0.5) * step)
+            .collect();
+        let ys: Vec<f64> = xs
+            .iter()
+            .map(|&x| target.evaluate(x as f32) as f64)
+            .collect();
+        let weights: Vec<f64> = xs
+            .iter()
+            .map(|&x| weight(x as f32).max(0.0) as f64)
+            .collect();
+        if !ys.iter().all(|y| y.is_finite()) {
+            return Err(MathError::InvalidCurve(
+                "target evaluates to non-finite values".to_string(),
+            ));
+        }
+
+        let min_sigma = step / 2.0;
+        let mut parameters = Vec::with_capacity(4 * n_lobes);
+        // greedy initialization: place each lobe at the largest weighted residual, with widths from its half maximum
+        for _ in 0..n_lobes {
+            let residual: Vec<f64> = xs
+                .iter()
+                .zip(&ys)
+                .map(|(&x, &y)| y - evaluate_mixture(&parameters, x, None))
+                .collect();
+            let peak = (0..FIT_SAMPLES)
+                .max_by(|&a, &b| (residual[a] * weights[a]).total_cmp(&(residual[b] * weights[b])))
+                .unwrap();
+            let height = residual[peak].max(0.0);
+            let half_width = |indices: &mut dyn Iterator<Item = usize>| {
+                let count = indices.take_while(|&i| residual[i] > height / 2.0).count();
+                // a gaussian falls to half its height at sigma * sqrt(2 ln 2)
+                ((count as f64 + 0.5) * step / (2.0 * 2f64.ln()).sqrt()).max(min_sigma)
+            };
+            let sigma1 = half_width(&mut (0..peak).rev());
+            let sigma2 = half_width(&mut (peak + 1..FIT_SAMPLES));
+            parameters.extend_from_slice(&[xs[peak], sigma1, sigma2, height]);
+        }
+
+        // levenberg-marquardt refinement, with the sigmas kept positive, the multipliers nonnegative
+        // and the offsets within bounds
+        let n = parameters.len();
+        let cost = |parameters: &[f64]| -> f64 {
+            xs.iter()
+                .zip(&ys)
+                .zip(&weights)
+                .map(|((&x, &y), &w)| w * (evaluate_mixture(parameters, x, None) - y).powi(2))
+                .sum()
+        };
+        let mut current_cost = cost(&parameters);
+        let mut damping = 1e-3;
+        let mut row = vec![0.0; n];
+        for _ in 0..MAX_ITERATIONS {
+            // the weighted jacobian and residuals, followed by the damping rows of the augmented least squares problem
+            let mut jacobian = Vec::with_capacity((FIT_SAMPLES + n) * n);
+            let mut residuals = Vec::with_capacity(FIT_SAMPLES + n);
+            let mut diagonal = vec![0.0f64; n];
+            for ((&x, &y), &w) in xs.iter().zip(&ys).zip(&weights) {
+                let r = evaluate_mixture(&parameters, x, Some(&mut row)) - y;
+                let sqrt_w = w.sqrt();
+                for (d, j) in diagonal.iter_mut().zip(&row) {
+                    *d += w * j * j;
+                }
+                jacobian.extend(row.iter().map(|j| sqrt_w * j));
+                residuals.push(-sqrt_w * r);
+            }
+            residuals.resize(FIT_SAMPLES + n, 0.0);
+            let mut improved = false;
+            while damping < 1e10 {
+                let mut augmented = jacobian.clone();
+                for (i, d) in diagonal.iter().enumerate() {
+                    let mut damping_row = vec![0.0; n];
+                    damping_row[i] = (damping * d.max(1e-12)).sqrt();
+                    augmented.extend(damping_row);
+                }
+                let Ok(delta) = numeric::lstsq_f64(&augmented, FIT_SAMPLES + n, n, &residuals)
+                else {
+                    damping *= 4.0;
+                    continue;
+                };
+                let candidate: Vec<f64> = parameters
+                    .chunks_exact(4)
+                    .zip(delta.chunks_exact(4))
+                    .flat_map(|(lobe, d)| {
+                        [
+                            (lobe[0] + d[0]).clamp(bounds.lower as f64, bounds.upper as f64),
+                            (lobe[1] + d[1]).max(min_sigma),
+                            (lobe[2] + d[2]).max(min_sigma),
+                            (lobe[3] + d[3]).max(0.0),
+                        ]
+                    })
+                    .collect();
+                let candidate_cost = cost(&candidate);
+                if candidate_cost < current_cost {
+                    improved = (current_cost - candidate_cost) > 1e-12 * current_cost;
+                    parameters = candidate;
+                    current_cost = candidate_cost;
+                    damping = (damping / 3.0).max(1e-12);
+                    break;
+                }
+                damping *= 4.0;
+            }
+            if !improved {
+                break;
+            }
+        }
+
+        let signal = parameters
+            .chunks_exact(4)
+            .map(|lobe| {
+                (
+                    lobe[0] as f32,
+                    lobe[1] as f32,
+                    lobe[2] as f32,
+                    lobe[3] as f32,
+                )
+            })
+            .collect();
+        Ok(Curve::Exponential { signal })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rms_error(a: &Curve, b: &Curve, bounds: Bounds1D) -> f32 {
+        let n = 400;
+        let sum: f32 = (0..n)
+            .map(|i| {
+                let x = bounds.lower + bounds.span() * (i as f32 + 0.5) / n as f32;
+                (a.evaluate(x) - b.evaluate(x)).powi(2)
+            })
+            .sum();
+        (sum / n as f32).sqrt()
+    }
+
+    #[test]
+    fn test_fit_gaussian_mixture() {
+        let bounds = Bounds1D::new(380.0, 780.0);
+        // recovers a mixture with the same number of lobes
+        let y_bar = Curve::y_bar();
+        let fit = Curve::fit_gaussian_mixture(&y_bar, bounds, 2).unwrap();
+        assert!(rms_error(&fit, &y_bar, bounds) < 1e-3);
+
+        // a white LED, with a narrow blue pump and a broad phosphor emission, tabulated
+        let led = Curve::from_function(
+            |x| gaussianf32(x, 1.0, 450.0, 10.0, 12.0) + gaussianf32(x, 0.6, 560.0, 40.0, 70.0),
+            200,
+            bounds,
+            InterpolationMode::Linear,
+        );
+        let one = Curve::fit_gaussian_mixture(&led, bounds, 1).unwrap();
+        let two = Curve::fit_gaussian_mixture(&led, bounds, 2).unwrap();
+        assert!(rms_error(&two, &led, bounds) < 1e-2);
+        assert!(rms_error(&two, &led, bounds) < rms_error(&one, &led, bounds) / 5.0);
+
+        // with no weight on the blue pump, a single lobe fits the phosphor instead
+        let phosphor = Curve::fit_gaussian_mixture_weighted(&led, bounds, 1, |x| {
+            if x < 500.0 {
+                0.0
+            } else {
+                1.0
+            }
+        })
+        .unwrap();
+        let Curve::Exponential { signal } = &phosphor else {
+            panic!()
+        };
+        assert!((signal[0].0 - 560.0).abs() < 5.0, "{:?}", signal);
+
+        // a dip below 0, which an unconstrained fit matches with a negative lobe
+        let dipped = Curve::from_function(
+            |x| gaussianf32(x, 1.0, 550.0, 60.0, 60.0) - gaussianf32(x, 1.5, 600.0, 20.0, 20.0),
+            200,
+            bounds,
+            InterpolationMode::Linear,
+        );
+        let fit = Curve::fit_gaussian_mixture(&dipped, bounds, 3).unwrap();
+        let Curve::Exponential { signal } = &fit else {
+            panic!()
+        };
+        assert!(signal.iter().all(|lobe| lobe.3 >= 0.0), "{:?}", signal);
+
+        assert!(Curve::fit_gaussian_mixture(&led, bounds, 0).is_err());
+        assert_eq!(
+            Curve::fit_gaussian_mixture(&led, Bounds1D::new(500.0, 500.0), 1).unwrap_err(),
+            MathError::EmptyBounds
+        );
+    }
+}
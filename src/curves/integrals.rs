@@ -0,0 +1,139 @@
+use crate::prelude::*;
+
+use std::ops::Deref;
+
+/// A curve along with its integrals over a set of registered bounds, for normalization factors
+/// that are needed in hot loops. the integrals are recomputed whenever the curve is modified through the wrapper,
+/// so they can't go stale. dereferences to the wrapped curve for evaluation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurveWithIntegrals {
+    curve: Curve,
+    samples: usize,
+    integrals: Vec<(Bounds1D, f32)>,
+}
+
+impl CurveWithIntegrals {
+    /// wraps `curve`, integrating it with `samples` trapezoidal steps per registered bounds
+    pub fn new(curve: Curve, samples: usize) -> Self {
+        CurveWithIntegrals {
+            curve,
+            samples,
+            integrals: Vec::new(),
+        }
+    }
+
+    /// registers `bounds`, computing the integral over it if it isn't already cached, and returns the integral
+    pub fn register(&mut self, bounds: Bounds1D) -> f32 {
+        if let Some(integral) = self.integral(bounds) {
+            return integral;
+        }
+        let integral = self.curve.evaluate_integral(bounds, self.samples, false);
+        self.integrals.push((bounds, integral));
+        integral
+    }
+
+    /// registers each of `bounds`, as a builder
+    pub fn with_bounds(mut self, bounds: &[Bounds1D]) -> Self {
+        for bounds in bounds {
+            self.register(*bounds);
+        }
+        self
+    }
+
+    /// the cached integral over `bounds`, or None if `bounds` hasn't been registered
+    pub fn integral(&self, bounds: Bounds1D) -> Option<f32> {
+        self.integrals
+            .iter()
+            .find(|(registered, _)| *registered == bounds)
+            .map(|(_, integral)| *integral)
+    }
+
+    /// the registered bounds and their integrals, in order of registration
+    pub fn integrals(&self) -> &[(Bounds1D, f32)] {
+        &self.integrals
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    /// modifies the curve with `f`, then recomputes the integrals over all registered bounds
+    pub fn modify(&mut self, f: impl FnOnce(&mut Curve)) {
+        f(&mut self.curve);
+        for (bounds, integral) in self.integrals.iter_mut() {
+            *integral = self.curve.evaluate_integral(*bounds, self.samples, false);
+        }
+    }
+
+    /// replaces the curve, recomputing the integrals over all registered bounds
+    pub fn set_curve(&mut self, curve: Curve) {
+        self.modify(|current| *current = curve);
+    }
+
+    pub fn into_inner(self) -> Curve {
+        self.curve
+    }
+}
+
+impl Deref for CurveWithIntegrals {
+    type Target = Curve;
+    fn deref(&self) -> &Self::Target {
+        &self.curve
+    }
+}
+
+impl SpectralPowerDistributionFunction<f32> for CurveWithIntegrals {
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        self.curve.evaluate_power(lambda)
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        self.curve.evaluate_clamped(lambda)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        self.curve.sample_power_and_pdf(wavelength_range, sample)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spectral::cmf::CIE_1931_TABLE_RANGE;
+    use crate::spectral::{BOUNDED_VISIBLE_RANGE, CMF_SUPPORT};
+
+    #[test]
+    fn test_curve_with_integrals() {
+        let mut cached = CurveWithIntegrals::new(Curve::Const(2.0), 100)
+            .with_bounds(&[BOUNDED_VISIBLE_RANGE, Bounds1D::new(500.0, 600.0)]);
+        assert_eq!(cached.integral(BOUNDED_VISIBLE_RANGE), Some(800.0));
+        assert_eq!(cached.integral(CMF_SUPPORT), None);
+        assert_eq!(cached.register(Bounds1D::new(500.0, 600.0)), 200.0);
+        assert_eq!(cached.integrals().len(), 2);
+        assert_eq!(cached.evaluate(550.0), 2.0);
+
+        // modifications recompute every registered integral
+        cached.modify(|curve| *curve = curve.clone().affine(0.5, 0.0));
+        assert_eq!(cached.integral(Bounds1D::new(500.0, 600.0)), Some(100.0));
+        cached.set_curve(Curve::Const(0.0));
+        assert_eq!(cached.integral(BOUNDED_VISIBLE_RANGE), Some(0.0));
+
+        // the y_bar normalization constants match integrating the color matching functions
+        for (cmf, bounds) in [
+            (CMF::Fitted, CMF_SUPPORT),
+            (CMF::Tabulated, CIE_1931_TABLE_RANGE),
+        ] {
+            let y = Curve::Const(1.0)
+                .convert_to_xyz_with_cmf(bounds, 0.1, false, cmf)
+                .y();
+            assert!(
+                (y - cmf.y_bar_integral()).abs() < 1e-3 * y,
+                "{:?} {}",
+                cmf,
+                y
+            );
+        }
+    }
+}
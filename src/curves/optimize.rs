@@ -0,0 +1,316 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+use super::interpolate;
+
+#[cfg(feature = "deepsize")]
+use deepsize::DeepSizeOf;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+
+// faster representations of curves that evaluate identically.
+// searching a `Vec<(f32, f32)>` touches a y value for every x that it compares, so large measured spectra
+// waste half of each cache line. `SplitTabulated` stores the xs contiguously, searches them without branches,
+// and for large signals first looks up the uniform cell containing x, which leaves only a few samples to search.
+
+// signals with at least this many samples get a uniform grid index
+const INDEX_THRESHOLD: usize = 32;
+
+// the number of values in the sorted `xs` that are below `x`, with a binary search that compiles to conditional moves
+fn count_below(xs: &[f32], x: f32) -> usize {
+    if xs.is_empty() {
+        return 0;
+    }
+    let mut base = 0;
+    let mut size = xs.len();
+    while size > 1 {
+        let half = size / 2;
+        base = if xs[base + half] < x {
+            base + half
+        } else {
+            base
+        };
+        size -= half;
+    }
+    base + (xs[base] < x) as usize
+}
+
+// the uniform cell of the index that contains `x`, for an index over `xs` with `cells` cells
+fn cell(xs: &[f32], cells: usize, x: f32) -> usize {
+    let (lower, upper) = (xs[0], xs[xs.len() - 1]);
+    let t = (x - lower) / (upper - lower) * cells as f32;
+    // NaN casts to 0, and out of range values saturate
+    (t as usize).min(cells - 1)
+}
+
+fn build_index(xs: &[f32], cells: usize) -> Vec<u32> {
+    let (lower, upper) = (xs[0], xs[xs.len() - 1]);
+    (0..=cells)
+        .map(|i| {
+            let edge = lower + (upper - lower) * i as f32 / cells as f32;
+            count_below(xs, edge) as u32
+        })
+        .collect()
+}
+
+/// Tabulated samples stored as separate sorted `xs` and `ys`, which evaluates like `Curve::Tabulated` but searches faster.
+/// large signals also get an index that divides [xs[0], xs[last]] into uniform cells, each storing the number of
+/// samples below its lower edge, which narrows the search to the samples within a cell.
+/// the samples are validated and the index is built on construction and deserialization, so the fields are private.
+/// serializes as the `signal` and `mode` of the tabulated curve it was built from.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "TabulatedSignal", into = "TabulatedSignal")
+)]
+#[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+pub struct SplitTabulated {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    mode: InterpolationMode,
+    index: Vec<u32>,
+}
+
+// the serialized form of `SplitTabulated`, matching the fields of `Curve::Tabulated`
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+struct TabulatedSignal {
+    signal: Vec<(f32, f32)>,
+    mode: InterpolationMode,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<TabulatedSignal> for SplitTabulated {
+    type Error = MathError;
+    fn try_from(tabulated: TabulatedSignal) -> Result<Self> {
+        SplitTabulated::new(&tabulated.signal, tabulated.mode)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SplitTabulated> for TabulatedSignal {
+    fn from(split: SplitTabulated) -> Self {
+        TabulatedSignal {
+            signal: split.signal(),
+            mode: split.mode,
+        }
+    }
+}
+
+impl SplitTabulated {
+    /// splits `signal`, which must be nonempty and sorted by x, failing with `InvalidCurve` otherwise
+    pub fn new(signal: &[(f32, f32)], mode: InterpolationMode) -> Result<Self> {
+        if signal.is_empty() {
+            return Err(MathError::InvalidCurve(
+                "tabulated signal is empty".to_string(),
+            ));
+        }
+        let xs: Vec<f32> = signal.iter().map(|(x, _)| *x).collect();
+        // also rejects NaNs
+        if xs.iter().any(|x| x.is_nan()) || !xs.windows(2).all(|w| w[0] <= w[1]) {
+            return Err(MathError::InvalidCurve(
+                "tabulated signal is not sorted by x".to_string(),
+            ));
+        }
+        let ys = signal.iter().map(|(_, y)| *y).collect();
+        let index = if xs.len() >= INDEX_THRESHOLD && xs[xs.len() - 1] > xs[0] {
+            build_index(&xs, xs.len())
+        } else {
+            Vec::new()
+        };
+        Ok(SplitTabulated {
+            xs,
+            ys,
+            mode,
+            index,
+        })
+    }
+
+    pub fn xs(&self) -> &[f32] {
+        &self.xs
+    }
+    pub fn ys(&self) -> &[f32] {
+        &self.ys
+    }
+    pub fn mode(&self) -> InterpolationMode {
+        self.mode
+    }
+    /// the samples as (x, y) pairs, as stored by `Curve::Tabulated`
+    pub fn signal(&self) -> Vec<(f32, f32)> {
+        self.xs
+            .iter()
+            .copied()
+            .zip(self.ys.iter().copied())
+            .collect()
+    }
+    /// whether the samples have a uniform grid index
+    pub fn is_indexed(&self) -> bool {
+        !self.index.is_empty()
+    }
+
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let (xs, ys, index) = (&self.xs, &self.ys, &self.index);
+        let position = if index.len() >= 2 && x > xs[0] && x < xs[xs.len() - 1] {
+            let cell = cell(xs, index.len() - 1, x);
+            // every sample below x is at least below the lower edge of its cell, and at most below the upper edge.
+            // the edges are rounded, so the range is widened by one sample on either side
+            let lower = (index[cell] as usize).saturating_sub(1);
+            let upper = (index[cell + 1] as usize + 1).min(xs.len());
+            lower + count_below(&xs[lower..upper], x)
+        } else {
+            count_below(xs, x)
+        };
+        if position == xs.len() {
+            return ys[xs.len() - 1];
+        }
+        if position == 0 {
+            return ys[0];
+        }
+        interpolate(
+            self.mode,
+            (xs[position - 1], ys[position - 1]),
+            (xs[position], ys[position]),
+            x,
+        )
+    }
+}
+
+impl Curve {
+    /// converts `Tabulated` curves, including those nested within other curves, into `SplitTabulated` curves,
+    /// which evaluate the same but are faster to search. large signals also get a uniform grid index.
+    /// empty or unsorted signals can't be split, and are left as they are.
+    pub fn optimize(&self) -> Curve {
+        match self {
+            Curve::Tabulated { signal, mode } => match SplitTabulated::new(signal, *mode) {
+                Ok(split) => Curve::SplitTabulated(split),
+                Err(_) => self.clone(),
+            },
+            Curve::Machine { seed, list } => Curve::Machine {
+                seed: *seed,
+                list: list.iter().map(|(op, c)| (*op, c.optimize())).collect(),
+            },
+            Curve::Clamped { curve, min, max } => Curve::Clamped {
+                curve: Box::new(curve.optimize()),
+                min: *min,
+                max: *max,
+            },
+            Curve::Affine {
+                curve,
+                scale,
+                offset,
+            } => Curve::Affine {
+                curve: Box::new(curve.optimize()),
+                scale: *scale,
+                offset: *offset,
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_optimize() {
+        let mut rng = StdRng::seed_from_u64(11);
+        // a thousand irregularly spaced samples, with a repeated x for a step
+        let mut x = 350.0;
+        let mut signal = Vec::new();
+        for i in 0..1000 {
+            if i != 500 {
+                x += rng.gen_range(0.05..1.0f32);
+            }
+            signal.push((x, rng.gen_range(0.0..1.0f32)));
+        }
+        for mode in [
+            InterpolationMode::Linear,
+            InterpolationMode::Nearest,
+            InterpolationMode::Cubic,
+        ] {
+            let curve = Curve::Tabulated {
+                signal: signal.clone(),
+                mode,
+            };
+            let optimized = curve.optimize();
+            let Curve::SplitTabulated(split) = &optimized else {
+                panic!("{:?}", optimized);
+            };
+            assert_eq!(split.index.len(), signal.len() + 1);
+            for i in 0..20000 {
+                let lambda = 340.0 + i as f32 * (x + 20.0 - 340.0) / 20000.0;
+                assert_eq!(
+                    optimized.evaluate(lambda),
+                    curve.evaluate(lambda),
+                    "{}",
+                    lambda
+                );
+            }
+            // exactly at the samples, where the search has to agree on which side to interpolate from
+            for &(x, _) in signal.iter().filter(|(x, _)| *x != signal[500].0) {
+                assert_eq!(optimized.evaluate(x), curve.evaluate(x));
+            }
+        }
+
+        // small signals are split without an index, and nested curves are optimized too
+        let small = Curve::Tabulated {
+            signal: vec![(400.0, 0.2), (500.0, 0.8), (600.0, 0.4)],
+            mode: InterpolationMode::Linear,
+        };
+        let wrapped = small.clone().clamped(0.0, 0.5).optimize();
+        let Curve::Clamped { curve, .. } = &wrapped else {
+            panic!("{:?}", wrapped);
+        };
+        assert!(matches!(**curve, Curve::SplitTabulated(ref split) if !split.is_indexed()));
+        for lambda in [300.0, 400.0, 450.0, 555.5, 700.0] {
+            assert_eq!(
+                wrapped.evaluate(lambda),
+                small.clone().clamped(0.0, 0.5).evaluate(lambda)
+            );
+        }
+
+        // signals that can't be searched are rejected, and left unoptimized
+        let unsorted = vec![(500.0, 0.2), (400.0, 0.8)];
+        assert!(matches!(
+            SplitTabulated::new(&unsorted, InterpolationMode::Linear),
+            Err(MathError::InvalidCurve(_))
+        ));
+        assert!(SplitTabulated::new(&[(f32::NAN, 1.0)], InterpolationMode::Linear).is_err());
+        let unsorted = Curve::Tabulated {
+            signal: unsorted,
+            mode: InterpolationMode::Linear,
+        };
+        assert_eq!(unsorted.optimize(), unsorted);
+        let empty = Curve::Tabulated {
+            signal: vec![],
+            mode: InterpolationMode::Linear,
+        };
+        assert_eq!(empty.optimize(), empty);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_split_tabulated_serde() {
+        let signal: Vec<(f32, f32)> = (0..40)
+            .map(|i| (400.0 + i as f32, i as f32 / 40.0))
+            .collect();
+        let split = Curve::Tabulated {
+            signal,
+            mode: InterpolationMode::Cubic,
+        }
+        .optimize();
+        let json = serde_json::to_string(&split).unwrap();
+        assert_eq!(serde_json::from_str::<Curve>(&json).unwrap(), split);
+        // the index is rebuilt rather than stored, and unsorted signals fail to deserialize
+        assert!(!json.contains("index"));
+        let unsorted = r#"{"SplitTabulated":{"signal":[[500.0,0.2],[400.0,0.8]],"mode":"Linear"}}"#;
+        assert!(serde_json::from_str::<Curve>(unsorted).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+// upsampling of coarse tabulated curves, i.e. measurements at 10nm, into smooth high resolution lookup tables.
+
+// one round of subdivision, inserting a point between each pair of neighbors.
+// new points use the 4 point scheme of Dubuc and Deslauriers, which reproduces cubics and interpolates the old points.
+// `smoothing` in [0, 1] then blends the old points towards the average of their new neighbors.
+fn subdivide(values: &[f32], smoothing: f32) -> Vec<f32> {
+    let n = values.len();
+    // ends are extended by reflecting the neighboring point through them, which keeps linear ramps linear
+    let at = |i: isize| -> f32 {
+        if i < 0 {
+            2.0 * values[0] - values[(-i) as usize]
+        } else if i as usize >= n {
+            2.0 * values[n - 1] - values[2 * (n - 1) - i as usize]
+        } else {
+            values[i as usize]
+        }
+    };
+    let mut refined = Vec::with_capacity(2 * n - 1);
+    for i in 0..n as isize {
+        refined.push(at(i));
+        if i + 1 < n as isize {
+            let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+            refined.push((9.0 * (p1 + p2) - (p0 + p3)) / 16.0);
+        }
+    }
+    if smoothing > 0.0 {
+        let inserted = refined.clone();
+        for i in (2..refined.len() - 1).step_by(2) {
+            let average = 0.5 * (inserted[i - 1] + inserted[i + 1]);
+            refined[i] += smoothing * 0.5 * (average - inserted[i]);
+        }
+    }
+    refined
+}
+
+impl Curve {
+    /// upsamples a `Linear`, `Tabulated` or `SplitTabulated` curve by `factor`, a power of two, with interpolating subdivision,
+    /// such that the result passes through the original samples when `smoothing` is 0.
+    /// `smoothing` in [0, 1] relaxes the original samples towards their neighbors, to suppress measurement noise.
+    /// values stay nonnegative if the original samples are, and the integral is preserved to within the accuracy
+    /// of the original sampling for smooth curves.
+    /// tabulated x values are subdivided at midpoints, so sparse irregular tables are treated as evenly spaced.
+    pub fn refine(&self, factor: usize, smoothing: f32) -> Result<Curve> {
+        if !factor.is_power_of_two() {
+            return Err(MathError::UnsupportedLength(factor));
+        }
+        debug_assert!((0.0..=1.0).contains(&smoothing));
+        let rounds = factor.trailing_zeros();
+        let refine_values = |values: &[f32]| {
+            let nonnegative = values.iter().all(|v| *v >= 0.0);
+            let mut values = values.to_vec();
+            for _ in 0..rounds {
+                if values.len() < 2 {
+                    break;
+                }
+                values = subdivide(&values, smoothing);
+            }
+            if nonnegative {
+                values.iter_mut().for_each(|v| *v = v.max(0.0));
+            }
+            values
+        };
+        match self {
+            Curve::Linear {
+                signal,
+                bounds,
+                mode,
+            } => {
+                // the samples of a linear curve start at bounds.lower, spaced by span / len.
+                // subdividing n samples gives factor * (n - 1) + 1 of them, so the evaluation past the last sample
+                // is padded with copies of it, matching how the original curve is evaluated there.
+                let mut refined = refine_values(signal);
+                let last = *refined.last().unwrap();
+                refined.resize(signal.len() * factor, last);
+                Ok(Curve::Linear {
+                    signal: refined,
+                    bounds: *bounds,
+                    mode: *mode,
+                })
+            }
+            Curve::Tabulated { signal, mode } => {
+                let xs: Vec<f32> = signal.iter().map(|(x, _)| *x).collect();
+                let ys: Vec<f32> = signal.iter().map(|(_, y)| *y).collect();
+                let mut refined_xs = xs.clone();
+                for _ in 0..rounds {
+                    if refined_xs.len() < 2 {
+                        break;
+                    }
+                    refined_xs = refined_xs
+                        .windows(2)
+                        .flat_map(|w| [w[0], 0.5 * (w[0] + w[1])])
+                        .chain(refined_xs.last().copied())
+                        .collect();
+                }
+                Ok(Curve::Tabulated {
+                    signal: refined_xs.into_iter().zip(refine_values(&ys)).collect(),
+                    mode: *mode,
+                })
+            }
+            // refined as the tabulated curve it was built from, and optimized again
+            Curve::SplitTabulated(split) => Ok(Curve::Tabulated {
+                signal: split.signal(),
+                mode: split.mode(),
+            }
+            .refine(factor, smoothing)?
+            .optimize()),
+            _ => Err(MathError::InvalidCurve(
+                "only Linear, Tabulated and SplitTabulated curves can be refined".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_refine() {
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let smooth = |x: f32| gaussianf32(x, 1.0, 550.0, 40.0, 60.0) + 0.1;
+        // measurements every 10nm
+        let coarse = Curve::Linear {
+            signal: (0..40).map(|i| smooth(380.0 + 10.0 * i as f32)).collect(),
+            bounds,
+            mode: InterpolationMode::Linear,
+        };
+        let fine = coarse.refine(8, 0.0).unwrap();
+        let Curve::Linear { signal, .. } = &fine else {
+            panic!()
+        };
+        assert_eq!(signal.len(), 320);
+
+        // passes through the original samples, is closer to the underlying function in between,
+        // and has the same integral
+        for i in 0..39 {
+            let x = 380.0 + 10.0 * i as f32;
+            assert!((fine.evaluate(x) - coarse.evaluate(x)).abs() < 1e-5);
+            let midpoint = x + 5.0;
+            let error = (fine.evaluate(midpoint) - smooth(midpoint)).abs();
+            assert!(error <= (coarse.evaluate(midpoint) - smooth(midpoint)).abs() + 1e-4);
+            assert!(error < 2e-3, "{} {}", midpoint, error);
+        }
+        let integral = |curve: &Curve| curve.evaluate_integral(bounds, 4000, false);
+        assert!((integral(&fine) - integral(&coarse)).abs() < 1e-3 * integral(&coarse));
+
+        // smoothing suppresses alternating noise
+        let noisy = Curve::Tabulated {
+            signal: (0..20)
+                .map(|i| {
+                    (
+                        400.0 + 20.0 * i as f32,
+                        0.5 + if i % 2 == 0 { 0.1 } else { -0.1 },
+                    )
+                })
+                .collect(),
+            mode: InterpolationMode::Linear,
+        };
+        let roughness = |curve: &Curve| {
+            (0..200)
+                .map(|i| {
+                    let x = 420.0 + i as f32 * 1.5;
+                    (curve.evaluate(x + 1.0) - curve.evaluate(x)).abs()
+                })
+                .sum::<f32>()
+        };
+        let relaxed = noisy.refine(4, 1.0).unwrap();
+        assert!(roughness(&relaxed) < 0.75 * roughness(&noisy.refine(4, 0.0).unwrap()));
+        let Curve::Tabulated { signal, .. } = &relaxed else {
+            panic!()
+        };
+        assert_eq!(signal.len(), 4 * 19 + 1);
+        // optimized curves refine the same, staying optimized
+        assert_eq!(noisy.optimize().refine(4, 1.0).unwrap(), relaxed.optimize());
+        assert!(signal.windows(2).all(|w| w[0].0 < w[1].0));
+
+        assert!(coarse.refine(3, 0.0).is_err());
+        assert!(Curve::Const(1.0).refine(2, 0.0).is_err());
+    }
+}
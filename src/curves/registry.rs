@@ -0,0 +1,146 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+/// A curve along with the name it is referred to by, e.g. in a scene description
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct NamedCurve {
+    pub name: String,
+    pub curve: Curve,
+}
+
+impl NamedCurve {
+    pub fn new(name: impl Into<String>, curve: Curve) -> Self {
+        NamedCurve {
+            name: name.into(),
+            curve,
+        }
+    }
+}
+
+impl SpectralPowerDistributionFunction<f32> for NamedCurve {
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        self.curve.evaluate_power(lambda)
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        self.curve.evaluate_clamped(lambda)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        self.curve.sample_power_and_pdf(wavelength_range, sample)
+    }
+}
+
+/// A collection of curves keyed by name, so that spectra shared between materials are stored once
+/// and referred to by name. serializes as a map from name to curve.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize), serde(transparent))]
+pub struct CurveRegistry {
+    curves: BTreeMap<String, Curve>,
+}
+
+impl CurveRegistry {
+    pub fn new() -> Self {
+        CurveRegistry::default()
+    }
+    pub fn len(&self) -> usize {
+        self.curves.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.curves.is_empty()
+    }
+    /// inserts `curve` under `name`, returning the curve that was previously registered under that name, if any
+    pub fn insert(&mut self, name: impl Into<String>, curve: Curve) -> Option<Curve> {
+        self.curves.insert(name.into(), curve)
+    }
+    pub fn insert_named(&mut self, named: NamedCurve) -> Option<Curve> {
+        self.curves.insert(named.name, named.curve)
+    }
+    pub fn remove(&mut self, name: &str) -> Option<Curve> {
+        self.curves.remove(name)
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.curves.contains_key(name)
+    }
+    pub fn get(&self, name: &str) -> Option<&Curve> {
+        self.curves.get(name)
+    }
+    /// like `get`, but reports missing curves as an error, for use while loading scenes
+    pub fn resolve(&self, name: &str) -> Result<&Curve> {
+        self.get(name)
+            .ok_or_else(|| MathError::InvalidCurve(format!("no curve named \"{}\"", name)))
+    }
+    pub fn get_named(&self, name: &str) -> Option<NamedCurve> {
+        self.get(name)
+            .map(|curve| NamedCurve::new(name, curve.clone()))
+    }
+    /// iterates over the names and curves in the registry, ordered by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Curve)> {
+        self.curves
+            .iter()
+            .map(|(name, curve)| (name.as_str(), curve))
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.curves.keys().map(|name| name.as_str())
+    }
+}
+
+impl FromIterator<NamedCurve> for CurveRegistry {
+    fn from_iter<I: IntoIterator<Item = NamedCurve>>(iter: I) -> Self {
+        let mut registry = CurveRegistry::new();
+        registry.extend(iter);
+        registry
+    }
+}
+
+impl Extend<NamedCurve> for CurveRegistry {
+    fn extend<I: IntoIterator<Item = NamedCurve>>(&mut self, iter: I) {
+        for named in iter {
+            self.insert_named(named);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_curve_registry() {
+        let mut registry: CurveRegistry = vec![
+            NamedCurve::new("white", Curve::Const(1.0)),
+            NamedCurve::new("grey", Curve::Const(0.5)),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["grey", "white"]);
+        assert_eq!(registry.get("grey").unwrap().evaluate(550.0), 0.5);
+        assert!(registry.get("black").is_none());
+        assert!(matches!(
+            registry.resolve("black"),
+            Err(MathError::InvalidCurve(_))
+        ));
+
+        // replacing a curve returns the previous one
+        let previous = registry.insert("grey", Curve::Const(0.18)).unwrap();
+        assert_eq!(previous.evaluate(550.0), 0.5);
+        let grey = registry.get_named("grey").unwrap();
+        assert_eq!(grey.name, "grey");
+        assert_eq!(grey.evaluate_power(550.0), 0.18);
+
+        assert!(registry.remove("white").is_some());
+        assert!(!registry.contains("white"));
+        assert_eq!(registry.iter().count(), 1);
+    }
+}
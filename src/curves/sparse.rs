@@ -0,0 +1,203 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// accumulation of irregularly spaced spectral observations, i.e. spectral splats from a simulation,
+// and their reconstruction into a regular `Curve`.
+
+/// How a `SparseSpectrum` estimates the value at a wavelength from nearby observations.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SparseReconstruction {
+    /// the weighted mean of the `k` nearest observations. adapts to the local density of observations.
+    KNearest { k: usize },
+    /// nadaraya-watson kernel regression, the mean of observations weighted by a gaussian of standard deviation
+    /// `bandwidth` (in nm) around the wavelength. wavelengths with no observations within 3 bandwidths
+    /// take the value of the nearest observation.
+    Kernel { bandwidth: f32 },
+}
+
+/// Irregular (lambda, value) observations, with a weight per observation.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, Default)]
+pub struct SparseSpectrum {
+    /// (lambda, value, weight) triples, in insertion order
+    observations: Vec<(f32, f32, f32)>,
+}
+
+impl SparseSpectrum {
+    pub fn new() -> Self {
+        SparseSpectrum::default()
+    }
+
+    pub fn add(&mut self, lambda: f32, value: f32) {
+        self.add_weighted(lambda, value, 1.0);
+    }
+
+    /// adds an observation that counts `weight` times as much as one added with `add`
+    pub fn add_weighted(&mut self, lambda: f32, value: f32, weight: f32) {
+        debug_assert!(weight >= 0.0);
+        if lambda.is_finite() && value.is_finite() && weight > 0.0 {
+            self.observations.push((lambda, value, weight));
+        }
+    }
+
+    /// moves the observations of `other` into `self`, i.e. to combine per thread accumulators
+    pub fn merge(&mut self, mut other: SparseSpectrum) {
+        self.observations.append(&mut other.observations);
+    }
+
+    pub fn len(&self) -> usize {
+        self.observations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observations.is_empty()
+    }
+
+    /// reconstructs a `Linear` curve with `resolution` samples over `bounds`.
+    /// returns None if there are no observations.
+    pub fn to_curve(
+        &self,
+        bounds: Bounds1D,
+        resolution: usize,
+        method: SparseReconstruction,
+    ) -> Option<Curve> {
+        if self.is_empty() || resolution == 0 {
+            return None;
+        }
+        let mut sorted = self.observations.clone();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // the same sample positions that `Curve::Linear` evaluates its signal at
+        let step = bounds.span() / resolution as f32;
+        let signal = (0..resolution)
+            .map(|i| reconstruct(&sorted, bounds.lower + i as f32 * step, method))
+            .collect();
+        Some(Curve::Linear {
+            signal,
+            bounds,
+            mode: InterpolationMode::Linear,
+        })
+    }
+}
+
+// the weighted mean of the observations in `observations`
+fn weighted_mean(observations: &[(f32, f32, f32)]) -> f32 {
+    let (sum, weight) = observations
+        .iter()
+        .fold((0.0, 0.0), |(sum, weight), &(_, v, w)| {
+            (sum + v * w, weight + w)
+        });
+    sum / weight
+}
+
+// estimates the value at `lambda` from observations sorted by wavelength
+fn reconstruct(sorted: &[(f32, f32, f32)], lambda: f32, method: SparseReconstruction) -> f32 {
+    let split = sorted.partition_point(|o| o.0 < lambda);
+    match method {
+        SparseReconstruction::KNearest { k } => {
+            // grow the window [lower, upper) towards whichever neighbor is closer
+            let (mut lower, mut upper) = (split, split);
+            while upper - lower < k.max(1) && (lower > 0 || upper < sorted.len()) {
+                let take_lower = match (lower.checked_sub(1), sorted.get(upper)) {
+                    (Some(below), Some(above)) => lambda - sorted[below].0 <= above.0 - lambda,
+                    (Some(_), None) => true,
+                    _ => false,
+                };
+                if take_lower {
+                    lower -= 1;
+                } else {
+                    upper += 1;
+                }
+            }
+            weighted_mean(&sorted[lower..upper])
+        }
+        SparseReconstruction::Kernel { bandwidth } => {
+            debug_assert!(bandwidth > 0.0);
+            let support = 3.0 * bandwidth;
+            let start = sorted.partition_point(|o| o.0 < lambda - support);
+            let end = sorted.partition_point(|o| o.0 <= lambda + support);
+            let (sum, weight) =
+                sorted[start..end]
+                    .iter()
+                    .fold((0.0, 0.0), |(sum, weight), &(l, v, w)| {
+                        let d = (l - lambda) / bandwidth;
+                        let kernel = w * (-0.5 * d * d).exp();
+                        (sum + kernel * v, weight + kernel)
+                    });
+            if weight > 0.0 {
+                sum / weight
+            } else {
+                reconstruct(sorted, lambda, SparseReconstruction::KNearest { k: 1 })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sparse_spectrum() {
+        let truth = |lambda: f32| 0.5 + 0.4 * ((lambda - 380.0) / 60.0).sin();
+        let mut sampler = RandomSampler::new();
+        let mut spectrum = SparseSpectrum::new();
+        let mut other = SparseSpectrum::new();
+        for i in 0..4000 {
+            let lambda = 380.0 + 400.0 * sampler.draw_1d().x;
+            let noise = 0.2 * (sampler.draw_1d().x - 0.5);
+            let target = if i % 2 == 0 {
+                &mut spectrum
+            } else {
+                &mut other
+            };
+            target.add(lambda, truth(lambda) + noise);
+        }
+        spectrum.merge(other);
+        spectrum.add_weighted(500.0, f32::NAN, 1.0);
+        assert_eq!(spectrum.len(), 4000);
+
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let max_error = |curve: &Curve| {
+            (0..100)
+                .map(|i| {
+                    let lambda = 385.0 + 3.9 * i as f32;
+                    (curve.evaluate(lambda) - truth(lambda)).abs()
+                })
+                .fold(0.0f32, f32::max)
+        };
+        let knn = spectrum
+            .to_curve(bounds, 200, SparseReconstruction::KNearest { k: 64 })
+            .unwrap();
+        assert!(max_error(&knn) < 0.05, "{}", max_error(&knn));
+        let kernel = spectrum
+            .to_curve(bounds, 200, SparseReconstruction::Kernel { bandwidth: 3.0 })
+            .unwrap();
+        assert!(max_error(&kernel) < 0.05, "{}", max_error(&kernel));
+        // an overly wide kernel flattens the curve towards its mean
+        let wide = spectrum
+            .to_curve(
+                bounds,
+                200,
+                SparseReconstruction::Kernel { bandwidth: 200.0 },
+            )
+            .unwrap();
+        assert!(max_error(&wide) > 0.2);
+
+        // a single observation, far from most of the domain
+        let mut single = SparseSpectrum::new();
+        single.add_weighted(400.0, 2.0, 3.0);
+        for method in [
+            SparseReconstruction::KNearest { k: 4 },
+            SparseReconstruction::Kernel { bandwidth: 1.0 },
+        ] {
+            let curve = single.to_curve(bounds, 10, method).unwrap();
+            assert_eq!(curve.evaluate(700.0), 2.0);
+        }
+        assert!(SparseSpectrum::new()
+            .to_curve(bounds, 10, SparseReconstruction::KNearest { k: 1 })
+            .is_none());
+    }
+}
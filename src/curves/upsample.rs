@@ -0,0 +1,246 @@
+use crate::error::Result;
+use crate::numeric;
+use crate::prelude::*;
+use crate::spectral::{x_bar, y_bar, z_bar};
+use std::sync::OnceLock;
+
+// conversion of RGB reflectances into smooth spectra, so that textures authored in RGB can be used spectrally.
+// spectra are the sigmoid polynomials of Jakob and Hanika, "A Low-Dimensional Function Space for Efficient
+// Spectral Upsampling" (2019): s(t) = S(c0 t^2 + c1 t + c2), with S(x) = 1/2 + x / (2 sqrt(1 + x^2)),
+// which are bounded to (0, 1) like physical reflectances. the sigmoid isn't expressible with curve ops,
+// so fitted spectra are tabulated at the wavelengths they were fitted at, which makes the round trip exact.
+//
+// a reflectance maps to RGB through its XYZ under an equal energy illuminant, adapted to the white of the color space,
+// so that a constant reflectance of 1 is RGB white in every space. see `Curve::reflectance_to_rgb`.
+
+/// wavelength range over which RGB colors are upsampled
+pub const UPSAMPLING_RANGE: Bounds1D = Bounds1D::new(360.0, 830.0);
+const STEP: f32 = 5.0;
+const NODES: usize = 95;
+
+// the largest rgb error of a successful fit
+const FIT_TOLERANCE: f64 = 1e-4;
+// fits move the target from gray to the color in this many steps, as the optimization diverges
+// when started far from saturated colors
+const CONTINUATION_STEPS: usize = 8;
+const ITERATIONS: usize = 30;
+
+fn node(i: usize) -> f32 {
+    UPSAMPLING_RANGE.lower + STEP * i as f32
+}
+
+// the weights of each of the 7 color spaces, indexed by their discriminant
+static RGB_WEIGHTS: [OnceLock<Vec<[f64; 3]>>; 7] = [const { OnceLock::new() }; 7];
+
+// the contribution of a unit reflectance at each node to the rgb color in `space`, computed once per color space
+fn rgb_weights(space: ColorSpace) -> &'static [[f64; 3]] {
+    RGB_WEIGHTS[space as usize].get_or_init(|| integrate_rgb_weights(space))
+}
+
+fn integrate_rgb_weights(space: ColorSpace) -> Vec<[f64; 3]> {
+    let cmf: Vec<[f32; 3]> = (0..NODES)
+        .map(|i| {
+            let angstroms = node(i) * 10.0;
+            // the trapezoid rule gives the endpoints half weight
+            let weight = if i == 0 || i == NODES - 1 { 0.5 } else { 1.0 };
+            [
+                x_bar(angstroms) * weight,
+                y_bar(angstroms) * weight,
+                z_bar(angstroms) * weight,
+            ]
+        })
+        .collect();
+    let total = cmf.iter().fold([0.0; 3], |acc, w| {
+        [acc[0] + w[0], acc[1] + w[1], acc[2] + w[2]]
+    });
+    // the white of the equal energy illuminant as integrated here, which differs slightly from E
+    let white = XYZColor::new(total[0], total[1], total[2]).xy();
+    cmf.iter()
+        .map(|[x, y, z]| {
+            let xyz = XYZColor::new(x / total[1], y / total[1], z / total[1]);
+            let rgb = space.from_xyz_adapted(xyz, white, AdaptationMethod::Bradford);
+            [rgb.r() as f64, rgb.g() as f64, rgb.b() as f64]
+        })
+        .collect()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    0.5 + x / (2.0 * (1.0 + x * x).sqrt())
+}
+
+// the normalized wavelength of a node, in [0, 1]
+fn normalized(i: usize) -> f64 {
+    i as f64 / (NODES - 1) as f64
+}
+
+// the rgb color of the sigmoid polynomial `c`, and its jacobian with respect to `c`
+fn rgb_and_jacobian(c: [f64; 3], weights: &[[f64; 3]]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut rgb = [0.0; 3];
+    let mut jacobian = [[0.0; 3]; 3];
+    for (i, w) in weights.iter().enumerate() {
+        let t = normalized(i);
+        let x = (c[0] * t + c[1]) * t + c[2];
+        let s = sigmoid(x);
+        let ds = 0.5 / (1.0 + x * x).powf(1.5);
+        let dx = [t * t, t, 1.0];
+        for channel in 0..3 {
+            rgb[channel] += w[channel] * s;
+            for k in 0..3 {
+                jacobian[channel][k] += w[channel] * ds * dx[k];
+            }
+        }
+    }
+    (rgb, jacobian)
+}
+
+fn error(rgb: [f64; 3], target: [f64; 3]) -> f64 {
+    (0..3)
+        .map(|i| (rgb[i] - target[i]).abs())
+        .fold(0.0, f64::max)
+}
+
+/// A sigmoid polynomial spectrum, S(c0 t^2 + c1 t + c2) for the wavelength t normalized over `UPSAMPLING_RANGE`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SigmoidPolynomial {
+    pub coefficients: [f32; 3],
+}
+
+impl SigmoidPolynomial {
+    pub fn evaluate(&self, lambda: f32) -> f32 {
+        let t =
+            ((lambda - UPSAMPLING_RANGE.lower) / UPSAMPLING_RANGE.span()).clamp(0.0, 1.0) as f64;
+        let [c0, c1, c2] = self.coefficients.map(|c| c as f64);
+        sigmoid((c0 * t + c1) * t + c2) as f32
+    }
+
+    /// fits the reflectance spectrum whose color in `space` is `color`, with Gauss-Newton iterations.
+    /// fails with `NotConverged` for colors that no reflectance has, i.e. with components outside of (0, 1),
+    /// and for the most saturated colors that reflectances barely reach.
+    pub fn fit(color: RGBColor, space: ColorSpace) -> Result<Self> {
+        let target = [color.r() as f64, color.g() as f64, color.b() as f64];
+        let weights = rgb_weights(space);
+        // start from the gray of the same mean, which a constant achieves
+        let mean = (target[0] + target[1] + target[2]) / 3.0;
+        let gray = (2.0 * mean - 1.0).clamp(-0.999, 0.999);
+        let mut c = [0.0, 0.0, gray / (1.0 - gray * gray).sqrt()];
+        let (mut rgb, mut jacobian) = rgb_and_jacobian(c, weights);
+        for step in 1..=CONTINUATION_STEPS {
+            let blend = step as f64 / CONTINUATION_STEPS as f64;
+            let goal = target.map(|v| mean + (v - mean) * blend);
+            for _ in 0..ITERATIONS {
+                let residual = [0, 1, 2].map(|i| goal[i] - rgb[i]);
+                if error(rgb, goal) < FIT_TOLERANCE * 1e-2 {
+                    break;
+                }
+                let Ok(delta) = numeric::lstsq_f64(jacobian.as_flattened(), 3, 3, &residual) else {
+                    break;
+                };
+                // halve the step until it improves the fit
+                let mut scale = 1.0;
+                loop {
+                    let candidate = [0, 1, 2].map(|k| c[k] + scale * delta[k]);
+                    let (candidate_rgb, candidate_jacobian) = rgb_and_jacobian(candidate, weights);
+                    if error(candidate_rgb, goal) < error(rgb, goal) || scale < 1e-4 {
+                        c = candidate;
+                        rgb = candidate_rgb;
+                        jacobian = candidate_jacobian;
+                        break;
+                    }
+                    scale *= 0.5;
+                }
+            }
+        }
+        let remaining = error(rgb, target);
+        if remaining.is_nan() || remaining >= FIT_TOLERANCE {
+            return Err(MathError::NotConverged(remaining as f32));
+        }
+        Ok(SigmoidPolynomial {
+            coefficients: c.map(|c| c as f32),
+        })
+    }
+
+    /// tabulates the spectrum at the wavelengths it's fitted at
+    pub fn to_curve(&self) -> Curve {
+        Curve::Tabulated {
+            signal: (0..NODES)
+                .map(|i| (node(i), self.evaluate(node(i))))
+                .collect(),
+            mode: InterpolationMode::Linear,
+        }
+    }
+}
+
+impl Curve {
+    /// a smooth reflectance spectrum with the color `color` in `space`. see `SigmoidPolynomial::fit`.
+    /// grays are returned as constants, including black and white, which sigmoids only approach.
+    pub fn from_rgb(color: RGBColor, space: ColorSpace) -> Result<Curve> {
+        if color.r() == color.g() && color.g() == color.b() && (0.0..=1.0).contains(&color.r()) {
+            return Ok(Curve::Const(color.r()));
+        }
+        Ok(SigmoidPolynomial::fit(color, space)?.to_curve())
+    }
+
+    /// the color of `self` as a reflectance in `space`, under an equal energy illuminant adapted to the white of `space`
+    pub fn reflectance_to_rgb(&self, space: ColorSpace) -> RGBColor {
+        let rgb = rgb_weights(space)
+            .iter()
+            .enumerate()
+            .fold([0.0; 3], |acc, (i, w)| {
+                let value = self.evaluate_power(node(i)) as f64;
+                [0, 1, 2].map(|c| acc[c] + w[c] * value)
+            });
+        RGBColor::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rgb_upsampling() {
+        for space in [ColorSpace::SRGB, ColorSpace::Rec2020] {
+            for color in [
+                RGBColor::new(0.8, 0.2, 0.1),
+                RGBColor::new(0.1, 0.6, 0.3),
+                RGBColor::new(0.2, 0.3, 0.9),
+                RGBColor::new(0.95, 0.9, 0.05),
+                RGBColor::new(0.02, 0.02, 0.03),
+                RGBColor::new(0.5, 0.5, 0.5),
+            ] {
+                let curve = Curve::from_rgb(color, space).unwrap();
+                let round_trip = curve.reflectance_to_rgb(space);
+                for (a, b) in round_trip.0.to_array()[..3]
+                    .iter()
+                    .zip(color.0.to_array().iter())
+                {
+                    assert!(
+                        (a - b).abs() < 2e-4,
+                        "{:?} {:?} {:?}",
+                        space,
+                        color,
+                        round_trip
+                    );
+                }
+                // spectra are valid reflectances
+                for i in 0..=100 {
+                    let value = curve.evaluate(UPSAMPLING_RANGE.lerp(i as f32 / 100.0));
+                    assert!((0.0..=1.0).contains(&value));
+                }
+            }
+        }
+        // white and black are constants, and colors that no reflectance has can't be fit
+        assert_eq!(
+            Curve::from_rgb(RGBColor::new(1.0, 1.0, 1.0), ColorSpace::SRGB),
+            Ok(Curve::Const(1.0))
+        );
+        let white = Curve::Const(1.0).reflectance_to_rgb(ColorSpace::ProPhoto);
+        assert!(white.0.to_array()[..3]
+            .iter()
+            .all(|v| (v - 1.0).abs() < 1e-5));
+        assert!(matches!(
+            Curve::from_rgb(RGBColor::new(1.5, 0.2, 0.2), ColorSpace::SRGB),
+            Err(MathError::NotConverged(_))
+        ));
+    }
+}
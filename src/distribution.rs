@@ -0,0 +1,428 @@
+use crate::curves::invert_cdf_edges;
+use crate::prelude::*;
+
+#[cfg(feature = "deepsize")]
+use deepsize::DeepSizeOf;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A piecewise constant distribution over `bounds`, with one bin per entry of `func`.
+/// the cdf is stored as N+1 (x, cdf(x)) edges, matching the representation used by `CurveWithCDF`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+pub struct Distribution1D {
+    pub func: Vec<f32>,
+    pub cdf: Vec<(f32, f32)>,
+    pub func_integral: f32,
+    pub bounds: Bounds1D,
+}
+
+impl Distribution1D {
+    pub fn new(func: &[f32], bounds: Bounds1D) -> Self {
+        assert!(!func.is_empty());
+        let n = func.len();
+        let bin_width = bounds.span() / n as f32;
+        let mut cdf = Vec::with_capacity(n + 1);
+        let mut s = 0.0;
+        cdf.push((bounds.lower, 0.0));
+        for (i, f) in func.iter().enumerate() {
+            debug_assert!(
+                *f >= 0.0,
+                "distribution values must be nonnegative, got {}",
+                f
+            );
+            s += f * bin_width;
+            cdf.push((bounds.lower + (i + 1) as f32 * bin_width, s));
+        }
+        if s > 0.0 {
+            cdf.iter_mut().for_each(|e| e.1 /= s);
+        } else {
+            // degenerate function, fall back to a uniform distribution
+            cdf.iter_mut()
+                .enumerate()
+                .for_each(|(i, e)| e.1 = i as f32 / n as f32);
+        }
+        Distribution1D {
+            func: func.to_vec(),
+            cdf,
+            func_integral: s,
+            bounds,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.func.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.func.is_empty()
+    }
+    /// index of the bin that contains `x`, clamped to the valid range
+    pub fn bin_index(&self, x: f32) -> usize {
+        let t = (x - self.bounds.lower) / self.bounds.span();
+        ((t * self.len() as f32).max(0.0) as usize).min(self.len() - 1)
+    }
+    /// returns the sampled x, its pdf wrt length along `bounds`, and the index of the bin that it fell in
+    pub fn sample(&self, sample: Sample1D) -> (f32, PDF<f32, Length>, usize) {
+        let (x, density) = invert_cdf_edges(&self.cdf, sample.x);
+        // the bin chosen by the inversion, as in `invert_cdf_edges`. bin_index(x) could round to a neighbor at the edges
+        let index = self
+            .cdf
+            .partition_point(|&(_, c)| c <= sample.x)
+            .clamp(1, self.len())
+            - 1;
+        (x, PDF::new(density), index)
+    }
+    pub fn pdf(&self, x: f32) -> PDF<f32, Length> {
+        if !self.bounds.contains(&x) && x != self.bounds.upper {
+            return PDF::new(0.0);
+        }
+        let index = self.bin_index(x);
+        let (x0, c0) = self.cdf[index];
+        let (x1, c1) = self.cdf[index + 1];
+        PDF::new((c1 - c0) / (x1 - x0))
+    }
+}
+
+/// A piecewise constant distribution over `x_bounds` x `y_bounds`, sampled by first choosing a row through the
+/// marginal distribution, then choosing x through that row's conditional distribution.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+pub struct Distribution2D {
+    pub conditionals: Vec<Distribution1D>,
+    pub marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(grid: &Grid2D<f32>, x_bounds: Bounds1D, y_bounds: Bounds1D) -> Self {
+        let conditionals: Vec<Distribution1D> = (0..grid.height())
+            .map(|y| Distribution1D::new(grid.row(y), x_bounds))
+            .collect();
+        let marginal_func: Vec<f32> = conditionals.iter().map(|c| c.func_integral).collect();
+        Distribution2D {
+            marginal: Distribution1D::new(&marginal_func, y_bounds),
+            conditionals,
+        }
+    }
+    /// builds a distribution over uv space ([0, 1]^2) from a row major buffer of luminance values,
+    /// i.e. for sampling an environment map or an emissive texture.
+    /// negative and non finite values are treated as 0.
+    /// if `sin_theta_weighting` is true, the buffer is treated as a lat-long map following `uv_to_direction`,
+    /// and each row is weighted by the sine of its inclination to account for the compression of texels towards the poles.
+    /// sampled pdfs can then be converted to solid angle with `lat_long_pdf_to_solid_angle`.
+    pub fn from_luminance_buffer(
+        width: usize,
+        height: usize,
+        buffer: &[f32],
+        sin_theta_weighting: bool,
+    ) -> Self {
+        assert_eq!(buffer.len(), width * height);
+        let grid = Grid2D::from_function(width, height, |x, y| {
+            let value = buffer[y * width + x];
+            let value = if value.is_finite() {
+                value.max(0.0)
+            } else {
+                0.0
+            };
+            if sin_theta_weighting {
+                value * (PI * (y as f32 + 0.5) / height as f32).sin()
+            } else {
+                value
+            }
+        });
+        Distribution2D::new(&grid, Bounds1D::new(0.0, 1.0), Bounds1D::new(0.0, 1.0))
+    }
+    /// same as `from_luminance_buffer`, using the Y component of each color as its luminance
+    pub fn from_xyz_buffer(
+        width: usize,
+        height: usize,
+        buffer: &[XYZColor],
+        sin_theta_weighting: bool,
+    ) -> Self {
+        let luminance: Vec<f32> = buffer.iter().map(|c| c.y()).collect();
+        Distribution2D::from_luminance_buffer(width, height, &luminance, sin_theta_weighting)
+    }
+    pub fn integral(&self) -> f32 {
+        self.marginal.func_integral
+    }
+    pub fn sample(&self, sample: Sample2D) -> ((f32, f32), PDF<f32, Area>) {
+        let (y, marginal_pdf, row) = self.marginal.sample(Sample1D { x: sample.y });
+        let (x, conditional_pdf, _) = self.conditionals[row].sample(Sample1D { x: sample.x });
+        ((x, y), PDF::new(*marginal_pdf * *conditional_pdf))
+    }
+    pub fn pdf(&self, point: (f32, f32)) -> PDF<f32, Area> {
+        let marginal_pdf = self.marginal.pdf(point.1);
+        let row = self.marginal.bin_index(point.1);
+        let conditional_pdf = self.conditionals[row].pdf(point.0);
+        PDF::new(*marginal_pdf * *conditional_pdf)
+    }
+}
+
+/// converts a pdf wrt area in the uv space of a lat-long map to a pdf wrt solid angle,
+/// following the parameterization of `uv_to_direction`. returns 0 at the poles, where the mapping is degenerate.
+pub fn lat_long_pdf_to_solid_angle(pdf: PDF<f32, Area>, v: f32) -> PDF<f32, SolidAngle> {
+    let sin_theta = (PI * v).sin();
+    if sin_theta <= 0.0 {
+        return PDF::new(0.0);
+    }
+    PDF::new(*pdf / (2.0 * PI * PI * sin_theta))
+}
+
+// the bilinear interpolant of a table with `n` cells along an axis is bilinear over n + 1 patches, split at the cell
+// centers. the outer patches are half a cell wide and constant along the axis, as the interpolant is clamped there.
+// returns the indices of the cells at either side of patch `i`, and its lower edge and width in units of cells.
+fn patch(n: usize, i: usize) -> (usize, usize, f32, f32) {
+    let lower = if i == 0 { 0.0 } else { i as f32 - 0.5 };
+    let width = if i == 0 || i == n { 0.5 } else { 1.0 };
+    (i.saturating_sub(1), i.min(n - 1), lower, width)
+}
+
+// the patch containing `c`, given in units of cells
+fn patch_index(n: usize, c: f32) -> usize {
+    ((c + 0.5).max(0.0) as usize).min(n)
+}
+
+// samples t in [0, 1) with a density proportional to (1 - t) a + t b, following pbrt-v4
+fn sample_linear(u: f32, a: f32, b: f32) -> f32 {
+    if u == 0.0 && a == 0.0 {
+        return 0.0;
+    }
+    let t = u * (a + b) / (a + ((1.0 - u) * a * a + u * b * b).sqrt());
+    t.min(1.0 - f32::EPSILON)
+}
+
+/// Tabulated 2D data, i.e. measured angular data such as a BRDF slice or a goniometer scan,
+/// over the domain `x_bounds` x `y_bounds`. evaluation bilinearly interpolates the four nearest table values,
+/// and sampling is proportional to that interpolant, such that pdfs are proportional to `evaluate`.
+/// `distribution` chooses between the bilinear patches of the interpolant, split at the cell centers.
+/// if `data` is modified directly, `rebuild_distribution` must be called before sampling again.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+pub struct Tabulated2D {
+    pub data: Grid2D<f32>,
+    pub x_bounds: Bounds1D,
+    pub y_bounds: Bounds1D,
+    pub distribution: Distribution2D,
+}
+
+impl Tabulated2D {
+    pub fn new(data: Grid2D<f32>, x_bounds: Bounds1D, y_bounds: Bounds1D) -> Self {
+        let distribution = Tabulated2D::patch_distribution(&data);
+        Tabulated2D {
+            data,
+            x_bounds,
+            y_bounds,
+            distribution,
+        }
+    }
+    // the mass of each patch in units of cells, the mean of its corners times its area
+    fn patch_distribution(data: &Grid2D<f32>) -> Distribution2D {
+        let (width, height) = (data.width(), data.height());
+        let masses = Grid2D::from_function(width + 1, height + 1, |i, j| {
+            let (x0, x1, _, patch_width) = patch(width, i);
+            let (y0, y1, _, patch_height) = patch(height, j);
+            let corners = data.at(x0, y0) + data.at(x1, y0) + data.at(x0, y1) + data.at(x1, y1);
+            patch_width * patch_height * corners / 4.0
+        });
+        Distribution2D::new(
+            &masses,
+            Bounds1D::new(0.0, (width + 1) as f32),
+            Bounds1D::new(0.0, (height + 1) as f32),
+        )
+    }
+    pub fn rebuild_distribution(&mut self) {
+        self.distribution = Tabulated2D::patch_distribution(&self.data);
+    }
+    // the area of a cell in the domain
+    fn cell_area(&self) -> f32 {
+        self.x_bounds.span() / self.data.width() as f32 * self.y_bounds.span()
+            / self.data.height() as f32
+    }
+    pub fn evaluate(&self, x: f32, y: f32) -> f32 {
+        let u = (x - self.x_bounds.lower) / self.x_bounds.span();
+        let v = (y - self.y_bounds.lower) / self.y_bounds.span();
+        self.data.bilinear(u, v)
+    }
+    /// integral of the bilinear interpolant over the domain
+    pub fn integral(&self) -> f32 {
+        self.distribution.integral() * self.cell_area()
+    }
+    /// scales the table such that its integral over the domain is 1
+    pub fn normalize(&mut self) {
+        let integral = self.integral();
+        if integral > 0.0 {
+            self.data.data_mut().iter_mut().for_each(|v| *v /= integral);
+            self.rebuild_distribution();
+        }
+    }
+    // the corners of patch (i, j), and its lower corner and size in units of cells
+    fn patch_corners(&self, i: usize, j: usize) -> ([f32; 4], (f32, f32), (f32, f32)) {
+        let (x0, x1, x_lower, patch_width) = patch(self.data.width(), i);
+        let (y0, y1, y_lower, patch_height) = patch(self.data.height(), j);
+        (
+            [
+                self.data.at(x0, y0),
+                self.data.at(x1, y0),
+                self.data.at(x0, y1),
+                self.data.at(x1, y1),
+            ],
+            (x_lower, y_lower),
+            (patch_width, patch_height),
+        )
+    }
+    // the pdf wrt area of the point (tx, ty) within patch (i, j), with (tx, ty) in [0, 1]^2
+    fn patch_pdf(&self, i: usize, j: usize, tx: f32, ty: f32) -> f32 {
+        let ([c00, c10, c01, c11], _, (patch_width, patch_height)) = self.patch_corners(i, j);
+        let probability = *self.distribution.pdf((i as f32 + 0.5, j as f32 + 0.5));
+        let mean = (c00 + c10 + c01 + c11) / 4.0;
+        // points are uniform within patches without mass, which are only sampled when the whole table is zero
+        let density = if mean > 0.0 {
+            let bottom = (1.0 - tx) * c00 + tx * c10;
+            let top = (1.0 - tx) * c01 + tx * c11;
+            ((1.0 - ty) * bottom + ty * top) / mean
+        } else {
+            1.0
+        };
+        probability * density / (patch_width * patch_height * self.cell_area())
+    }
+    /// returns the sampled point, the table value at that point, and the pdf wrt the area of the domain
+    pub fn sample(&self, sample: Sample2D) -> ((f32, f32), f32, PDF<f32, Area>) {
+        // choose a patch, reusing the position within its bins as the sample within the patch
+        let ((px, py), _) = self.distribution.sample(sample);
+        let (i, j) = (
+            (px as usize).min(self.data.width()),
+            (py as usize).min(self.data.height()),
+        );
+        let (u, v) = (
+            (px - i as f32).clamp(0.0, 1.0),
+            (py - j as f32).clamp(0.0, 1.0),
+        );
+        let ([c00, c10, c01, c11], (x_lower, y_lower), (patch_width, patch_height)) =
+            self.patch_corners(i, j);
+        // the marginal density of y is linear, as is the density of x given y
+        let ty = sample_linear(v, c00 + c10, c01 + c11);
+        let tx = sample_linear(u, (1.0 - ty) * c00 + ty * c01, (1.0 - ty) * c10 + ty * c11);
+        let x = self.x_bounds.lower
+            + (x_lower + tx * patch_width) / self.data.width() as f32 * self.x_bounds.span();
+        let y = self.y_bounds.lower
+            + (y_lower + ty * patch_height) / self.data.height() as f32 * self.y_bounds.span();
+        (
+            (x, y),
+            self.evaluate(x, y),
+            PDF::new(self.patch_pdf(i, j, tx, ty)),
+        )
+    }
+    pub fn pdf(&self, x: f32, y: f32) -> PDF<f32, Area> {
+        let (width, height) = (self.data.width(), self.data.height());
+        let cx = (x - self.x_bounds.lower) / self.x_bounds.span() * width as f32;
+        let cy = (y - self.y_bounds.lower) / self.y_bounds.span() * height as f32;
+        if !(0.0..=width as f32).contains(&cx) || !(0.0..=height as f32).contains(&cy) {
+            return PDF::new(0.0);
+        }
+        let (i, j) = (patch_index(width, cx), patch_index(height, cy));
+        let (_, (x_lower, y_lower), (patch_width, patch_height)) = self.patch_corners(i, j);
+        PDF::new(self.patch_pdf(
+            i,
+            j,
+            ((cx - x_lower) / patch_width).clamp(0.0, 1.0),
+            ((cy - y_lower) / patch_height).clamp(0.0, 1.0),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_distribution1d() {
+        let dist = Distribution1D::new(&[1.0, 3.0, 0.0, 4.0], Bounds1D::new(0.0, 4.0));
+        assert_eq!(dist.func_integral, 8.0);
+        assert_eq!(*dist.pdf(0.5), 1.0 / 8.0);
+        assert_eq!(*dist.pdf(2.5), 0.0);
+        assert_eq!(*dist.pdf(5.0), 0.0);
+        for i in 0..100 {
+            let (x, pdf, index) = dist.sample(Sample1D::new(i as f32 / 100.0));
+            // the zero bin is never sampled
+            assert_ne!(index, 2);
+            assert_eq!(*pdf, *dist.pdf(x));
+            assert_eq!(*pdf, dist.func[index] / dist.func_integral);
+        }
+    }
+
+    #[test]
+    fn test_environment_map_distribution() {
+        // a constant environment map should be sampled uniformly over the sphere once sin theta weighting is applied
+        let (width, height) = (32, 16);
+        let buffer = vec![XYZColor::new(0.5, 1.0, 0.5); width * height];
+        let distribution = Distribution2D::from_xyz_buffer(width, height, &buffer, true);
+        for _ in 0..1000 {
+            let ((u, v), pdf) = distribution.sample(Sample2D::new_random_sample());
+            assert!((*pdf - *distribution.pdf((u, v))).abs() < 1e-3 * *pdf);
+            // rows are piecewise constant, so the weighting is only exact at the center of each row
+            let row_center = ((v * height as f32).floor() + 0.5) / height as f32;
+            let solid_angle_pdf = lat_long_pdf_to_solid_angle(pdf, row_center);
+            assert!(
+                (*solid_angle_pdf * 4.0 * PI - 1.0).abs() < 0.01,
+                "{:?}",
+                solid_angle_pdf
+            );
+        }
+
+        let mut luminance = vec![0.0; width * height];
+        luminance[5 * width + 7] = 1.0;
+        luminance[0] = f32::NAN;
+        luminance[1] = -1.0;
+        let distribution = Distribution2D::from_luminance_buffer(width, height, &luminance, false);
+        let ((u, v), _) = distribution.sample(Sample2D::new_random_sample());
+        assert_eq!(
+            ((u * width as f32) as usize, (v * height as f32) as usize),
+            (7, 5)
+        );
+    }
+
+    #[test]
+    fn test_tabulated2d_sampling() {
+        let data = Grid2D::from_function(8, 4, |x, y| 1.0 + (x * y) as f32);
+        let x_bounds = Bounds1D::new(0.0, 2.0);
+        let y_bounds = Bounds1D::new(-1.0, 1.0);
+        let mut table = Tabulated2D::new(data, x_bounds, y_bounds);
+
+        // the integral is that of the bilinear interpolant
+        let n = 400;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let x = x_bounds.lerp((i as f32 + 0.5) / n as f32);
+                let y = y_bounds.lerp((j as f32 + 0.5) / n as f32);
+                integral +=
+                    table.evaluate(x, y) * x_bounds.span() * y_bounds.span() / (n * n) as f32;
+            }
+        }
+        assert!(
+            (table.integral() - integral).abs() < 1e-3 * integral,
+            "{} {}",
+            table.integral(),
+            integral
+        );
+        table.normalize();
+        assert!((table.integral() - 1.0).abs() < 1e-5);
+
+        // the pdf is proportional to the interpolated table, so every sample estimates the integral exactly
+        for _ in 0..10000 {
+            let ((x, y), value, pdf) = table.sample(Sample2D::new_random_sample());
+            assert!(x_bounds.lower <= x && x <= x_bounds.upper);
+            assert!(y_bounds.lower <= y && y <= y_bounds.upper);
+            assert!((*pdf - *table.pdf(x, y)).abs() < 1e-4 * *pdf);
+            assert!((value / *pdf - 1.0).abs() < 1e-4, "{} {}", value, *pdf);
+        }
+        assert_eq!(*table.pdf(2.5, 0.0), 0.0);
+
+        // a table without mass falls back to choosing patches uniformly, with pdfs that still match
+        let zero = Tabulated2D::new(Grid2D::from_function(3, 2, |_, _| 0.0), x_bounds, y_bounds);
+        let ((x, y), value, pdf) = zero.sample(Sample2D::new(0.3, 0.7));
+        assert_eq!(value, 0.0);
+        assert!(*pdf > 0.0 && (*pdf - *zero.pdf(x, y)).abs() < 1e-4 * *pdf);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::prelude::*;
+
+use std::cmp::Ordering;
+use std::ops::{AddAssign, MulAssign, Sub};
+
+// forward mode automatic differentiation with dual numbers, a + b e where e^2 = 0.
+// evaluating f at x + e gives f(x) + f'(x) e, so that arithmetic on duals carries the derivative along with the value,
+// exactly up to rounding, without choosing a step size as finite differences do.
+
+/// A value together with its derivative with respect to some variable
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Dual<T> {
+    pub value: T,
+    pub derivative: T,
+}
+
+impl<T: Field> Dual<T> {
+    pub const fn new(value: T, derivative: T) -> Self {
+        Dual { value, derivative }
+    }
+    /// a value that doesn't depend on the variable
+    pub const fn constant(value: T) -> Self {
+        Dual::new(value, T::ZERO)
+    }
+    /// the variable being differentiated with respect to, at `value`
+    pub const fn variable(value: T) -> Self {
+        Dual::new(value, T::ONE)
+    }
+}
+
+impl Dual<f32> {
+    pub fn recip(self) -> Self {
+        let inv = 1.0 / self.value;
+        Dual::new(inv, -self.derivative * inv * inv)
+    }
+    pub fn exp(self) -> Self {
+        let exp = self.value.exp();
+        Dual::new(exp, self.derivative * exp)
+    }
+    pub fn ln(self) -> Self {
+        Dual::new(self.value.ln(), self.derivative / self.value)
+    }
+    pub fn sqrt(self) -> Self {
+        let sqrt = self.value.sqrt();
+        Dual::new(sqrt, self.derivative * 0.5 / sqrt)
+    }
+    pub fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Dual::constant(1.0);
+        }
+        Dual::new(
+            self.value.powi(n),
+            self.derivative * n as f32 * self.value.powi(n - 1),
+        )
+    }
+    pub fn powf(self, p: f32) -> Self {
+        Dual::new(
+            self.value.powf(p),
+            self.derivative * p * self.value.powf(p - 1.0),
+        )
+    }
+    pub fn sin(self) -> Self {
+        Dual::new(self.value.sin(), self.derivative * self.value.cos())
+    }
+    pub fn cos(self) -> Self {
+        Dual::new(self.value.cos(), -self.derivative * self.value.sin())
+    }
+    /// clamps the value, with a derivative of 0 wherever it was clamped
+    pub fn clamp(self, min: f32, max: f32) -> Self {
+        if self.value < min {
+            Dual::constant(min)
+        } else if self.value > max {
+            Dual::constant(max)
+        } else {
+            self
+        }
+    }
+}
+
+impl<T: Field> Add for Dual<T> {
+    type Output = Dual<T>;
+    fn add(self, rhs: Dual<T>) -> Self::Output {
+        Dual::new(self.value + rhs.value, self.derivative + rhs.derivative)
+    }
+}
+
+impl<T: Field> Add<T> for Dual<T> {
+    type Output = Dual<T>;
+    fn add(self, rhs: T) -> Self::Output {
+        Dual::new(self.value + rhs, self.derivative)
+    }
+}
+
+impl<T: Field> AddAssign for Dual<T> {
+    fn add_assign(&mut self, rhs: Dual<T>) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Field> Sub for Dual<T> {
+    type Output = Dual<T>;
+    fn sub(self, rhs: Dual<T>) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl<T: Field> Sub<T> for Dual<T> {
+    type Output = Dual<T>;
+    fn sub(self, rhs: T) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl<T: Field> Neg for Dual<T> {
+    type Output = Dual<T>;
+    fn neg(self) -> Self::Output {
+        Dual::new(-self.value, -self.derivative)
+    }
+}
+
+impl<T: Field> Mul for Dual<T> {
+    type Output = Dual<T>;
+    fn mul(self, rhs: Dual<T>) -> Self::Output {
+        // product rule
+        Dual::new(
+            self.value * rhs.value,
+            self.derivative * rhs.value + self.value * rhs.derivative,
+        )
+    }
+}
+
+impl<T: Field> Mul<T> for Dual<T> {
+    type Output = Dual<T>;
+    fn mul(self, rhs: T) -> Self::Output {
+        Dual::new(self.value * rhs, self.derivative * rhs)
+    }
+}
+
+impl<T: Field> MulAssign for Dual<T> {
+    fn mul_assign(&mut self, rhs: Dual<T>) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: Field> Div for Dual<T> {
+    type Output = Dual<T>;
+    fn div(self, rhs: Dual<T>) -> Self::Output {
+        // quotient rule, (a' b - a b') / b^2
+        let value = self.value / rhs.value;
+        Dual::new(
+            value,
+            (self.derivative + -(value * rhs.derivative)) / rhs.value,
+        )
+    }
+}
+
+impl<T: Field> Div<T> for Dual<T> {
+    type Output = Dual<T>;
+    fn div(self, rhs: T) -> Self::Output {
+        Dual::new(self.value / rhs, self.derivative / rhs)
+    }
+}
+
+impl Abs for Dual<f32> {
+    fn abs(self) -> Self {
+        if self.value < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+}
+
+// duals are ordered by their values, ignoring the derivatives
+impl TotalPartialOrd for Dual<f32> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        PartialOrd::partial_cmp(&self.value, &other.value)
+    }
+}
+
+impl CheckNAN for Dual<f32> {
+    fn check_nan(&self) -> CheckResult {
+        match (self.value.is_nan(), self.derivative.is_nan()) {
+            (true, true) => CheckResult::All,
+            (false, false) => CheckResult::None,
+            _ => CheckResult::Some,
+        }
+    }
+}
+
+impl CheckInf for Dual<f32> {
+    fn check_inf(&self) -> CheckResult {
+        match (self.value.is_infinite(), self.derivative.is_infinite()) {
+            (true, true) => CheckResult::All,
+            (false, false) => CheckResult::None,
+            _ => CheckResult::Some,
+        }
+    }
+}
+
+impl Field for Dual<f32> {
+    const ZERO: Self = Dual::constant(0.0);
+    const ONE: Self = Dual::constant(1.0);
+    // the derivative is that of whichever argument is selected
+    fn max(&self, other: Self) -> Self {
+        if other.value > self.value {
+            other
+        } else {
+            *self
+        }
+    }
+    fn min(&self, other: Self) -> Self {
+        if other.value < self.value {
+            other
+        } else {
+            *self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dual() {
+        let x = Dual::variable(2.0f32);
+        // d/dx (x^3 + 1) / x = 2x - 1 / x^2
+        let f = (x * x * x + 1.0) / x;
+        assert_eq!(f.value, 4.5);
+        assert_eq!(f.derivative, 3.75);
+        // chain rule through exp and sqrt, d/dx exp(sqrt(x)) = exp(sqrt(x)) / (2 sqrt(x))
+        let g = x.sqrt().exp();
+        let expected = 2.0f32.sqrt().exp() / (2.0 * 2.0f32.sqrt());
+        assert!((g.derivative - expected).abs() < 1e-6);
+        assert_eq!(x.powi(3).derivative, 12.0);
+        assert_eq!((-x).abs(), x);
+        assert_eq!(x.clamp(0.0, 1.0), Dual::constant(1.0));
+        assert_eq!(Field::max(&x, Dual::constant(1.0)), x);
+        assert_eq!(Dual::<f32>::ONE * x, x);
+    }
+}
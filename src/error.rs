@@ -0,0 +1,52 @@
+use crate::bounds::Bounds1D;
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MathError {
+    /// a curve was constructed with data that can't be evaluated, i.e. an empty or unsorted signal
+    InvalidCurve(String),
+    /// a matrix that needed to be inverted was singular
+    SingularMatrix,
+    /// a bounds or range had no extent
+    EmptyBounds,
+    /// a value was outside of the domain where it is valid
+    OutOfDomain(f32, Bounds1D),
+    /// a slice had a length that isn't supported, i.e. a transform size that isn't a power of two
+    UnsupportedLength(usize),
+    /// a matrix had a shape that isn't supported, given as (rows, columns), i.e. an underdetermined system
+    UnsupportedShape(usize, usize),
+    /// an iterative fit or solver didn't converge, with the error that remained
+    NotConverged(f32),
+    /// a density that needed to be positive somewhere was zero at every point tried, given as the number of points
+    ZeroDensity(usize),
+}
+
+pub type Result<T> = std::result::Result<T, MathError>;
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::InvalidCurve(reason) => write!(f, "invalid curve: {}", reason),
+            MathError::SingularMatrix => write!(f, "matrix is singular and cannot be inverted"),
+            MathError::EmptyBounds => write!(f, "bounds are empty"),
+            MathError::OutOfDomain(value, domain) => write!(
+                f,
+                "value {} is outside of the domain [{}, {}]",
+                value, domain.lower, domain.upper
+            ),
+            MathError::UnsupportedLength(length) => write!(f, "unsupported length {}", length),
+            MathError::UnsupportedShape(rows, cols) => {
+                write!(f, "unsupported matrix shape {}x{}", rows, cols)
+            }
+            MathError::NotConverged(error) => {
+                write!(f, "failed to converge, with a remaining error of {}", error)
+            }
+            MathError::ZeroDensity(tries) => {
+                write!(f, "density is zero at all {} points tried", tries)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
@@ -0,0 +1,275 @@
+use crate::prelude::*;
+use crate::traits::Sqrt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Running statistics of a Monte Carlo estimator, accumulated one sample at a time using Welford's algorithm,
+/// which avoids the catastrophic cancellation of the naive E[x^2] - E[x]^2 formulation.
+/// summaries of independent batches, e.g. from parallel workers, can be combined with `merge`.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EstimateSummary<T: Field> {
+    pub count: usize,
+    mean: T,
+    // sum of squared deviations from the mean
+    m2: T,
+}
+
+impl<T: Field + FromScalar<f32> + Sqrt> EstimateSummary<T> {
+    pub fn new() -> Self {
+        EstimateSummary {
+            count: 0,
+            mean: T::ZERO,
+            m2: T::ZERO,
+        }
+    }
+
+    pub fn from_samples(samples: impl IntoIterator<Item = T>) -> Self {
+        let mut summary = EstimateSummary::new();
+        for sample in samples {
+            summary.add(sample);
+        }
+        summary
+    }
+
+    /// adds a sample of the estimator, i.e. f(x) / p(x)
+    pub fn add(&mut self, sample: T) {
+        self.count += 1;
+        let delta = sample + -self.mean;
+        self.mean += delta / T::from_scalar(self.count as f32);
+        self.m2 += delta * (sample + -self.mean);
+    }
+
+    /// combines the statistics of two independent batches of samples
+    pub fn merge(&self, other: &Self) -> Self {
+        if self.count == 0 {
+            return *other;
+        }
+        if other.count == 0 {
+            return *self;
+        }
+        let count = self.count + other.count;
+        let (n_a, n_b, n) = (
+            T::from_scalar(self.count as f32),
+            T::from_scalar(other.count as f32),
+            T::from_scalar(count as f32),
+        );
+        let delta = other.mean + -self.mean;
+        EstimateSummary {
+            count,
+            mean: self.mean + delta * n_b / n,
+            m2: self.m2 + other.m2 + delta * delta * n_a * n_b / n,
+        }
+    }
+
+    pub fn mean(&self) -> T {
+        self.mean
+    }
+    /// the unbiased sample variance, or 0 with fewer than two samples
+    pub fn variance(&self) -> T {
+        if self.count < 2 {
+            return T::ZERO;
+        }
+        self.m2 / T::from_scalar((self.count - 1) as f32)
+    }
+    /// the standard deviation of the mean
+    pub fn standard_error(&self) -> T {
+        if self.count == 0 {
+            return T::ZERO;
+        }
+        (self.variance() / T::from_scalar(self.count as f32)).sqrt()
+    }
+    /// the standard error relative to the magnitude of the mean
+    pub fn relative_error(&self) -> T {
+        self.standard_error() / self.mean.abs()
+    }
+    /// the interval mean +- z * standard error, where z is the standard score for the desired confidence level,
+    /// e.g. 1.96 for 95% or 2.576 for 99%
+    pub fn confidence_interval(&self, z: f32) -> (T, T) {
+        let half_width = T::from_scalar(z) * self.standard_error();
+        (self.mean + -half_width, self.mean + half_width)
+    }
+}
+
+impl<T: Field + FromScalar<f32> + Sqrt> Default for EstimateSummary<T> {
+    fn default() -> Self {
+        EstimateSummary::new()
+    }
+}
+
+/// evaluates `samples` samples of an estimator, given a function that returns the i'th sample f(x_i) / p(x_i)
+pub fn mc_estimate<T, F>(samples: usize, mut estimator: F) -> EstimateSummary<T>
+where
+    T: Field + FromScalar<f32> + Sqrt,
+    F: FnMut(usize) -> T,
+{
+    EstimateSummary::from_samples((0..samples).map(&mut estimator))
+}
+
+/// The result of `integrate_with_control_variate`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ControlVariateEstimate {
+    pub estimate: f32,
+    pub standard_error: f32,
+    /// the coefficient of the control variate, Cov(f, g) / Var(g), estimated from the same samples
+    pub coefficient: f32,
+    /// statistics of the plain estimator of the integral of f, without the control variate
+    pub plain: EstimateSummary<f32>,
+}
+
+impl ControlVariateEstimate {
+    /// the factor by which the control variate reduced the variance, compared to the plain estimator
+    pub fn variance_reduction(&self) -> f32 {
+        if self.standard_error == 0.0 {
+            return f32::INFINITY;
+        }
+        (self.plain.standard_error() / self.standard_error).powi(2)
+    }
+}
+
+/// estimates the integral of `f` over `bounds` using `g` as a control variate, given the exact integral of `g`.
+/// both curves are evaluated at the same uniformly sampled wavelengths, and the estimator is
+/// mean(f / p) - c * (mean(g / p) - integral(g)), with c chosen to minimize the variance.
+/// the closer `g` is to being proportional to `f`, the more variance is removed.
+pub fn integrate_with_control_variate<S: Sampler + ?Sized>(
+    f: &Curve,
+    g: &Curve,
+    g_exact_integral: f32,
+    bounds: Bounds1D,
+    samples: usize,
+    sampler: &mut S,
+) -> ControlVariateEstimate {
+    let mut plain = EstimateSummary::new();
+    let mut control = EstimateSummary::new();
+    // running co-moment of the two estimators, accumulated alongside their means
+    let mut co_moment = 0.0;
+    for _ in 0..samples {
+        let (sample, pdf) = f.sample_power_and_pdf(bounds, sampler.draw_1d());
+        let pdf = *pdf;
+        let f_sample = sample.energy / pdf;
+        let g_sample = g.evaluate(sample.lambda) / pdf;
+        let f_delta = f_sample - plain.mean();
+        plain.add(f_sample);
+        control.add(g_sample);
+        co_moment += f_delta * (g_sample - control.mean());
+    }
+    if samples < 2 {
+        return ControlVariateEstimate {
+            estimate: plain.mean(),
+            standard_error: plain.standard_error(),
+            coefficient: 0.0,
+            plain,
+        };
+    }
+    let covariance = co_moment / (samples - 1) as f32;
+    let coefficient = if control.variance() > 0.0 {
+        covariance / control.variance()
+    } else {
+        0.0
+    };
+    let residual_variance = (plain.variance() - coefficient * covariance).max(0.0);
+    ControlVariateEstimate {
+        estimate: plain.mean() - coefficient * (control.mean() - g_exact_integral),
+        standard_error: (residual_variance / samples as f32).sqrt(),
+        coefficient,
+        plain,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_estimate_summary() {
+        let data = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let summary = EstimateSummary::from_samples(data.iter().copied());
+        assert_eq!(summary.mean(), 5.0);
+        assert!((summary.variance() - 32.0 / 7.0).abs() < 1e-5);
+        assert!((summary.standard_error() - (32.0f32 / 7.0 / 8.0).sqrt()).abs() < 1e-5);
+        let (lower, upper) = summary.confidence_interval(1.96);
+        assert!((upper - lower - 2.0 * 1.96 * summary.standard_error()).abs() < 1e-5);
+
+        // merging batches matches accumulating all samples at once
+        let left = EstimateSummary::from_samples(data[..3].iter().copied());
+        let right = EstimateSummary::from_samples(data[3..].iter().copied());
+        let merged = left.merge(&right);
+        assert_eq!(merged.count, 8);
+        assert!((merged.mean() - 5.0).abs() < 1e-6);
+        assert!((merged.variance() - summary.variance()).abs() < 1e-5);
+        assert_eq!(EstimateSummary::new().merge(&summary), summary);
+
+        let wide = EstimateSummary::from_samples(
+            data.iter()
+                .map(|&v| f32x4::from_array([v, 2.0 * v, -v, 1.0])),
+        );
+        assert_eq!(wide.mean(), f32x4::from_array([5.0, 10.0, -5.0, 1.0]));
+        assert_eq!(wide.variance()[3], 0.0);
+    }
+
+    #[test]
+    fn test_parallel_estimate() {
+        // integrate x^3 over [0, 1] in parallel batches, each with its own rng
+        let summary = (0..8u64)
+            .into_par_iter()
+            .map(|batch| {
+                let mut rng = StdRng::seed_from_u64(batch);
+                mc_estimate(10000, |_| rng.gen::<f32>().powi(3))
+            })
+            .reduce(EstimateSummary::new, |a, b| a.merge(&b));
+        assert_eq!(summary.count, 80000);
+        let (lower, upper) = summary.confidence_interval(4.0);
+        assert!(lower < 0.25 && 0.25 < upper, "{:?}", summary);
+        // the variance of x^3 for uniform x is 1/7 - 1/16
+        assert!((summary.variance() - (1.0 / 7.0 - 1.0 / 16.0)).abs() < 1e-3);
+        assert!(summary.relative_error() < 0.01);
+    }
+
+    #[test]
+    fn test_control_variate() {
+        // y bar against a single gaussian lobe, whose integral over the whole line is alpha * sqrt(pi / 2) * (sigma1 + sigma2).
+        // the bounds are more than 4 sigma away from its center, so the truncated tails are negligible.
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let f = Curve::y_bar();
+        let g = Curve::Exponential {
+            signal: vec![(560.0, 40.0, 40.0, 1.0)],
+        };
+        let g_integral = (PI / 2.0).sqrt() * 80.0;
+        let reference = f.evaluate_integral(bounds, 100000, false);
+
+        let mut sampler = RandomSampler::new();
+        let result = crate::random::scoped_seed(3, || {
+            integrate_with_control_variate(&f, &g, g_integral, bounds, 4096, &mut sampler)
+        });
+        assert_eq!(result.plain.count, 4096);
+        assert!(
+            (result.estimate - reference).abs() < 4.0 * result.standard_error,
+            "{:?} {}",
+            result,
+            reference
+        );
+        assert!(result.coefficient > 0.5 && result.coefficient < 1.5);
+        assert!(
+            result.variance_reduction() > 5.0,
+            "{}",
+            result.variance_reduction()
+        );
+
+        // a constant control variate carries no information about f
+        let flat = integrate_with_control_variate(
+            &f,
+            &Curve::Const(1.0),
+            400.0,
+            bounds,
+            256,
+            &mut sampler,
+        );
+        assert_eq!(flat.coefficient, 0.0);
+        assert_eq!(flat.estimate, flat.plain.mean());
+    }
+}
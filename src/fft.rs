@@ -0,0 +1,403 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+// small radix 2 fourier and cosine transforms over f32 slices, for frequency space analysis of
+// noise masks, spectra and filters. multidimensional data is stored row major with x varying fastest,
+// and is transformed one axis at a time.
+
+/// the largest supported transform size along any one axis
+pub const MAX_TRANSFORM_SIZE: usize = 1024;
+
+fn check_size(n: usize) -> Result<()> {
+    if n == 0 || n > MAX_TRANSFORM_SIZE || !n.is_power_of_two() {
+        return Err(MathError::UnsupportedLength(n));
+    }
+    Ok(())
+}
+
+fn check_dimensions(dimensions: &[usize], lengths: &[usize]) -> Result<()> {
+    for &n in dimensions {
+        check_size(n)?;
+    }
+    let total = dimensions.iter().product::<usize>();
+    match lengths.iter().find(|&&length| length != total) {
+        Some(&length) => Err(MathError::UnsupportedLength(length)),
+        None => Ok(()),
+    }
+}
+
+// unnormalized transform with kernel exp(sign * 2 pi i k n / N)
+fn transform(re: &mut [f32], im: &mut [f32], sign: f32) {
+    let n = re.len();
+    let bits = n.trailing_zeros();
+    if n < 2 {
+        return;
+    }
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let (cos, sin): (Vec<f32>, Vec<f32>) = (0..n / 2)
+        .map(|k| {
+            let (sin, cos) = (std::f64::consts::TAU * k as f64 / n as f64).sin_cos();
+            (cos as f32, sign * sin as f32)
+        })
+        .unzip();
+
+    let mut half = 1;
+    while half < n {
+        let stride = n / (2 * half);
+        for start in (0..n).step_by(2 * half) {
+            if half >= 4 {
+                for j in (0..half).step_by(4) {
+                    let (a, b) = (start + j, start + j + half);
+                    let w_re = f32x4::from_array([0, 1, 2, 3].map(|k| cos[(j + k) * stride]));
+                    let w_im = f32x4::from_array([0, 1, 2, 3].map(|k| sin[(j + k) * stride]));
+                    let (a_re, a_im) = (
+                        f32x4::from_slice(&re[a..a + 4]),
+                        f32x4::from_slice(&im[a..a + 4]),
+                    );
+                    let (b_re, b_im) = (
+                        f32x4::from_slice(&re[b..b + 4]),
+                        f32x4::from_slice(&im[b..b + 4]),
+                    );
+                    let t_re = b_re * w_re - b_im * w_im;
+                    let t_im = b_re * w_im + b_im * w_re;
+                    (a_re + t_re).copy_to_slice(&mut re[a..a + 4]);
+                    (a_im + t_im).copy_to_slice(&mut im[a..a + 4]);
+                    (a_re - t_re).copy_to_slice(&mut re[b..b + 4]);
+                    (a_im - t_im).copy_to_slice(&mut im[b..b + 4]);
+                }
+            } else {
+                for j in 0..half {
+                    let (a, b) = (start + j, start + j + half);
+                    let (w_re, w_im) = (cos[j * stride], sin[j * stride]);
+                    let t_re = re[b] * w_re - im[b] * w_im;
+                    let t_im = re[b] * w_im + im[b] * w_re;
+                    re[b] = re[a] - t_re;
+                    im[b] = im[a] - t_im;
+                    re[a] += t_re;
+                    im[a] += t_im;
+                }
+            }
+        }
+        half *= 2;
+    }
+}
+
+/// in place forward discrete fourier transform, unnormalized.
+/// the length must be a power of two no larger than `MAX_TRANSFORM_SIZE`
+pub fn fft(re: &mut [f32], im: &mut [f32]) -> Result<()> {
+    check_dimensions(&[re.len()], &[im.len()])?;
+    transform(re, im, -1.0);
+    Ok(())
+}
+
+/// in place inverse discrete fourier transform, normalized by 1/n such that it inverts `fft`
+pub fn ifft(re: &mut [f32], im: &mut [f32]) -> Result<()> {
+    check_dimensions(&[re.len()], &[im.len()])?;
+    transform(re, im, 1.0);
+    let scale = 1.0 / re.len() as f32;
+    re.iter_mut().chain(im.iter_mut()).for_each(|v| *v *= scale);
+    Ok(())
+}
+
+// orthonormal dct-ii, computed from a half length reordering and a full length fft (makhoul's algorithm)
+fn dct_line(values: &mut [f32]) {
+    let n = values.len();
+    let mut re = vec![0.0; n];
+    let mut im = vec![0.0; n];
+    for k in 0..n / 2 {
+        re[k] = values[2 * k];
+        re[n - 1 - k] = values[2 * k + 1];
+    }
+    if n == 1 {
+        re[0] = values[0];
+    }
+    transform(&mut re, &mut im, -1.0);
+    for k in 0..n {
+        let (sin, cos) = (-PI * k as f32 / (2 * n) as f32).sin_cos();
+        let scale = if k == 0 { 1.0 } else { 2.0f32.sqrt() } / (n as f32).sqrt();
+        values[k] = (re[k] * cos - im[k] * sin) * scale;
+    }
+}
+
+// orthonormal dct-iii, the inverse of `dct_line`
+fn idct_line(values: &mut [f32]) {
+    let n = values.len();
+    let mut re = vec![0.0; n];
+    let mut im = vec![0.0; n];
+    let unscale = |k: usize| (n as f32).sqrt() / if k == 0 { 1.0 } else { 2.0f32.sqrt() };
+    for k in 0..n {
+        let x = values[k] * unscale(k);
+        let x_mirror = if k == 0 {
+            0.0
+        } else {
+            values[n - k] * unscale(n - k)
+        };
+        // (x - i x_mirror) * exp(i pi k / 2n)
+        let (sin, cos) = (PI * k as f32 / (2 * n) as f32).sin_cos();
+        re[k] = x * cos + x_mirror * sin;
+        im[k] = x * sin - x_mirror * cos;
+    }
+    transform(&mut re, &mut im, 1.0);
+    let scale = 1.0 / n as f32;
+    for k in 0..n / 2 {
+        values[2 * k] = re[k] * scale;
+        values[2 * k + 1] = re[n - 1 - k] * scale;
+    }
+    if n == 1 {
+        values[0] = re[0];
+    }
+}
+
+/// in place orthonormal discrete cosine transform (dct-ii)
+pub fn dct(values: &mut [f32]) -> Result<()> {
+    check_size(values.len())?;
+    dct_line(values);
+    Ok(())
+}
+
+/// in place orthonormal inverse discrete cosine transform (dct-iii)
+pub fn idct(values: &mut [f32]) -> Result<()> {
+    check_size(values.len())?;
+    idct_line(values);
+    Ok(())
+}
+
+// applies `line_transform` to every line along every axis of the given arrays, which share the same dimensions
+fn transform_axes<const K: usize>(
+    mut arrays: [&mut [f32]; K],
+    dimensions: &[usize],
+    line_transform: impl Fn([&mut [f32]; K]),
+) {
+    let total = dimensions.iter().product::<usize>();
+    let mut stride = 1;
+    for &n in dimensions {
+        let mut scratch: [Vec<f32>; K] = std::array::from_fn(|_| vec![0.0; n]);
+        for line in 0..total / n {
+            let base = (line / stride) * stride * n + line % stride;
+            for (array, line) in arrays.iter().zip(scratch.iter_mut()) {
+                for (i, v) in line.iter_mut().enumerate() {
+                    *v = array[base + i * stride];
+                }
+            }
+            line_transform(scratch.each_mut().map(|line| line.as_mut_slice()));
+            for (array, line) in arrays.iter_mut().zip(scratch.iter()) {
+                for (i, v) in line.iter().enumerate() {
+                    array[base + i * stride] = *v;
+                }
+            }
+        }
+        stride *= n;
+    }
+}
+
+/// in place forward 2d fourier transform of a `width` by `height` grid, unnormalized
+pub fn fft_2d(re: &mut [f32], im: &mut [f32], width: usize, height: usize) -> Result<()> {
+    check_dimensions(&[width, height], &[re.len(), im.len()])?;
+    transform_axes([re, im], &[width, height], |[re, im]| {
+        transform(re, im, -1.0)
+    });
+    Ok(())
+}
+
+/// in place inverse 2d fourier transform, normalized such that it inverts `fft_2d`
+pub fn ifft_2d(re: &mut [f32], im: &mut [f32], width: usize, height: usize) -> Result<()> {
+    check_dimensions(&[width, height], &[re.len(), im.len()])?;
+    transform_axes([&mut *re, &mut *im], &[width, height], |[re, im]| {
+        transform(re, im, 1.0)
+    });
+    let scale = 1.0 / (width * height) as f32;
+    re.iter_mut().chain(im.iter_mut()).for_each(|v| *v *= scale);
+    Ok(())
+}
+
+/// in place forward 3d fourier transform of a `width` by `height` by `depth` volume, unnormalized
+pub fn fft_3d(
+    re: &mut [f32],
+    im: &mut [f32],
+    width: usize,
+    height: usize,
+    depth: usize,
+) -> Result<()> {
+    check_dimensions(&[width, height, depth], &[re.len(), im.len()])?;
+    transform_axes([re, im], &[width, height, depth], |[re, im]| {
+        transform(re, im, -1.0)
+    });
+    Ok(())
+}
+
+/// in place inverse 3d fourier transform, normalized such that it inverts `fft_3d`
+pub fn ifft_3d(
+    re: &mut [f32],
+    im: &mut [f32],
+    width: usize,
+    height: usize,
+    depth: usize,
+) -> Result<()> {
+    check_dimensions(&[width, height, depth], &[re.len(), im.len()])?;
+    transform_axes([&mut *re, &mut *im], &[width, height, depth], |[re, im]| {
+        transform(re, im, 1.0)
+    });
+    let scale = 1.0 / (width * height * depth) as f32;
+    re.iter_mut().chain(im.iter_mut()).for_each(|v| *v *= scale);
+    Ok(())
+}
+
+/// in place orthonormal 2d dct-ii of a `width` by `height` grid
+pub fn dct_2d(values: &mut [f32], width: usize, height: usize) -> Result<()> {
+    check_dimensions(&[width, height], &[values.len()])?;
+    transform_axes([values], &[width, height], |[line]| dct_line(line));
+    Ok(())
+}
+
+/// in place orthonormal 2d dct-iii, the inverse of `dct_2d`
+pub fn idct_2d(values: &mut [f32], width: usize, height: usize) -> Result<()> {
+    check_dimensions(&[width, height], &[values.len()])?;
+    transform_axes([values], &[width, height], |[line]| idct_line(line));
+    Ok(())
+}
+
+/// in place orthonormal 3d dct-ii of a `width` by `height` by `depth` volume
+pub fn dct_3d(values: &mut [f32], width: usize, height: usize, depth: usize) -> Result<()> {
+    check_dimensions(&[width, height, depth], &[values.len()])?;
+    transform_axes([values], &[width, height, depth], |[line]| dct_line(line));
+    Ok(())
+}
+
+/// in place orthonormal 3d dct-iii, the inverse of `dct_3d`
+pub fn idct_3d(values: &mut [f32], width: usize, height: usize, depth: usize) -> Result<()> {
+    check_dimensions(&[width, height, depth], &[values.len()])?;
+    transform_axes([values], &[width, height, depth], |[line]| idct_line(line));
+    Ok(())
+}
+
+/// the power spectrum |F|^2 / n of a real valued `width` by `height` grid, i.e. a noise mask.
+/// the dc component is at index 0, and frequencies are not shifted to the center.
+pub fn power_spectrum_2d(values: &[f32], width: usize, height: usize) -> Result<Vec<f32>> {
+    let mut re = values.to_vec();
+    let mut im = vec![0.0; values.len()];
+    fft_2d(&mut re, &mut im, width, height)?;
+    let scale = 1.0 / values.len() as f32;
+    Ok(re
+        .iter()
+        .zip(im.iter())
+        .map(|(re, im)| (re * re + im * im) * scale)
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn naive_dft(values: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let n = values.len();
+        (0..n)
+            .map(|k| {
+                values
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (j, v)| {
+                        let angle = -2.0 * PI * (j * k % n) as f32 / n as f32;
+                        (re + v * angle.cos(), im + v * angle.sin())
+                    })
+            })
+            .unzip()
+    }
+
+    fn naive_dct(values: &[f32]) -> Vec<f32> {
+        let n = values.len() as f32;
+        (0..values.len())
+            .map(|k| {
+                let scale = if k == 0 {
+                    (1.0 / n).sqrt()
+                } else {
+                    (2.0 / n).sqrt()
+                };
+                scale
+                    * values
+                        .iter()
+                        .enumerate()
+                        .map(|(j, v)| v * (PI * (2 * j + 1) as f32 * k as f32 / (2.0 * n)).cos())
+                        .sum::<f32>()
+            })
+            .collect()
+    }
+
+    fn assert_close(a: &[f32], b: &[f32], tolerance: f32) {
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!((a - b).abs() < tolerance, "{} {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_fft_and_dct() {
+        for n in [1, 2, 4, 8, 32, 256] {
+            let values = (0..n).map(|_| debug_random() - 0.5).collect::<Vec<_>>();
+            let (expected_re, expected_im) = naive_dft(&values);
+            let mut re = values.clone();
+            let mut im = vec![0.0; n];
+            fft(&mut re, &mut im).unwrap();
+            assert_close(&re, &expected_re, 1e-3);
+            assert_close(&im, &expected_im, 1e-3);
+            ifft(&mut re, &mut im).unwrap();
+            assert_close(&re, &values, 1e-5);
+
+            let mut cosine = values.clone();
+            dct(&mut cosine).unwrap();
+            assert_close(&cosine, &naive_dct(&values), 1e-3);
+            idct(&mut cosine).unwrap();
+            assert_close(&cosine, &values, 1e-5);
+        }
+
+        // the 2d transform of a separable function is the product of the 1d transforms
+        let (width, height) = (16, 8);
+        let xs = (0..width)
+            .map(|x| (x as f32 * 0.3).sin())
+            .collect::<Vec<_>>();
+        let ys = (0..height).map(|y| 1.0 + y as f32).collect::<Vec<_>>();
+        let mut grid = (0..width * height)
+            .map(|i| xs[i % width] * ys[i / width])
+            .collect::<Vec<_>>();
+        let original = grid.clone();
+        dct_2d(&mut grid, width, height).unwrap();
+        let (x_dct, y_dct) = (naive_dct(&xs), naive_dct(&ys));
+        let expected = (0..width * height)
+            .map(|i| x_dct[i % width] * y_dct[i / width])
+            .collect::<Vec<_>>();
+        assert_close(&grid, &expected, 1e-3);
+        idct_2d(&mut grid, width, height).unwrap();
+        assert_close(&grid, &original, 1e-4);
+
+        let mut re = original.clone();
+        let mut im = vec![0.0; width * height];
+        fft_2d(&mut re, &mut im, width, height).unwrap();
+        ifft_2d(&mut re, &mut im, width, height).unwrap();
+        assert_close(&re, &original, 1e-4);
+
+        let mut volume = (0..4 * 8 * 2).map(|i| i as f32).collect::<Vec<_>>();
+        let energy = volume.iter().map(|v| v * v).sum::<f32>();
+        dct_3d(&mut volume, 4, 8, 2).unwrap();
+        // orthonormal transforms preserve energy
+        assert!((volume.iter().map(|v| v * v).sum::<f32>() - energy).abs() < 1e-3 * energy);
+        idct_3d(&mut volume, 4, 8, 2).unwrap();
+        assert!((volume[37] - 37.0).abs() < 1e-3);
+
+        // the power spectrum of a constant is a single dc spike
+        let spectrum = power_spectrum_2d(&[1.0; 64], 8, 8).unwrap();
+        assert!((spectrum[0] - 64.0).abs() < 1e-4);
+        assert!(spectrum[1..].iter().all(|v| v.abs() < 1e-6));
+
+        assert_eq!(
+            fft(&mut [0.0; 12], &mut [0.0; 12]),
+            Err(MathError::UnsupportedLength(12))
+        );
+        assert!(dct(&mut vec![0.0; 2048]).is_err());
+        assert!(dct_2d(&mut [0.0; 15], 4, 4).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// firefly suppression. clamping each channel of a sample independently shifts its color towards white
+// (or towards whichever channels were below the threshold), so instead every function here computes a single scale factor
+// from the sample's luminance and applies it to all channels or lanes, which preserves chromaticity.
+
+/// maps `luminance` to at most `max`, leaving values below `knee * max` untouched and rolling off smoothly above that.
+/// a knee of 1 is a hard clamp. the roll off is continuous and has a slope of 1 at the knee, and approaches `max` asymptotically.
+pub fn roll_off(luminance: f32, max: f32, knee: f32) -> f32 {
+    debug_assert!((0.0..=1.0).contains(&knee));
+    let start = knee * max;
+    if luminance <= start {
+        return luminance;
+    }
+    let headroom = max - start;
+    if headroom <= 0.0 {
+        return max;
+    }
+    start + headroom * -(-(luminance - start) / headroom).exp_m1()
+}
+
+/// Per bounce firefly clamping parameters.
+/// the luminance threshold at bounce `n` is `max_luminance * bounce_scale^(n - 1)` for n >= 1,
+/// such that deeper bounces, where fireflies typically originate, can be clamped more aggressively.
+/// camera rays and directly visible emission (bounce 0) are only clamped if `clamp_direct` is set.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FireflyClamp {
+    pub max_luminance: f32,
+    pub knee: f32,
+    pub bounce_scale: f32,
+    pub clamp_direct: bool,
+}
+
+impl FireflyClamp {
+    /// a hard clamp at `max_luminance` for all indirect bounces
+    pub fn new(max_luminance: f32) -> Self {
+        FireflyClamp {
+            max_luminance,
+            knee: 1.0,
+            bounce_scale: 1.0,
+            clamp_direct: false,
+        }
+    }
+    pub fn with_knee(mut self, knee: f32) -> Self {
+        self.knee = knee;
+        self
+    }
+    pub fn with_bounce_scale(mut self, bounce_scale: f32) -> Self {
+        self.bounce_scale = bounce_scale;
+        self
+    }
+    pub fn with_clamp_direct(mut self, clamp_direct: bool) -> Self {
+        self.clamp_direct = clamp_direct;
+        self
+    }
+
+    pub fn threshold(&self, bounce: usize) -> f32 {
+        if bounce == 0 {
+            if self.clamp_direct {
+                self.max_luminance
+            } else {
+                f32::INFINITY
+            }
+        } else {
+            self.max_luminance * self.bounce_scale.powi(bounce as i32 - 1)
+        }
+    }
+
+    /// the factor that a sample with the given luminance should be scaled by at `bounce`
+    pub fn scale_factor(&self, luminance: f32, bounce: usize) -> f32 {
+        let threshold = self.threshold(bounce);
+        if luminance <= 0.0 || luminance <= self.knee * threshold || threshold.is_infinite() {
+            return 1.0;
+        }
+        roll_off(luminance, threshold, self.knee) / luminance
+    }
+
+    pub fn apply_xyz(&self, color: XYZColor, bounce: usize) -> XYZColor {
+        color * self.scale_factor(color.y(), bounce)
+    }
+
+    /// clamps a single wavelength sample, using the luminance of its contribution, i.e. energy * y_bar(lambda)
+    pub fn apply_single(&self, sample: SingleWavelength, bounce: usize) -> SingleWavelength {
+        let luminance = XYZColor::from(sample).y();
+        let factor = self.scale_factor(luminance, bounce);
+        sample.replace_energy(sample.energy * factor)
+    }
+
+    /// clamps a hero wavelength packet as a whole, using the combined luminance of its lanes,
+    /// such that the relative energies of the lanes are preserved
+    #[cfg(feature = "simdfloat_patch")]
+    pub fn apply_hero(&self, sample: HeroWavelength, bounce: usize) -> HeroWavelength {
+        let luminance = XYZColor::from(sample).y();
+        let factor = self.scale_factor(luminance, bounce);
+        sample.replace_energy(sample.energy * f32x4::splat(factor))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roll_off() {
+        assert_eq!(roll_off(5.0, 10.0, 0.8), 5.0);
+        assert_eq!(roll_off(15.0, 10.0, 1.0), 10.0);
+        let mut last = 0.0;
+        for i in 0..100 {
+            let rolled = roll_off(i as f32, 10.0, 0.5);
+            assert!(rolled >= last && rolled <= 10.0);
+            last = rolled;
+        }
+        // slope 1 at the knee
+        assert!((roll_off(5.001, 10.0, 0.5) - 5.001).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_firefly_clamp() {
+        let clamp = FireflyClamp::new(10.0).with_bounce_scale(0.5);
+        assert_eq!(clamp.threshold(0), f32::INFINITY);
+        assert_eq!(clamp.threshold(1), 10.0);
+        assert_eq!(clamp.threshold(3), 2.5);
+
+        let firefly = XYZColor::new(30.0, 40.0, 10.0);
+        let clamped = clamp.apply_xyz(firefly, 1);
+        assert!((clamped.y() - 10.0).abs() < 1e-5);
+        // chromaticity is preserved
+        assert!((clamped.x() / clamped.y() - 0.75).abs() < 1e-6);
+        assert!((clamped.z() / clamped.y() - 0.25).abs() < 1e-6);
+        assert_eq!(clamp.apply_xyz(firefly, 0).0, firefly.0);
+
+        let soft = clamp.with_knee(0.5);
+        let rolled = soft.apply_xyz(firefly, 1);
+        assert!(rolled.y() > 5.0 && rolled.y() < 10.0);
+
+        let sample = SingleWavelength::new(550.0, 1000.0);
+        let clamped = clamp.apply_single(sample, 2);
+        assert_eq!(clamped.lambda, 550.0);
+        assert!((XYZColor::from(clamped).y() - 5.0).abs() < 1e-4);
+    }
+
+    #[cfg(feature = "simdfloat_patch")]
+    #[test]
+    fn test_firefly_clamp_hero() {
+        let clamp = FireflyClamp::new(10.0).with_bounce_scale(0.5);
+        let hero = HeroWavelength::new(
+            f32x4::from_array([450.0, 500.0, 550.0, 600.0]),
+            f32x4::from_array([100.0, 200.0, 300.0, 400.0]),
+        );
+        let clamped = clamp.apply_hero(hero, 1);
+        assert!((XYZColor::from(clamped).y() - 10.0).abs() < 1e-3);
+        let ratio = clamped.energy / hero.energy;
+        assert!((ratio - f32x4::splat(ratio[0]))
+            .to_array()
+            .iter()
+            .all(|d| d.abs() < 1e-6));
+    }
+}
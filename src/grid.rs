@@ -0,0 +1,133 @@
+#[cfg(feature = "deepsize")]
+use deepsize::DeepSizeOf;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A dense, row major 2D array of values.
+/// cell (x, y) is considered to cover [x / width, (x + 1) / width] x [y / height, (y + 1) / height] in uv space,
+/// with its value located at the cell center.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "deepsize", derive(DeepSizeOf))]
+pub struct Grid2D<T> {
+    width: usize,
+    height: usize,
+    data: Vec<T>,
+}
+
+impl<T: Copy> Grid2D<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Self {
+        Grid2D {
+            width,
+            height,
+            data: vec![fill; width * height],
+        }
+    }
+    pub fn from_vec(width: usize, height: usize, data: Vec<T>) -> Self {
+        assert_eq!(
+            data.len(),
+            width * height,
+            "data length does not match grid dimensions"
+        );
+        Grid2D {
+            width,
+            height,
+            data,
+        }
+    }
+    pub fn from_function<F>(width: usize, height: usize, mut func: F) -> Self
+    where
+        F: FnMut(usize, usize) -> T,
+    {
+        let mut data = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(func(x, y));
+            }
+        }
+        Grid2D {
+            width,
+            height,
+            data,
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    #[inline(always)]
+    pub fn at(&self, x: usize, y: usize) -> T {
+        debug_assert!(x < self.width && y < self.height);
+        self.data[y * self.width + x]
+    }
+    #[inline(always)]
+    pub fn at_mut(&mut self, x: usize, y: usize) -> &mut T {
+        debug_assert!(x < self.width && y < self.height);
+        &mut self.data[y * self.width + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize, value: T) {
+        *self.at_mut(x, y) = value;
+    }
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+    pub fn data_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+    pub fn map<U: Copy, F: FnMut(T) -> U>(&self, func: F) -> Grid2D<U> {
+        Grid2D {
+            width: self.width,
+            height: self.height,
+            data: self.data.iter().copied().map(func).collect(),
+        }
+    }
+}
+
+impl Grid2D<f32> {
+    /// bilinearly interpolates between cell centers, with (u, v) in [0, 1]^2.
+    /// values beyond the outermost cell centers are clamped.
+    pub fn bilinear(&self, u: f32, v: f32) -> f32 {
+        let x = (u * self.width as f32 - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let bottom = (1.0 - tx) * self.at(x0, y0) + tx * self.at(x1, y0);
+        let top = (1.0 - tx) * self.at(x0, y1) + tx * self.at(x1, y1);
+        (1.0 - ty) * bottom + ty * top
+    }
+    /// returns the value of the cell containing (u, v)
+    pub fn nearest(&self, u: f32, v: f32) -> f32 {
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.at(x, y)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_grid_bilinear() {
+        let grid = Grid2D::from_function(2, 2, |x, y| (x + 2 * y) as f32);
+        // cell centers
+        assert_eq!(grid.bilinear(0.25, 0.25), 0.0);
+        assert_eq!(grid.bilinear(0.75, 0.25), 1.0);
+        assert_eq!(grid.bilinear(0.25, 0.75), 2.0);
+        // halfway between all four centers
+        assert_eq!(grid.bilinear(0.5, 0.5), 1.5);
+        // clamped outside of the outermost centers
+        assert_eq!(grid.bilinear(0.0, 0.0), 0.0);
+        assert_eq!(grid.bilinear(1.0, 1.0), 3.0);
+
+        assert_eq!(grid.nearest(0.99, 0.1), 1.0);
+        assert_eq!(grid.row(1), &[2.0, 3.0]);
+    }
+}
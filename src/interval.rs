@@ -0,0 +1,446 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use std::ops::Sub;
+
+// conservative interval arithmetic. every operation rounds its result outwards by one ulp,
+// which bounds the rounding error of the underlying f32 operation, so the true value of a computation
+// is always contained in the resulting interval. this is used to track the error of computed intersection points,
+// so that spawned rays can be offset just far enough to avoid self intersection.
+
+/// A closed interval [lower, upper] of f32 values
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Interval {
+    pub lower: f32,
+    pub upper: f32,
+}
+
+impl Interval {
+    pub fn new(a: f32, b: f32) -> Self {
+        Interval {
+            lower: a.min(b),
+            upper: a.max(b),
+        }
+    }
+    pub const fn from_value(v: f32) -> Self {
+        Interval { lower: v, upper: v }
+    }
+    /// the interval [v - error, v + error], rounded outwards
+    pub fn from_value_and_error(v: f32, error: f32) -> Self {
+        if error == 0.0 {
+            return Interval::from_value(v);
+        }
+        Interval {
+            lower: (v - error).next_down(),
+            upper: (v + error).next_up(),
+        }
+    }
+    pub const ZERO: Interval = Interval::from_value(0.0);
+    pub const EVERYTHING: Interval = Interval {
+        lower: f32::NEG_INFINITY,
+        upper: f32::INFINITY,
+    };
+
+    pub fn midpoint(&self) -> f32 {
+        (self.lower + self.upper) / 2.0
+    }
+    pub fn width(&self) -> f32 {
+        self.upper - self.lower
+    }
+    /// half of the width, i.e. the maximum error of the midpoint
+    pub fn error(&self) -> f32 {
+        self.width() / 2.0
+    }
+    pub fn is_exact(&self) -> bool {
+        self.lower == self.upper
+    }
+    pub fn contains(&self, v: f32) -> bool {
+        self.lower <= v && v <= self.upper
+    }
+    pub fn contains_zero(&self) -> bool {
+        self.contains(0.0)
+    }
+
+    pub fn abs(&self) -> Interval {
+        if self.lower >= 0.0 {
+            *self
+        } else if self.upper <= 0.0 {
+            -*self
+        } else {
+            Interval {
+                lower: 0.0,
+                upper: self.upper.max(-self.lower),
+            }
+        }
+    }
+    pub fn square(&self) -> Interval {
+        let abs = self.abs();
+        if abs.is_exact() && abs.lower == 0.0 {
+            return Interval::ZERO;
+        }
+        Interval {
+            lower: (abs.lower * abs.lower).next_down().max(0.0),
+            upper: (abs.upper * abs.upper).next_up(),
+        }
+    }
+    /// square root, with any negative part of the interval clamped to 0
+    pub fn sqrt(&self) -> Interval {
+        Interval {
+            lower: self.lower.max(0.0).sqrt().next_down().max(0.0),
+            upper: self.upper.max(0.0).sqrt().next_up(),
+        }
+    }
+}
+
+impl From<f32> for Interval {
+    fn from(v: f32) -> Self {
+        Interval::from_value(v)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+    fn neg(self) -> Self::Output {
+        Interval {
+            lower: -self.upper,
+            upper: -self.lower,
+        }
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+    fn add(self, rhs: Interval) -> Self::Output {
+        Interval {
+            lower: (self.lower + rhs.lower).next_down(),
+            upper: (self.upper + rhs.upper).next_up(),
+        }
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+    fn sub(self, rhs: Interval) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+    fn mul(self, rhs: Interval) -> Self::Output {
+        let products = [
+            self.lower * rhs.lower,
+            self.lower * rhs.upper,
+            self.upper * rhs.lower,
+            self.upper * rhs.upper,
+        ];
+        let lower = products.iter().copied().fold(f32::INFINITY, f32::min);
+        let upper = products.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        Interval {
+            lower: lower.next_down(),
+            upper: upper.next_up(),
+        }
+    }
+}
+
+impl Mul<f32> for Interval {
+    type Output = Interval;
+    fn mul(self, rhs: f32) -> Self::Output {
+        self * Interval::from_value(rhs)
+    }
+}
+
+impl Div for Interval {
+    type Output = Interval;
+    fn div(self, rhs: Interval) -> Self::Output {
+        if rhs.contains_zero() {
+            // the quotient is unbounded
+            return Interval::EVERYTHING;
+        }
+        let quotients = [
+            self.lower / rhs.lower,
+            self.lower / rhs.upper,
+            self.upper / rhs.lower,
+            self.upper / rhs.upper,
+        ];
+        let lower = quotients.iter().copied().fold(f32::INFINITY, f32::min);
+        let upper = quotients.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        Interval {
+            lower: lower.next_down(),
+            upper: upper.next_up(),
+        }
+    }
+}
+
+impl Div<f32> for Interval {
+    type Output = Interval;
+    fn div(self, rhs: f32) -> Self::Output {
+        self / Interval::from_value(rhs)
+    }
+}
+
+/// A vector whose components are intervals
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vec3Interval {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Vec3Interval {
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Vec3Interval { x, y, z }
+    }
+    pub fn from_value_and_error(v: Vec3, error: Vec3) -> Self {
+        Vec3Interval {
+            x: Interval::from_value_and_error(v.x(), error.x()),
+            y: Interval::from_value_and_error(v.y(), error.y()),
+            z: Interval::from_value_and_error(v.z(), error.z()),
+        }
+    }
+    pub fn midpoint(&self) -> Vec3 {
+        Vec3::new(self.x.midpoint(), self.y.midpoint(), self.z.midpoint())
+    }
+    /// the maximum error of the midpoint in each dimension
+    pub fn error(&self) -> Vec3 {
+        Vec3::new(self.x.error(), self.y.error(), self.z.error())
+    }
+    pub fn contains(&self, v: Vec3) -> bool {
+        self.x.contains(v.x()) && self.y.contains(v.y()) && self.z.contains(v.z())
+    }
+    pub fn dot(&self, other: Vec3Interval) -> Interval {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    pub fn norm_squared(&self) -> Interval {
+        self.x.square() + self.y.square() + self.z.square()
+    }
+}
+
+impl From<Vec3> for Vec3Interval {
+    fn from(v: Vec3) -> Self {
+        Vec3Interval::new(v.x().into(), v.y().into(), v.z().into())
+    }
+}
+
+impl Add for Vec3Interval {
+    type Output = Vec3Interval;
+    fn add(self, rhs: Vec3Interval) -> Self::Output {
+        Vec3Interval::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Vec3Interval {
+    type Output = Vec3Interval;
+    fn sub(self, rhs: Vec3Interval) -> Self::Output {
+        Vec3Interval::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Neg for Vec3Interval {
+    type Output = Vec3Interval;
+    fn neg(self) -> Self::Output {
+        Vec3Interval::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<Interval> for Vec3Interval {
+    type Output = Vec3Interval;
+    fn mul(self, rhs: Interval) -> Self::Output {
+        Vec3Interval::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Mul<f32> for Vec3Interval {
+    type Output = Vec3Interval;
+    fn mul(self, rhs: f32) -> Self::Output {
+        self * Interval::from_value(rhs)
+    }
+}
+
+/// A point whose coordinates are intervals, such as a computed intersection point along with its error bounds
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Point3Interval {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Point3Interval {
+    pub const fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Point3Interval { x, y, z }
+    }
+    pub fn from_value_and_error(p: Point3, error: Vec3) -> Self {
+        Point3Interval {
+            x: Interval::from_value_and_error(p.x(), error.x()),
+            y: Interval::from_value_and_error(p.y(), error.y()),
+            z: Interval::from_value_and_error(p.z(), error.z()),
+        }
+    }
+    pub fn midpoint(&self) -> Point3 {
+        Point3::new(self.x.midpoint(), self.y.midpoint(), self.z.midpoint())
+    }
+    /// the maximum error of the midpoint in each dimension
+    pub fn error(&self) -> Vec3 {
+        Vec3::new(self.x.error(), self.y.error(), self.z.error())
+    }
+    pub fn contains(&self, p: Point3) -> bool {
+        self.x.contains(p.x()) && self.y.contains(p.y()) && self.z.contains(p.z())
+    }
+
+    /// computes a ray origin for a ray leaving the surface at this point in `direction`, given the surface normal.
+    /// the origin is offset along the normal just far enough to leave the error bounds of the point,
+    /// on the same side of the surface as `direction`, such that the ray can not reintersect the surface it left.
+    pub fn offset_ray_origin(&self, normal: Vec3, direction: Vec3) -> Point3 {
+        let error = self.error();
+        let distance = normal.x().abs() * error.x()
+            + normal.y().abs() * error.y()
+            + normal.z().abs() * error.z();
+        let mut offset = normal * distance;
+        if direction * normal < 0.0 {
+            offset = -offset;
+        }
+        let origin = self.midpoint() + offset;
+        // round away from the midpoint, so that the offset survives the rounding of the addition
+        let round = |v: f32, o: f32| {
+            if o > 0.0 {
+                v.next_up()
+            } else if o < 0.0 {
+                v.next_down()
+            } else {
+                v
+            }
+        };
+        Point3::new(
+            round(origin.x(), offset.x()),
+            round(origin.y(), offset.y()),
+            round(origin.z(), offset.z()),
+        )
+    }
+}
+
+impl From<Point3> for Point3Interval {
+    fn from(p: Point3) -> Self {
+        Point3Interval::new(p.x().into(), p.y().into(), p.z().into())
+    }
+}
+
+impl Add<Vec3Interval> for Point3Interval {
+    type Output = Point3Interval;
+    fn add(self, rhs: Vec3Interval) -> Self::Output {
+        Point3Interval::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub for Point3Interval {
+    type Output = Vec3Interval;
+    fn sub(self, rhs: Point3Interval) -> Self::Output {
+        Vec3Interval::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+// transforming intervals goes through the same matrix layout as transforming points and vectors,
+// such that `Transform3::to_world` and `Transform3::to_local` apply to them as well.
+fn transform_intervals(matrix: Matrix4x4, v: [Interval; 4]) -> [Interval; 4] {
+    let m = nalgebra::Matrix4::from(matrix);
+    let mut result = [Interval::ZERO; 4];
+    for (i, component) in result.iter_mut().enumerate() {
+        *component = v
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value != Interval::ZERO)
+            .fold(Interval::ZERO, |sum, (j, value)| sum + *value * m[(i, j)]);
+    }
+    result
+}
+
+impl Mul<Vec3Interval> for Matrix4x4 {
+    type Output = Vec3Interval;
+    fn mul(self, rhs: Vec3Interval) -> Self::Output {
+        let [x, y, z, _] = transform_intervals(self, [rhs.x, rhs.y, rhs.z, Interval::ZERO]);
+        Vec3Interval::new(x, y, z)
+    }
+}
+
+impl Mul<Point3Interval> for Matrix4x4 {
+    type Output = Point3Interval;
+    fn mul(self, rhs: Point3Interval) -> Self::Output {
+        let [x, y, z, w] =
+            transform_intervals(self, [rhs.x, rhs.y, rhs.z, Interval::from_value(1.0)]);
+        if w == Interval::from_value(1.0) {
+            Point3Interval::new(x, y, z)
+        } else {
+            Point3Interval::new(x / w, y / w, z / w)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interval_ops() {
+        let a = Interval::new(1.0, 2.0);
+        let b = Interval::new(-3.0, 0.5);
+        assert!((a + b).contains(-2.0) && (a + b).contains(2.5));
+        assert!((a - b).contains(0.5) && (a - b).contains(5.0) && !(a - b).contains(0.0));
+        let product = a * b;
+        assert!(product.contains(-6.0) && product.contains(1.0) && !product.contains(1.1));
+        assert_eq!(a / b, Interval::EVERYTHING);
+        assert!((b / a).contains(-3.0) && (b / a).contains(0.5));
+        assert!(b.square().contains(9.0) && b.square().contains(0.0) && b.square().lower >= 0.0);
+        assert!(Interval::from_value(2.0).sqrt().contains(2.0f32.sqrt()));
+
+        // 0.1 + 0.2 is inexact, and the exact sum is contained in the result
+        let sum = Interval::from_value(0.1) + Interval::from_value(0.2);
+        let exact = 0.1f32 as f64 + 0.2f32 as f64;
+        assert!(sum.lower as f64 <= exact && exact <= sum.upper as f64);
+    }
+
+    #[test]
+    fn test_interval_transform() {
+        let transform = Transform3::from_stack(
+            Some(Transform3::from_scale(Vec3::new(1.3, 0.7, 2.1))),
+            Some(Transform3::from_axis_angle(
+                Vec3::new(1.0, 1.0, 0.3).normalized(),
+                0.7,
+            )),
+            Some(Transform3::from_translation(Vec3::new(10.0, -3.0, 0.1))),
+        );
+        let p = Point3::new(0.3, -1.7, 2.9);
+        let interval = transform.to_world(Point3Interval::from(p));
+        let transformed = transform.to_world(p);
+        assert!(interval.contains(transformed));
+        assert!(interval.error().norm() < 1e-4);
+
+        // compute the exact result in f64, which must be contained in the interval
+        let m = nalgebra::Matrix4::from(transform.forward).cast::<f64>();
+        let exact = m * nalgebra::Vector4::new(p.x() as f64, p.y() as f64, p.z() as f64, 1.0);
+        assert!(interval.x.lower as f64 <= exact.x && exact.x <= interval.x.upper as f64);
+        assert!(interval.y.lower as f64 <= exact.y && exact.y <= interval.y.upper as f64);
+        assert!(interval.z.lower as f64 <= exact.z && exact.z <= interval.z.upper as f64);
+
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert!(transform
+            .to_world(Vec3Interval::from(v))
+            .contains(transform.to_world(v)));
+    }
+
+    #[test]
+    fn test_offset_ray_origin() {
+        // a point on the plane z = 0, with some error in its computation
+        let hit = Point3Interval::from_value_and_error(
+            Point3::new(1.0, 2.0, 0.0),
+            Vec3::new(1e-6, 1e-6, 1e-5),
+        );
+        let origin = hit.offset_ray_origin(Vec3::Z, Vec3::new(0.3, 0.0, 1.0));
+        assert!(origin.z() > hit.z.upper);
+        let origin = hit.offset_ray_origin(Vec3::Z, Vec3::new(0.3, 0.0, -1.0));
+        assert!(origin.z() < hit.z.lower);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::prelude::*;
+
+// the numerics of irradiance caching (Ward et al. 1988, Ward and Heckbert 1992), following the notation of
+// Krivanek et al., "Practical Global Illumination with Irradiance Caching" (2009).
+// irradiance is gathered at sparse records from a stratified, cosine weighted hemisphere,
+// along with its gradients wrt translating and rotating the record, which are then used to extrapolate
+// each record to nearby points before blending them.
+
+/// A cached irradiance sample, with gradients in world space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IrradianceRecord {
+    pub position: Point3,
+    pub normal: Vec3,
+    pub irradiance: f32,
+    /// the harmonic mean distance to the surfaces seen from the record, which sets how far the record is valid.
+    /// infinite if nothing was hit.
+    pub radius: f32,
+    /// the change in irradiance per unit translation of the record
+    pub translation_gradient: Vec3,
+    /// the change in irradiance per radian of rotation of the normal, about the axis given by the gradient's direction
+    pub rotation_gradient: Vec3,
+}
+
+impl IrradianceRecord {
+    /// gathers irradiance at `position` from `m` x `n` hemisphere strata, with `m` divisions in theta and `n` in phi,
+    /// placed such that each stratum covers the same projected solid angle.
+    /// `trace` is given a world space direction, and returns the incident radiance along it
+    /// and the distance to the surface it came from, which may be infinite.
+    pub fn gather(
+        position: Point3,
+        normal: Vec3,
+        m: usize,
+        n: usize,
+        sampler: &mut impl Sampler,
+        mut trace: impl FnMut(Vec3) -> (f32, f32),
+    ) -> Self {
+        let frame = TangentFrame::from_normal(normal);
+        let mut radiance = vec![0.0f32; m * n];
+        let mut distance = vec![0.0f32; m * n];
+        let mut rotation = Vec3::ZERO;
+        let mut inverse_distance_sum = 0.0;
+        for j in 0..m {
+            for k in 0..n {
+                let s = sampler.draw_2d();
+                // inverting the cdf of the projected solid angle, sin^2 theta is uniform
+                let sin_theta = ((j as f32 + s.x) / m as f32).sqrt();
+                let cos_theta = (1.0 - sin_theta * sin_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * (k as f32 + s.y) / n as f32;
+                let (sin_phi, cos_phi) = phi.sin_cos();
+                let local = Vec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta);
+                let (l, r) = trace(frame.to_world(&local));
+                radiance[j * n + k] = l;
+                distance[j * n + k] = r;
+                inverse_distance_sum += 1.0 / r;
+                // the rotation gradient sums tan(theta) L along the direction perpendicular to phi in the base plane,
+                // with the sign such that rotating the normal by omega changes the irradiance by omega . gradient
+                rotation = rotation
+                    + Vec3::new(-sin_phi, cos_phi, 0.0) * (sin_theta / cos_theta.max(1e-6) * l);
+            }
+        }
+        let samples = (m * n) as f32;
+        let irradiance = PI / samples * radiance.iter().sum::<f32>();
+        let rotation = rotation * (PI / samples);
+
+        // the translation gradient estimates how the boundaries between strata move as the record does,
+        // with the change in solid angle of each boundary scaled by the difference in radiance across it,
+        // and by the distance to the nearer of the two surfaces
+        let mut translation = Vec3::ZERO;
+        for k in 0..n {
+            let phi_center = 2.0 * PI * (k as f32 + 0.5) / n as f32;
+            let phi_boundary = 2.0 * PI * k as f32 / n as f32;
+            let u = Vec3::new(phi_center.cos(), phi_center.sin(), 0.0);
+            let v = Vec3::new(-phi_boundary.sin(), phi_boundary.cos(), 0.0);
+            let previous_k = (k + n - 1) % n;
+            let mut radial = 0.0;
+            let mut azimuthal = 0.0;
+            for j in 0..m {
+                let sin_lower = (j as f32 / m as f32).sqrt();
+                let sin_upper = ((j + 1) as f32 / m as f32).sqrt();
+                let here = j * n + k;
+                if j > 0 {
+                    // the boundary between rings j - 1 and j
+                    let below = (j - 1) * n + k;
+                    let cos_squared = 1.0 - sin_lower * sin_lower;
+                    radial += sin_lower * cos_squared / distance[here].min(distance[below])
+                        * (radiance[here] - radiance[below]);
+                }
+                // the boundary between wedges k - 1 and k
+                let beside = j * n + previous_k;
+                azimuthal += (sin_upper - sin_lower) / distance[here].min(distance[beside])
+                    * (radiance[here] - radiance[beside]);
+            }
+            translation = translation + u * (2.0 * PI / n as f32 * radial) + v * azimuthal;
+        }
+
+        IrradianceRecord {
+            position,
+            normal: frame.normal,
+            irradiance,
+            radius: samples / inverse_distance_sum,
+            translation_gradient: frame.to_world(&translation),
+            rotation_gradient: frame.to_world(&rotation),
+        }
+    }
+
+    /// clamps the radius to [`min`, `max`], after limiting it such that extrapolating with the translation gradient
+    /// can't change the irradiance by more than itself within the radius
+    pub fn clamp_radius(mut self, min: f32, max: f32) -> Self {
+        let gradient = self.translation_gradient.norm();
+        if gradient > 0.0 {
+            self.radius = self.radius.min(self.irradiance / gradient);
+        }
+        self.radius = self.radius.clamp(min, max);
+        self
+    }
+
+    /// the weight of the record at `position` with `normal`, w = 1 / (|p - p_i| / R_i + sqrt(1 - n . n_i)),
+    /// or 0 if the point is in front of the record, where the record can't have seen the occluders that affect it
+    pub fn weight(&self, position: Point3, normal: Vec3) -> f32 {
+        let offset = position - self.position;
+        // Ward's test for points in front of the record, along the average of the two normals
+        if offset * ((normal + self.normal) * 0.5) < -1e-3 * self.radius {
+            return 0.0;
+        }
+        let angular = (1.0 - (normal * self.normal).min(1.0)).sqrt();
+        let error = offset.norm() / self.radius + angular;
+        if error == 0.0 {
+            f32::INFINITY
+        } else {
+            1.0 / error
+        }
+    }
+
+    /// the irradiance extrapolated to `position` with `normal` using the gradients,
+    /// E_i + (n_i x n) . rotation_gradient + (p - p_i) . translation_gradient
+    pub fn extrapolate(&self, position: Point3, normal: Vec3) -> f32 {
+        self.irradiance
+            + self.normal.cross(normal) * self.rotation_gradient
+            + (position - self.position) * self.translation_gradient
+    }
+}
+
+/// blends the extrapolated irradiance of the records whose weight at `position` exceeds 1 / `accuracy`,
+/// returning None if there are none, in which case a new record should be gathered.
+/// typical values for `accuracy` are between 0.1 and 0.3.
+pub fn interpolate_irradiance<'a>(
+    records: impl IntoIterator<Item = &'a IrradianceRecord>,
+    position: Point3,
+    normal: Vec3,
+    accuracy: f32,
+) -> Option<f32> {
+    let (mut sum, mut weight_sum) = (0.0, 0.0);
+    for record in records {
+        let weight = record.weight(position, normal);
+        if weight <= 1.0 / accuracy {
+            continue;
+        }
+        if weight.is_infinite() {
+            return Some(record.irradiance);
+        }
+        sum += weight * record.extrapolate(position, normal).max(0.0);
+        weight_sum += weight;
+    }
+    (weight_sum > 0.0).then(|| sum / weight_sum)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // a spherical lambertian emitter of unit radiance and radius `radius` at `center`, in an otherwise black scene
+    fn trace_sphere(origin: Point3, direction: Vec3, center: Point3, radius: f32) -> (f32, f32) {
+        let to_center = center - origin;
+        let along = to_center * direction;
+        let discriminant = along * along - (to_center.norm_squared() - radius * radius);
+        if along <= 0.0 || discriminant < 0.0 {
+            return (0.0, f32::INFINITY);
+        }
+        (1.0, along - discriminant.sqrt())
+    }
+
+    // the exact irradiance from the sphere, pi (r / d)^2 cos(alpha), while it is entirely above the horizon
+    fn sphere_irradiance(position: Point3, normal: Vec3, center: Point3, radius: f32) -> f32 {
+        let to_center = center - position;
+        PI * radius * radius / to_center.norm_squared() * (to_center.normalized() * normal)
+    }
+
+    #[test]
+    fn test_irradiance_gradients() {
+        let mut sampler = RandomSampler::new();
+        let (center, radius) = (Point3::new(1.0, 0.5, 4.0), 1.0);
+        let position = Point3::ORIGIN;
+        let normal = Vec3::Z;
+        let record = crate::random::scoped_seed(7, || {
+            IrradianceRecord::gather(position, normal, 64, 128, &mut sampler, |w| {
+                trace_sphere(position, w, center, radius)
+            })
+        });
+        let exact = sphere_irradiance(position, normal, center, radius);
+        assert!((record.irradiance - exact).abs() < 2e-2 * exact);
+
+        // the gradients agree with finite differences of the exact irradiance
+        let h = 1e-2;
+        for axis in [Vec3::X, Vec3::Y] {
+            let moved = sphere_irradiance(position + axis * h, normal, center, radius);
+            let expected = (moved - exact) / h;
+            let estimated = record.translation_gradient * axis;
+            assert!(
+                (estimated - expected).abs() < 0.1 * exact,
+                "{:?} {} {}",
+                axis,
+                estimated,
+                expected
+            );
+
+            // rotating the normal by h about the axis
+            let rotated = (normal + axis.cross(normal) * h).normalized();
+            let expected = (sphere_irradiance(position, rotated, center, radius) - exact) / h;
+            let estimated = record.rotation_gradient * axis;
+            assert!(
+                (estimated - expected).abs() < 0.1 * exact,
+                "{:?} {} {}",
+                axis,
+                estimated,
+                expected
+            );
+        }
+
+        // extrapolation improves on the record's own value nearby, and interpolation uses it
+        let nearby = Point3::new(0.3, -0.2, 0.0);
+        let tilted = Vec3::new(0.1, 0.05, 1.0).normalized();
+        let exact_nearby = sphere_irradiance(nearby, tilted, center, radius);
+        let extrapolated = record.extrapolate(nearby, tilted);
+        assert!((extrapolated - exact_nearby).abs() < (record.irradiance - exact_nearby).abs());
+        let record = record.clamp_radius(0.1, 10.0);
+        assert!(record.radius <= 10.0 && record.radius >= 0.1);
+        let interpolated = interpolate_irradiance([&record], nearby, tilted, 0.5).unwrap();
+        assert!((interpolated - extrapolated).abs() < 1e-6);
+        assert!(
+            interpolate_irradiance([&record], Point3::new(50.0, 0.0, 0.0), normal, 0.5).is_none()
+        );
+        // points in front of the record are rejected
+        assert_eq!(record.weight(Point3::new(0.0, 0.0, -1.0), normal), 0.0);
+    }
+}
@@ -4,30 +4,62 @@
 pub mod prelude;
 pub mod traits;
 
+pub mod albedo;
+pub mod anisotropic;
+pub mod aperture;
 pub mod bounds;
 pub mod color;
+pub mod complex;
 pub mod curves;
+pub mod distribution;
+pub mod dual;
+pub mod error;
+pub mod estimate;
+pub mod fft;
+pub mod firefly;
+pub mod grid;
+pub mod interval;
+pub mod irradiance_cache;
+pub mod mis;
 pub mod misc;
+pub mod numeric;
 pub mod pdf;
+pub mod photographic;
+pub mod photometric;
 pub mod point;
+pub mod polarization;
 pub mod random;
 pub mod ray;
 pub mod sample;
+pub mod sdf;
+pub mod simd_util;
+pub mod sky;
+pub mod solid_angle;
 pub mod spectral;
+pub mod spectral_noise;
+pub mod spectrum;
+pub mod sphere_map;
 pub mod tangent_frame;
+pub mod tolerance;
 pub mod transform;
 pub mod vec;
 
+pub use error::{MathError, Result};
+
 use std::fmt::Debug;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+/// Which sides of a surface are visible, relative to its geometric normal. see `tangent_frame::resolve_normal`.
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum Sidedness {
+    /// only the side the normal points to
     Forward,
+    /// only the side opposite the normal
     Reverse,
+    /// both sides
     Dual,
 }
 
@@ -0,0 +1,187 @@
+use crate::prelude::*;
+
+// multiple importance sampling weights, for hero wavelength sampling where each lane may have been drawn
+// from a different technique.
+//
+// with n techniques that each produce one sample, the balance heuristic weight for the sample x_i from technique i is
+//     w_i(x_i) = p_i(x_i) / sum_j p_j(x_i)
+// which makes the combined estimate sum_i w_i(x_i) f(x_i) / p_i(x_i) = sum_i f(x_i) / sum_j p_j(x_i).
+//
+// stochastic MIS (SMIS, West et al. 2022) uses the exact same weights when the techniques themselves are drawn at random,
+// i.e. when each lane picks its own random parameter for a parametric family of techniques.
+// continuous MIS (CMIS, West et al. 2020) replaces the sum over techniques with an integral over the technique parameter,
+// which `continuous_balance_pdf` estimates through quadrature.
+
+pub fn balance_heuristic(pdf: f32, other_pdfs: &[f32]) -> f32 {
+    let sum = pdf + other_pdfs.iter().sum::<f32>();
+    if sum > 0.0 {
+        pdf / sum
+    } else {
+        0.0
+    }
+}
+
+pub fn power_heuristic(pdf: f32, other_pdfs: &[f32]) -> f32 {
+    let sum = pdf * pdf + other_pdfs.iter().map(|p| p * p).sum::<f32>();
+    if sum > 0.0 {
+        pdf * pdf / sum
+    } else {
+        0.0
+    }
+}
+
+/// evaluates sum_j p_j(lambda_i) for each lane i, where lane j of `lambdas` was drawn from `techniques[j]`
+/// and `pdf(lambda, technique)` evaluates the density of a technique at lambda.
+/// dividing each lane's contribution by this combined pdf and summing the lanes yields the balance heuristic estimate.
+pub fn hero_combined_pdf<T, F>(lambdas: f32x4, techniques: &[T; 4], pdf: F) -> f32x4
+where
+    F: Fn(f32, &T) -> f32,
+{
+    let lambdas = lambdas.to_array();
+    let mut combined = [0.0; 4];
+    for (i, lambda) in lambdas.iter().enumerate() {
+        combined[i] = techniques.iter().map(|t| pdf(*lambda, t)).sum();
+    }
+    f32x4::from_array(combined)
+}
+
+/// balance heuristic weights for each lane, where lane j of `lambdas` was drawn from `techniques[j]`.
+/// for stochastic MIS, `techniques` holds the randomly drawn technique parameters of each lane.
+pub fn hero_balance_weights<T, F>(lambdas: f32x4, techniques: &[T; 4], pdf: F) -> f32x4
+where
+    F: Fn(f32, &T) -> f32,
+{
+    let array = lambdas.to_array();
+    let own = f32x4::from_array([
+        pdf(array[0], &techniques[0]),
+        pdf(array[1], &techniques[1]),
+        pdf(array[2], &techniques[2]),
+        pdf(array[3], &techniques[3]),
+    ]);
+    let combined = hero_combined_pdf(lambdas, techniques, pdf);
+    combined
+        .simd_gt(f32x4::splat(0.0))
+        .select(own / combined, f32x4::splat(0.0))
+}
+
+/// combines the per lane contributions `f` into a single balance heuristic estimate,
+/// given the combined pdfs from `hero_combined_pdf`.
+pub fn hero_mis_estimate(f: f32x4, combined_pdf: f32x4) -> f32 {
+    combined_pdf
+        .simd_gt(f32x4::splat(0.0))
+        .select(f / combined_pdf, f32x4::splat(0.0))
+        .reduce_sum()
+}
+
+/// the continuous MIS combined pdf, integral of p(x | t) p(t) dt, for a technique parameter t uniformly distributed over
+/// `parameter_bounds`, estimated with `samples` midpoint quadrature points.
+/// the CMIS estimate for a sample x drawn with any parameter is then f(x) / continuous_balance_pdf(x).
+pub fn continuous_balance_pdf<F>(x: f32, parameter_bounds: Bounds1D, samples: usize, pdf: F) -> f32
+where
+    F: Fn(f32, f32) -> f32,
+{
+    (0..samples)
+        .map(|i| pdf(x, parameter_bounds.lerp((i as f32 + 0.5) / samples as f32)))
+        .sum::<f32>()
+        / samples as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[derive(Clone)]
+    enum Technique {
+        Uniform,
+        Peaked(Distribution1D),
+    }
+
+    fn technique_pdf(lambda: f32, technique: &Technique) -> f32 {
+        match technique {
+            Technique::Uniform => 1.0 / BOUNDED_VISIBLE_RANGE.span(),
+            Technique::Peaked(distribution) => *distribution.pdf(lambda),
+        }
+    }
+
+    fn technique_sample(technique: &Technique, x: f32) -> f32 {
+        match technique {
+            Technique::Uniform => BOUNDED_VISIBLE_RANGE.sample(x),
+            Technique::Peaked(distribution) => distribution.sample(Sample1D::new(x)).0,
+        }
+    }
+
+    fn variance(techniques: &[Technique; 4], f: impl Fn(f32) -> f32, expected: f32) -> f32 {
+        let mut rng = StdRng::seed_from_u64(0);
+        let n = 20000;
+        let mut sum_sq = 0.0;
+        for _ in 0..n {
+            let lambdas = f32x4::from_array(
+                [0, 1, 2, 3].map(|i| technique_sample(&techniques[i], rng.gen_range(0.0..1.0f32))),
+            );
+            let combined = hero_combined_pdf(lambdas, techniques, technique_pdf);
+            let estimate = hero_mis_estimate(lambdas.to_array().map(&f).into(), combined);
+            sum_sq += (estimate - expected).powi(2);
+        }
+        sum_sq / n as f32
+    }
+
+    #[test]
+    fn test_hero_mis_variance() {
+        // a constant floor plus a narrow peak, which neither technique handles well on its own
+        let f = |lambda: f32| 1.0 + 20.0 * (-((lambda - 550.0) / 10.0).powi(2)).exp();
+        let expected = BOUNDED_VISIBLE_RANGE.span() + 20.0 * 10.0 * PI.sqrt();
+
+        let peak: Vec<f32> = (0..400)
+            .map(|i| {
+                let lambda = BOUNDED_VISIBLE_RANGE.lerp((i as f32 + 0.5) / 400.0);
+                1e-3 + (-((lambda - 550.0) / 15.0).powi(2)).exp()
+            })
+            .collect();
+        let peaked = Technique::Peaked(Distribution1D::new(&peak, BOUNDED_VISIBLE_RANGE));
+
+        let uniform_only = [0; 4].map(|_| Technique::Uniform);
+        let peaked_only = [0; 4].map(|_| peaked.clone());
+        let mixed = [
+            Technique::Uniform,
+            Technique::Uniform,
+            peaked.clone(),
+            peaked.clone(),
+        ];
+
+        let uniform_variance = variance(&uniform_only, f, expected);
+        let peaked_variance = variance(&peaked_only, f, expected);
+        let mis_variance = variance(&mixed, f, expected);
+        assert!(
+            mis_variance < uniform_variance && mis_variance < peaked_variance,
+            "{} {} {}",
+            mis_variance,
+            uniform_variance,
+            peaked_variance
+        );
+    }
+
+    #[test]
+    fn test_weights() {
+        let lambdas = f32x4::from_array([400.0, 500.0, 600.0, 700.0]);
+        let techniques = [0.0, 0.0, 1.0, 1.0];
+        // technique 1 is twice as likely everywhere, so weights are 1/6 and 2/6
+        let weights = hero_balance_weights(lambdas, &techniques, |_, t| 1.0 + t);
+        assert_eq!(
+            weights.to_array(),
+            [1.0 / 6.0, 1.0 / 6.0, 2.0 / 6.0, 2.0 / 6.0]
+        );
+        assert!((weights.reduce_sum() - 1.0).abs() < 1e-6);
+
+        assert_eq!(balance_heuristic(1.0, &[3.0]), 0.25);
+        assert_eq!(power_heuristic(1.0, &[3.0]), 0.1);
+
+        // uniform densities shifted by the technique parameter, with the union of supports being [0, 2]
+        let pdf = |x: f32, t: f32| if (t..t + 1.0).contains(&x) { 1.0 } else { 0.0 };
+        let combined = continuous_balance_pdf(0.5, Bounds1D::new(0.0, 1.0), 1000, pdf);
+        assert!((combined - 0.5).abs() < 1e-2);
+    }
+}
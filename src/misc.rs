@@ -29,25 +29,157 @@ pub fn gaussian_f32x4(x: f32x4, alpha: f32, mu: f32, sigma1: f32, sigma2: f32) -
 
 }
 
+// the sigma used on either side of mu is the same for all of the above: sigma1 strictly below mu, and sigma2 at and above it.
+// thus the antiderivative below is continuous at mu, as erf(0) = 0 on both sides.
+
+// erfc following the chebyshev fit of numerical recipes, t exp(-z^2 + P(t)) with t = 1 / (1 + z / 2) for z = |x|,
+// which has a fractional error below 1.2e-7 everywhere. coefficients of P are in order of decreasing degree.
+const ERFC_COEFFICIENTS: [f64; 10] = [
+    0.17087277,
+    -0.82215223,
+    1.48851587,
+    -1.13520398,
+    0.27886807,
+    -0.18628806,
+    0.09678418,
+    0.37409196,
+    1.00002368,
+    -1.26551223,
+];
+// the taylor series of erf(x) sqrt(pi) / (2 x) in x^2, in order of decreasing degree.
+// it's used below `ERF_TAYLOR_THRESHOLD`, as 1 - erfc cancels for small x
+const ERF_TAYLOR_COEFFICIENTS: [f64; 6] = [
+    -1.0 / 1320.0,
+    1.0 / 216.0,
+    -1.0 / 42.0,
+    0.1,
+    -1.0 / 3.0,
+    1.0,
+];
+const ERF_TAYLOR_THRESHOLD: f64 = 0.5;
+
+fn erfc_f64(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = ERFC_COEFFICIENTS.iter().fold(0.0, |acc, c| c + t * acc);
+    let result = t * (-z * z + poly).exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+fn erf_f64(x: f64) -> f64 {
+    if x.abs() < ERF_TAYLOR_THRESHOLD {
+        let series = ERF_TAYLOR_COEFFICIENTS
+            .iter()
+            .fold(0.0, |acc, c| c + x * x * acc);
+        std::f64::consts::FRAC_2_SQRT_PI * x * series
+    } else {
+        1.0 - erfc_f64(x)
+    }
+}
+
+/// the error function, 2/sqrt(pi) times the integral of exp(-t^2) from 0 to x
+pub fn erf(x: f32) -> f32 {
+    erf_f64(x as f64) as f32
+}
+
+/// the complementary error function, 1 - erf(x), which retains its relative precision for large x
+pub fn erfc(x: f32) -> f32 {
+    erfc_f64(x as f64) as f32
+}
+
+#[cfg(feature="simdfloat_patch")]
+pub fn erfc_f32x4(x: f32x4) -> f32x4 {
+    let z = SimdFloat::abs(x);
+    let t = f32x4::splat(1.0) / (f32x4::splat(1.0) + f32x4::splat(0.5) * z);
+    let poly = ERFC_COEFFICIENTS.iter().fold(f32x4::splat(0.0), |acc, c| {
+        f32x4::splat(*c as f32) + t * acc
+    });
+    let result = t * (-z * z + poly).exp();
+    x.simd_lt(f32x4::splat(0.0))
+        .select(f32x4::splat(2.0) - result, result)
+}
+
+#[cfg(feature="simdfloat_patch")]
+pub fn erf_f32x4(x: f32x4) -> f32x4 {
+    let x2 = x * x;
+    let series = ERF_TAYLOR_COEFFICIENTS
+        .iter()
+        .fold(f32x4::splat(0.0), |acc, c| {
+            f32x4::splat(*c as f32) + x2 * acc
+        });
+    let taylor = f32x4::splat(std::f32::consts::FRAC_2_SQRT_PI) * x * series;
+    SimdFloat::abs(x)
+        .simd_lt(f32x4::splat(ERF_TAYLOR_THRESHOLD as f32))
+        .select(taylor, f32x4::splat(1.0) - erfc_f32x4(x))
+}
+
+// the antiderivative of `gaussian`, which is 0 at mu
+fn gaussian_antiderivative(x: f64, alpha: f64, mu: f64, sigma1: f64, sigma2: f64) -> f64 {
+    let sigma = if x < mu { sigma1 } else { sigma2 };
+    alpha
+        * sigma
+        * (std::f64::consts::PI / 2.0).sqrt()
+        * erf_f64((x - mu) / (sigma * std::f64::consts::SQRT_2))
+}
+
+/// the exact integral of `gaussianf32(x, alpha, mu, sigma1, sigma2)` from `a` to `b`
+pub fn gaussian_integral(a: f32, b: f32, alpha: f32, mu: f32, sigma1: f32, sigma2: f32) -> f32 {
+    let [alpha, mu, sigma1, sigma2] = [alpha, mu, sigma1, sigma2].map(|v| v as f64);
+    (gaussian_antiderivative(b as f64, alpha, mu, sigma1, sigma2)
+        - gaussian_antiderivative(a as f64, alpha, mu, sigma1, sigma2)) as f32
+}
+
 pub fn w(x: f32, mul: f32, offset: f32, sigma: f32) -> f32 {
     mul * (-(x - offset).powi(2) / sigma).exp() / (sigma * PI).sqrt()
 }
 
-const HCC2: f32 = 1.1910429723971884140794892e-29;
-const HKC: f32 = 1.438777085924334052222404423195819240925e-2;
+// the radiation constants, with lengths expressed in nanometers rather than meters so that
+// intermediate values such as lambda^-5 stay within the range of f32 from 1nm up to 1m.
+const HCC2_NM: f32 = 1.191043e16;
+const HKC_NM: f32 = 1.4387771e7;
+// beyond this, exp(x) overflows f32, and planck's law is indistinguishable from wien's approximation
+const WIEN_THRESHOLD: f32 = 80.0;
+// below this, exp(x) - 1 is evaluated with a taylor series to avoid cancellation
+const TAYLOR_THRESHOLD: f32 = 1e-2;
 
+/// spectral radiance of a blackbody at `temperature` kelvin and `lambda` nanometers.
+/// returns 0 for nonpositive temperatures or wavelengths, and remains finite for extreme values of lambda * temperature.
 pub fn blackbody(temperature: f32, lambda: f32) -> f32 {
-    let lambda = lambda * 1e-9;
-
-    lambda.powi(-5) * HCC2 / ((HKC / (lambda * temperature)).exp() - 1.0)
+    if !(temperature > 0.0 && lambda > 0.0) {
+        return 0.0;
+    }
+    let x = HKC_NM / (lambda * temperature);
+    let scale = HCC2_NM * lambda.powi(-5);
+    if x > WIEN_THRESHOLD {
+        scale * (-x).exp()
+    } else {
+        // exp_m1 retains precision in the rayleigh-jeans regime where x is small
+        scale / x.exp_m1()
+    }
 }
 
 #[cfg(feature="simdfloat_patch")]
 pub fn blackbody_f32x4(temperature: f32, lambda: f32x4) -> f32x4 {
-    let lambda = lambda * f32x4::splat(1e-9);
+    let valid = lambda.simd_gt(f32x4::splat(0.0))
+        & f32x4::splat(temperature).simd_gt(f32x4::splat(0.0));
+    let safe_lambda = valid.select(lambda, f32x4::splat(1.0));
+    let x = f32x4::splat(HKC_NM) / (safe_lambda * f32x4::splat(temperature.max(f32::MIN_POSITIVE)));
+    let inv_lambda = f32x4::splat(1.0) / safe_lambda;
+    let inv_lambda2 = inv_lambda * inv_lambda;
+    let scale = f32x4::splat(HCC2_NM) * inv_lambda2 * inv_lambda2 * inv_lambda;
 
-    lambda.powf(f32x4::splat(-5.0)) * f32x4::splat(HCC2)
-        / ((f32x4::splat(HKC) / (lambda * f32x4::splat(temperature))).exp() - f32x4::splat(1.0))
+    let taylor = x * (f32x4::splat(1.0) + x * (f32x4::splat(0.5) + x / f32x4::splat(6.0)));
+    let expm1 = x
+        .simd_lt(f32x4::splat(TAYLOR_THRESHOLD))
+        .select(taylor, x.simd_min(f32x4::splat(WIEN_THRESHOLD)).exp() - f32x4::splat(1.0));
+    let planck = scale / expm1;
+    let wien = scale * (-x).exp();
+    let result = x.simd_gt(f32x4::splat(WIEN_THRESHOLD)).select(wien, planck);
+    valid.select(result, f32x4::splat(0.0))
 }
 
 pub fn max_blackbody_lambda(temp: f32) -> f32 {
@@ -81,6 +213,149 @@ pub fn direction_to_uv(direction: Vec3) -> (f32, f32) {
 mod test {
     use super::*;
 
+    fn reference_blackbody(temperature: f64, lambda: f64) -> f64 {
+        let lambda = lambda * 1e-9;
+        let hcc2 = 1.1910429723971884e-29;
+        let hkc = 1.438777085924334e-2;
+        hcc2 * lambda.powi(-5) / (hkc / (lambda * temperature)).exp_m1()
+    }
+
+    #[test]
+    fn test_blackbody_extremes() {
+        let temperatures = [1.0, 10.0, 300.0, 1000.0, 5778.0, 10000.0, 100000.0];
+        let lambdas = [100.0, 380.0, 550.0, 1000.0, 10000.0, 100000.0];
+        for &temperature in &temperatures {
+            for &lambda in &lambdas {
+                let value = blackbody(temperature, lambda);
+                assert!(
+                    value.is_finite() && value >= 0.0,
+                    "{} {} {}",
+                    temperature,
+                    lambda,
+                    value
+                );
+                let reference = reference_blackbody(temperature as f64, lambda as f64);
+                if reference > f32::MIN_POSITIVE as f64 * 1e3 {
+                    let relative_error = ((value as f64 - reference) / reference).abs();
+                    assert!(
+                        relative_error < 1e-4,
+                        "{} {} {} {}",
+                        temperature,
+                        lambda,
+                        value,
+                        reference
+                    );
+                }
+            }
+            #[cfg(feature="simdfloat_patch")]
+            {
+                let lambdas = [100.0, 550.0, 10000.0, 100000.0];
+                let simd = blackbody_f32x4(temperature, f32x4::from_array(lambdas));
+                for (i, lambda) in lambdas.iter().enumerate() {
+                    let scalar = blackbody(temperature, *lambda);
+                    assert!(simd[i].is_finite());
+                    assert!(
+                        (simd[i] - scalar).abs() <= 1e-4 * scalar,
+                        "{} {} {}",
+                        temperature,
+                        lambda,
+                        simd[i]
+                    );
+                }
+            }
+        }
+        assert_eq!(blackbody(0.0, 550.0), 0.0);
+        assert_eq!(blackbody(5000.0, -1.0), 0.0);
+        #[cfg(feature="simdfloat_patch")]
+        assert_eq!(blackbody_f32x4(0.0, f32x4::splat(550.0)), f32x4::splat(0.0));
+    }
+
+    #[test]
+    fn test_erf() {
+        // reference values from Abramowitz and Stegun
+        for (x, reference) in [
+            (0.01, 0.01128342),
+            (0.3, 0.3286268),
+            (0.5, 0.5204999),
+            (1.0, 0.8427008),
+            (2.0, 0.9953223),
+        ] {
+            assert!((erf(x) - reference).abs() < 2e-7, "{} {}", x, erf(x));
+            assert!((erf(-x) + reference).abs() < 2e-7);
+            assert!((erfc(x) - (1.0 - reference)).abs() < 2e-7);
+        }
+        // erfc keeps its relative precision in the tail
+        assert!((erfc(3.0) / 2.2090497e-5 - 1.0).abs() < 1e-6);
+        assert!((erfc(-3.0) - (2.0 - 2.2090497e-5)).abs() < 1e-6);
+
+        #[cfg(feature="simdfloat_patch")]
+        {
+            let xs = [-2.5, -0.2, 0.4, 1.7];
+            let (simd_erf, simd_erfc) = (
+                erf_f32x4(f32x4::from_array(xs)),
+                erfc_f32x4(f32x4::from_array(xs)),
+            );
+            for (i, x) in xs.iter().enumerate() {
+                assert!((simd_erf[i] - erf(*x)).abs() < 1e-6);
+                assert!((simd_erfc[i] - erfc(*x)).abs() < 1e-6 * erfc(*x).max(1e-2));
+            }
+        }
+
+        // both sides of an asymmetric gaussian use their own sigma, including at the peak, in all versions
+        let lambdas = [520.0, 550.0, 580.0, 610.0];
+        for lambda in lambdas.iter() {
+            let scalar = gaussianf32(*lambda, 2.0, 550.0, 10.0, 30.0);
+            assert!(
+                (gaussian(*lambda as f64, 2.0, 550.0, 10.0, 30.0) as f32 - scalar).abs() < 1e-6
+            );
+        }
+        #[cfg(feature="simdfloat_patch")]
+        {
+            let simd = gaussian_f32x4(f32x4::from_array(lambdas), 2.0, 550.0, 10.0, 30.0);
+            for (i, lambda) in lambdas.iter().enumerate() {
+                assert!((simd[i] - gaussianf32(*lambda, 2.0, 550.0, 10.0, 30.0)).abs() < 1e-6);
+            }
+        }
+        assert_eq!(gaussianf32(520.0, 1.0, 550.0, 10.0, 30.0), (-4.5f32).exp());
+        assert_eq!(gaussianf32(550.0, 1.0, 550.0, 10.0, 30.0), 1.0);
+
+        // the integral across the peak matches a fine trapezoidal sum, and the whole curve has the area of its two halves
+        let n = 100000;
+        let (a, b) = (500.0, 640.0);
+        let trapezoid = (0..n)
+            .map(|i| {
+                let x0 = a + (b - a) * i as f64 / n as f64;
+                let x1 = a + (b - a) * (i + 1) as f64 / n as f64;
+                0.5 * (x1 - x0)
+                    * (gaussian(x0, 2.0, 550.0, 10.0, 30.0) + gaussian(x1, 2.0, 550.0, 10.0, 30.0))
+            })
+            .sum::<f64>();
+        let exact = gaussian_integral(a as f32, b as f32, 2.0, 550.0, 10.0, 30.0);
+        assert!(
+            (exact as f64 - trapezoid).abs() < 1e-5 * trapezoid,
+            "{} {}",
+            exact,
+            trapezoid
+        );
+        let total = gaussian_integral(0.0, 1000.0, 2.0, 550.0, 10.0, 30.0);
+        assert!((total - 2.0 * (PI / 2.0).sqrt() * 40.0).abs() < 1e-4);
+
+        // exponential curves integrate exactly, and their cdfs are exact at the edges
+        let curve = Curve::Exponential {
+            signal: vec![(450.0, 8.0, 12.0, 1.0), (580.0, 30.0, 50.0, 0.5)],
+        };
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let expected = gaussian_integral(380.0, 780.0, 1.0, 450.0, 8.0, 12.0)
+            + gaussian_integral(380.0, 780.0, 0.5, 580.0, 30.0, 50.0);
+        assert_eq!(curve.evaluate_integral(bounds, 4, false), expected);
+        let cdf = curve.to_cdf(bounds, 4).unwrap();
+        assert_eq!(cdf.pdf_integral, expected);
+        let half = (gaussian_integral(380.0, 580.0, 1.0, 450.0, 8.0, 12.0)
+            + gaussian_integral(380.0, 580.0, 0.5, 580.0, 30.0, 50.0))
+            / expected;
+        assert!((cdf.cdf.evaluate(580.0) - half).abs() < 1e-6);
+    }
+
     #[test]
     fn test_direction_to_uv() {
         let direction = random_on_unit_sphere(Sample2D::new_random_sample());
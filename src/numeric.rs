@@ -0,0 +1,131 @@
+use crate::error::Result;
+use crate::prelude::*;
+
+// small dense linear algebra, for fitting routines and for users who don't want nalgebra in their own dependency tree.
+// matrices are given as row major slices of f32, and computations are done in f64.
+
+/// solves the linear least squares problem min |a x - b|^2 for x, where `a` is a `rows` x `cols` matrix in row major order
+/// and `b` has `rows` entries. uses householder QR, which avoids squaring the condition number like the normal equations do.
+/// returns `UnsupportedLength` if the slices don't match the dimensions, `UnsupportedShape` if there are fewer rows
+/// than columns, and `SingularMatrix` if the columns of `a` are (numerically) linearly dependent.
+pub fn lstsq(a: &[f32], rows: usize, cols: usize, b: &[f32]) -> Result<Vec<f32>> {
+    let a: Vec<f64> = a.iter().map(|&v| v as f64).collect();
+    let b: Vec<f64> = b.iter().map(|&v| v as f64).collect();
+    let x = lstsq_f64(&a, rows, cols, &b)?;
+    Ok(x.into_iter().map(|v| v as f32).collect())
+}
+
+/// `lstsq` for inputs that are already in f64, i.e. jacobians of fits that shouldn't be rounded to f32
+pub fn lstsq_f64(a: &[f64], rows: usize, cols: usize, b: &[f64]) -> Result<Vec<f64>> {
+    if a.len() != rows * cols {
+        return Err(MathError::UnsupportedLength(a.len()));
+    }
+    if b.len() != rows {
+        return Err(MathError::UnsupportedLength(b.len()));
+    }
+    if cols == 0 || rows < cols {
+        return Err(MathError::UnsupportedShape(rows, cols));
+    }
+    let mut r = a.to_vec();
+    let mut qtb = b.to_vec();
+    let scale = r.iter().fold(0.0f64, |acc, v| acc.max(v.abs()));
+
+    // reduce `a` to upper triangular form with householder reflections, applying them to `b` as well
+    for k in 0..cols {
+        let norm = (k..rows)
+            .map(|i| r[i * cols + k].powi(2))
+            .sum::<f64>()
+            .sqrt();
+        if norm <= f64::EPSILON * scale * rows as f64 {
+            return Err(MathError::SingularMatrix);
+        }
+        // reflect column k onto -sign(a_kk) * norm * e_k, the choice of sign avoids cancellation
+        let alpha = if r[k * cols + k] > 0.0 { -norm } else { norm };
+        let mut v: Vec<f64> = (k..rows).map(|i| r[i * cols + k]).collect();
+        v[0] -= alpha;
+        let v_norm_squared: f64 = v.iter().map(|x| x * x).sum();
+        let reflect = |column: &mut [f64]| {
+            let dot: f64 = v.iter().zip(column.iter()).map(|(vi, ci)| vi * ci).sum();
+            let factor = 2.0 * dot / v_norm_squared;
+            for (ci, vi) in column.iter_mut().zip(&v) {
+                *ci -= factor * vi;
+            }
+        };
+        for j in k..cols {
+            let mut column: Vec<f64> = (k..rows).map(|i| r[i * cols + j]).collect();
+            reflect(&mut column);
+            for (i, value) in (k..rows).zip(column) {
+                r[i * cols + j] = value;
+            }
+        }
+        reflect(&mut qtb[k..]);
+    }
+
+    // back substitution with the upper triangle
+    let mut x = vec![0.0f64; cols];
+    for k in (0..cols).rev() {
+        let sum: f64 = (k + 1..cols).map(|j| r[k * cols + j] * x[j]).sum();
+        x[k] = (qtb[k] - sum) / r[k * cols + k];
+    }
+    Ok(x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lstsq() {
+        // a square system is solved exactly
+        let a = [2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0];
+        let x = lstsq(&a, 3, 3, &[8.0, -11.0, -3.0]).unwrap();
+        for (x, expected) in x.iter().zip([2.0, 3.0, -1.0]) {
+            assert!((x - expected).abs() < 1e-5, "{:?}", x);
+        }
+        let a64: Vec<f64> = a.iter().map(|&v| v as f64).collect();
+        let x = lstsq_f64(&a64, 3, 3, &[8.0, -11.0, -3.0]).unwrap();
+        for (x, expected) in x.iter().zip([2.0, 3.0, -1.0]) {
+            assert!((x - expected).abs() < 1e-12, "{:?}", x);
+        }
+
+        // fitting a line to noisy points matches the closed form regression
+        let points = [(0.0, 1.1), (1.0, 2.9), (2.0, 5.2), (3.0, 6.8), (4.0, 9.1)];
+        let a: Vec<f32> = points.iter().flat_map(|&(x, _)| [1.0, x]).collect();
+        let b: Vec<f32> = points.iter().map(|&(_, y)| y).collect();
+        let fit = lstsq(&a, points.len(), 2, &b).unwrap();
+        let n = points.len() as f32;
+        let (sx, sy) = points
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+        let sxy: f32 = points.iter().map(|&(x, y)| x * y).sum();
+        let sxx: f32 = points.iter().map(|&(x, _)| x * x).sum();
+        let slope = (n * sxy - sx * sy) / (n * sxx - sx * sx);
+        let intercept = (sy - slope * sx) / n;
+        assert!((fit[1] - slope).abs() < 1e-5 && (fit[0] - intercept).abs() < 1e-5);
+
+        // an ill conditioned polynomial fit still recovers the coefficients, where the normal equations would lose them
+        let xs: Vec<f32> = (0..50).map(|i| 1.0 + i as f32 / 49.0).collect();
+        let a: Vec<f32> = xs
+            .iter()
+            .flat_map(|&x| [1.0, x, x * x, x * x * x])
+            .collect();
+        let b: Vec<f32> = xs
+            .iter()
+            .map(|&x| 0.5 - 2.0 * x + 0.25 * x * x + x * x * x)
+            .collect();
+        let coefficients = lstsq(&a, xs.len(), 4, &b).unwrap();
+        for (c, expected) in coefficients.iter().zip([0.5, -2.0, 0.25, 1.0]) {
+            assert!((c - expected).abs() < 1e-2, "{:?}", coefficients);
+        }
+
+        assert_eq!(
+            lstsq(&[1.0, 2.0, 2.0, 4.0], 2, 2, &[1.0, 2.0]),
+            Err(MathError::SingularMatrix)
+        );
+        assert_eq!(
+            lstsq(&[1.0, 2.0], 1, 2, &[1.0]),
+            Err(MathError::UnsupportedShape(1, 2))
+        );
+        assert!(lstsq(&[1.0, 2.0, 3.0], 2, 2, &[1.0, 2.0]).is_err());
+    }
+}
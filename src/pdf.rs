@@ -5,6 +5,86 @@ use std::{
 
 use crate::prelude::*;
 
+#[cfg(feature = "validate_pdfs")]
+use std::{cmp::Ordering, panic::Location, sync::RwLock};
+
+// pdf validation, enabled with the `validate_pdfs` feature.
+// every pdf construction and measure conversion is checked, and violations are reported with the call site
+// that created the pdf, which is propagated through the conversions with #[track_caller].
+// zero pdfs are legitimate, i.e. for directions that can't be sampled, so they're only reported and never asserted on.
+
+#[cfg(feature = "validate_pdfs")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PdfViolationKind {
+    NaN,
+    Infinite,
+    Negative,
+    Zero,
+    /// a Uniform01 pdf was evaluated at a point outside of [0, 1]
+    OutOfSupport,
+}
+
+#[cfg(feature = "validate_pdfs")]
+#[derive(Clone, Debug)]
+pub struct PdfViolation {
+    pub kind: PdfViolationKind,
+    /// the debug formatted pdf value
+    pub value: String,
+    pub measure: &'static str,
+    pub location: &'static Location<'static>,
+}
+
+#[cfg(feature = "validate_pdfs")]
+static VALIDATION_HOOK: RwLock<Option<fn(&PdfViolation)>> = RwLock::new(None);
+
+/// sets a function to be called for every pdf that fails validation, i.e. to log violations in release builds.
+#[cfg(feature = "validate_pdfs")]
+pub fn set_pdf_validation_hook(hook: Option<fn(&PdfViolation)>) {
+    *VALIDATION_HOOK.write().unwrap() = hook;
+}
+
+#[cfg(feature = "validate_pdfs")]
+fn report<T: Field, M: Measure>(
+    kind: PdfViolationKind,
+    v: &T,
+    location: &'static Location<'static>,
+) {
+    if let Some(hook) = *VALIDATION_HOOK.read().unwrap() {
+        hook(&PdfViolation {
+            kind,
+            value: format!("{:?}", v),
+            measure: std::any::type_name::<M>(),
+            location,
+        });
+    }
+    debug_assert!(
+        kind == PdfViolationKind::Zero,
+        "invalid pdf {:?} ({:?}, {}) created at {}",
+        v,
+        kind,
+        std::any::type_name::<M>(),
+        location
+    );
+}
+
+#[cfg(feature = "validate_pdfs")]
+#[track_caller]
+fn validate<T: Field, M: Measure>(v: &T) {
+    let kind = if v.check_nan() != CheckResult::None {
+        PdfViolationKind::NaN
+    } else if v.check_inf() != CheckResult::None {
+        PdfViolationKind::Infinite
+    } else {
+        // for simd types, this is only Greater or Equal if every lane is nonnegative
+        match v.partial_cmp(&T::ZERO) {
+            Some(Ordering::Greater) => return,
+            Some(Ordering::Equal) => PdfViolationKind::Zero,
+            _ => PdfViolationKind::Negative,
+        }
+    };
+    report::<T, M>(kind, v, Location::caller());
+}
+
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
 pub struct PDF<T: Field, M: Measure> {
     v: T,
@@ -14,17 +94,47 @@ pub struct PDF<T: Field, M: Measure> {
 }
 
 impl<T: Field, M: Measure> PDF<T, M> {
+    #[track_caller]
     pub fn new(v: T) -> Self {
+        #[cfg(feature = "validate_pdfs")]
+        validate::<T, M>(&v);
         Self {
             v,
             measure: M::default(),
         }
     }
+    #[track_caller]
     pub fn new_with_measure(v: T, m: M) -> Self {
+        #[cfg(feature = "validate_pdfs")]
+        validate::<T, M>(&v);
         Self { v, measure: m }
     }
 }
 
+impl<T: Field> PDF<T, Uniform01> {
+    /// with the `validate_pdfs` feature, checks that the point `x` this pdf was evaluated at is within [0, 1].
+    /// otherwise this is a no-op.
+    #[track_caller]
+    #[allow(unused_variables)]
+    pub fn check_support(self, x: T) -> Self {
+        #[cfg(feature = "validate_pdfs")]
+        {
+            let above_zero = matches!(
+                x.partial_cmp(&T::ZERO),
+                Some(Ordering::Greater | Ordering::Equal)
+            );
+            let below_one = matches!(
+                x.partial_cmp(&T::ONE),
+                Some(Ordering::Less | Ordering::Equal)
+            );
+            if !(above_zero && below_one) {
+                report::<T, Uniform01>(PdfViolationKind::OutOfSupport, &x, Location::caller());
+            }
+        }
+        self
+    }
+}
+
 // deref, to make things easier. don't need to access pdf.0 anymore, just do *pdf
 impl<T: Field, M: Measure> Deref for PDF<T, M> {
     type Target = T;
@@ -43,6 +153,7 @@ impl<T: Field, M: Measure> DerefMut for PDF<T, M> {
 
 // impl From (and Into) when Measure can be inferred
 impl<T: Field, M: Measure> From<T> for PDF<T, M> {
+    #[track_caller]
     fn from(v: T) -> Self {
         Self::new(v)
     }
@@ -77,6 +188,7 @@ where
 {
     type Output = Self;
 
+    #[track_caller]
     fn mul(self, rhs: S) -> Self::Output {
         PDF::new(self.v * T::from_scalar(rhs))
     }
@@ -87,6 +199,7 @@ where
 {
     type Output = Self;
 
+    #[track_caller]
     fn div(self, rhs: S) -> Self::Output {
         PDF::new(self.v / T::from_scalar(rhs))
     }
@@ -94,6 +207,7 @@ where
 
 // special conversions
 impl<T: Field> PDF<T, SolidAngle> {
+    #[track_caller]
     pub fn convert_to_projected_solid_angle<S: Scalar>(
         &self,
         cos_theta: S,
@@ -106,6 +220,7 @@ impl<T: Field> PDF<T, SolidAngle> {
 }
 
 impl<T: Field> PDF<T, Area> {
+    #[track_caller]
     pub fn convert_to_solid_angle<S: Scalar>(
         &self,
         cos_theta: S,
@@ -119,6 +234,7 @@ impl<T: Field> PDF<T, Area> {
 }
 
 impl<T: Field> PDF<T, Area> {
+    #[track_caller]
     pub fn convert_to_projected_solid_angle<S: Scalar>(
         &self,
         cos_i: S,
@@ -134,6 +250,70 @@ impl<T: Field> PDF<T, Area> {
     }
 }
 
+// half vector jacobians, for converting pdfs of sampled microfacet normals to pdfs of scattered directions.
+// all directions point away from the surface, and `eta` is the ratio eta_i / eta_o of the index of refraction
+// on the side of `wi` to the index on the side of `wo`.
+
+/// the half vector of a reflection, oriented towards the side of `wo`
+pub fn half_vector_reflection(wo: Vec3, wi: Vec3) -> Vec3 {
+    (wo + wi).normalized()
+}
+
+/// the generalized half vector of a refraction, oriented towards the side of `wo`.
+/// wh = -(wo + eta * wi) / |wo + eta * wi|, up to the sign that puts it on the side of `wo`
+pub fn half_vector_refraction(wo: Vec3, wi: Vec3, eta: f32) -> Vec3 {
+    let wh = (wo + wi * eta).normalized();
+    if wh * wo < 0.0 {
+        -wh
+    } else {
+        wh
+    }
+}
+
+/// |dwh / dwi| for reflection, 1 / (4 |wo . wh|)
+pub fn reflection_jacobian(wo: Vec3, wh: Vec3) -> f32 {
+    1.0 / (4.0 * (wo * wh).abs())
+}
+
+/// |dwh / dwi| for refraction, eta^2 |wi . wh| / (wo . wh + eta * wi . wh)^2
+pub fn refraction_jacobian(wo: Vec3, wi: Vec3, wh: Vec3, eta: f32) -> f32 {
+    let (cos_o, cos_i) = (wo * wh, wi * wh);
+    let denominator = cos_o + eta * cos_i;
+    eta * eta * cos_i.abs() / (denominator * denominator)
+}
+
+impl<T: Field> PDF<T, HalfVector> {
+    /// the pdf of the direction wi reflected about the sampled half vector wh, given wo . wh
+    #[track_caller]
+    pub fn convert_reflection_to_solid_angle<S: Scalar>(&self, wo_dot_wh: S) -> PDF<T, SolidAngle>
+    where
+        T: FromScalar<S>,
+    {
+        let four = T::from_scalar(S::ONE + S::ONE + S::ONE + S::ONE);
+        PDF::new(self.v / (four * T::from_scalar(wo_dot_wh).abs()))
+    }
+
+    /// the pdf of the direction wi refracted through the sampled half vector wh, given wo . wh, wi . wh and eta = eta_i / eta_o
+    #[track_caller]
+    pub fn convert_refraction_to_solid_angle<S: Scalar>(
+        &self,
+        wo_dot_wh: S,
+        wi_dot_wh: S,
+        eta: S,
+    ) -> PDF<T, SolidAngle>
+    where
+        T: FromScalar<S>,
+    {
+        let (cos_o, cos_i, eta) = (
+            T::from_scalar(wo_dot_wh),
+            T::from_scalar(wi_dot_wh),
+            T::from_scalar(eta),
+        );
+        let denominator = cos_o + eta * cos_i;
+        PDF::new(self.v * eta * eta * cos_i.abs() / (denominator * denominator))
+    }
+}
+
 // impl<T> PDF<T, ProjectedSolidAngle> where T: Field {}
 impl<T: Field> PDF<T, ProjectedSolidAngle> {
     fn convert_to_throughput(self, area_pdf: PDF<T, Area>) -> PDF<T, Throughput> {
@@ -176,6 +356,102 @@ mod test {
         assert!(*projected_solid_angle0 == *projected_solid_angle1);
     }
     #[test]
+    fn test_half_vector_jacobians() {
+        // compares the jacobians to the ratio of the solid angles spanned by small perturbations of wi and of wh
+        let finite_difference = |wi: Vec3, half_vector: &dyn Fn(Vec3) -> Vec3| {
+            let frame = TangentFrame::from_normal(wi);
+            let h = 1e-3;
+            let perturbed = |t: Vec3| (wi + t * h).normalized();
+            let (di1, di2) = (
+                perturbed(frame.tangent) - wi,
+                perturbed(frame.bitangent) - wi,
+            );
+            let wh = half_vector(wi);
+            let (dh1, dh2) = (
+                half_vector(perturbed(frame.tangent)) - wh,
+                half_vector(perturbed(frame.bitangent)) - wh,
+            );
+            dh1.cross(dh2).norm() / di1.cross(di2).norm()
+        };
+
+        let wo = Vec3::new(0.3, -0.2, 0.9).normalized();
+        let wi = Vec3::new(-0.5, 0.4, 0.6).normalized();
+        let wh = half_vector_reflection(wo, wi);
+        let jacobian = reflection_jacobian(wo, wh);
+        let expected = finite_difference(wi, &|wi| half_vector_reflection(wo, wi));
+        assert!(
+            (jacobian - expected).abs() < 1e-2 * expected,
+            "{} {}",
+            jacobian,
+            expected
+        );
+
+        let eta = 1.5;
+        let wi = Vec3::new(-0.2, 0.3, -0.9).normalized();
+        let wh = half_vector_refraction(wo, wi, eta);
+        assert!(wh * wo > 0.0 && wh * wi < 0.0);
+        let jacobian = refraction_jacobian(wo, wi, wh, eta);
+        let expected = finite_difference(wi, &|wi| half_vector_refraction(wo, wi, eta));
+        assert!(
+            (jacobian - expected).abs() < 1e-2 * expected,
+            "{} {}",
+            jacobian,
+            expected
+        );
+
+        // the pdf conversions apply the same factors
+        let pdf: PDF<f32, HalfVector> = PDF::new(2.0);
+        let refracted = pdf.convert_refraction_to_solid_angle(wo * wh, wi * wh, eta);
+        assert!((*refracted - 2.0 * jacobian).abs() < 1e-6);
+        let reflected = pdf.convert_reflection_to_solid_angle(0.5);
+        assert_eq!(*reflected, 1.0);
+        let pdf: PDF<f32x4, HalfVector> = PDF::new(f32x4::splat(2.0));
+        let reflected = pdf.convert_reflection_to_solid_angle(0.5);
+        assert_eq!(*reflected, f32x4::splat(1.0));
+    }
+
+    #[cfg(feature = "validate_pdfs")]
+    #[test]
+    fn test_pdf_validation() {
+        use std::sync::Mutex;
+        static REPORTS: Mutex<Vec<PdfViolation>> = Mutex::new(Vec::new());
+        set_pdf_validation_hook(Some(|violation| {
+            REPORTS.lock().unwrap().push(violation.clone())
+        }));
+        // only count reports from this file, as other tests may run concurrently
+        let reports_here = |kind| {
+            REPORTS
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| r.kind == kind && r.location.file().ends_with("pdf.rs"))
+                .count()
+        };
+
+        let valid: PDF<f32, Area> = PDF::new(2.0);
+        let _ = valid.convert_to_solid_angle(0.5, 4.0);
+        let _ = PDF::<f32x4, SolidAngle>::new(f32x4::from_array([0.0, 1.0, 2.0, 3.0]));
+        assert_eq!(reports_here(PdfViolationKind::Zero), 0);
+
+        // zero pdfs are reported but not asserted on
+        let _ = valid.convert_to_solid_angle(0.0, 4.0);
+        assert_eq!(reports_here(PdfViolationKind::Zero), 1);
+
+        let nan = std::panic::catch_unwind(|| PDF::<f32, SolidAngle>::new(f32::NAN));
+        let negative = std::panic::catch_unwind(|| {
+            PDF::<f32x4, Area>::new(f32x4::from_array([1.0, -1.0, 1.0, 1.0]))
+        });
+        let outside =
+            std::panic::catch_unwind(|| PDF::<f32, Uniform01>::new(1.0).check_support(1.5));
+        if cfg!(debug_assertions) {
+            assert!(nan.is_err() && negative.is_err() && outside.is_err());
+        }
+        assert_eq!(reports_here(PdfViolationKind::NaN), 1);
+        assert_eq!(reports_here(PdfViolationKind::Negative), 1);
+        assert_eq!(reports_here(PdfViolationKind::OutOfSupport), 1);
+        set_pdf_validation_hook(None);
+    }
+    #[test]
     fn test_solid_angle_pdf() {}
     #[test]
     fn test_projected_solid_angle_pdf() {}
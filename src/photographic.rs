@@ -0,0 +1,135 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// photographic exposure, for matching a simulated camera to the physical units of the spectral code.
+// spectral radiance in W / (m^2 sr nm) integrated against the CIE 1931 color matching functions gives XYZ in W / (m^2 sr),
+// and luminance in cd / m^2 is Y scaled by the maximum luminous efficacy.
+// exposure values follow the usual convention of being relative to ISO 100, i.e. EV100.
+
+/// the maximum luminous efficacy of radiation in lm / W, which converts Y from radiometric to photometric units
+pub const MAX_LUMINOUS_EFFICACY: f32 = 683.0;
+/// the reflected light meter calibration constant K in cd s / m^2, as used by most camera manufacturers (ISO 2720)
+pub const METER_CALIBRATION: f32 = 12.5;
+
+/// the exposure value at ISO 100 of a camera with f-number `aperture`, `shutter` time in seconds and sensitivity `iso`
+pub fn ev100(aperture: f32, shutter: f32, iso: f32) -> f32 {
+    (aperture * aperture / shutter * 100.0 / iso).log2()
+}
+
+/// the exposure value at ISO 100 that a reflected light meter suggests for a scene with average luminance `luminance`
+pub fn ev100_from_luminance(luminance: f32) -> f32 {
+    (luminance * 100.0 / METER_CALIBRATION).log2()
+}
+
+/// the average scene luminance in cd / m^2 that a reflected light meter would expose at `ev100`
+pub fn luminance_from_ev100(ev100: f32) -> f32 {
+    METER_CALIBRATION / 100.0 * ev100.exp2()
+}
+
+/// the number of stops between two f-numbers, positive when `to` lets in less light than `from`
+pub fn stops_between_apertures(from: f32, to: f32) -> f32 {
+    2.0 * (to / from).log2()
+}
+
+/// the luminance in cd / m^2 that saturates the sensor at `ev100`, from the saturation based sensitivity
+/// L_max = 78 / (q S) * N^2 / t, with the lens and vignetting attenuation q = 0.65
+pub fn saturation_luminance(ev100: f32) -> f32 {
+    78.0 / (0.65 * 100.0) * ev100.exp2()
+}
+
+/// the factor that scales luminance in cd / m^2 to sensor values, such that the saturation luminance maps to 1
+pub fn luminance_exposure_scale(ev100: f32) -> f32 {
+    1.0 / saturation_luminance(ev100)
+}
+
+/// the factor that scales XYZ or spectral radiance from radiometric units to sensor values at `ev100`.
+/// unlike `luminance_exposure_scale`, this includes the luminous efficacy, so it can be applied directly
+/// to the output of the color matching functions.
+pub fn radiance_exposure_scale(ev100: f32) -> f32 {
+    MAX_LUMINOUS_EFFICACY * luminance_exposure_scale(ev100)
+}
+
+/// Physical camera settings: the f-number, shutter time in seconds and ISO sensitivity.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraExposure {
+    pub aperture: f32,
+    pub shutter: f32,
+    pub iso: f32,
+}
+
+impl CameraExposure {
+    pub fn new(aperture: f32, shutter: f32, iso: f32) -> Self {
+        CameraExposure {
+            aperture,
+            shutter,
+            iso,
+        }
+    }
+
+    /// the settings that expose at `ev100` with the given aperture and iso, solving for the shutter time
+    pub fn from_ev100(ev100: f32, aperture: f32, iso: f32) -> Self {
+        let shutter = aperture * aperture * 100.0 / (iso * ev100.exp2());
+        CameraExposure::new(aperture, shutter, iso)
+    }
+
+    /// the settings a reflected light meter suggests for a scene with average luminance `luminance` in cd / m^2
+    pub fn metered(luminance: f32, aperture: f32, iso: f32) -> Self {
+        CameraExposure::from_ev100(ev100_from_luminance(luminance), aperture, iso)
+    }
+
+    pub fn ev100(&self) -> f32 {
+        ev100(self.aperture, self.shutter, self.iso)
+    }
+
+    /// the factor to apply to luminance in cd / m^2 for this exposure
+    pub fn luminance_scale(&self) -> f32 {
+        luminance_exposure_scale(self.ev100())
+    }
+
+    /// the factor to apply to XYZ or spectral radiance for this exposure, see `radiance_exposure_scale`
+    pub fn radiance_scale(&self) -> f32 {
+        radiance_exposure_scale(self.ev100())
+    }
+
+    pub fn expose(&self, color: XYZColor) -> XYZColor {
+        color * self.radiance_scale()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exposure_value() {
+        // sunny 16: f/16 at 1/100s and ISO 100 is about EV 15
+        let sunny = CameraExposure::new(16.0, 1.0 / 100.0, 100.0);
+        assert!((sunny.ev100() - 14.64).abs() < 1e-2);
+        // doubling the iso or halving the shutter time gain one stop at the same scene
+        assert!((ev100(16.0, 1.0 / 100.0, 200.0) - (sunny.ev100() - 1.0)).abs() < 1e-5);
+        assert!((ev100(16.0, 1.0 / 200.0, 100.0) - (sunny.ev100() + 1.0)).abs() < 1e-5);
+        assert!((stops_between_apertures(2.8, 5.6) - 2.0).abs() < 1e-5);
+        assert!((radiance_exposure_scale(10.0) / radiance_exposure_scale(11.0) - 2.0).abs() < 1e-4);
+
+        let settings = CameraExposure::from_ev100(12.0, 8.0, 400.0);
+        assert!((settings.ev100() - 12.0).abs() < 1e-4);
+        assert!((ev100_from_luminance(luminance_from_ev100(7.5)) - 7.5).abs() < 1e-5);
+
+        // a metered scene lands at a fixed fraction of the saturation luminance, about 0.1 with these constants,
+        // independently of how bright the scene is
+        for luminance in [0.5, 100.0, 5000.0] {
+            let metered = CameraExposure::metered(luminance, 4.0, 100.0);
+            let exposed = luminance * metered.luminance_scale();
+            assert!((exposed - 0.65 * 12.5 / 78.0).abs() < 1e-4, "{}", exposed);
+        }
+
+        // spectral radiance integrated against the color matching functions converts through the luminous efficacy
+        let radiance = XYZColor::new(0.01, 0.01, 0.01);
+        let metered = CameraExposure::metered(MAX_LUMINOUS_EFFICACY * radiance.y(), 2.8, 100.0);
+        let exposed = metered.expose(radiance);
+        assert!((exposed.y() - 0.65 * 12.5 / 78.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::error::Result;
+use crate::photographic::MAX_LUMINOUS_EFFICACY;
+use crate::prelude::*;
+
+use std::ops::Sub;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// typed photometric quantities, so that values in lumens can't be passed where watts are expected.
+// each is the photopic counterpart of a radiometric quantity weighted by V(lambda), i.e. y_bar:
+// luminance of radiance in W / (m^2 sr), illuminance of irradiance in W / m^2 and luminous flux of power in W.
+// the radiometric values are what integrating against the color matching functions produces, i.e. the Y of an XYZColor,
+// and convert with the maximum luminous efficacy of 683 lm / W.
+
+/// Luminance in cd / m^2, also called nits. the photometric counterpart of radiance.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Luminance(pub f32);
+
+/// Illuminance in lux, i.e. lm / m^2. the photometric counterpart of irradiance.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct Illuminance(pub f32);
+
+/// Luminous flux in lumens. the photometric counterpart of radiant power.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct LuminousFlux(pub f32);
+
+impl Luminance {
+    pub const fn from_nits(nits: f32) -> Self {
+        Luminance(nits)
+    }
+    pub fn nits(&self) -> f32 {
+        self.0
+    }
+    /// the luminance of `y` in W / (m^2 sr), i.e. the Y of the XYZ of a spectral radiance
+    pub fn from_radiometric(y: f32) -> Self {
+        Luminance(MAX_LUMINOUS_EFFICACY * y)
+    }
+    /// the Y in W / (m^2 sr) that has this luminance
+    pub fn radiometric(&self) -> f32 {
+        self.0 / MAX_LUMINOUS_EFFICACY
+    }
+    /// the illuminance at normal incidence from a small source of this luminance, that subtends `solid_angle` steradians
+    pub fn illuminance(&self, solid_angle: f32) -> Illuminance {
+        Illuminance(self.0 * solid_angle)
+    }
+}
+
+impl Illuminance {
+    pub const fn from_lux(lux: f32) -> Self {
+        Illuminance(lux)
+    }
+    pub fn lux(&self) -> f32 {
+        self.0
+    }
+    /// the illuminance of `y` in W / m^2, i.e. the Y of the XYZ of a spectral irradiance
+    pub fn from_radiometric(y: f32) -> Self {
+        Illuminance(MAX_LUMINOUS_EFFICACY * y)
+    }
+    pub fn radiometric(&self) -> f32 {
+        self.0 / MAX_LUMINOUS_EFFICACY
+    }
+    /// the luminance of a lambertian surface with reflectance `albedo` under this illuminance, albedo E / pi
+    pub fn lambertian_luminance(&self, albedo: f32) -> Luminance {
+        Luminance(albedo * self.0 / PI)
+    }
+    /// the flux received by `area` square meters
+    pub fn flux(&self, area: f32) -> LuminousFlux {
+        LuminousFlux(self.0 * area)
+    }
+}
+
+impl LuminousFlux {
+    pub const fn from_lumens(lumens: f32) -> Self {
+        LuminousFlux(lumens)
+    }
+    pub fn lumens(&self) -> f32 {
+        self.0
+    }
+    /// the flux of `y` in W, i.e. the Y of the XYZ of a spectral power distribution
+    pub fn from_radiometric(y: f32) -> Self {
+        LuminousFlux(MAX_LUMINOUS_EFFICACY * y)
+    }
+    pub fn radiometric(&self) -> f32 {
+        self.0 / MAX_LUMINOUS_EFFICACY
+    }
+    /// the average illuminance when this flux is spread over `area` square meters
+    pub fn illuminance(&self, area: f32) -> Illuminance {
+        Illuminance(self.0 / area)
+    }
+    /// the illuminance at normal incidence at `distance` meters from an isotropic point source with this flux
+    pub fn isotropic_illuminance(&self, distance: f32) -> Illuminance {
+        Illuminance(self.0 / (4.0 * PI * distance * distance))
+    }
+    /// the radiant power in W of a source with this flux, whose radiation has `luminous_efficacy` in lm / W.
+    /// see `Curve::luminous_efficacy`.
+    pub fn radiant_power(&self, luminous_efficacy: f32) -> f32 {
+        self.0 / luminous_efficacy
+    }
+}
+
+impl Add for Luminance {
+    type Output = Luminance;
+    fn add(self, other: Luminance) -> Luminance {
+        Luminance(self.0 + other.0)
+    }
+}
+
+impl Sub for Luminance {
+    type Output = Luminance;
+    fn sub(self, other: Luminance) -> Luminance {
+        Luminance(self.0 - other.0)
+    }
+}
+
+impl Mul<f32> for Luminance {
+    type Output = Luminance;
+    fn mul(self, other: f32) -> Luminance {
+        Luminance(self.0 * other)
+    }
+}
+
+impl Add for Illuminance {
+    type Output = Illuminance;
+    fn add(self, other: Illuminance) -> Illuminance {
+        Illuminance(self.0 + other.0)
+    }
+}
+
+impl Sub for Illuminance {
+    type Output = Illuminance;
+    fn sub(self, other: Illuminance) -> Illuminance {
+        Illuminance(self.0 - other.0)
+    }
+}
+
+impl Mul<f32> for Illuminance {
+    type Output = Illuminance;
+    fn mul(self, other: f32) -> Illuminance {
+        Illuminance(self.0 * other)
+    }
+}
+
+impl Add for LuminousFlux {
+    type Output = LuminousFlux;
+    fn add(self, other: LuminousFlux) -> LuminousFlux {
+        LuminousFlux(self.0 + other.0)
+    }
+}
+
+impl Sub for LuminousFlux {
+    type Output = LuminousFlux;
+    fn sub(self, other: LuminousFlux) -> LuminousFlux {
+        LuminousFlux(self.0 - other.0)
+    }
+}
+
+impl Mul<f32> for LuminousFlux {
+    type Output = LuminousFlux;
+    fn mul(self, other: f32) -> LuminousFlux {
+        LuminousFlux(self.0 * other)
+    }
+}
+
+// the photometric integral of a spectral curve is the same for every quantity, only the units of the curve differ.
+// all of these integrate with the same rule as `Curve::convert_to_luminance`, and scotopic modes give scotopic units.
+impl Curve {
+    /// the luminance of `self` as spectral radiance in W / (m^2 sr nm)
+    pub fn to_luminance(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        mode: LuminanceMode,
+    ) -> Luminance {
+        Luminance(self.convert_to_luminance(integration_bounds, step_size, mode))
+    }
+
+    /// the illuminance of `self` as spectral irradiance in W / (m^2 nm)
+    pub fn to_illuminance(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        mode: LuminanceMode,
+    ) -> Illuminance {
+        Illuminance(self.convert_to_luminance(integration_bounds, step_size, mode))
+    }
+
+    /// the luminous flux of `self` as spectral power in W / nm
+    pub fn to_luminous_flux(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        mode: LuminanceMode,
+    ) -> LuminousFlux {
+        LuminousFlux(self.convert_to_luminance(integration_bounds, step_size, mode))
+    }
+
+    /// the luminous efficacy of radiation with the spectral distribution of `self`, in lm / W.
+    /// at most 683 lm / W for monochromatic light at 555nm, and about 95 lm / W for daylight over 360nm to 830nm.
+    /// returns 0 if `self` has no power within `integration_bounds`.
+    pub fn luminous_efficacy(&self, integration_bounds: Bounds1D, step_size: f32) -> f32 {
+        let iterations = (integration_bounds.span() / step_size) as usize;
+        let power: f32 = (0..iterations)
+            .map(|i| {
+                self.evaluate_power(integration_bounds.lower + (i as f32) * step_size) * step_size
+            })
+            .sum();
+        if power <= 0.0 {
+            return 0.0;
+        }
+        self.convert_to_luminance(integration_bounds, step_size, LuminanceMode::Photopic) / power
+    }
+
+    /// scales `self`, as spectral radiance in W / (m^2 sr nm), to have `luminance`.
+    /// fails with `InvalidCurve` if `self` has no luminance to scale.
+    pub fn scaled_to_luminance(
+        self,
+        luminance: Luminance,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+    ) -> Result<Curve> {
+        let current = self.to_luminance(integration_bounds, step_size, LuminanceMode::Photopic);
+        self.scaled_to(luminance.0, current.0)
+    }
+
+    /// scales `self`, as spectral power in W / nm, to have `flux`, i.e. for a lamp rated at 800 lumens.
+    /// fails with `InvalidCurve` if `self` has no luminous flux to scale.
+    pub fn scaled_to_luminous_flux(
+        self,
+        flux: LuminousFlux,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+    ) -> Result<Curve> {
+        let current = self.to_luminous_flux(integration_bounds, step_size, LuminanceMode::Photopic);
+        self.scaled_to(flux.0, current.0)
+    }
+
+    fn scaled_to(self, target: f32, current: f32) -> Result<Curve> {
+        if current.is_nan() || current <= 0.0 {
+            return Err(MathError::InvalidCurve(
+                "curve has no photopic luminance to scale".to_string(),
+            ));
+        }
+        Ok(self.affine(target / current, 0.0))
+    }
+}
+
+impl XYZColor {
+    /// the luminance of self as the XYZ of a spectral radiance
+    pub fn luminance(&self) -> Luminance {
+        Luminance::from_radiometric(self.y())
+    }
+
+    /// the XYZ of a radiance with `luminance` and `chromaticity`, i.e. for a light specified as 1000 nits of D65 white
+    pub fn from_luminance(luminance: Luminance, chromaticity: Chromaticity) -> XYZColor {
+        XYZColor::from(XYYColor::from_chromaticity(
+            chromaticity,
+            luminance.radiometric(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_photometric_units() {
+        // a white lambertian surface under pi lux has a luminance of 1 nit
+        assert!((Illuminance::from_lux(PI).lambertian_luminance(1.0).nits() - 1.0).abs() < 1e-6);
+        // a 100W incandescent bulb emits about 1600lm, giving about 32 lux at 2m
+        let bulb = LuminousFlux::from_lumens(1600.0);
+        let lux = bulb.isotropic_illuminance(2.0).lux();
+        assert!((lux - 31.83).abs() < 1e-2, "{}", lux);
+        assert_eq!(bulb.illuminance(4.0).flux(4.0), bulb);
+
+        // radiometric values convert through the luminous efficacy
+        assert_eq!(Luminance::from_radiometric(1.0).nits(), 683.0);
+        assert!((Luminance::from_nits(100.0).radiometric() - 100.0 / 683.0).abs() < 1e-7);
+        assert_eq!(
+            LuminousFlux::from_radiometric(2.0) - LuminousFlux::from_radiometric(1.0),
+            LuminousFlux(683.0)
+        );
+        assert!(Illuminance(1.0) < Illuminance(2.0));
+
+        // integration of spectral curves, where a 1 W/nm flat spectrum integrates to 683 times the integral of y_bar
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let flat = Curve::Const(1.0);
+        let flux = flat.to_luminous_flux(bounds, 1.0, LuminanceMode::Photopic);
+        let y = flat.convert_to_xyz(bounds, 1.0, false).y();
+        assert!(
+            (flux.radiometric() - y).abs() < 1e-3 * y,
+            "{:?} {}",
+            flux,
+            y
+        );
+        assert_eq!(
+            flat.to_luminance(bounds, 1.0, LuminanceMode::Photopic)
+                .nits(),
+            flux.lumens()
+        );
+
+        // lights specified in photometric units convert to radiometric curves and colors
+        let bulb = Curve::Blackbody {
+            temperature: 2700.0,
+            boost: 1.0,
+        }
+        .scaled_to_luminous_flux(LuminousFlux::from_lumens(800.0), bounds, 1.0)
+        .unwrap();
+        let lumens = bulb
+            .to_luminous_flux(bounds, 1.0, LuminanceMode::Photopic)
+            .lumens();
+        assert!((lumens - 800.0).abs() < 1e-2, "{}", lumens);
+        let efficacy = bulb.luminous_efficacy(bounds, 1.0);
+        // the trapezoidal rule differs slightly from the left sums of the photometric integrals
+        let watts = bulb.evaluate_integral(bounds, 400, false);
+        assert!(
+            (LuminousFlux(lumens).radiant_power(efficacy) - watts).abs() < 1e-2 * watts,
+            "{} {}",
+            efficacy,
+            watts
+        );
+        // monochromatic light at 555nm is the most efficient
+        let green = Curve::Exponential {
+            signal: vec![(555.0, 0.5, 0.5, 1.0)],
+        };
+        let peak = green.luminous_efficacy(bounds, 0.05);
+        assert!(
+            (peak - 683.0).abs() < 0.01 * 683.0 && peak > efficacy,
+            "{}",
+            peak
+        );
+        assert!(Curve::Const(0.0)
+            .scaled_to_luminance(Luminance(100.0), bounds, 1.0)
+            .is_err());
+
+        let white = XYZColor::from_luminance(Luminance::from_nits(1000.0), white_point::D65);
+        assert!((white.luminance().nits() - 1000.0).abs() < 1e-2);
+        assert!((XYYColor::from(white).chromaticity().0 - white_point::D65.0).abs() < 1e-6);
+    }
+}
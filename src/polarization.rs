@@ -0,0 +1,221 @@
+use crate::complex::Complexx4;
+use crate::prelude::*;
+
+use std::simd::cmp::SimdPartialEq;
+
+// polarized specular reflection, in terms of the complex fresnel amplitude coefficients and the Mueller matrices
+// that act on Stokes vectors (I, Q, U, V).
+// Stokes vectors are expressed in the s / p basis of the plane of incidence, with +Q being s polarized light,
+// and the matrices follow the conventions of Mitsuba 3, so that they compose with its reference frames.
+// the index of refraction is relative, eta + i k of the far medium over the real index of the incident medium,
+// and cos_theta_i is measured on the incident side, in [0, 1].
+
+/// The complex amplitude reflection coefficients of s and p polarized light, for a packet of 4 wavelengths.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FresnelAmplitudes {
+    pub rs: Complexx4,
+    pub rp: Complexx4,
+}
+
+impl FresnelAmplitudes {
+    /// the reflectance of s polarized light, |rs|^2
+    pub fn reflectance_s(&self) -> f32x4 {
+        self.rs.norm_squared()
+    }
+    /// the reflectance of p polarized light, |rp|^2
+    pub fn reflectance_p(&self) -> f32x4 {
+        self.rp.norm_squared()
+    }
+    /// the reflectance of unpolarized light, the average of the s and p reflectances
+    pub fn reflectance(&self) -> f32x4 {
+        f32x4::splat(0.5) * (self.reflectance_s() + self.reflectance_p())
+    }
+    /// the phase shift of reflected s polarized light, in (-pi, pi]
+    pub fn phase_s(&self) -> f32x4 {
+        self.rs.arg()
+    }
+    /// the phase shift of reflected p polarized light, in (-pi, pi]
+    pub fn phase_p(&self) -> f32x4 {
+        self.rp.arg()
+    }
+    /// the phase of p relative to s, which turns linearly polarized light elliptical,
+    /// i.e. under total internal reflection or on conductors
+    pub fn retardance(&self) -> f32x4 {
+        (self.rp * self.rs.conj()).arg()
+    }
+
+    /// the Mueller matrix of the reflection
+    pub fn mueller(&self) -> MuellerMatrixx4 {
+        let half = f32x4::splat(0.5);
+        let (rs, rp) = (self.reflectance_s(), self.reflectance_p());
+        let a = half * (rs + rp);
+        let b = half * (rs - rp);
+        let cross = self.rs * self.rp.conj();
+        let (c, d) = (cross.re, cross.im);
+        let zero = f32x4::splat(0.0);
+        MuellerMatrixx4([
+            [a, b, zero, zero],
+            [b, a, zero, zero],
+            [zero, zero, c, d],
+            [zero, zero, -d, c],
+        ])
+    }
+}
+
+/// computes the complex fresnel amplitude coefficients of specular reflection for each lane.
+/// handles dielectrics (k = 0), conductors, and total internal reflection when eta < 1,
+/// where both reflectances are 1 and only the phases differ.
+pub fn fresnel_amplitudes(eta: f32x4, k: f32x4, cos_theta_i: f32x4) -> FresnelAmplitudes {
+    let cos_i = Complexx4::from_real(cos_theta_i.simd_clamp(f32x4::splat(0.0), f32x4::splat(1.0)));
+    let eta = Complexx4::new(eta, k);
+    let sin2_i = Complexx4::from_real(f32x4::splat(1.0)) - cos_i * cos_i;
+    // snell's law with a complex index. under total internal reflection the argument is negative and real,
+    // and the root must be the one on the positive imaginary axis, so that the transmitted wave decays.
+    let mut cos2_t = Complexx4::ONE - sin2_i / (eta * eta);
+    cos2_t.im = cos2_t
+        .im
+        .simd_eq(f32x4::splat(0.0))
+        .select(f32x4::splat(0.0), cos2_t.im);
+    let cos_t = cos2_t.sqrt();
+
+    let eta_cos_t = eta * cos_t;
+    let eta_cos_i = eta * cos_i;
+    FresnelAmplitudes {
+        rs: (cos_i - eta_cos_t) / (cos_i + eta_cos_t),
+        rp: (eta_cos_i - cos_t) / (eta_cos_i + cos_t),
+    }
+}
+
+/// the Mueller matrix of specular reflection for each lane, see `fresnel_amplitudes`
+pub fn fresnel_mueller(eta: f32x4, k: f32x4, cos_theta_i: f32x4) -> MuellerMatrixx4 {
+    fresnel_amplitudes(eta, k, cos_theta_i).mueller()
+}
+
+/// the Mueller matrix of specular reflection for a single wavelength, see `fresnel_amplitudes`
+pub fn fresnel_mueller_single(eta: f32, k: f32, cos_theta_i: f32) -> MuellerMatrix {
+    fresnel_mueller(
+        f32x4::splat(eta),
+        f32x4::splat(k),
+        f32x4::splat(cos_theta_i),
+    )
+    .lane(0)
+}
+
+/// A 4x4 Mueller matrix acting on Stokes vectors, stored by rows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MuellerMatrix(pub [[f32; 4]; 4]);
+
+impl MuellerMatrix {
+    pub const IDENTITY: MuellerMatrix = MuellerMatrix([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    /// applies the matrix to the Stokes vector `stokes`
+    pub fn apply(&self, stokes: [f32; 4]) -> [f32; 4] {
+        self.0.map(|row| (0..4).map(|j| row[j] * stokes[j]).sum())
+    }
+}
+
+impl Mul for MuellerMatrix {
+    type Output = MuellerMatrix;
+    /// composes the two, such that (a * b).apply(s) == a.apply(b.apply(s))
+    fn mul(self, rhs: MuellerMatrix) -> Self::Output {
+        MuellerMatrix(std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum())
+        }))
+    }
+}
+
+/// Mueller matrices for a packet of 4 wavelengths, stored by rows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MuellerMatrixx4(pub [[f32x4; 4]; 4]);
+
+impl MuellerMatrixx4 {
+    /// the matrix of lane `i`
+    pub fn lane(&self, i: usize) -> MuellerMatrix {
+        MuellerMatrix(self.0.map(|row| row.map(|v| v[i])))
+    }
+
+    /// applies each lane's matrix to the corresponding lane of `stokes`
+    pub fn apply(&self, stokes: [f32x4; 4]) -> [f32x4; 4] {
+        self.0.map(|row| {
+            row.iter()
+                .zip(stokes)
+                .fold(f32x4::splat(0.0), |sum, (m, s)| sum + *m * s)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fresnel_mueller() {
+        let zero = f32x4::splat(0.0);
+        let one = f32x4::splat(1.0);
+
+        // normal incidence on glass, and the brewster angle where p polarized light isn't reflected
+        let glass = fresnel_amplitudes(f32x4::splat(1.5), zero, one);
+        assert!((glass.reflectance()[0] - 0.04).abs() < 1e-6);
+        let brewster = (1.0f32 / (1.0 + 1.5 * 1.5)).sqrt();
+        let at_brewster = fresnel_amplitudes(f32x4::splat(1.5), zero, f32x4::splat(brewster));
+        assert!(at_brewster.reflectance_p()[0] < 1e-8);
+        // so unpolarized light is fully s polarized after reflecting
+        let reflected = fresnel_mueller_single(1.5, 0.0, brewster).apply([1.0, 0.0, 0.0, 0.0]);
+        assert!((reflected[1] / reflected[0] - 1.0).abs() < 1e-5);
+
+        // a conductor at normal incidence reflects ((n - 1)^2 + k^2) / ((n + 1)^2 + k^2)
+        let (n, k) = (0.2, 3.4);
+        let gold = fresnel_amplitudes(f32x4::splat(n), f32x4::splat(k), one);
+        let expected = ((n - 1.0) * (n - 1.0) + k * k) / ((n + 1.0) * (n + 1.0) + k * k);
+        assert!((gold.reflectance()[0] - expected).abs() < 1e-5);
+
+        // total internal reflection from inside glass reflects everything, and retards p relative to s by
+        // tan(delta / 2) = cos(theta) sqrt(sin^2(theta) - n^2) / sin^2(theta)
+        let cos_theta = f32x4::from_array([0.1, 0.3, 0.5, 0.7]);
+        let inside = fresnel_amplitudes(f32x4::splat(1.0 / 1.5), zero, cos_theta);
+        let mueller = inside.mueller();
+        for i in 0..4 {
+            let c = cos_theta[i];
+            let sin2 = 1.0 - c * c;
+            assert!((inside.reflectance_s()[i] - 1.0).abs() < 1e-5);
+            assert!((inside.reflectance_p()[i] - 1.0).abs() < 1e-5);
+            let expected = 2.0 * (c * (sin2 - 1.0 / 2.25).sqrt() / sin2).atan();
+            assert!(
+                (inside.retardance()[i].abs() - expected).abs() < 1e-4,
+                "{} {}",
+                inside.retardance()[i],
+                expected
+            );
+            // a pure retarder, which preserves the degree of polarization
+            let m = mueller.lane(i).0;
+            assert!((m[0][0] - 1.0).abs() < 1e-5 && m[0][1].abs() < 1e-5);
+            assert!((m[2][2] * m[2][2] + m[2][3] * m[2][3] - 1.0).abs() < 1e-4);
+        }
+        // below the critical angle, a dielectric has no retardance
+        let outside = fresnel_amplitudes(f32x4::splat(1.0 / 1.5), zero, f32x4::splat(0.9));
+        assert!(
+            outside.retardance()[0].abs() < 1e-5
+                || (outside.retardance()[0].abs() - PI).abs() < 1e-5
+        );
+        assert!(outside.reflectance_s()[0] < 1.0);
+
+        // lanes and composition agree with the scalar matrices
+        let s = [1.0, 0.2, -0.3, 0.1];
+        let lanes = mueller.apply(s.map(f32x4::splat));
+        let single = mueller.lane(2).apply(s);
+        for (lane, single) in lanes.iter().zip(single) {
+            assert!((lane[2] - single).abs() < 1e-6);
+        }
+        let twice = mueller.lane(1) * mueller.lane(1);
+        let applied = mueller.lane(1).apply(mueller.lane(1).apply(s));
+        for (a, b) in twice.apply(s).iter().zip(applied) {
+            assert!((a - b).abs() < 1e-5);
+        }
+        assert_eq!((MuellerMatrix::IDENTITY * twice), twice);
+    }
+}
@@ -1,25 +1,124 @@
-pub(crate) use std::simd::{cmp::SimdPartialOrd, f32x4, num::SimdFloat, StdFloat};
+// the prelude is split into topical submodules, so that downstream crates can import only the parts they need.
+// `prelude::*` re-exports all of them. module specific helpers are left out, and remain available from their modules.
+
+/// geometric types, transforms and numeric traits
+pub mod base {
+    pub use crate::bounds::{Bounds1D, Bounds2D, Bounds3D, Bounds3D4, WrappedBounds1D};
+    pub use crate::complex::Complexx4;
+    pub use crate::dual::Dual;
+    pub use crate::error::MathError;
+    pub use crate::grid::Grid2D;
+    pub use crate::interval::{Interval, Point3Interval, Vec3Interval};
+    pub use crate::misc::{direction_to_uv, uv_to_direction};
+    pub use crate::point::Point3;
+    pub use crate::ray::Ray;
+    pub use crate::sphere_map::partition::{DirectionSet, SpherePartition};
+    pub use crate::sphere_map::quadrature::{fibonacci_sphere, SphereQuadrature};
+    pub use crate::sphere_map::statistics::{vmf_concentration, DirectionStatistics};
+    pub use crate::sphere_map::{
+        cylindrical_equal_area_to_direction, direction_to_cylindrical_equal_area,
+        equal_area_sphere_to_square, equal_area_square_to_sphere, SphereMapping,
+    };
+    pub use crate::tangent_frame::{resolve_normal, ResolvedNormal, TangentFrame};
+    pub use crate::tolerance::Tolerance;
+    #[cfg(feature = "simdfloat_patch")]
+    pub use crate::traits::SimdFloatPatch;
+    pub use crate::traits::{
+        Abs, CheckInf, CheckNAN, CheckResult, Field, FromScalar, Scalar, ToScalar, TotalPartialOrd,
+    };
+    pub use crate::transform::animated::AnimatedTransform;
+    pub use crate::transform::matrix3::Matrix3x3;
+    pub use crate::transform::{Matrix4x4, Transform3};
+    pub use crate::vec::{Axis, Vec3};
+}
 
-pub use crate::bounds::*;
-pub use crate::color::*;
-pub use crate::misc::*;
-pub use crate::pdf::*;
-pub use crate::point::Point3;
-pub use crate::random::*;
-pub use crate::ray::*;
-pub use crate::sample::*;
-pub use crate::spectral::{
-    HeroWavelength, SingleWavelength, WavelengthEnergy, WavelengthEnergyTrait,
-};
-pub use crate::traits::*;
+/// wavelengths, spectral curves and color
+pub mod spectral {
+    pub use crate::color::*;
+    pub use crate::curves::batch::convert_many_to_xyz;
+    pub use crate::curves::integrals::CurveWithIntegrals;
+    pub use crate::curves::registry::{CurveRegistry, NamedCurve};
+    pub use crate::curves::sparse::{SparseReconstruction, SparseSpectrum};
+    pub use crate::curves::upsample::SigmoidPolynomial;
+    pub use crate::curves::{
+        Curve, CurveWithCDF, InterpolationMode, SpectralPowerDistributionFunction, SplitTabulated,
+    };
+    pub use crate::misc::{
+        blackbody, erf, erfc, gaussian, gaussian_integral, gaussianf32, max_blackbody_lambda, w,
+    };
+    #[cfg(feature = "simdfloat_patch")]
+    pub use crate::misc::{blackbody_f32x4, erf_f32x4, erfc_f32x4, gaussian_f32x4};
+    pub use crate::photometric::{Illuminance, Luminance, LuminousFlux};
+    pub use crate::spectral::blue_noise::{wavelength_offset, BlueNoiseMask};
+    pub use crate::spectral::cmf::CMF;
+    pub use crate::spectral::luminosity::LuminanceMode;
+    pub use crate::spectral::{
+        HeroWavelength, SingleWavelength, WavelengthEnergy, WavelengthEnergyTrait, WavelengthRange,
+    };
+    pub use crate::spectrum::field::{
+        CheckerboardField, ConstantField, CurveRef, GridField, SpectralField,
+    };
+    pub use crate::spectrum::sampled::SampledSpectrum;
+    pub use crate::spectrum::{ProceduralSpectrum, Spectrum};
+}
 
-pub use crate::curves::{
-    Curve, CurveWithCDF, InterpolationMode, SpectralPowerDistributionFunction,
-};
+/// samples, samplers, distributions, pdfs and their measures
+pub mod sampling {
+    pub use crate::anisotropic::{
+        elliptical_cosine_power_direction, elliptical_cosine_power_pdf, ggx_d, ggx_g1, ggx_lambda,
+        ggx_reflection_pdf, ggx_visible_normal, ggx_visible_normal_pdf, roughness_to_exponent,
+    };
+    pub use crate::distribution::{
+        lat_long_pdf_to_solid_angle, Distribution1D, Distribution2D, Tabulated2D,
+    };
+    pub use crate::estimate::{
+        integrate_with_control_variate, mc_estimate, ControlVariateEstimate, EstimateSummary,
+    };
+    // the slice based heuristics from `mis` are renamed to avoid clashing with the two technique versions from `misc`
+    pub use crate::mis::{
+        balance_heuristic as mis_balance_heuristic, power_heuristic as mis_power_heuristic,
+    };
+    pub use crate::misc::{power_heuristic, power_heuristic_hero};
+    pub use crate::pdf::{
+        half_vector_reflection, half_vector_refraction, reflection_jacobian, refraction_jacobian,
+        PDF,
+    };
+    pub use crate::random::stream::StreamRng;
+    pub use crate::random::{
+        concentric_disk_to_square, concentric_hemisphere_direction,
+        concentric_hemisphere_to_square, concentric_sample_disk, debug_random,
+        random_cosine_direction, random_in_unit_disk, random_in_unit_sphere, random_on_unit_sphere,
+        random_to_sphere, stratified_hemisphere_directions, weighted_cosine_direction,
+        HemisphereWeighting,
+    };
+    pub use crate::sample::light::{
+        sample_disk_area, sample_disk_solid_angle, sample_rect_area, SphericalRectangle,
+    };
+    pub use crate::sample::mcmc::{
+        effective_sample_size, Chain, MarkovDomain, Metropolis, MutationKernel,
+    };
+    pub use crate::sample::medium::{henyey_greenstein, sample_henyey_greenstein, SpectralMedium};
+    pub use crate::sample::rejection::{rejection_sample, RejectionSample, RejectionStatistics};
+    pub use crate::sample::sequence::{GoldenRatioSequence, R2Sequence, RecurrenceSampler};
+    pub use crate::sample::{
+        RandomSampler, Sample1D, Sample2D, Sample3D, Sample4D, Sampler, StratifiedSampler,
+    };
+    pub use crate::solid_angle::{
+        disk_solid_angle, rect_solid_angle, sphere_solid_angle, spherical_cap_solid_angle,
+        spherical_polygon_solid_angle, spherical_triangle_solid_angle,
+    };
+    pub use crate::traits::{
+        Area, HalfVector, Length, Measure, PathThroughput, ProjectedSolidAngle, SolidAngle,
+        Throughput, Uniform01,
+    };
+}
 
-pub use crate::tangent_frame::TangentFrame;
-pub use crate::transform::*;
-pub use crate::vec::{Axis, Vec3};
+pub use self::base::*;
+pub use self::sampling::*;
+pub use self::spectral::*;
+
+pub(crate) use std::ops::{Add, Div, Mul, Neg};
+pub(crate) use std::simd::{cmp::SimdPartialOrd, f32x4, num::SimdFloat, StdFloat};
 
 pub(crate) use std::f32::consts::PI;
 pub(crate) use std::f32::INFINITY;
@@ -1,8 +1,52 @@
 use crate::prelude::*;
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::cell::RefCell;
+
+pub mod stream;
+
+thread_local! {
+    // the rng installed by `scoped_seed` on this thread, if any
+    static SCOPED_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+// restores the previously installed rng when a scope ends, including when it unwinds
+struct ScopeGuard(Option<StdRng>);
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SCOPED_RNG.with(|rng| *rng.borrow_mut() = previous);
+    }
+}
+
+/// runs `f` with `debug_random`, and everything built on it such as `Sample2D::new_random_sample`, `RandomSampler`
+/// and `StratifiedSampler`, drawing from an rng seeded with `seed` instead of the thread rng.
+/// the seed only applies to the current thread, so work spawned onto other threads (i.e. with rayon) should be seeded separately.
+/// scopes can be nested, and the enclosing rng resumes where it left off once the inner scope ends.
+pub fn scoped_seed<R>(seed: u64, f: impl FnOnce() -> R) -> R {
+    let previous = SCOPED_RNG.with(|rng| rng.borrow_mut().replace(StdRng::seed_from_u64(seed)));
+    let _guard = ScopeGuard(previous);
+    f()
+}
+
+// a new rng derived from the scoped rng, for types that own their rng. None outside of `scoped_seed`.
+pub(crate) fn fork_scoped_rng() -> Option<StdRng> {
+    SCOPED_RNG.with(|rng| {
+        rng.borrow_mut()
+            .as_mut()
+            .map(|rng| StdRng::seed_from_u64(rng.gen()))
+    })
+}
+
+/// a uniform random number in [0, 1), from the scoped rng if called within `scoped_seed` and from the thread rng otherwise
 pub fn debug_random() -> f32 {
-    // uses thread local rng
-    rand::random()
+    SCOPED_RNG.with(|rng| match rng.borrow_mut().as_mut() {
+        Some(rng) => rng.gen(),
+        None => rand::random(),
+    })
 }
 
 pub fn random_in_unit_sphere(r: Sample3D) -> Vec3 {
@@ -61,3 +105,185 @@ pub fn random_to_sphere(r: Sample2D, radius: f32, distance_squared: f32) -> Vec3
     y *= sqrt_1_z2;
     return Vec3::new(x, y, z);
 }
+
+/// maps a uniform sample on the unit square to the unit disk, preserving relative areas and stratification
+/// (Shirley and Chiu's concentric mapping). returns (x, y) on the disk.
+pub fn concentric_sample_disk(r: Sample2D) -> (f32, f32) {
+    let (u, v) = (2.0 * r.x - 1.0, 2.0 * r.y - 1.0);
+    if u == 0.0 && v == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (radius, theta) = if u.abs() > v.abs() {
+        (u, PI / 4.0 * (v / u))
+    } else {
+        (v, PI / 2.0 - PI / 4.0 * (u / v))
+    };
+    let (sin, cos) = theta.sin_cos();
+    (radius * cos, radius * sin)
+}
+
+/// the inverse of `concentric_sample_disk`, mapping a point on the unit disk back to the unit square
+pub fn concentric_disk_to_square(x: f32, y: f32) -> (f32, f32) {
+    let radius = (x * x + y * y).sqrt();
+    if radius == 0.0 {
+        return (0.5, 0.5);
+    }
+    let phi = y.atan2(x);
+    // the forward mapping uses a negative radius for the left and bottom sectors, which rotates their angle by pi
+    let (u, v) = if phi.abs() <= PI / 4.0 {
+        (radius, radius * phi / (PI / 4.0))
+    } else if phi.abs() >= 3.0 * PI / 4.0 {
+        let theta = if phi > 0.0 { phi - PI } else { phi + PI };
+        (-radius, -radius * theta / (PI / 4.0))
+    } else if phi > 0.0 {
+        (radius * (PI / 2.0 - phi) / (PI / 4.0), radius)
+    } else {
+        let theta = phi + PI;
+        (-radius * (PI / 2.0 - theta) / (PI / 4.0), -radius)
+    };
+    ((u + 1.0) / 2.0, (v + 1.0) / 2.0)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HemisphereWeighting {
+    Uniform,
+    Cosine,
+}
+
+/// maps a sample to a direction in the +z hemisphere through the concentric disk mapping,
+/// returning the direction and its pdf wrt solid angle.
+pub fn concentric_hemisphere_direction(
+    r: Sample2D,
+    weighting: HemisphereWeighting,
+) -> (Vec3, PDF<f32, SolidAngle>) {
+    let (x, y) = concentric_sample_disk(r);
+    let r2 = (x * x + y * y).min(1.0);
+    match weighting {
+        HemisphereWeighting::Uniform => {
+            // equal area projection from the disk to the hemisphere
+            let scale = (2.0 - r2).sqrt();
+            (
+                Vec3::new(x * scale, y * scale, 1.0 - r2),
+                PDF::new(1.0 / (2.0 * PI)),
+            )
+        }
+        HemisphereWeighting::Cosine => {
+            let z = (1.0 - r2).sqrt();
+            (Vec3::new(x, y, z), PDF::new(z / PI))
+        }
+    }
+}
+
+/// the inverse of `concentric_hemisphere_direction`, mapping a direction in the +z hemisphere back to the unit square
+pub fn concentric_hemisphere_to_square(
+    direction: Vec3,
+    weighting: HemisphereWeighting,
+) -> (f32, f32) {
+    let (x, y) = match weighting {
+        HemisphereWeighting::Uniform => {
+            let r2 = 1.0 - direction.z().clamp(0.0, 1.0);
+            let scale = (2.0 - r2).sqrt();
+            (direction.x() / scale, direction.y() / scale)
+        }
+        HemisphereWeighting::Cosine => (direction.x(), direction.y()),
+    };
+    concentric_disk_to_square(x, y)
+}
+
+/// generates `x_strata * y_strata` directions over the +z hemisphere from a jittered grid,
+/// mapped through the concentric disk mapping so that the stratification is preserved.
+/// each entry contains the direction, its pdf wrt solid angle, and its quadrature weight 1 / (N * pdf),
+/// such that summing f(direction) * weight estimates the integral of f over the hemisphere.
+pub fn stratified_hemisphere_directions<S: Sampler + ?Sized>(
+    sampler: &mut S,
+    x_strata: usize,
+    y_strata: usize,
+    weighting: HemisphereWeighting,
+) -> Vec<(Vec3, PDF<f32, SolidAngle>, f32)> {
+    let n = x_strata * y_strata;
+    let mut directions = Vec::with_capacity(n);
+    for j in 0..y_strata {
+        for i in 0..x_strata {
+            let jitter = sampler.draw_2d();
+            let sample = Sample2D::new(
+                ((i as f32 + jitter.x) / x_strata as f32).min(1.0 - f32::EPSILON),
+                ((j as f32 + jitter.y) / y_strata as f32).min(1.0 - f32::EPSILON),
+            );
+            let (direction, pdf) = concentric_hemisphere_direction(sample, weighting);
+            let weight = if Tolerance::EXACT.rejects_pdf(pdf) {
+                0.0
+            } else {
+                1.0 / (n as f32 * *pdf)
+            };
+            directions.push((direction, pdf, weight));
+        }
+    }
+    directions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scoped_seed() {
+        let draw = || {
+            let mut stratified = StratifiedSampler::new(4, 4, 4);
+            (
+                Sample2D::new_random_sample(),
+                RandomSampler::new().draw_1d().x,
+                stratified.draw_2d(),
+                stratified.draw_3d(),
+            )
+        };
+        let a = scoped_seed(7, draw);
+        let b = scoped_seed(7, draw);
+        assert_eq!(format!("{:?}", a), format!("{:?}", b));
+        assert_ne!(format!("{:?}", a), format!("{:?}", scoped_seed(8, draw)));
+
+        // an inner scope doesn't disturb the sequence of the outer one
+        let (outer, nested) = scoped_seed(1, || {
+            let first = debug_random();
+            let inner = scoped_seed(2, debug_random);
+            (vec![first, debug_random()], inner)
+        });
+        let expected = scoped_seed(1, || vec![debug_random(), debug_random()]);
+        assert_eq!(outer, expected);
+        assert_eq!(nested, scoped_seed(2, debug_random));
+        assert!(std::panic::catch_unwind(|| scoped_seed(3, || panic!())).is_err());
+        // the thread rng is restored after unwinding
+        assert!(SCOPED_RNG.with(|rng| rng.borrow().is_none()));
+    }
+
+    #[test]
+    fn test_stratified_hemisphere_directions() {
+        let mut sampler = RandomSampler::new();
+        let uniform =
+            stratified_hemisphere_directions(&mut sampler, 16, 16, HemisphereWeighting::Uniform);
+        assert_eq!(uniform.len(), 256);
+        let mut estimate = 0.0;
+        for (direction, _, weight) in &uniform {
+            assert!(direction.z() >= 0.0);
+            assert!((direction.norm() - 1.0).abs() < 1e-4);
+            estimate += direction.z() * direction.z() * weight;
+        }
+        // integral of cos^2 over the hemisphere is 2pi/3.
+        // the standard deviation of this estimate without stratification would be about 0.12
+        assert!((estimate - 2.0 * PI / 3.0).abs() < 0.06, "{}", estimate);
+
+        let cosine =
+            stratified_hemisphere_directions(&mut sampler, 16, 16, HemisphereWeighting::Cosine);
+        let estimate: f32 = cosine.iter().map(|(d, _, weight)| d.z() * weight).sum();
+        // integral of cos over the hemisphere is pi, and this is exact for cosine weighted directions
+        assert!((estimate - PI).abs() < 1e-3, "{}", estimate);
+
+        for weighting in [HemisphereWeighting::Uniform, HemisphereWeighting::Cosine] {
+            for _ in 0..1000 {
+                let sample = Sample2D::new_random_sample();
+                let (direction, _) = concentric_hemisphere_direction(sample, weighting);
+                let (u, v) = concentric_hemisphere_to_square(direction, weighting);
+                assert!((u - sample.x).abs() < 1e-4 && (v - sample.y).abs() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::prelude::*;
+
+use rand::{RngCore, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// a pcg32 generator (O'Neill, "PCG: A Family of Simple Fast Space-Efficient Statistically Good Algorithms for Random Number Generation").
+// every odd increment selects an independent stream, and the underlying lcg can be advanced in O(log n),
+// which is what makes it possible to hand out reproducible streams to threads and tiles without any coordination.
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const DEFAULT_STREAM: u64 = 1442695040888963407 >> 1;
+
+/// the splitmix64 finalizer, used to turn structured keys like tile coordinates into well distributed seeds
+pub fn mix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// A small seedable rng that can be split into decorrelated streams and advanced in O(log n) steps,
+/// for deterministic sampling across threads, tiles and frames.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct StreamRng {
+    state: u64,
+    increment: u64,
+}
+
+impl StreamRng {
+    /// the generator at position `seed` on stream `stream`. distinct streams are distinct sequences, not offsets of one sequence.
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = StreamRng {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    pub fn from_seed_u64(seed: u64) -> Self {
+        StreamRng::new(seed, DEFAULT_STREAM)
+    }
+
+    /// a generator keyed on a tile and frame, which is the same wherever and whenever it is constructed.
+    /// `seed` distinguishes otherwise identical renders.
+    pub fn for_tile(seed: u64, tile_x: u32, tile_y: u32, frame: u32) -> Self {
+        let key =
+            mix64(mix64(mix64(seed) ^ tile_x as u64) ^ ((tile_y as u64) << 32 | frame as u64));
+        StreamRng::new(key, mix64(key))
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(MULTIPLIER)
+            .wrapping_add(self.increment);
+    }
+
+    /// skips `delta` outputs, such that `advance(n)` is equivalent to drawing n u32s and discarding them.
+    /// deltas wrap around the period of 2^64, so `advance(u64::MAX)` steps back by one.
+    pub fn advance(&mut self, mut delta: u64) {
+        // compose the affine map x -> multiplier * x + increment with itself by repeated squaring
+        let (mut accumulated_multiplier, mut accumulated_increment) = (1u64, 0u64);
+        let (mut multiplier, mut increment) = (MULTIPLIER, self.increment);
+        while delta > 0 {
+            if delta & 1 == 1 {
+                accumulated_multiplier = accumulated_multiplier.wrapping_mul(multiplier);
+                accumulated_increment = accumulated_increment
+                    .wrapping_mul(multiplier)
+                    .wrapping_add(increment);
+            }
+            increment = multiplier.wrapping_add(1).wrapping_mul(increment);
+            multiplier = multiplier.wrapping_mul(multiplier);
+            delta >>= 1;
+        }
+        self.state = accumulated_multiplier
+            .wrapping_mul(self.state)
+            .wrapping_add(accumulated_increment);
+    }
+
+    /// a copy of self advanced by `delta`
+    pub fn jumped(&self, delta: u64) -> Self {
+        let mut rng = *self;
+        rng.advance(delta);
+        rng
+    }
+
+    /// `n` generators on distinct streams, derived from the next output of self.
+    /// the children are reproducible given the state of self, and self only advances by one draw.
+    pub fn split(&mut self, n: usize) -> Vec<StreamRng> {
+        let base = self.next_u64();
+        (0..n as u64)
+            .map(|i| {
+                let key = mix64(base ^ mix64(i));
+                StreamRng::new(key, mix64(key ^ self.increment))
+            })
+            .collect()
+    }
+
+    /// a uniform random number in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        // the top 24 bits, which are exactly representable
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+}
+
+impl RngCore for StreamRng {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+    fn next_u64(&mut self) -> u64 {
+        (self.next_u32() as u64) << 32 | self.next_u32() as u64
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for StreamRng {
+    type Seed = [u8; 16];
+    fn from_seed(seed: Self::Seed) -> Self {
+        let [state, stream] = [0, 8].map(|i| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&seed[i..i + 8]);
+            u64::from_le_bytes(bytes)
+        });
+        StreamRng::new(state, stream)
+    }
+    fn seed_from_u64(state: u64) -> Self {
+        StreamRng::from_seed_u64(state)
+    }
+}
+
+impl Sampler for StreamRng {
+    fn draw_1d(&mut self) -> Sample1D {
+        Sample1D::new(self.next_f32())
+    }
+    fn draw_2d(&mut self) -> Sample2D {
+        Sample2D::new(self.next_f32(), self.next_f32())
+    }
+    fn draw_3d(&mut self) -> Sample3D {
+        Sample3D::new(self.next_f32(), self.next_f32(), self.next_f32())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_stream_rng() {
+        // matches the reference pcg32 demo output for seed 42 on stream 54
+        let mut rng = StreamRng::new(42, 54);
+        let expected = [
+            0xa15c02b7u32,
+            0x7b47f409,
+            0xba1d3330,
+            0x83d2f293,
+            0xbfa4784b,
+            0xcbed606e,
+        ];
+        for value in expected {
+            assert_eq!(rng.next_u32(), value);
+        }
+
+        // jumping ahead matches drawing, and wraps around to step backwards
+        let start = StreamRng::from_seed_u64(7);
+        let mut drawn = start;
+        for _ in 0..1000 {
+            drawn.next_u32();
+        }
+        assert_eq!(start.jumped(1000), drawn);
+        assert_eq!(drawn.jumped(u64::MAX).jumped(1), drawn);
+        assert_eq!(start.jumped(1000).jumped(u64::MAX - 999), start);
+
+        // split streams are reproducible and uncorrelated
+        let children = StreamRng::from_seed_u64(3).split(4);
+        assert_eq!(children, StreamRng::from_seed_u64(3).split(4));
+        let n = 20000;
+        let (mut a, mut b) = (children[0], children[1]);
+        let pairs: Vec<(f32, f32)> = (0..n)
+            .map(|_| (a.next_f32() - 0.5, b.next_f32() - 0.5))
+            .collect();
+        let correlation = pairs.iter().map(|(x, y)| x * y).sum::<f32>()
+            / pairs.iter().map(|(x, _)| x * x).sum::<f32>();
+        assert!(correlation.abs() < 0.05, "{}", correlation);
+        let mean = pairs.iter().map(|(x, _)| x).sum::<f32>() / n as f32;
+        assert!(mean.abs() < 0.01);
+
+        // tiles are keyed on their coordinates and frame, independent of the order in which they are rendered
+        let render = |tile: u32| {
+            let mut rng = StreamRng::for_tile(1, tile % 8, tile / 8, 0);
+            (0..64).map(|_| rng.draw_2d().x).sum::<f32>()
+        };
+        let parallel: Vec<f32> = (0..64).into_par_iter().rev().map(render).collect();
+        let serial: Vec<f32> = (0..64).rev().map(render).collect();
+        assert_eq!(parallel, serial);
+        assert_ne!(
+            StreamRng::for_tile(1, 0, 0, 0),
+            StreamRng::for_tile(1, 0, 0, 1)
+        );
+        assert_ne!(
+            StreamRng::for_tile(1, 1, 0, 0),
+            StreamRng::for_tile(1, 0, 1, 0)
+        );
+        assert_ne!(
+            StreamRng::for_tile(1, 0, 0, 0),
+            StreamRng::for_tile(2, 0, 0, 0)
+        );
+    }
+}
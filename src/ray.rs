@@ -50,6 +50,36 @@ impl Ray {
     pub fn point_at_parameter(self, time: f32) -> Point3 {
         self.origin + self.direction * time
     }
+    pub fn at(self, t: f32) -> Point3 {
+        self.point_at_parameter(t)
+    }
+    /// the interval of valid ray parameters, [0, tmax]
+    pub fn parametric_interval(&self) -> Bounds1D {
+        Bounds1D::new(0.0, self.tmax)
+    }
+    /// returns a ray starting at `at(t)`, with tmax reduced by `t` such that it still ends at the same point.
+    /// advancing past tmax yields a ray with a tmax of 0.
+    pub fn advance(self, t: f32) -> Self {
+        debug_assert!(t >= 0.0);
+        Ray {
+            origin: self.at(t),
+            tmax: (self.tmax - t).max(0.0),
+            ..self
+        }
+    }
+    /// restricts the ray to the part of its parametric interval that overlaps `bounds`,
+    /// advancing the origin to the start of the overlap. returns None if there is no overlap.
+    /// parameters of the returned ray are relative to its new origin.
+    pub fn clip_to_bounds(self, bounds: Bounds1D) -> Option<Self> {
+        let start = bounds.lower.max(0.0);
+        let end = bounds.upper.min(self.tmax);
+        if start > end {
+            return None;
+        }
+        let mut ray = self.advance(start);
+        ray.tmax = end - start;
+        Some(ray)
+    }
 }
 
 impl Default for Ray {
@@ -57,3 +87,32 @@ impl Default for Ray {
         Ray::new(Point3::default(), Vec3::default())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_advance_and_clip() {
+        let ray = Ray::new(Point3::ORIGIN, Vec3::X).with_tmax(10.0);
+        let advanced = ray.advance(4.0);
+        assert_eq!(advanced.origin, Point3::new(4.0, 0.0, 0.0));
+        assert_eq!(advanced.tmax, 6.0);
+        assert_eq!(advanced.at(advanced.tmax), ray.at(ray.tmax));
+        assert_eq!(ray.advance(12.0).tmax, 0.0);
+        assert_eq!(
+            Ray::new(Point3::ORIGIN, Vec3::X).advance(5.0).tmax,
+            f32::INFINITY
+        );
+
+        let clipped = ray.clip_to_bounds(Bounds1D::new(2.0, 20.0)).unwrap();
+        assert_eq!(clipped.origin, Point3::new(2.0, 0.0, 0.0));
+        assert_eq!(clipped.tmax, 8.0);
+        let clipped = ray.clip_to_bounds(Bounds1D::new(-5.0, 3.0)).unwrap();
+        assert_eq!(clipped.origin, Point3::ORIGIN);
+        assert_eq!(clipped.tmax, 3.0);
+        assert!(ray.clip_to_bounds(Bounds1D::new(11.0, 20.0)).is_none());
+        assert!(ray.clip_to_bounds(Bounds1D::new(-3.0, -1.0)).is_none());
+        assert_eq!(ray.parametric_interval(), Bounds1D::new(0.0, 10.0));
+    }
+}
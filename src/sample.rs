@@ -1,8 +1,17 @@
 use crate::prelude::*;
+use crate::random::fork_scoped_rng;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, RngCore};
 
 use std::f32::EPSILON;
+
+pub mod light;
+pub mod mcmc;
+pub mod medium;
+pub mod profile;
+pub mod rejection;
+pub mod sequence;
+
 // TODO: add measure generic like with pdf to define what measure a sample is obtained wrt
 
 #[derive(Debug, Copy, Clone)]
@@ -68,6 +77,183 @@ impl Sample3D {
     }
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct Sample4D {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Sample4D {
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Sample4D { x, y, z, w }
+    }
+    pub fn new_random_sample() -> Self {
+        Sample4D::new(
+            debug_random(),
+            debug_random(),
+            debug_random(),
+            debug_random(),
+        )
+    }
+}
+
+// splitting and merging samples of different dimensions
+
+impl Sample2D {
+    pub fn split(self) -> (Sample1D, Sample1D) {
+        (Sample1D { x: self.x }, Sample1D { x: self.y })
+    }
+    pub fn merge(a: Sample1D, b: Sample1D) -> Self {
+        Sample2D::new(a.x, b.x)
+    }
+}
+
+impl Sample3D {
+    /// splits into the first two dimensions and the last dimension
+    pub fn split(self) -> (Sample2D, Sample1D) {
+        (
+            Sample2D {
+                x: self.x,
+                y: self.y,
+            },
+            Sample1D { x: self.z },
+        )
+    }
+    pub fn merge(a: Sample2D, b: Sample1D) -> Self {
+        Sample3D::new(a.x, a.y, b.x)
+    }
+}
+
+impl Sample4D {
+    /// splits into the first two dimensions and the last two dimensions
+    pub fn split(self) -> (Sample2D, Sample2D) {
+        (
+            Sample2D {
+                x: self.x,
+                y: self.y,
+            },
+            Sample2D {
+                x: self.z,
+                y: self.w,
+            },
+        )
+    }
+    pub fn merge(a: Sample2D, b: Sample2D) -> Self {
+        Sample4D::new(a.x, a.y, b.x, b.y)
+    }
+}
+
+// conversions to and from plain arrays and tuples, i.e. for data from external rngs or sample sequences.
+// conversions into samples assert that each component is in [0, 1) in debug builds.
+// 1D and 2D conversions go through `Sample1D::new` and `Sample2D::new`, which assert already,
+// while the const constructors of 3D and 4D samples can't, so their conversions assert explicitly.
+
+impl From<f32> for Sample1D {
+    fn from(x: f32) -> Self {
+        Sample1D::new(x)
+    }
+}
+
+impl From<Sample1D> for f32 {
+    fn from(sample: Sample1D) -> Self {
+        sample.x
+    }
+}
+
+impl From<[f32; 2]> for Sample2D {
+    fn from([x, y]: [f32; 2]) -> Self {
+        Sample2D::new(x, y)
+    }
+}
+
+impl From<(f32, f32)> for Sample2D {
+    fn from((x, y): (f32, f32)) -> Self {
+        Sample2D::new(x, y)
+    }
+}
+
+impl From<Sample2D> for [f32; 2] {
+    fn from(sample: Sample2D) -> Self {
+        [sample.x, sample.y]
+    }
+}
+
+impl From<Sample2D> for (f32, f32) {
+    fn from(sample: Sample2D) -> Self {
+        (sample.x, sample.y)
+    }
+}
+
+impl From<[f32; 3]> for Sample3D {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        debug_assert!([x, y, z].iter().all(|v| (0.0..1.0).contains(v)));
+        Sample3D::new(x, y, z)
+    }
+}
+
+impl From<(f32, f32, f32)> for Sample3D {
+    fn from((x, y, z): (f32, f32, f32)) -> Self {
+        Sample3D::from([x, y, z])
+    }
+}
+
+impl From<Sample3D> for [f32; 3] {
+    fn from(sample: Sample3D) -> Self {
+        [sample.x, sample.y, sample.z]
+    }
+}
+
+impl From<Sample3D> for (f32, f32, f32) {
+    fn from(sample: Sample3D) -> Self {
+        (sample.x, sample.y, sample.z)
+    }
+}
+
+impl From<[f32; 4]> for Sample4D {
+    fn from([x, y, z, w]: [f32; 4]) -> Self {
+        debug_assert!([x, y, z, w].iter().all(|v| (0.0..1.0).contains(v)));
+        Sample4D::new(x, y, z, w)
+    }
+}
+
+impl From<(f32, f32, f32, f32)> for Sample4D {
+    fn from((x, y, z, w): (f32, f32, f32, f32)) -> Self {
+        Sample4D::from([x, y, z, w])
+    }
+}
+
+impl From<Sample4D> for [f32; 4] {
+    fn from(sample: Sample4D) -> Self {
+        [sample.x, sample.y, sample.z, sample.w]
+    }
+}
+
+impl From<Sample4D> for (f32, f32, f32, f32) {
+    fn from(sample: Sample4D) -> Self {
+        (sample.x, sample.y, sample.z, sample.w)
+    }
+}
+
+impl From<(Sample1D, Sample1D)> for Sample2D {
+    fn from((a, b): (Sample1D, Sample1D)) -> Self {
+        Sample2D::merge(a, b)
+    }
+}
+
+impl From<(Sample2D, Sample1D)> for Sample3D {
+    fn from((a, b): (Sample2D, Sample1D)) -> Self {
+        Sample3D::merge(a, b)
+    }
+}
+
+impl From<(Sample2D, Sample2D)> for Sample4D {
+    fn from((a, b): (Sample2D, Sample2D)) -> Self {
+        Sample4D::merge(a, b)
+    }
+}
+
 pub trait Sampler {
     fn draw_1d(&mut self) -> Sample1D;
     fn draw_2d(&mut self) -> Sample2D;
@@ -110,7 +296,10 @@ impl StratifiedSampler {
             first: (0..xdim).into_iter().collect(),
             second: (0..(xdim * ydim)).into_iter().collect(),
             third: (0..(xdim * ydim * zdim)).into_iter().collect(),
-            rng: Box::new(thread_rng()),
+            rng: match fork_scoped_rng() {
+                Some(rng) => Box::new(rng),
+                None => Box::new(thread_rng()),
+            },
         }
     }
 }
@@ -233,6 +422,32 @@ impl Sampler for StratifiedSampler {
 #[cfg(test)]
 mod test {
     use super::*;
+
+    #[test]
+    fn test_sample_conversions() {
+        let sample: Sample2D = [0.25, 0.5].into();
+        assert_eq!(<(f32, f32)>::from(sample), (0.25, 0.5));
+        let (a, b) = sample.split();
+        assert_eq!((a.x, b.x), (0.25, 0.5));
+
+        let sample = Sample4D::from((0.1, 0.2, 0.3, 0.4));
+        let (first, second) = sample.split();
+        assert_eq!(<[f32; 2]>::from(second), [0.3, 0.4]);
+        let merged: Sample4D = (first, second).into();
+        assert_eq!(<[f32; 4]>::from(merged), [0.1, 0.2, 0.3, 0.4]);
+
+        let sample = Sample3D::merge(first, Sample1D::from(0.75));
+        assert_eq!(<(f32, f32, f32)>::from(sample), (0.1, 0.2, 0.75));
+        assert_eq!(f32::from(sample.split().1), 0.75);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_sample_conversion_out_of_range() {
+        let _ = Sample2D::from([0.5, 1.0]);
+    }
+
     fn function(x: f32) -> f32 {
         x * x - x + 1.0
     }
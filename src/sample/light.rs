@@ -0,0 +1,279 @@
+use crate::prelude::*;
+use crate::tangent_frame::TangentFrame;
+
+// sampling of points on rectangle and disk shaped lights.
+// area sampling is uniform over the surface of the light, and its pdf becomes pdf * distance^2 / |cos| in solid angle
+// at the shading point. that factor varies over the light, which is noisy for large or nearby lights,
+// so rectangles also have exact uniform solid angle sampling.
+
+/// uniformly samples the parallelogram with corner `corner` and edges `edge_u` and `edge_v`
+pub fn sample_rect_area(
+    corner: Point3,
+    edge_u: Vec3,
+    edge_v: Vec3,
+    sample: Sample2D,
+) -> (Point3, PDF<f32, Area>) {
+    let area = edge_u.cross(edge_v).norm();
+    (
+        corner + edge_u * sample.x + edge_v * sample.y,
+        PDF::new(1.0 / area),
+    )
+}
+
+/// uniformly samples the disk at `center` with unit `normal` and `radius`
+pub fn sample_disk_area(
+    center: Point3,
+    normal: Vec3,
+    radius: f32,
+    sample: Sample2D,
+) -> (Point3, PDF<f32, Area>) {
+    let (x, y) = concentric_sample_disk(sample);
+    let frame = TangentFrame::from_normal(normal);
+    (
+        center + frame.to_world(&Vec3::new(x * radius, y * radius, 0.0)),
+        PDF::new(1.0 / (PI * radius * radius)),
+    )
+}
+
+/// samples the disk at `center` with unit `normal` and `radius` uniformly by area,
+/// returning the pdf of the direction from `point` towards the sampled point in solid angle.
+/// the pdf is zero if `point` lies in the plane of the disk.
+pub fn sample_disk_solid_angle(
+    point: Point3,
+    center: Point3,
+    normal: Vec3,
+    radius: f32,
+    sample: Sample2D,
+) -> (Point3, PDF<f32, SolidAngle>) {
+    let (sampled, pdf) = sample_disk_area(center, normal, radius, sample);
+    let offset = sampled - point;
+    let distance_squared = offset.norm_squared();
+    let cos_theta = normal * offset / distance_squared.sqrt();
+    if cos_theta == 0.0 {
+        return (sampled, PDF::new(0.0));
+    }
+    (sampled, PDF::new(*pdf * distance_squared / cos_theta.abs()))
+}
+
+// the angle between two unit vectors, accurate near 0 and pi
+fn angle_between(a: Vec3, b: Vec3) -> f32 {
+    if a * b < 0.0 {
+        PI - 2.0 * ((a + b).norm() / 2.0).min(1.0).asin()
+    } else {
+        2.0 * ((b - a).norm() / 2.0).min(1.0).asin()
+    }
+}
+
+/// A rectangle as seen from a point, for sampling directions uniformly within the solid angle that it subtends.
+/// follows Ureña, Fajardo and King, "An Area-Preserving Parametrization for Spherical Rectangles" (2013).
+#[derive(Copy, Clone, Debug)]
+pub struct SphericalRectangle {
+    point: Point3,
+    frame: TangentFrame,
+    x0: f32,
+    x1: f32,
+    y0: f32,
+    y1: f32,
+    z0: f32,
+    b0: f32,
+    b1: f32,
+    k: f32,
+    solid_angle: f32,
+}
+
+impl SphericalRectangle {
+    /// the rectangle with corner `corner` and perpendicular edges `edge_u` and `edge_v`, seen from `point`.
+    /// returns None if the rectangle subtends no solid angle, i.e. when `point` lies in its plane.
+    pub fn new(point: Point3, corner: Point3, edge_u: Vec3, edge_v: Vec3) -> Option<Self> {
+        let (length_u, length_v) = (edge_u.norm(), edge_v.norm());
+        let (x, y) = (edge_u / length_u, edge_v / length_v);
+        debug_assert!(
+            Tolerance::LOOSE.is_near_zero(x * y),
+            "rectangle edges must be perpendicular"
+        );
+        let mut frame = TangentFrame::new(x, y, x.cross(y));
+        let d = frame.to_local(&(corner - point));
+        let (x0, y0, mut z0) = (d.x(), d.y(), d.z());
+        // orient the frame such that the rectangle lies below the point
+        if z0 > 0.0 {
+            frame.normal = -frame.normal;
+            z0 = -z0;
+        }
+        if z0 == 0.0 {
+            return None;
+        }
+        let (x1, y1) = (x0 + length_u, y0 + length_v);
+
+        // the normals of the planes through the point and each edge, and the interior angles between them
+        let v00 = Vec3::new(x0, y0, z0);
+        let v01 = Vec3::new(x0, y1, z0);
+        let v10 = Vec3::new(x1, y0, z0);
+        let v11 = Vec3::new(x1, y1, z0);
+        let n0 = v00.cross(v10).normalized();
+        let n1 = v10.cross(v11).normalized();
+        let n2 = v11.cross(v01).normalized();
+        let n3 = v01.cross(v00).normalized();
+        let g0 = angle_between(-n0, n1);
+        let g1 = angle_between(-n1, n2);
+        let g2 = angle_between(-n2, n3);
+        let g3 = angle_between(-n3, n0);
+        let solid_angle = g0 + g1 + g2 + g3 - 2.0 * PI;
+        if solid_angle <= 0.0 {
+            return None;
+        }
+        Some(SphericalRectangle {
+            point,
+            frame,
+            x0,
+            x1,
+            y0,
+            y1,
+            z0,
+            b0: n0.z(),
+            b1: n2.z(),
+            k: 2.0 * PI - g2 - g3,
+            solid_angle,
+        })
+    }
+
+    pub fn solid_angle(&self) -> f32 {
+        self.solid_angle
+    }
+
+    /// the pdf of every direction towards the rectangle
+    pub fn pdf(&self) -> PDF<f32, SolidAngle> {
+        PDF::new(1.0 / self.solid_angle)
+    }
+
+    /// a point on the rectangle, such that the direction towards it is uniformly distributed in solid angle
+    pub fn sample(&self, sample: Sample2D) -> (Point3, PDF<f32, SolidAngle>) {
+        let (x0, x1, y0, y1, z0) = (self.x0, self.x1, self.y0, self.y1, self.z0);
+        // the angle au selects the plane through the point containing the sample, which hits the rectangle at x = xu
+        let au = sample.x * self.solid_angle + self.k;
+        let fu = (au.cos() * self.b0 - self.b1) / au.sin();
+        let cu = (1.0 / (fu * fu + self.b0 * self.b0).sqrt())
+            .copysign(fu)
+            .clamp(-1.0 + f32::EPSILON, 1.0 - f32::EPSILON);
+        let xu = (-(cu * z0) / (1.0 - cu * cu).max(0.0).sqrt()).clamp(x0, x1);
+
+        // then y is sampled such that the direction is uniform along the arc of that plane within the rectangle
+        let distance = (xu * xu + z0 * z0).sqrt();
+        let h0 = y0 / (distance * distance + y0 * y0).sqrt();
+        let h1 = y1 / (distance * distance + y1 * y1).sqrt();
+        let hv = h0 + sample.y * (h1 - h0);
+        let yv = if hv * hv < 1.0 - 1e-6 {
+            hv * distance / (1.0 - hv * hv).sqrt()
+        } else {
+            y1
+        };
+        (
+            self.point + self.frame.to_world(&Vec3::new(xu, yv, z0)),
+            self.pdf(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_light_sampling() {
+        let mut sampler = StreamRng::from_seed_u64(1);
+        let n = 20000;
+        let point = Point3::new(0.3, -0.2, 0.0);
+        let corner = Point3::new(-1.0, -0.5, 0.8);
+        let (edge_u, edge_v) = (Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.4));
+
+        // the solid angle agrees with the exact expression, and samples land on the rectangle
+        let rect = SphericalRectangle::new(point, corner, edge_u, edge_v).unwrap();
+        let exact = rect_solid_angle(point, corner, edge_u, edge_v);
+        assert!(
+            (rect.solid_angle() - exact).abs() < 1e-4 * exact,
+            "{} {}",
+            rect.solid_angle(),
+            exact
+        );
+        // directions are uniform in solid angle, so the fraction of samples in the half nearer to edge_u
+        // matches the fraction of the solid angle that it subtends
+        let half = rect_solid_angle(point, corner, edge_u, edge_v * 0.5);
+        let mut inside = 0;
+        // the cosine weighted integral over the light, estimated by both techniques
+        let normal = Vec3::Z;
+        let (mut uniform_estimate, mut area_estimate) = (0.0, 0.0);
+        for _ in 0..n {
+            let (p, pdf) = rect.sample(sampler.draw_2d());
+            let local = p - corner;
+            let (u, v) = (
+                local * edge_u / edge_u.norm_squared(),
+                local * edge_v / edge_v.norm_squared(),
+            );
+            assert!(
+                (-1e-4..1.0001).contains(&u) && (-1e-4..1.0001).contains(&v),
+                "{} {}",
+                u,
+                v
+            );
+            assert!((local - edge_u * u - edge_v * v).norm() < 1e-4);
+            if v < 0.5 {
+                inside += 1;
+            }
+            uniform_estimate += (p - point).normalized() * normal / *pdf;
+
+            let (p, pdf) = sample_rect_area(corner, edge_u, edge_v, sampler.draw_2d());
+            let offset = p - point;
+            let cos_light = edge_u.cross(edge_v).normalized() * offset.normalized();
+            let pdf = *pdf * offset.norm_squared() / cos_light.abs();
+            area_estimate += offset.normalized() * normal / pdf;
+        }
+        let fraction = inside as f32 / n as f32;
+        assert!(
+            (fraction - half / exact).abs() < 0.015,
+            "{} {}",
+            fraction,
+            half / exact
+        );
+        let (uniform_estimate, area_estimate) =
+            (uniform_estimate / n as f32, area_estimate / n as f32);
+        assert!(
+            (uniform_estimate - area_estimate).abs() < 0.02 * uniform_estimate,
+            "{} {}",
+            uniform_estimate,
+            area_estimate
+        );
+
+        // seen edge on, the rectangle subtends nothing, and from either side it subtends the same solid angle
+        assert!(SphericalRectangle::new(
+            Point3::new(0.0, 0.0, 0.8),
+            corner,
+            edge_u,
+            Vec3::new(0.0, 1.0, 0.0)
+        )
+        .is_none());
+        let behind =
+            SphericalRectangle::new(Point3::new(0.3, -0.2, 2.0), corner, edge_u, edge_v).unwrap();
+        let (p, _) = behind.sample(Sample2D::new(0.5, 0.5));
+        assert!((p - corner) * edge_u.cross(edge_v) < 1e-4);
+
+        // area sampling a disk, with the estimated solid angle matching the exact one
+        let center = Point3::new(0.0, 0.0, 1.0);
+        let disk_normal = Vec3::new(0.0, 0.6, -0.8);
+        let mut solid_angle = 0.0;
+        for _ in 0..n {
+            let (p, pdf) = sample_disk_area(center, disk_normal, 0.5, sampler.draw_2d());
+            assert!(((p - center) * disk_normal).abs() < 1e-5);
+            assert!((p - center).norm() <= 0.5 + 1e-5);
+            assert!((*pdf - 1.0 / (PI * 0.25)).abs() < 1e-5);
+            let (_, pdf) =
+                sample_disk_solid_angle(point, center, disk_normal, 0.5, sampler.draw_2d());
+            solid_angle += 1.0 / *pdf;
+        }
+        let exact = disk_solid_angle(point, center, disk_normal, 0.5);
+        assert!(
+            (solid_angle / n as f32 - exact).abs() < 0.02 * exact,
+            "{} {}",
+            solid_angle / n as f32,
+            exact
+        );
+    }
+}
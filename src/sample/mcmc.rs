@@ -0,0 +1,409 @@
+use crate::error::{MathError, Result};
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// metropolis-hastings sampling over a Bounds1D or Bounds2D, for densities that are only known pointwise.
+// the chain is stationary wrt the target without needing its integral or cdf, at the cost of correlated samples,
+// so results should be judged by the effective sample size rather than the raw sample count.
+
+// the number of uniform proposals tried per walker when looking for a starting point where the target is nonzero
+const INITIALIZATION_TRIES: usize = 1024;
+
+/// A domain that a Metropolis chain can walk over
+pub trait MarkovDomain: Copy {
+    type Point: Copy;
+    const DIMENSION: usize;
+
+    fn uniform<S: Sampler + ?Sized>(&self, sampler: &mut S) -> Self::Point;
+    fn contains_point(&self, point: Self::Point) -> bool;
+    /// offsets `point` by `delta`, given in units of the span along each axis
+    fn perturb(&self, point: Self::Point, delta: [f32; 2]) -> Self::Point;
+    /// `anchor + z * (point - anchor)`
+    fn stretch(&self, anchor: Self::Point, point: Self::Point, z: f32) -> Self::Point;
+}
+
+impl MarkovDomain for Bounds1D {
+    type Point = f32;
+    const DIMENSION: usize = 1;
+
+    fn uniform<S: Sampler + ?Sized>(&self, sampler: &mut S) -> f32 {
+        self.sample(sampler.draw_1d().x)
+    }
+    fn contains_point(&self, point: f32) -> bool {
+        self.contains(&point)
+    }
+    fn perturb(&self, point: f32, delta: [f32; 2]) -> f32 {
+        point + delta[0] * self.span()
+    }
+    fn stretch(&self, anchor: f32, point: f32, z: f32) -> f32 {
+        anchor + z * (point - anchor)
+    }
+}
+
+impl MarkovDomain for Bounds2D {
+    type Point = (f32, f32);
+    const DIMENSION: usize = 2;
+
+    fn uniform<S: Sampler + ?Sized>(&self, sampler: &mut S) -> (f32, f32) {
+        let s = sampler.draw_2d();
+        self.sample(s.x, s.y)
+    }
+    fn contains_point(&self, point: (f32, f32)) -> bool {
+        self.contains(point)
+    }
+    fn perturb(&self, point: (f32, f32), delta: [f32; 2]) -> (f32, f32) {
+        (
+            point.0 + delta[0] * self.x.span(),
+            point.1 + delta[1] * self.y.span(),
+        )
+    }
+    fn stretch(&self, anchor: (f32, f32), point: (f32, f32), z: f32) -> (f32, f32) {
+        (
+            anchor.0 + z * (point.0 - anchor.0),
+            anchor.1 + z * (point.1 - anchor.1),
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum MutationKernel {
+    /// independent proposals, uniform over the domain. mixes well for broad targets but rarely hits narrow peaks.
+    Uniform,
+    /// a random walk with a gaussian step of standard deviation `sigma`, as a fraction of the span along each axis
+    Gaussian { sigma: f32 },
+    /// the affine invariant stretch move of Goodman and Weare, which moves each walker along the line through another walker.
+    /// `a` > 1 controls the range of the stretch, and 2 is the usual choice. requires at least 2 walkers.
+    Stretch { a: f32 },
+}
+
+/// A set of Metropolis chains (walkers) over a domain
+#[derive(Clone, Debug)]
+pub struct Metropolis<D: MarkovDomain> {
+    pub domain: D,
+    pub kernel: MutationKernel,
+    walkers: Vec<D::Point>,
+    values: Vec<f32>,
+    pub proposals: usize,
+    pub acceptances: usize,
+}
+
+impl<D: MarkovDomain> Metropolis<D> {
+    /// starts `walkers` chains at uniformly distributed points where `target` is positive.
+    /// `target` is the unnormalized density, and is treated as 0 outside of `domain`.
+    /// fails with `ZeroDensity` if no positive point is found for a walker within a fixed number of tries.
+    pub fn new<S: Sampler + ?Sized>(
+        domain: D,
+        kernel: MutationKernel,
+        walkers: usize,
+        target: impl Fn(D::Point) -> f32,
+        sampler: &mut S,
+    ) -> Result<Self> {
+        if walkers == 0 || matches!(kernel, MutationKernel::Stretch { .. }) && walkers < 2 {
+            return Err(MathError::UnsupportedLength(walkers));
+        }
+        let mut points = Vec::with_capacity(walkers);
+        let mut values = Vec::with_capacity(walkers);
+        for _ in 0..walkers {
+            let start = (0..INITIALIZATION_TRIES)
+                .map(|_| {
+                    let point = domain.uniform(sampler);
+                    (point, target(point))
+                })
+                .find(|(_, value)| *value > 0.0)
+                .ok_or(MathError::ZeroDensity(INITIALIZATION_TRIES))?;
+            points.push(start.0);
+            values.push(start.1);
+        }
+        Ok(Metropolis {
+            domain,
+            kernel,
+            walkers: points,
+            values,
+            proposals: 0,
+            acceptances: 0,
+        })
+    }
+
+    pub fn walkers(&self) -> &[D::Point] {
+        &self.walkers
+    }
+
+    pub fn acceptance_rate(&self) -> f32 {
+        if self.proposals == 0 {
+            return 0.0;
+        }
+        self.acceptances as f32 / self.proposals as f32
+    }
+
+    /// proposes a mutation for every walker once, in order
+    pub fn step<S: Sampler + ?Sized>(&mut self, target: impl Fn(D::Point) -> f32, sampler: &mut S) {
+        let n = self.walkers.len();
+        for j in 0..n {
+            let current = self.walkers[j];
+            // the proposal and the ratio of proposal densities q(x | y) / q(y | x)
+            let (proposal, proposal_ratio) = match self.kernel {
+                MutationKernel::Uniform => (self.domain.uniform(sampler), 1.0),
+                MutationKernel::Gaussian { sigma } => {
+                    // box-muller
+                    let s = sampler.draw_2d();
+                    let r = sigma * (-2.0 * (1.0 - s.x).ln()).sqrt();
+                    let (sin, cos) = (2.0 * PI * s.y).sin_cos();
+                    (self.domain.perturb(current, [r * cos, r * sin]), 1.0)
+                }
+                MutationKernel::Stretch { a } => {
+                    let s = sampler.draw_2d();
+                    // choose another walker uniformly
+                    let k = (j + 1 + ((s.x * (n - 1) as f32) as usize).min(n - 2)) % n;
+                    // z ~ g(z) ∝ 1 / sqrt(z) on [1 / a, a]
+                    let z = ((a - 1.0) * s.y + 1.0).powi(2) / a;
+                    (
+                        self.domain.stretch(self.walkers[k], current, z),
+                        z.powi(D::DIMENSION as i32 - 1),
+                    )
+                }
+            };
+            self.proposals += 1;
+            if !self.domain.contains_point(proposal) {
+                continue;
+            }
+            let value = target(proposal);
+            let acceptance = proposal_ratio * value / self.values[j];
+            if sampler.draw_1d().x < acceptance {
+                self.walkers[j] = proposal;
+                self.values[j] = value;
+                self.acceptances += 1;
+            }
+        }
+    }
+
+    /// advances the chains by `sweeps` steps and discards them, so that the walkers forget their starting points.
+    /// the acceptance statistics are reset afterwards, such that they only reflect the stationary chain.
+    pub fn burn_in<S: Sampler + ?Sized>(
+        &mut self,
+        sweeps: usize,
+        target: impl Fn(D::Point) -> f32,
+        sampler: &mut S,
+    ) {
+        for _ in 0..sweeps {
+            self.step(&target, sampler);
+        }
+        self.proposals = 0;
+        self.acceptances = 0;
+    }
+
+    /// advances the chains by `sweeps` steps, recording every walker after every step
+    pub fn run<S: Sampler + ?Sized>(
+        &mut self,
+        sweeps: usize,
+        target: impl Fn(D::Point) -> f32,
+        sampler: &mut S,
+    ) -> Chain<D::Point> {
+        let mut samples = Vec::with_capacity(sweeps * self.walkers.len());
+        for _ in 0..sweeps {
+            self.step(&target, sampler);
+            samples.extend_from_slice(&self.walkers);
+        }
+        Chain {
+            samples,
+            walkers: self.walkers.len(),
+        }
+    }
+}
+
+/// The recorded states of a set of walkers, ordered by step and then by walker
+#[derive(Clone, Debug)]
+pub struct Chain<P> {
+    pub samples: Vec<P>,
+    pub walkers: usize,
+}
+
+impl<P: Copy> Chain<P> {
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+    /// the states of a single walker, in order
+    pub fn walker(&self, index: usize) -> impl Iterator<Item = P> + '_ {
+        self.samples
+            .iter()
+            .skip(index)
+            .step_by(self.walkers)
+            .copied()
+    }
+    /// the average of `f` over all recorded states
+    pub fn mean(&self, f: impl Fn(P) -> f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().map(|p| f(*p)).sum::<f32>() / self.samples.len() as f32
+    }
+    /// the effective sample size of the estimate of the mean of `f`, summed over the walkers
+    pub fn effective_sample_size(&self, f: impl Fn(P) -> f32) -> f32 {
+        (0..self.walkers)
+            .map(|index| {
+                let series: Vec<f32> = self.walker(index).map(&f).collect();
+                effective_sample_size(&series)
+            })
+            .sum()
+    }
+}
+
+/// the number of independent samples that would estimate the mean of `series` as well as it does,
+/// `n / (1 + 2 sum(autocorrelation))`. the autocorrelation sum is truncated with Geyer's initial positive sequence.
+pub fn effective_sample_size(series: &[f32]) -> f32 {
+    let n = series.len();
+    if n < 2 {
+        return n as f32;
+    }
+    let mean = series.iter().sum::<f32>() / n as f32;
+    let autocovariance = |lag: usize| {
+        series
+            .iter()
+            .zip(&series[lag..])
+            .map(|(a, b)| (a - mean) * (b - mean))
+            .sum::<f32>()
+            / n as f32
+    };
+    let variance = autocovariance(0);
+    if variance <= 0.0 {
+        return n as f32;
+    }
+    // sum pairs of consecutive autocorrelations while they stay positive
+    let mut tau = -1.0;
+    let mut lag = 0;
+    while lag + 1 < n {
+        let pair = (autocovariance(lag) + autocovariance(lag + 1)) / variance;
+        if pair <= 0.0 {
+            break;
+        }
+        tau += 2.0 * pair;
+        lag += 2;
+    }
+    n as f32 / tau.max(f32::EPSILON)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_metropolis() {
+        // a strongly peaked emission spectrum, sampled by mcmc and by inverting its cdf
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let emission = Curve::Exponential {
+            signal: vec![(545.0, 4.0, 6.0, 1.0), (611.0, 3.0, 3.0, 0.5)],
+        };
+        let cdf = emission.to_cdf(bounds, 4000).unwrap();
+        let n = 20000;
+        let mut reference = Vec::with_capacity(n);
+        for i in 0..n {
+            let u = (i as f32 + 0.5) / n as f32;
+            reference.push(cdf.invert(bounds, u).unwrap().0);
+        }
+        let reference_mean = reference.iter().sum::<f32>() / n as f32;
+        let reference_deviation = (reference
+            .iter()
+            .map(|x| (x - reference_mean).powi(2))
+            .sum::<f32>()
+            / n as f32)
+            .sqrt();
+        let below_580 = reference.iter().filter(|x| **x < 580.0).count() as f32 / n as f32;
+
+        let mut sampler = RandomSampler::new();
+        crate::random::scoped_seed(5, || {
+            let target = |x: f32| emission.evaluate(x);
+            for kernel in [
+                MutationKernel::Gaussian { sigma: 0.1 },
+                MutationKernel::Uniform,
+            ] {
+                let mut metropolis =
+                    Metropolis::new(bounds, kernel, 8, target, &mut sampler).unwrap();
+                metropolis.burn_in(500, target, &mut sampler);
+                let chain = metropolis.run(n / 8, target, &mut sampler);
+                assert_eq!(chain.len(), n);
+                // independent proposals rarely land on the peaks, but every accepted one decorrelates the chain
+                let rate = metropolis.acceptance_rate();
+                assert!(rate > 0.02 && rate < 0.95, "{:?} {}", kernel, rate);
+
+                let ess = chain.effective_sample_size(|x| x);
+                assert!(ess > 100.0 && ess < n as f32, "{:?} {}", kernel, ess);
+                // the error of the mean shrinks with the effective, not the raw, sample count
+                let mean = chain.mean(|x| x);
+                let error = 5.0 * reference_deviation / ess.sqrt();
+                assert!(
+                    (mean - reference_mean).abs() < error,
+                    "{:?} {} {}",
+                    kernel,
+                    mean,
+                    reference_mean
+                );
+                let fraction = chain.mean(|x| if x < 580.0 { 1.0 } else { 0.0 });
+                assert!(
+                    (fraction - below_580).abs() < 0.05,
+                    "{:?} {} {}",
+                    kernel,
+                    fraction,
+                    below_580
+                );
+            }
+
+            // a separable 2d target, whose marginal means are known, walked by stretching between walkers
+            let domain = Bounds2D::new(Bounds1D::new(0.0, 1.0), Bounds1D::new(0.0, 2.0));
+            let target = |(x, y): (f32, f32)| x * x * (2.0 - y);
+            let mut metropolis = Metropolis::new(
+                domain,
+                MutationKernel::Stretch { a: 2.0 },
+                16,
+                target,
+                &mut sampler,
+            )
+            .unwrap();
+            metropolis.burn_in(100, target, &mut sampler);
+            let chain = metropolis.run(2000, target, &mut sampler);
+            // E[x] = 3/4 for x^2 on [0, 1] and E[y] = 2/3 for 2 - y on [0, 2]
+            assert!((chain.mean(|p| p.0) - 0.75).abs() < 0.02);
+            assert!((chain.mean(|p| p.1) - 2.0 / 3.0).abs() < 0.04);
+        });
+
+        // the stretch move needs a partner, and a target that is 0 everywhere has nowhere to start
+        assert!(Metropolis::new(
+            bounds,
+            MutationKernel::Stretch { a: 2.0 },
+            1,
+            |_| 1.0,
+            &mut sampler
+        )
+        .is_err());
+        assert!(matches!(
+            Metropolis::new(bounds, MutationKernel::Uniform, 1, |_| 0.0, &mut sampler),
+            Err(MathError::ZeroDensity(INITIALIZATION_TRIES))
+        ));
+
+        // an ar(1) process with coefficient rho has an effective sample size of about n (1 - rho) / (1 + rho)
+        let mut x = 0.0;
+        let series: Vec<f32> = crate::random::scoped_seed(6, || {
+            (0..n)
+                .map(|_| {
+                    x = 0.9 * x + debug_random() - 0.5;
+                    x
+                })
+                .collect()
+        });
+        let ess = effective_sample_size(&series);
+        let expected = n as f32 * 0.1 / 1.9;
+        assert!(
+            (ess - expected).abs() < 0.3 * expected,
+            "{} {}",
+            ess,
+            expected
+        );
+        let independent: Vec<f32> =
+            crate::random::scoped_seed(7, || (0..n).map(|_| debug_random()).collect());
+        assert!(effective_sample_size(&independent) > 0.7 * n as f32);
+    }
+}
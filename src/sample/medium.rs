@@ -0,0 +1,215 @@
+use crate::prelude::*;
+
+// sampling of homogeneous participating media with wavelength dependent coefficients and phase functions,
+// i.e. colored media like juice, skin and atmosphere.
+// with hero wavelength spectral sampling, distances and directions are sampled with the parameters of the hero lane,
+// and every lane reports its own pdf for the same sample, so that the lanes can be combined with MIS.
+// directions are given as the direction of propagation, so g > 0 scatters forward.
+
+// the largest |g| that the phase function is evaluated with, which keeps it finite
+const MAX_ASYMMETRY: f32 = 0.999;
+
+/// the Henyey-Greenstein phase function, for the angle theta between the incident and scattered directions of propagation
+pub fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let g = g.clamp(-MAX_ASYMMETRY, MAX_ASYMMETRY);
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denominator * denominator.max(0.0).sqrt())
+}
+
+/// samples a scattered direction of propagation from the Henyey-Greenstein phase function around `direction`.
+/// the pdf in solid angle is `henyey_greenstein(cos_theta, g)`.
+pub fn sample_henyey_greenstein(direction: Vec3, g: f32, sample: Sample2D) -> Vec3 {
+    let g = g.clamp(-MAX_ASYMMETRY, MAX_ASYMMETRY);
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * sample.x
+    } else {
+        let s = (1.0 - g * g) / (1.0 + g - 2.0 * g * sample.x);
+        ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+    };
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (2.0 * PI * sample.y).sin_cos();
+    TangentFrame::from_normal(direction).to_world(&Vec3::new(
+        sin_theta * cos_phi,
+        sin_theta * sin_phi,
+        cos_theta,
+    ))
+}
+
+/// A homogeneous medium with absorption and scattering coefficients and a Henyey-Greenstein asymmetry g
+/// that vary with wavelength. wavelengths are given as packets with the hero wavelength in the first lane.
+/// `g` is evaluated without clamping to 0, so curves such as `Curve::Const(0.3).affine(-1.0, 0.0)`
+/// describe backward scattering.
+#[derive(Copy, Clone, Debug)]
+pub struct SpectralMedium<'a> {
+    pub sigma_a: &'a Curve,
+    pub sigma_s: &'a Curve,
+    pub g: &'a Curve,
+}
+
+impl<'a> SpectralMedium<'a> {
+    pub fn new(sigma_a: &'a Curve, sigma_s: &'a Curve, g: &'a Curve) -> Self {
+        SpectralMedium {
+            sigma_a,
+            sigma_s,
+            g,
+        }
+    }
+
+    /// the extinction coefficient sigma_a + sigma_s
+    pub fn sigma_t(&self, lambda: f32x4) -> f32x4 {
+        f32x4::from_array(std::array::from_fn(|i| {
+            self.sigma_a.evaluate_power(lambda[i]) + self.sigma_s.evaluate_power(lambda[i])
+        }))
+    }
+
+    /// the single scattering albedo sigma_s / sigma_t, which is 0 where the medium doesn't interact at all
+    pub fn albedo(&self, lambda: f32x4) -> f32x4 {
+        let sigma_t = self.sigma_t(lambda);
+        f32x4::from_array(std::array::from_fn(|i| {
+            if sigma_t[i] > 0.0 {
+                self.sigma_s.evaluate_power(lambda[i]) / sigma_t[i]
+            } else {
+                0.0
+            }
+        }))
+    }
+
+    pub fn asymmetry(&self, lambda: f32x4) -> f32x4 {
+        f32x4::from_array(std::array::from_fn(|i| self.g.evaluate(lambda[i])))
+    }
+
+    /// the fraction of light that travels `distance` without interacting
+    pub fn transmittance(&self, lambda: f32x4, distance: f32) -> f32x4 {
+        let optical_depth = self.sigma_t(lambda) * f32x4::splat(distance);
+        f32x4::from_array(optical_depth.to_array().map(|tau| (-tau).exp()))
+    }
+
+    /// samples the distance to the next interaction proportionally to the hero lane's transmittance,
+    /// returning it along with the pdf of that distance for every lane, sigma_t exp(-sigma_t t).
+    /// returns None if the hero lane doesn't interact with the medium.
+    pub fn sample_distance(
+        &self,
+        lambda: f32x4,
+        sample: Sample1D,
+    ) -> Option<(f32, PDF<f32x4, Length>)> {
+        let sigma_t = self.sigma_t(lambda);
+        if sigma_t[0] <= 0.0 {
+            return None;
+        }
+        let distance = -(1.0 - sample.x).max(f32::MIN_POSITIVE).ln() / sigma_t[0];
+        Some((
+            distance,
+            PDF::new(sigma_t * self.transmittance(lambda, distance)),
+        ))
+    }
+
+    /// the phase function of every lane, for scattering from `direction` into `scattered`
+    pub fn phase(&self, lambda: f32x4, direction: Vec3, scattered: Vec3) -> f32x4 {
+        let cos_theta = direction * scattered;
+        let g = self.asymmetry(lambda);
+        f32x4::from_array(g.to_array().map(|g| henyey_greenstein(cos_theta, g)))
+    }
+
+    /// samples a scattered direction with the hero lane's phase function, returning the pdf of that direction
+    /// for every lane. since the phase function is sampled exactly, each lane's pdf equals its phase function.
+    pub fn sample_phase(
+        &self,
+        lambda: f32x4,
+        direction: Vec3,
+        sample: Sample2D,
+    ) -> (Vec3, PDF<f32x4, SolidAngle>) {
+        let g = self.g.evaluate(lambda[0]);
+        let scattered = sample_henyey_greenstein(direction, g, sample);
+        (
+            scattered,
+            PDF::new(self.phase(lambda, direction, scattered)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+    #[test]
+    fn test_spectral_medium() {
+        // the phase function is normalized, and its mean cosine is g
+        for g in [-0.7, 0.0, 0.4, 0.95] {
+            let n = 20000;
+            let (mut integral, mut mean_cosine) = (0.0, 0.0);
+            for i in 0..n {
+                let cos_theta = -1.0 + 2.0 * (i as f32 + 0.5) / n as f32;
+                let p = henyey_greenstein(cos_theta, g) * 2.0 * PI * 2.0 / n as f32;
+                integral += p;
+                mean_cosine += p * cos_theta;
+            }
+            assert!((integral - 1.0).abs() < 1e-3, "{} {}", g, integral);
+            assert!((mean_cosine - g).abs() < 1e-3, "{} {}", g, mean_cosine);
+        }
+
+        // juice-like: absorbing blue, forward scattering more at short wavelengths
+        let sigma_a = Curve::Linear {
+            signal: vec![2.0, 1.0, 0.2, 0.1],
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Linear,
+        };
+        let sigma_s = Curve::Const(1.5);
+        let g = Curve::Linear {
+            signal: vec![0.9, 0.6, 0.3, -0.2],
+            bounds: BOUNDED_VISIBLE_RANGE,
+            mode: InterpolationMode::Linear,
+        };
+        let medium = SpectralMedium::new(&sigma_a, &sigma_s, &g);
+        let lambda = HeroWavelength::new_from_range(0.1, BOUNDED_VISIBLE_RANGE).lambda;
+        let asymmetry = medium.asymmetry(lambda);
+        assert!(asymmetry.to_array().iter().any(|g| *g < 0.0));
+
+        // the mean scattered cosine of the hero lane matches its g, and pdfs match the per-lane phase functions
+        let mut sampler = StreamRng::from_seed_u64(3);
+        let direction = Vec3::new(0.0, 0.6, 0.8);
+        let n = 20000;
+        let mut mean_cosine = 0.0;
+        for _ in 0..n {
+            let (scattered, pdf) = medium.sample_phase(lambda, direction, sampler.draw_2d());
+            assert!((scattered.norm() - 1.0).abs() < 1e-4);
+            let cos_theta = scattered * direction;
+            mean_cosine += cos_theta;
+            for i in 0..4 {
+                let expected = henyey_greenstein(cos_theta, asymmetry[i]);
+                assert!(((*pdf)[i] - expected).abs() <= 1e-3 * expected.max(1.0));
+            }
+        }
+        assert!(
+            (mean_cosine / n as f32 - asymmetry[0]).abs() < 0.02,
+            "{} {}",
+            mean_cosine / n as f32,
+            asymmetry[0]
+        );
+
+        // sampled distances follow the hero lane's transmittance, with a mean free path of 1 / sigma_t
+        let sigma_t = medium.sigma_t(lambda);
+        let mut mean_distance = 0.0;
+        for _ in 0..n {
+            let (distance, pdf) = medium.sample_distance(lambda, sampler.draw_1d()).unwrap();
+            mean_distance += distance;
+            let transmittance = medium.transmittance(lambda, distance);
+            for i in 0..4 {
+                assert!(((*pdf)[i] - sigma_t[i] * transmittance[i]).abs() < 1e-5);
+            }
+        }
+        assert!(
+            (mean_distance / n as f32 * sigma_t[0] - 1.0).abs() < 0.03,
+            "{}",
+            mean_distance / n as f32
+        );
+        let albedo = medium.albedo(lambda);
+        assert!((albedo[0] - 1.5 / sigma_t[0]).abs() < 1e-6);
+
+        // a vacuum can't be sampled
+        let zero = Curve::Const(0.0);
+        assert!(SpectralMedium::new(&zero, &zero, &g)
+            .sample_distance(lambda, Sample1D::new(0.5))
+            .is_none());
+    }
+}
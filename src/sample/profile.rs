@@ -0,0 +1,216 @@
+use crate::prelude::*;
+
+use std::collections::BTreeMap;
+
+// instrumentation for sampler usage.
+// low discrepancy samplers assign each draw within a sample to a dimension of the sequence, in order,
+// so a decision has to consume the same dimensions in every sample to benefit from stratification.
+// when a variable number of draws precedes a decision, i.e. after a path of variable length,
+// its dimensions shift and alias with those of other decisions, which silently degrades convergence.
+
+/// how many draws of each kind a labeled region consumed, summed over all samples
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegionUsage {
+    pub draws_1d: usize,
+    pub draws_2d: usize,
+    pub draws_3d: usize,
+    /// the total number of dimensions consumed
+    pub dimensions: usize,
+    /// the number of samples in which the region made at least one draw
+    pub samples: usize,
+}
+
+/// A dimension that was used by different regions, or by draws of different kinds, in different samples
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DimensionAliasing {
+    pub dimension: usize,
+    /// the region and draw size (1, 2 or 3) that first used the dimension
+    pub first: (&'static str, usize),
+    /// the region and draw size that later used it differently
+    pub second: (&'static str, usize),
+}
+
+/// Wraps a sampler, recording the draws made within each labeled region and detecting dimension aliasing.
+/// call `start_sample` at the beginning of each sample (i.e. each camera path) and `set_region` before each decision.
+pub struct ProfilingSampler<S: Sampler> {
+    inner: S,
+    region: &'static str,
+    dimension: usize,
+    started: bool,
+    regions_in_sample: Vec<&'static str>,
+    usage: BTreeMap<&'static str, RegionUsage>,
+    // the region and draw size that first used each dimension, along with the draw's first dimension
+    dimension_owners: Vec<(&'static str, usize, usize)>,
+    aliasing: Vec<DimensionAliasing>,
+}
+
+impl<S: Sampler> ProfilingSampler<S> {
+    pub const UNLABELED: &'static str = "unlabeled";
+
+    pub fn new(inner: S) -> Self {
+        ProfilingSampler {
+            inner,
+            region: Self::UNLABELED,
+            dimension: 0,
+            started: false,
+            regions_in_sample: Vec::new(),
+            usage: BTreeMap::new(),
+            dimension_owners: Vec::new(),
+            aliasing: Vec::new(),
+        }
+    }
+
+    /// starts a new sample, resetting the dimension counter and the region label
+    pub fn start_sample(&mut self) {
+        self.dimension = 0;
+        self.region = Self::UNLABELED;
+        self.regions_in_sample.clear();
+        self.started = true;
+    }
+
+    /// attributes subsequent draws to `label`
+    pub fn set_region(&mut self, label: &'static str) {
+        self.region = label;
+    }
+
+    /// the number of dimensions consumed so far in the current sample
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn usage(&self, label: &str) -> Option<&RegionUsage> {
+        self.usage.get(label)
+    }
+
+    /// the usage of every region that made a draw, ordered by label
+    pub fn regions(&self) -> impl Iterator<Item = (&'static str, &RegionUsage)> {
+        self.usage.iter().map(|(label, usage)| (*label, usage))
+    }
+
+    /// every detected aliasing, at most once per dimension and pair of (region, draw size)
+    pub fn aliasing(&self) -> &[DimensionAliasing] {
+        &self.aliasing
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn record(&mut self, size: usize) {
+        debug_assert!(self.started, "call start_sample before drawing");
+        let region = self.region;
+        let usage = self.usage.entry(region).or_default();
+        match size {
+            1 => usage.draws_1d += 1,
+            2 => usage.draws_2d += 1,
+            _ => usage.draws_3d += 1,
+        }
+        usage.dimensions += size;
+        if !self.regions_in_sample.contains(&region) {
+            self.regions_in_sample.push(region);
+            usage.samples += 1;
+        }
+
+        let start = self.dimension;
+        for dimension in start..start + size {
+            match self.dimension_owners.get(dimension) {
+                None => self.dimension_owners.push((region, size, start)),
+                Some(&(owner, owner_size, owner_start)) => {
+                    // the same region making the same draw, aligned the same way, is the expected case
+                    if owner == region && owner_size == size && owner_start == start {
+                        continue;
+                    }
+                    let aliasing = DimensionAliasing {
+                        dimension,
+                        first: (owner, owner_size),
+                        second: (region, size),
+                    };
+                    if !self.aliasing.contains(&aliasing) {
+                        self.aliasing.push(aliasing);
+                    }
+                }
+            }
+        }
+        self.dimension += size;
+    }
+}
+
+impl<S: Sampler> Sampler for ProfilingSampler<S> {
+    fn draw_1d(&mut self) -> Sample1D {
+        self.record(1);
+        self.inner.draw_1d()
+    }
+    fn draw_2d(&mut self) -> Sample2D {
+        self.record(2);
+        self.inner.draw_2d()
+    }
+    fn draw_3d(&mut self) -> Sample3D {
+        self.record(3);
+        self.inner.draw_3d()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_profiling_sampler() {
+        let mut sampler = ProfilingSampler::new(RandomSampler::new());
+        // a fixed sequence of decisions never aliases
+        for _ in 0..10 {
+            sampler.start_sample();
+            sampler.set_region("camera");
+            sampler.draw_2d();
+            sampler.set_region("light");
+            sampler.draw_1d();
+            sampler.draw_2d();
+        }
+        assert!(sampler.aliasing().is_empty());
+        assert_eq!(
+            sampler.usage("light"),
+            Some(&RegionUsage {
+                draws_1d: 10,
+                draws_2d: 10,
+                draws_3d: 0,
+                dimensions: 30,
+                samples: 10,
+            })
+        );
+        assert_eq!(sampler.dimension(), 5);
+
+        // a path of variable length shifts the light dimensions into those of the bounces
+        for bounces in 0..3 {
+            sampler.start_sample();
+            sampler.set_region("camera");
+            sampler.draw_2d();
+            sampler.set_region("bsdf");
+            for _ in 0..bounces {
+                sampler.draw_3d();
+            }
+            sampler.set_region("light");
+            sampler.draw_1d();
+            sampler.draw_2d();
+        }
+        let aliasing = sampler.aliasing();
+        assert!(!aliasing.is_empty());
+        assert_eq!(aliasing[0].dimension, 2);
+        assert_eq!(aliasing[0].first, ("light", 1));
+        assert_eq!(aliasing[0].second, ("bsdf", 3));
+        assert!(aliasing
+            .iter()
+            .all(|a| a.first.0 != "camera" && a.second.0 != "camera"));
+        assert_eq!(sampler.usage("bsdf").unwrap().samples, 2);
+        assert_eq!(
+            sampler
+                .regions()
+                .map(|(label, _)| label)
+                .collect::<Vec<_>>(),
+            vec!["bsdf", "camera", "light"]
+        );
+    }
+}
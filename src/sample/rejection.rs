@@ -0,0 +1,167 @@
+use crate::prelude::*;
+
+use std::ops::AddAssign;
+
+// rejection sampling, for distributions without a tractable cdf, i.e. products of curves or procedural spectra.
+// a proposal q is sampled and accepted with probability f(x) / (M q(x)), where the majorant M bounds f / q.
+// accepted samples are distributed proportionally to f, and each one takes M / integral(f) proposals on average.
+
+/// An accepted sample, with the target pdf at it and the number of proposals it took, including the accepted one
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RejectionSample<T> {
+    pub value: T,
+    pub pdf: f32,
+    pub tries: usize,
+}
+
+/// Acceptance statistics, which can be accumulated over many calls to `rejection_sample`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RejectionStatistics {
+    pub proposals: usize,
+    pub acceptances: usize,
+    /// the number of proposals where f(x) > M q(x), which bias the result towards the proposal.
+    /// should be 0 for a valid majorant.
+    pub majorant_violations: usize,
+}
+
+impl RejectionStatistics {
+    pub fn acceptance_rate(&self) -> f32 {
+        if self.proposals == 0 {
+            return 0.0;
+        }
+        self.acceptances as f32 / self.proposals as f32
+    }
+    /// estimates the integral of the target, which is the acceptance rate times the majorant
+    /// when the proposal pdf is normalized. useful for normalizing targets that aren't.
+    pub fn estimated_normalization(&self, majorant: f32) -> f32 {
+        self.acceptance_rate() * majorant
+    }
+}
+
+impl AddAssign for RejectionStatistics {
+    fn add_assign(&mut self, rhs: Self) {
+        self.proposals += rhs.proposals;
+        self.acceptances += rhs.acceptances;
+        self.majorant_violations += rhs.majorant_violations;
+    }
+}
+
+/// draws proposals from `proposal`, which returns a sample and its pdf, until one is accepted or `max_tries` is reached.
+/// `target_pdf` is the density to sample, which must satisfy target_pdf(x) <= `majorant` * q(x) everywhere.
+/// if it isn't normalized, the returned pdf isn't either, see `RejectionStatistics::estimated_normalization`.
+/// returns None if every proposal was rejected, along with the statistics of this call.
+pub fn rejection_sample<T, S: Sampler + ?Sized>(
+    mut proposal: impl FnMut(&mut S) -> (T, f32),
+    target_pdf: impl Fn(&T) -> f32,
+    majorant: f32,
+    sampler: &mut S,
+    max_tries: usize,
+) -> (Option<RejectionSample<T>>, RejectionStatistics) {
+    debug_assert!(majorant > 0.0);
+    let mut statistics = RejectionStatistics::default();
+    for tries in 1..=max_tries {
+        let (value, proposal_pdf) = proposal(sampler);
+        statistics.proposals += 1;
+        if proposal_pdf <= 0.0 {
+            continue;
+        }
+        let pdf = target_pdf(&value);
+        let acceptance = pdf / (majorant * proposal_pdf);
+        if acceptance > 1.0 {
+            statistics.majorant_violations += 1;
+        }
+        if sampler.draw_1d().x < acceptance {
+            statistics.acceptances += 1;
+            return (Some(RejectionSample { value, pdf, tries }), statistics);
+        }
+    }
+    (None, statistics)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::curves::Op;
+
+    #[test]
+    fn test_rejection_sampling() {
+        // the product of two curves, which has no cdf of its own
+        let bounds = Bounds1D::new(400.0, 700.0);
+        let product = Curve::Machine {
+            seed: 1.0,
+            list: vec![
+                (
+                    Op::Mul,
+                    Curve::Exponential {
+                        signal: vec![(500.0, 30.0, 30.0, 1.0), (620.0, 20.0, 40.0, 0.6)],
+                    },
+                ),
+                (Op::Mul, Curve::Const(0.8)),
+            ],
+        };
+        let uniform = |sampler: &mut RandomSampler| {
+            (
+                bounds.lower + sampler.draw_1d().x * bounds.span(),
+                1.0 / bounds.span(),
+            )
+        };
+        // the product is at most 0.8, so f / q is at most 0.8 * span
+        let majorant = 0.8 * bounds.span();
+        let mut sampler = RandomSampler::new();
+        let mut statistics = RejectionStatistics::default();
+        let n = 20000;
+        let bins = 10;
+        let mut histogram = vec![0usize; bins];
+        crate::random::scoped_seed(11, || {
+            for _ in 0..n {
+                let (sample, call) = rejection_sample(
+                    uniform,
+                    |x| product.evaluate(*x),
+                    majorant,
+                    &mut sampler,
+                    1000,
+                );
+                statistics += call;
+                let sample = sample.unwrap();
+                assert_eq!(sample.pdf, product.evaluate(sample.value));
+                let bin = ((sample.value - bounds.lower) / bounds.span() * bins as f32) as usize;
+                histogram[bin.min(bins - 1)] += 1;
+            }
+        });
+        assert_eq!(statistics.acceptances, n);
+        assert_eq!(statistics.majorant_violations, 0);
+
+        // accepted samples follow the normalized target, and the acceptance rate estimates its integral
+        let integral = product.evaluate_integral(bounds, 3000, false);
+        let normalization = statistics.estimated_normalization(majorant);
+        assert!(
+            (normalization - integral).abs() < 0.03 * integral,
+            "{} {}",
+            normalization,
+            integral
+        );
+        for (i, count) in histogram.iter().enumerate() {
+            let bin_bounds = Bounds1D::new(
+                bounds.lower + i as f32 * 30.0,
+                bounds.lower + (i + 1) as f32 * 30.0,
+            );
+            let expected = n as f32 * product.evaluate_integral(bin_bounds, 300, false) / integral;
+            let tolerance = 5.0 * expected.sqrt() + 5.0;
+            assert!(
+                (*count as f32 - expected).abs() < tolerance,
+                "{} {} {}",
+                i,
+                count,
+                expected
+            );
+        }
+
+        // a majorant that is too small is detected, and a zero target is never accepted
+        let (_, call) = rejection_sample(uniform, |x| product.evaluate(*x), 1.0, &mut sampler, 100);
+        assert!(call.majorant_violations > 0);
+        let (sample, call) = rejection_sample(uniform, |_| 0.0, majorant, &mut sampler, 50);
+        assert!(sample.is_none());
+        assert_eq!(call.proposals, 50);
+        assert_eq!(call.acceptance_rate(), 0.0);
+    }
+}
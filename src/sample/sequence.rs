@@ -0,0 +1,139 @@
+use crate::prelude::*;
+
+// additive recurrence sequences, x_i = frac(offset + i alpha), following Roberts (2018),
+// "The Unreasonable Effectiveness of Quasirandom Sequences". alpha is built from the inverse powers of the
+// generalized golden ratio of the dimension, the positive root of x^(d+1) = x + 1, which makes the points spread
+// evenly for any number of them. any point can be queried by its index with no tables and no state,
+// which suits progressive rendering. distinct offsets per pixel or per path decorrelate the sequences,
+// i.e. for the hero wavelengths of neighbouring pixels.
+
+// 1 / phi, for the golden ratio phi
+const ALPHA_1: f64 = 0.6180339887498949;
+// 1 / g and 1 / g^2, for the plastic number g = 1.3247...
+const ALPHA_2: [f64; 2] = [0.7548776662466927, 0.5698402909980532];
+// 1 / g, 1 / g^2 and 1 / g^3, for g = 1.2207... the positive root of x^4 = x + 1
+const ALPHA_3: [f64; 3] = [0.8191725133961645, 0.6710436067037893, 0.5497004779019703];
+
+// frac(offset + i alpha) in f64, such that indices in the billions keep their precision
+#[inline(always)]
+fn recurrence(offset: f32, index: u32, alpha: f64) -> f32 {
+    let x = (offset as f64 + index as f64 * alpha).fract() as f32;
+    // rounding to f32 can reach 1
+    x.min(1.0 - f32::EPSILON)
+}
+
+/// The 1D golden ratio sequence, frac(offset + i / phi)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GoldenRatioSequence {
+    pub offset: f32,
+}
+
+impl GoldenRatioSequence {
+    pub const fn new(offset: f32) -> Self {
+        GoldenRatioSequence { offset }
+    }
+    /// the i'th point of the sequence
+    pub fn sequence(&self, i: u32) -> Sample1D {
+        Sample1D::new(recurrence(self.offset, i, ALPHA_1))
+    }
+}
+
+/// The 2D R2 sequence, frac(offset + i (1 / g, 1 / g^2)) for the plastic number g
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct R2Sequence {
+    pub offset: (f32, f32),
+}
+
+impl R2Sequence {
+    pub const fn new(offset: (f32, f32)) -> Self {
+        R2Sequence { offset }
+    }
+    /// the i'th point of the sequence
+    pub fn sequence(&self, i: u32) -> Sample2D {
+        Sample2D::new(
+            recurrence(self.offset.0, i, ALPHA_2[0]),
+            recurrence(self.offset.1, i, ALPHA_2[1]),
+        )
+    }
+}
+
+/// A `Sampler` drawing from the golden ratio, R2 and R3 sequences for 1, 2 and 3 dimensional samples,
+/// each with its own index. all dimensions share `offset`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RecurrenceSampler {
+    pub offset: f32,
+    pub indices: [u32; 3],
+}
+
+impl RecurrenceSampler {
+    pub const fn new(offset: f32) -> Self {
+        RecurrenceSampler {
+            offset,
+            indices: [0; 3],
+        }
+    }
+}
+
+impl Sampler for RecurrenceSampler {
+    fn draw_1d(&mut self) -> Sample1D {
+        let i = self.indices[0];
+        self.indices[0] = i.wrapping_add(1);
+        GoldenRatioSequence::new(self.offset).sequence(i)
+    }
+    fn draw_2d(&mut self) -> Sample2D {
+        let i = self.indices[1];
+        self.indices[1] = i.wrapping_add(1);
+        R2Sequence::new((self.offset, self.offset)).sequence(i)
+    }
+    fn draw_3d(&mut self) -> Sample3D {
+        let i = self.indices[2];
+        self.indices[2] = i.wrapping_add(1);
+        Sample3D::new(
+            recurrence(self.offset, i, ALPHA_3[0]),
+            recurrence(self.offset, i, ALPHA_3[1]),
+            recurrence(self.offset, i, ALPHA_3[2]),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_recurrence_sequences() {
+        // the golden ratio sequence has no large gaps for any prefix
+        let golden = GoldenRatioSequence::new(0.5);
+        for n in [10, 100, 1000] {
+            let mut points: Vec<f32> = (0..n).map(|i| golden.sequence(i).x).collect();
+            points.sort_by(|a, b| a.total_cmp(b));
+            let wrap = points[0] + 1.0 - points[n as usize - 1];
+            let largest = points.windows(2).map(|w| w[1] - w[0]).fold(wrap, f32::max);
+            assert!(largest < 2.0 / n as f32, "{} {}", n, largest);
+        }
+
+        // the R2 sequence is close to evenly stratified over an 8x8 grid
+        let r2 = R2Sequence::new((0.5, 0.5));
+        let mut counts = [0usize; 64];
+        for i in 0..1024 {
+            let sample = r2.sequence(i);
+            counts[(sample.x * 8.0) as usize + 8 * (sample.y * 8.0) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (13..=19).contains(&c)),
+            "{:?}",
+            counts
+        );
+        // large indices stay in [0, 1)
+        let last = r2.sequence(u32::MAX);
+        assert!(last.x < 1.0 && last.y < 1.0);
+
+        let mut sampler = RecurrenceSampler::new(0.5);
+        sampler.draw_1d();
+        let first = sampler.draw_2d();
+        assert_eq!((first.x, first.y), (0.5, 0.5));
+        assert_eq!(sampler.draw_1d().x, golden.sequence(1).x);
+        let sample = sampler.draw_3d();
+        assert!(sample.x < 1.0 && sample.y < 1.0 && sample.z < 1.0);
+    }
+}
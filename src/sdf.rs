@@ -0,0 +1,259 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// numeric scaffolding for signed distance fields, given as closures from points to signed distances.
+// a field with lipschitz constant L satisfies |f(a) - f(b)| <= L * |a - b|, so f(p) / L is a lower bound
+// on the distance from p to the surface. exact distance fields have L = 1, while deformed or blended fields
+// usually need a larger L for sphere tracing to remain conservative.
+
+/// Sphere tracing parameters.
+/// `over_relaxation` in [1, 2) enlarges each step by that factor, falling back to the conservative step
+/// whenever the unbounding spheres of consecutive steps stop overlapping (Keinert et al. 2014).
+/// the hit tolerance grows with distance by `tolerance_growth`, i.e. the tangent of half the pixel footprint angle,
+/// so that distant surfaces aren't refined beyond what a pixel can resolve.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SphereTracer {
+    pub max_steps: usize,
+    pub lipschitz: f32,
+    pub over_relaxation: f32,
+    pub hit_tolerance: f32,
+    pub tolerance_growth: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SphereTraceHit {
+    pub t: f32,
+    pub point: Point3,
+    /// the signed distance at the hit point, which is within the hit tolerance of 0
+    pub distance: f32,
+    pub steps: usize,
+}
+
+impl SphereTracer {
+    pub fn new(max_steps: usize) -> Self {
+        SphereTracer {
+            max_steps,
+            lipschitz: 1.0,
+            over_relaxation: 1.0,
+            hit_tolerance: 1e-4,
+            tolerance_growth: 0.0,
+        }
+    }
+    pub fn with_lipschitz(mut self, lipschitz: f32) -> Self {
+        self.lipschitz = lipschitz;
+        self
+    }
+    pub fn with_over_relaxation(mut self, over_relaxation: f32) -> Self {
+        debug_assert!((1.0..2.0).contains(&over_relaxation));
+        self.over_relaxation = over_relaxation;
+        self
+    }
+    pub fn with_hit_tolerance(mut self, hit_tolerance: f32, tolerance_growth: f32) -> Self {
+        self.hit_tolerance = hit_tolerance;
+        self.tolerance_growth = tolerance_growth;
+        self
+    }
+
+    /// marches `ray` (with a normalized direction) through `sdf` from t = 0 to `ray.tmax`,
+    /// returning the first point where the distance drops below the hit tolerance
+    pub fn trace(&self, ray: Ray, sdf: impl Fn(Point3) -> f32) -> Option<SphereTraceHit> {
+        let mut omega = self.over_relaxation;
+        let mut t = 0.0;
+        // the position and safe step radius of the last step that is known not to have skipped the surface
+        let mut previous = (0.0, 0.0);
+        // rays starting inside the surface march through negative distances
+        let mut sign = None;
+        for step in 0..self.max_steps {
+            let point = ray.at(t);
+            let distance = sdf(point);
+            let sign = *sign.get_or_insert(1.0f32.copysign(distance));
+            let radius = distance.abs() / self.lipschitz;
+            let (previous_t, previous_radius) = previous;
+            if omega > 1.0 && radius + previous_radius < t - previous_t {
+                // the unbounding spheres don't overlap, so the relaxed step may have passed through the surface.
+                // go back and continue with conservative steps.
+                omega = 1.0;
+                t = previous_t + previous_radius;
+                continue;
+            }
+            if radius < self.hit_tolerance + self.tolerance_growth * t {
+                return Some(SphereTraceHit {
+                    t,
+                    point,
+                    distance,
+                    steps: step + 1,
+                });
+            }
+            previous = (t, radius);
+            // a relaxed step that overshot the surface without being caught above steps back
+            t += omega * sign * distance / self.lipschitz;
+            if t > ray.tmax {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// estimates the normal of `sdf` at `p` from central differences with step `h`, using 6 evaluations
+pub fn central_difference_normal(sdf: impl Fn(Point3) -> f32, p: Point3, h: f32) -> Vec3 {
+    let gradient = Vec3::new(
+        sdf(p + Vec3::X * h) - sdf(p - Vec3::X * h),
+        sdf(p + Vec3::Y * h) - sdf(p - Vec3::Y * h),
+        sdf(p + Vec3::Z * h) - sdf(p - Vec3::Z * h),
+    );
+    gradient.normalized()
+}
+
+/// estimates the normal of `sdf` at `p` from the vertices of a tetrahedron of size `h`, using 4 evaluations
+pub fn tetrahedral_normal(sdf: impl Fn(Point3) -> f32, p: Point3, h: f32) -> Vec3 {
+    let vertices = [
+        Vec3::new(1.0, -1.0, -1.0),
+        Vec3::new(-1.0, -1.0, 1.0),
+        Vec3::new(-1.0, 1.0, -1.0),
+        Vec3::new(1.0, 1.0, 1.0),
+    ];
+    vertices
+        .iter()
+        .fold(Vec3::ZERO, |gradient, &k| gradient + k * sdf(p + k * h))
+        .normalized()
+}
+
+/// conservatively bounds the zero set of `sdf` within the box [`min`, `max`] by recursive subdivision,
+/// discarding cells that the lipschitz bound proves to be empty, i.e. where |f(center)| > L * half diagonal.
+/// returns the (min, max) corners of the union of the remaining cells after `depth` subdivisions,
+/// or None if the surface doesn't intersect the box.
+pub fn bound_sdf_surface(
+    sdf: &impl Fn(Point3) -> f32,
+    lipschitz: f32,
+    min: Point3,
+    max: Point3,
+    depth: usize,
+) -> Option<(Point3, Point3)> {
+    let half_extent = (max - min) * 0.5;
+    let center = min + half_extent;
+    if sdf(center).abs() > lipschitz * half_extent.norm() {
+        return None;
+    }
+    if depth == 0 {
+        return Some((min, max));
+    }
+    let mut bounds: Option<(Point3, Point3)> = None;
+    for octant in 0..8 {
+        let offset = Vec3::new(
+            (octant & 1) as f32 * half_extent.x(),
+            ((octant >> 1) & 1) as f32 * half_extent.y(),
+            ((octant >> 2) & 1) as f32 * half_extent.z(),
+        );
+        let child_min = min + offset;
+        let child = bound_sdf_surface(
+            sdf,
+            lipschitz,
+            child_min,
+            child_min + half_extent,
+            depth - 1,
+        );
+        bounds = match (bounds, child) {
+            (Some((a_min, a_max)), Some((b_min, b_max))) => Some((
+                Point3(a_min.0.simd_min(b_min.0)),
+                Point3(a_max.0.simd_max(b_max.0)),
+            )),
+            (a, b) => a.or(b),
+        };
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sphere(p: Point3) -> f32 {
+        (p - Point3::new(0.0, 0.0, 5.0)).norm() - 1.0
+    }
+
+    #[test]
+    fn test_sphere_tracing() {
+        let ray = Ray::new(Point3::ORIGIN, Vec3::Z);
+        let plain = SphereTracer::new(128).trace(ray, sphere).unwrap();
+        assert!((plain.t - 4.0).abs() < 1e-3);
+
+        // a field scaled by 0.5 is exact again once its lipschitz constant is given
+        let scaled = |p: Point3| 0.5 * sphere(p);
+        let slow = SphereTracer::new(128)
+            .with_lipschitz(0.5)
+            .trace(ray, scaled)
+            .unwrap();
+        assert!((slow.t - 4.0).abs() < 1e-3);
+        let relaxed = SphereTracer::new(128)
+            .with_over_relaxation(1.4)
+            .trace(ray, |p| {
+                // a conservative field that is much smaller than the true distance converges slowly,
+                // and over-relaxation recovers some of the lost steps
+                0.3 * sphere(p)
+            });
+        let relaxed = relaxed.unwrap();
+        let unrelaxed = SphereTracer::new(128)
+            .trace(ray, |p| 0.3 * sphere(p))
+            .unwrap();
+        assert!((relaxed.t - 4.0).abs() < 1e-3);
+        assert!(
+            relaxed.steps < unrelaxed.steps,
+            "{:?} {:?}",
+            relaxed,
+            unrelaxed
+        );
+
+        // over-relaxation must not skip thin features
+        let thin = |p: Point3| (p.z() - 3.0).abs() - 0.01;
+        let hit = SphereTracer::new(256)
+            .with_over_relaxation(1.9)
+            .trace(ray, thin)
+            .unwrap();
+        assert!((hit.t - 2.99).abs() < 1e-3, "{:?}", hit);
+
+        let miss = Ray::new(Point3::ORIGIN, Vec3::X);
+        let miss = Ray {
+            tmax: 100.0,
+            ..miss
+        };
+        assert!(SphereTracer::new(128).trace(miss, sphere).is_none());
+
+        let p = Point3::new(0.6, 0.0, 5.8);
+        let expected = (p - Point3::new(0.0, 0.0, 5.0)).normalized();
+        assert!((central_difference_normal(sphere, p, 1e-3) - expected).norm() < 1e-3);
+        assert!((tetrahedral_normal(sphere, p, 1e-3) - expected).norm() < 1e-3);
+
+        let (min, max) = bound_sdf_surface(
+            &sphere,
+            1.0,
+            Point3::new(-4.0, -4.0, 0.0),
+            Point3::new(4.0, 4.0, 8.0),
+            5,
+        )
+        .unwrap();
+        // contains the sphere, and is tight to within a couple of cells of size 0.25
+        for (lower, upper, (expected_lower, expected_upper)) in [
+            (min.x(), max.x(), (-1.0, 1.0)),
+            (min.z(), max.z(), (4.0, 6.0)),
+        ] {
+            assert!(
+                lower <= expected_lower && lower > expected_lower - 0.5,
+                "{}",
+                lower
+            );
+            assert!(
+                upper >= expected_upper && upper < expected_upper + 0.5,
+                "{}",
+                upper
+            );
+        }
+        let far = |p: Point3| sphere(p) + 100.0;
+        assert!(
+            bound_sdf_surface(&far, 1.0, Point3::ORIGIN, Point3::new(1.0, 1.0, 1.0), 3).is_none()
+        );
+    }
+}
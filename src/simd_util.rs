@@ -0,0 +1,181 @@
+use crate::prelude::*;
+
+use std::simd::{cmp::SimdPartialEq, f32x8};
+
+// reductions over slices of f32, processed in chunks of 8 with a scalar remainder.
+// note that simd_min and simd_max ignore NaN values unless every value is NaN.
+
+const LANES: usize = 8;
+
+pub fn reduce_sum(values: &[f32]) -> f32 {
+    let chunks = values.chunks_exact(LANES);
+    let remainder = chunks.remainder().iter().sum::<f32>();
+    chunks
+        .fold(f32x8::splat(0.0), |acc, chunk| {
+            acc + f32x8::from_slice(chunk)
+        })
+        .reduce_sum()
+        + remainder
+}
+
+pub fn reduce_min(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let chunks = values.chunks_exact(LANES);
+    let remainder = chunks
+        .remainder()
+        .iter()
+        .fold(f32::INFINITY, |acc, v| acc.min(*v));
+    let min = chunks
+        .fold(f32x8::splat(f32::INFINITY), |acc, chunk| {
+            acc.simd_min(f32x8::from_slice(chunk))
+        })
+        .reduce_min();
+    Some(min.min(remainder))
+}
+
+pub fn reduce_max(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    let chunks = values.chunks_exact(LANES);
+    let remainder = chunks
+        .remainder()
+        .iter()
+        .fold(f32::NEG_INFINITY, |acc, v| acc.max(*v));
+    let max = chunks
+        .fold(f32x8::splat(f32::NEG_INFINITY), |acc, chunk| {
+            acc.simd_max(f32x8::from_slice(chunk))
+        })
+        .reduce_max();
+    Some(max.max(remainder))
+}
+
+// index of the first value that is equal to `target`
+fn position_of(values: &[f32], target: f32) -> Option<usize> {
+    let splatted = f32x8::splat(target);
+    let chunks = values.chunks_exact(LANES);
+    let remainder_start = values.len() - chunks.remainder().len();
+    for (i, chunk) in chunks.enumerate() {
+        let mask = f32x8::from_slice(chunk).simd_eq(splatted).to_bitmask();
+        if mask != 0 {
+            return Some(i * LANES + mask.trailing_zeros() as usize);
+        }
+    }
+    values[remainder_start..]
+        .iter()
+        .position(|v| *v == target)
+        .map(|i| remainder_start + i)
+}
+
+/// returns the index and value of the first minimum in `values`
+pub fn argmin(values: &[f32]) -> Option<(usize, f32)> {
+    let min = reduce_min(values)?;
+    position_of(values, min).map(|i| (i, min))
+}
+
+/// returns the index and value of the first maximum in `values`
+pub fn argmax(values: &[f32]) -> Option<(usize, f32)> {
+    let max = reduce_max(values)?;
+    position_of(values, max).map(|i| (i, max))
+}
+
+/// computes the `p`th percentile (p in [0, 100]) of `values`, linearly interpolating between the closest ranks.
+/// NaN values are sorted after all other values.
+pub fn percentile(values: &[f32], p: f32) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    debug_assert!((0.0..=100.0).contains(&p));
+    let mut scratch = values.to_vec();
+    let rank = (p / 100.0).clamp(0.0, 1.0) * (values.len() - 1) as f32;
+    let lower_index = rank.floor() as usize;
+    let t = rank - lower_index as f32;
+
+    let (_, lower, rest) = scratch.select_nth_unstable_by(lower_index, f32::total_cmp);
+    let lower = *lower;
+    if t == 0.0 || rest.is_empty() {
+        return Some(lower);
+    }
+    // the next rank is the minimum of the partition above the lower rank
+    let upper = rest.iter().copied().min_by(f32::total_cmp).unwrap_or(lower);
+    Some(lower + t * (upper - lower))
+}
+
+pub fn median(values: &[f32]) -> Option<f32> {
+    percentile(values, 50.0)
+}
+
+// reductions over the lanes of a single f32x4
+
+/// returns the lane index of the first minimum
+pub fn lane_argmin(v: f32x4) -> usize {
+    let mask = v.simd_eq(f32x4::splat(v.reduce_min())).to_bitmask();
+    // if every lane is NaN, no lane compares equal
+    (mask.trailing_zeros() as usize).min(3)
+}
+
+/// returns the lane index of the first maximum
+pub fn lane_argmax(v: f32x4) -> usize {
+    let mask = v.simd_eq(f32x4::splat(v.reduce_max())).to_bitmask();
+    (mask.trailing_zeros() as usize).min(3)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_values(n: usize) -> Vec<f32> {
+        (0..n)
+            .map(|i| ((i * 7919) % 1013) as f32 * 0.5 - 100.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_reductions_match_scalar() {
+        for n in [0, 1, 5, 8, 9, 31, 1000] {
+            let values = test_values(n);
+            let scalar_sum: f32 = values.iter().sum();
+            assert!((reduce_sum(&values) - scalar_sum).abs() <= 1e-3 * scalar_sum.abs().max(1.0));
+
+            let scalar_min = values.iter().copied().reduce(f32::min);
+            let scalar_max = values.iter().copied().reduce(f32::max);
+            assert_eq!(reduce_min(&values), scalar_min);
+            assert_eq!(reduce_max(&values), scalar_max);
+
+            let scalar_argmin =
+                scalar_min.map(|m| (values.iter().position(|v| *v == m).unwrap(), m));
+            let scalar_argmax =
+                scalar_max.map(|m| (values.iter().position(|v| *v == m).unwrap(), m));
+            assert_eq!(argmin(&values), scalar_argmin);
+            assert_eq!(argmax(&values), scalar_argmax);
+        }
+    }
+
+    #[test]
+    fn test_argmax_in_remainder() {
+        let mut values = vec![0.0; 11];
+        values[10] = 5.0;
+        values[3] = -2.0;
+        assert_eq!(argmax(&values), Some((10, 5.0)));
+        assert_eq!(argmin(&values), Some((3, -2.0)));
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values = [5.0, 1.0, 4.0, 2.0, 3.0];
+        assert_eq!(percentile(&values, 0.0), Some(1.0));
+        assert_eq!(percentile(&values, 100.0), Some(5.0));
+        assert_eq!(median(&values), Some(3.0));
+        assert_eq!(percentile(&values, 12.5), Some(1.5));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_lane_reductions() {
+        let v = f32x4::from_array([3.0, -1.0, 7.0, -1.0]);
+        assert_eq!(lane_argmin(v), 1);
+        assert_eq!(lane_argmax(v), 2);
+    }
+}
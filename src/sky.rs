@@ -0,0 +1,282 @@
+use crate::curves::atmosphere::angstrom_beta;
+use crate::prelude::*;
+use crate::spectral::{y_bar_bounded, CMF_SUPPORT};
+
+// the analytic daylight model of Preetham, Shirley and Smits, "A Practical Analytic Model for Daylight" (1999).
+// the model gives the luminance and chromaticity of the sky in any direction, which are converted to spectra through
+// the CIE daylight basis functions. directions are expressed in a frame where +Z is the zenith.
+
+/// wavelength range of the CIE daylight basis functions
+pub const DAYLIGHT_BASIS_RANGE: Bounds1D = Bounds1D::new(300.0, 830.0);
+
+// CIE daylight basis functions S0, S1 and S2, from 300nm to 830nm in 10nm increments
+const S0: [f32; 54] = [
+    0.04, 6.0, 29.6, 55.3, 57.3, 61.8, 61.5, 68.8, 63.4, 65.8, 94.8, 104.8, 105.9, 96.8, 113.9,
+    125.6, 125.5, 121.3, 121.3, 113.5, 113.1, 110.8, 106.5, 108.8, 105.3, 104.4, 100.0, 96.0, 95.1,
+    89.1, 90.5, 90.3, 88.4, 84.0, 85.1, 81.9, 82.6, 84.9, 81.3, 71.9, 74.3, 76.4, 63.3, 71.7, 77.0,
+    65.2, 47.7, 68.6, 65.0, 66.0, 61.0, 53.3, 58.9, 61.9,
+];
+const S1: [f32; 54] = [
+    0.02, 4.5, 22.4, 42.0, 40.6, 41.6, 38.0, 42.4, 38.5, 35.0, 43.4, 46.3, 43.9, 37.1, 36.7, 35.9,
+    32.6, 27.9, 24.3, 20.1, 16.2, 13.2, 8.6, 6.1, 4.2, 1.9, 0.0, -1.6, -3.5, -3.5, -5.8, -7.2,
+    -8.6, -9.5, -10.9, -10.7, -12.0, -14.0, -13.6, -12.0, -13.3, -12.9, -10.6, -11.6, -12.2, -10.2,
+    -7.8, -11.2, -10.4, -10.6, -9.7, -8.3, -9.3, -9.8,
+];
+const S2: [f32; 54] = [
+    0.0, 2.0, 4.0, 8.5, 7.8, 6.7, 5.3, 6.1, 3.0, 1.2, -1.1, -0.5, -0.7, -1.2, -2.6, -2.9, -2.8,
+    -2.6, -2.6, -1.8, -1.5, -1.3, -1.2, -1.0, -0.5, -0.3, 0.0, 0.2, 0.5, 2.1, 3.2, 4.1, 4.7, 5.1,
+    6.7, 7.3, 8.6, 9.8, 10.2, 8.3, 9.6, 8.5, 7.0, 7.6, 8.0, 6.7, 5.2, 7.4, 6.8, 7.0, 6.4, 5.5, 6.1,
+    6.5,
+];
+
+/// mean angular radius of the sun as seen from the earth, in radians
+pub const SUN_ANGULAR_RADIUS: f32 = 0.004_65;
+// luminance of the solar disc outside of the atmosphere, in kcd/m^2
+const SUN_LUMINANCE: f32 = 1.6e6;
+const SUN_TEMPERATURE: f32 = 5778.0;
+
+fn daylight_basis(lambda: f32) -> (f32, f32, f32) {
+    let t = ((lambda - DAYLIGHT_BASIS_RANGE.lower) / 10.0).clamp(0.0, 53.0);
+    let index = (t as usize).min(52);
+    let t = t - index as f32;
+    let lerp = |table: &[f32; 54]| table[index] * (1.0 - t) + table[index + 1] * t;
+    (lerp(&S0), lerp(&S1), lerp(&S2))
+}
+
+// integrates `func` against y_bar at 1nm increments over the daylight basis range
+fn luminance_integral(func: impl Fn(f32) -> f32) -> f32 {
+    let steps = DAYLIGHT_BASIS_RANGE.span() as usize;
+    (0..=steps)
+        .map(|i| {
+            let lambda = DAYLIGHT_BASIS_RANGE.lower + i as f32;
+            func(lambda) * y_bar_bounded(lambda * 10.0, CMF_SUPPORT)
+        })
+        .sum()
+}
+
+// coefficients of the perez sky distribution function
+#[derive(Copy, Clone, Debug)]
+struct Perez([f32; 5]);
+
+impl Perez {
+    fn evaluate(&self, cos_theta: f32, gamma: f32) -> f32 {
+        let [a, b, c, d, e] = self.0;
+        let cos_gamma = gamma.cos();
+        (1.0 + a * (b / cos_theta.max(1e-3)).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+}
+
+/// Spectral radiance of a clear daylight sky, for a given sun direction and atmospheric turbidity.
+/// radiance is scaled such that integrating it against `y_bar` (per nanometer) yields luminance in kcd/m^2.
+#[derive(Clone, Debug)]
+pub struct SkyModel {
+    pub sun_direction: Vec3,
+    /// atmospheric turbidity. the model is fit for values from roughly 2 (very clear) to 10 (hazy)
+    pub turbidity: f32,
+    pub sun_angular_radius: f32,
+    theta_sun: f32,
+    perez: [Perez; 3],
+    // zenith values of luminance and x, y chromaticity, divided by the perez function at the zenith
+    zenith: [f32; 3],
+    // luminance integrals of the daylight basis functions
+    basis_luminance: (f32, f32, f32),
+    sun_scale: f32,
+}
+
+impl SkyModel {
+    pub fn new(sun_direction: Vec3, turbidity: f32) -> Self {
+        debug_assert!((1.0..=20.0).contains(&turbidity));
+        let sun_direction = sun_direction.normalized();
+        // the model is only valid for the sun at or above the horizon
+        let theta_sun = sun_direction.z().clamp(0.0, 1.0).acos();
+        let t = turbidity;
+
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let (th, th2, th3) = (
+            theta_sun,
+            theta_sun * theta_sun,
+            theta_sun * theta_sun * theta_sun,
+        );
+        let zenith_x = t * t * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_y = t * t * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        let zenith = [zenith_luminance, zenith_x, zenith_y];
+        let mut normalized_zenith = [0.0; 3];
+        for i in 0..3 {
+            normalized_zenith[i] = zenith[i] / perez[i].evaluate(1.0, theta_sun);
+        }
+
+        let basis_luminance = (
+            luminance_integral(|lambda| daylight_basis(lambda).0),
+            luminance_integral(|lambda| daylight_basis(lambda).1),
+            luminance_integral(|lambda| daylight_basis(lambda).2),
+        );
+        let sun_scale =
+            SUN_LUMINANCE / luminance_integral(|lambda| blackbody(SUN_TEMPERATURE, lambda));
+
+        SkyModel {
+            sun_direction,
+            turbidity,
+            sun_angular_radius: SUN_ANGULAR_RADIUS,
+            theta_sun,
+            perez,
+            zenith: normalized_zenith,
+            basis_luminance,
+            sun_scale,
+        }
+    }
+
+    /// luminance (in kcd/m^2) and CIE xy chromaticity of the sky in `direction`, excluding the solar disc
+    pub fn luminance_and_chromaticity(&self, direction: Vec3) -> (f32, f32, f32) {
+        let direction = direction.normalized();
+        let cos_theta = direction.z();
+        let gamma = (direction * self.sun_direction).clamp(-1.0, 1.0).acos();
+        let [luminance, x, y] =
+            [0, 1, 2].map(|i| self.zenith[i] * self.perez[i].evaluate(cos_theta, gamma));
+        (luminance, x, y)
+    }
+
+    fn daylight_weights(&self, direction: Vec3) -> Option<(f32, f32, f32)> {
+        if direction.z() <= 0.0 {
+            return None;
+        }
+        let (luminance, x, y) = self.luminance_and_chromaticity(direction);
+        // CIE daylight basis weights from chromaticity
+        let m = 0.0241 + 0.2562 * x - 0.7341 * y;
+        let m1 = (-1.3515 - 1.7703 * x + 5.9114 * y) / m;
+        let m2 = (0.0300 - 31.4424 * x + 30.0717 * y) / m;
+        let (y0, y1, y2) = self.basis_luminance;
+        let scale = luminance / (y0 + m1 * y1 + m2 * y2);
+        Some((scale, scale * m1, scale * m2))
+    }
+
+    pub fn in_solar_disc(&self, direction: Vec3) -> bool {
+        direction.normalized() * self.sun_direction >= self.sun_angular_radius.cos()
+    }
+
+    /// spectral radiance of the solar disc at `lambda`, attenuated by the atmosphere along the path to the sun
+    pub fn sun_radiance(&self, lambda: f32) -> f32 {
+        if self.theta_sun >= PI / 2.0 {
+            return 0.0;
+        }
+        // relative optical mass, following Kasten's formula
+        let theta_degrees = self.theta_sun.to_degrees();
+        let mass = 1.0 / (self.theta_sun.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+        let micrometers = lambda / 1000.0;
+        // rayleigh scattering and aerosol extinction, using the angstrom turbidity formula
+        let rayleigh = 0.008735 * micrometers.powf(-4.08);
+        let beta = angstrom_beta(self.turbidity);
+        let aerosol = beta * micrometers.powf(-1.3);
+        let transmittance = (-mass * (rayleigh + aerosol)).exp();
+        self.sun_scale * blackbody(SUN_TEMPERATURE, lambda) * transmittance
+    }
+
+    /// spectral radiance in `direction` at `lambda`, including the solar disc
+    pub fn evaluate(&self, direction: Vec3, lambda: f32) -> f32 {
+        let sky = match self.daylight_weights(direction) {
+            Some((w0, w1, w2)) => {
+                let (s0, s1, s2) = daylight_basis(lambda);
+                (w0 * s0 + w1 * s1 + w2 * s2).max(0.0)
+            }
+            None => return 0.0,
+        };
+        if self.in_solar_disc(direction) {
+            sky + self.sun_radiance(lambda)
+        } else {
+            sky
+        }
+    }
+
+    /// spectral radiance in `direction` as a curve over `DAYLIGHT_BASIS_RANGE`, including the solar disc
+    pub fn radiance(&self, direction: Vec3) -> Curve {
+        let (w0, w1, w2) = match self.daylight_weights(direction) {
+            Some(weights) => weights,
+            None => return Curve::Const(0.0),
+        };
+        let sun = self.in_solar_disc(direction);
+        let signal = (0..54)
+            .map(|i| {
+                let lambda = DAYLIGHT_BASIS_RANGE.lower + 10.0 * i as f32;
+                let sky = (w0 * S0[i] + w1 * S1[i] + w2 * S2[i]).max(0.0);
+                let radiance = if sun {
+                    sky + self.sun_radiance(lambda)
+                } else {
+                    sky
+                };
+                (lambda, radiance)
+            })
+            .collect();
+        // tabulated at the wavelengths of the basis, such that the curve spans exactly `DAYLIGHT_BASIS_RANGE`
+        Curve::Tabulated {
+            signal,
+            mode: InterpolationMode::Linear,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sky_model() {
+        let sun = Vec3::new(0.0, 0.6, 0.8);
+        let sky = SkyModel::new(sun, 3.0);
+
+        // the zenith matches the zenith luminance formula
+        let (luminance, x, y) = sky.luminance_and_chromaticity(Vec3::Z);
+        let theta_sun = 0.8f32.acos();
+        let chi = (4.0 / 9.0 - 3.0 / 120.0) * (PI - 2.0 * theta_sun);
+        let expected = (4.0453 * 3.0 - 4.9710) * chi.tan() - 0.2155 * 3.0 + 2.4192;
+        assert!((luminance - expected).abs() < 1e-3 * expected);
+        assert!(0.2 < x && x < 0.35 && 0.2 < y && y < 0.4, "{} {}", x, y);
+
+        // the spectrum reproduces the model's luminance and, approximately, its chromaticity
+        let xyz = sky
+            .radiance(Vec3::Z)
+            .convert_to_xyz(DAYLIGHT_BASIS_RANGE, 1.0, false);
+        assert!((xyz.y() - luminance).abs() < 0.01 * luminance, "{:?}", xyz);
+        let sum = xyz.x() + xyz.y() + xyz.z();
+        assert!((xyz.x() / sum - x).abs() < 0.01 && (xyz.y() / sum - y).abs() < 0.01);
+
+        // circumsolar brightening
+        let near_sun = Vec3::new(0.0, 0.65, 0.76).normalized();
+        let away_from_sun = Vec3::new(0.0, -0.65, 0.76).normalized();
+        assert!(sky.evaluate(near_sun, 550.0) > sky.evaluate(away_from_sun, 550.0));
+
+        // the solar disc is much brighter than the sky, and nothing comes from below the horizon
+        assert!(sky.evaluate(sun, 550.0) > 1000.0 * sky.evaluate(near_sun, 550.0));
+        assert_eq!(sky.evaluate(-Vec3::Z, 550.0), 0.0);
+        assert_eq!(sky.radiance(Vec3::new(1.0, 0.0, -0.1)).evaluate(550.0), 0.0);
+    }
+}
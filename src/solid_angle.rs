@@ -0,0 +1,276 @@
+use crate::prelude::*;
+
+// exact solid angles, for the pdfs of light sampling techniques that pick a direction uniformly within the solid angle
+// subtended by a light. all results are in steradians, and shapes seen edge on or from behind still subtend their
+// (possibly zero) solid angle, i.e. the orientation of a surface doesn't matter.
+
+/// the solid angle of a cone with half angle theta, given cos(theta)
+pub fn spherical_cap_solid_angle(cos_theta: f32) -> f32 {
+    2.0 * PI * (1.0 - cos_theta.clamp(-1.0, 1.0))
+}
+
+/// the area of the spherical triangle with unit vertices a, b and c.
+/// this is the spherical excess A + B + C - pi of Girard's theorem, computed with the formula of
+/// Van Oosterom and Strackee, which stays accurate for small triangles where the angle sum cancels.
+pub fn spherical_triangle_solid_angle(a: Vec3, b: Vec3, c: Vec3) -> f32 {
+    2.0 * (a * b.cross(c)).abs().atan2(1.0 + a * b + b * c + c * a)
+}
+
+/// the area of a simple spherical polygon with unit vertices in order, from the sum of its interior angles,
+/// which is (n - 2) pi plus the area. the polygon must lie within a hemisphere, and either winding works.
+pub fn spherical_polygon_solid_angle(vertices: &[Vec3]) -> f32 {
+    let n = vertices.len();
+    if n < 3 {
+        return 0.0;
+    }
+    let mut turning = 0.0;
+    for i in 0..n {
+        let (previous, vertex, next) = (
+            vertices[(i + n - 1) % n],
+            vertices[i],
+            vertices[(i + 1) % n],
+        );
+        // the turning angle between two arcs is the angle between the normals of their great circles.
+        // cross products stay accurate for the nearly parallel vertices of short edges, unlike projected tangents.
+        let (incoming, outgoing) = (previous.cross(vertex), vertex.cross(next));
+        turning += (vertex * incoming.cross(outgoing)).atan2(incoming * outgoing);
+    }
+    // the interior angles are pi minus the turning angles, and turn in the opposite direction for the opposite winding
+    (2.0 * PI - turning.abs()).max(0.0)
+}
+
+/// the solid angle of a sphere seen from `point`, which is the whole sphere of directions from inside it
+pub fn sphere_solid_angle(point: Point3, center: Point3, radius: f32) -> f32 {
+    let distance_squared = (center - point).norm_squared();
+    if distance_squared <= radius * radius {
+        return 4.0 * PI;
+    }
+    spherical_cap_solid_angle((1.0 - radius * radius / distance_squared).sqrt())
+}
+
+// carlson's symmetric elliptic integrals, by duplication until the arguments agree to within TOLERANCE,
+// followed by the taylor series of Carlson (1995), "Numerical computation of real or complex elliptic integrals".
+// the truncation error is of the order of TOLERANCE^6.
+const TOLERANCE: f64 = 1e-3;
+
+// R_C(x, y) for y > 0
+fn carlson_rc(mut x: f64, mut y: f64) -> f64 {
+    loop {
+        let lambda = 2.0 * x.sqrt() * y.sqrt() + y;
+        x = 0.25 * (x + lambda);
+        y = 0.25 * (y + lambda);
+        let mean = (x + 2.0 * y) / 3.0;
+        let s = (y - mean) / mean;
+        if s.abs() < TOLERANCE {
+            return (1.0 + s * s * (0.3 + s * (1.0 / 7.0 + s * (0.375 + s * 9.0 / 22.0))))
+                / mean.sqrt();
+        }
+    }
+}
+
+// R_F(x, y, z) for nonnegative arguments, at most one of which is 0
+fn carlson_rf(mut x: f64, mut y: f64, mut z: f64) -> f64 {
+    loop {
+        let (sx, sy, sz) = (x.sqrt(), y.sqrt(), z.sqrt());
+        let lambda = sx * (sy + sz) + sy * sz;
+        x = 0.25 * (x + lambda);
+        y = 0.25 * (y + lambda);
+        z = 0.25 * (z + lambda);
+        let mean = (x + y + z) / 3.0;
+        let (dx, dy, dz) = ((mean - x) / mean, (mean - y) / mean, (mean - z) / mean);
+        if dx.abs().max(dy.abs()).max(dz.abs()) < TOLERANCE {
+            let e2 = dx * dy - dz * dz;
+            let e3 = dx * dy * dz;
+            return (1.0 + (e2 / 24.0 - 0.1 - 3.0 / 44.0 * e3) * e2 + e3 / 14.0) / mean.sqrt();
+        }
+    }
+}
+
+// R_J(x, y, z, p) for nonnegative x, y and z, at most one of which is 0, and p > 0
+fn carlson_rj(mut x: f64, mut y: f64, mut z: f64, mut p: f64) -> f64 {
+    let (mut sum, mut factor) = (0.0, 1.0);
+    loop {
+        let (sx, sy, sz) = (x.sqrt(), y.sqrt(), z.sqrt());
+        let lambda = sx * (sy + sz) + sy * sz;
+        sum += factor
+            * carlson_rc(
+                (p * (sx + sy + sz) + sx * sy * sz).powi(2),
+                p * (p + lambda).powi(2),
+            );
+        factor *= 0.25;
+        x = 0.25 * (x + lambda);
+        y = 0.25 * (y + lambda);
+        z = 0.25 * (z + lambda);
+        p = 0.25 * (p + lambda);
+        let mean = (x + y + z + 2.0 * p) / 5.0;
+        let (dx, dy, dz, dp) = (
+            (mean - x) / mean,
+            (mean - y) / mean,
+            (mean - z) / mean,
+            (mean - p) / mean,
+        );
+        if dx.abs().max(dy.abs()).max(dz.abs()).max(dp.abs()) < TOLERANCE {
+            let ea = dx * (dy + dz) + dy * dz;
+            let eb = dx * dy * dz;
+            let ec = dp * dp;
+            let ed = ea - 3.0 * ec;
+            let ee = eb + 2.0 * dp * (ea - ec);
+            let series = 1.0
+                + ed * (-3.0 / 14.0 + 9.0 / 88.0 * ed - 9.0 / 52.0 * ee)
+                + eb * (1.0 / 6.0 + dp * (-3.0 / 11.0 + dp * 3.0 / 26.0))
+                + dp * ea * (1.0 / 3.0 - dp * 3.0 / 22.0)
+                - dp * ec / 3.0;
+            return 3.0 * sum + factor * series / (mean * mean.sqrt());
+        }
+    }
+}
+
+// the complete elliptic integrals of the first and third kind, K(m) and Pi(n, m), with parameter m = k^2
+fn elliptic_k(m: f64) -> f64 {
+    carlson_rf(0.0, 1.0 - m, 1.0)
+}
+
+fn elliptic_pi(n: f64, m: f64) -> f64 {
+    elliptic_k(m) + n / 3.0 * carlson_rj(0.0, 1.0 - m, 1.0, 1.0 - n)
+}
+
+/// the solid angle of a disk with unit `normal` seen from `point`.
+/// off axis, this is the closed form of Paxton (1959), "Solid angle calculation for a circular disk",
+/// in terms of complete elliptic integrals.
+pub fn disk_solid_angle(point: Point3, center: Point3, normal: Vec3, radius: f32) -> f32 {
+    let offset = point - center;
+    let height = (offset * normal).abs() as f64;
+    let lateral = (offset - normal * (offset * normal)).norm() as f64;
+    let radius = radius as f64;
+    if height == 0.0 || radius == 0.0 {
+        return 0.0;
+    }
+    if lateral == 0.0 {
+        return (2.0 * std::f64::consts::PI * (1.0 - height / height.hypot(radius))) as f32;
+    }
+    // the distance to the farthest point of the rim, and the parameters of the integrals
+    let farthest = height.hypot(lateral + radius);
+    let m = 4.0 * lateral * radius / (farthest * farthest);
+    let k = elliptic_k(m);
+    let solid_angle = if lateral == radius {
+        std::f64::consts::PI - 2.0 * height / farthest * k
+    } else {
+        let n = 4.0 * lateral * radius / (lateral + radius).powi(2);
+        let ratio = (lateral - radius) / (lateral + radius);
+        // points above the disk see the whole hemisphere in the limit of zero height
+        let limit = if lateral < radius {
+            2.0 * std::f64::consts::PI
+        } else {
+            0.0
+        };
+        limit - 2.0 * height / farthest * (k - ratio * elliptic_pi(n, m))
+    };
+    solid_angle.max(0.0) as f32
+}
+
+/// the solid angle of the parallelogram with corners `corner`, `corner + edge_u`, `corner + edge_v`
+/// and `corner + edge_u + edge_v`, seen from `point`
+pub fn rect_solid_angle(point: Point3, corner: Point3, edge_u: Vec3, edge_v: Vec3) -> f32 {
+    let direction = |p: Point3| (p - point).normalized();
+    let (a, b, c, d) = (
+        direction(corner),
+        direction(corner + edge_u),
+        direction(corner + edge_u + edge_v),
+        direction(corner + edge_v),
+    );
+    // both halves are seen from the same side, so their areas add up
+    spherical_triangle_solid_angle(a, b, c) + spherical_triangle_solid_angle(a, c, d)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_solid_angles() {
+        assert_eq!(spherical_cap_solid_angle(1.0), 0.0);
+        assert!((spherical_cap_solid_angle(0.0) - 2.0 * PI).abs() < 1e-6);
+        assert!((spherical_cap_solid_angle(-1.0) - 4.0 * PI).abs() < 1e-6);
+
+        // an octant is an eighth of the sphere, with three right angles
+        let octant = [Vec3::X, Vec3::Y, Vec3::Z];
+        assert!(
+            (spherical_triangle_solid_angle(Vec3::X, Vec3::Y, Vec3::Z) - PI / 2.0).abs() < 1e-6
+        );
+        assert!((spherical_polygon_solid_angle(&octant) - PI / 2.0).abs() < 1e-5);
+        let reversed = [Vec3::Z, Vec3::Y, Vec3::X];
+        assert!((spherical_polygon_solid_angle(&reversed) - PI / 2.0).abs() < 1e-5);
+        // the polygon and triangle formulas agree on a small, irregular quad
+        let quad = [
+            Vec3::new(0.1, 0.0, 1.0).normalized(),
+            Vec3::new(0.3, 0.05, 1.0).normalized(),
+            Vec3::new(0.25, 0.2, 1.0).normalized(),
+            Vec3::new(0.05, 0.15, 1.0).normalized(),
+        ];
+        let triangles = spherical_triangle_solid_angle(quad[0], quad[1], quad[2])
+            + spherical_triangle_solid_angle(quad[0], quad[2], quad[3]);
+        assert!(
+            (spherical_polygon_solid_angle(&quad) - triangles).abs() < 1e-4 * triangles.max(1e-2)
+        );
+
+        // a sphere subtends a cap, and everything from inside
+        let origin = Point3::ORIGIN;
+        let center = Point3::new(0.0, 0.0, 2.0);
+        let expected = 2.0 * PI * (1.0 - (0.75f32).sqrt());
+        assert!((sphere_solid_angle(origin, center, 1.0) - expected).abs() < 1e-6);
+        assert_eq!(sphere_solid_angle(center, center, 1.0), 4.0 * PI);
+
+        // a disk on axis subtends the same cone as a sphere whose silhouette it matches
+        let on_axis = disk_solid_angle(origin, center, Vec3::Z, 1.0);
+        assert!((on_axis - 2.0 * PI * (1.0 - 2.0 / 5.0f32.sqrt())).abs() < 1e-6);
+        // off axis, a disk is equivalent to a fine polygon, whether the point is within, on or beyond its rim,
+        // and an infinitely close disk covers a hemisphere
+        for point in [
+            Point3::new(0.7, -0.4, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.6, 0.8, 1.2),
+            Point3::new(-2.5, 1.0, 1.0),
+        ] {
+            let polygon: Vec<Vec3> = (0..4096)
+                .map(|i| {
+                    let (sin, cos) = (i as f32 * 2.0 * PI / 4096.0).sin_cos();
+                    (Point3::new(cos, sin, 1.5) - point).normalized()
+                })
+                .collect();
+            let off_axis = disk_solid_angle(point, Point3::new(0.0, 0.0, 1.5), Vec3::Z, 1.0);
+            assert!(
+                (off_axis - spherical_polygon_solid_angle(&polygon)).abs() < 1e-3,
+                "{:?} {}",
+                point,
+                off_axis
+            );
+        }
+        let close = disk_solid_angle(Point3::new(0.5, 0.0, 1e-4), origin, Vec3::Z, 1.0);
+        assert!((close - 2.0 * PI).abs() < 1e-3, "{}", close);
+        assert!(
+            (disk_solid_angle(origin, Point3::new(0.0, 0.0, 1e-3), -Vec3::Z, 1e3) - 2.0 * PI).abs()
+                < 1e-2
+        );
+        assert_eq!(
+            disk_solid_angle(origin, Point3::new(1.0, 0.0, 0.0), Vec3::Z, 1.0),
+            0.0
+        );
+
+        // a face of a cube seen from its center is a sixth of the sphere
+        let face = rect_solid_angle(
+            origin,
+            Point3::new(-1.0, -1.0, 1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+        );
+        assert!((face - 4.0 * PI / 6.0).abs() < 1e-5, "{}", face);
+        // and a small rect far away is close to its area times cos / distance^2
+        let far = rect_solid_angle(
+            origin,
+            Point3::new(10.0, -0.05, -0.05),
+            Vec3::new(0.0, 0.1, 0.0),
+            Vec3::new(0.0, 0.0, 0.1),
+        );
+        assert!((far - 0.01 / 100.0).abs() < 1e-7, "{}", far);
+    }
+}
@@ -1,7 +1,23 @@
+use crate::error::Result;
 use crate::prelude::*;
+use std::convert::TryFrom;
+use std::simd::Simd;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod blue_noise;
+pub mod cmf;
+pub mod luminosity;
 
 pub const EXTENDED_VISIBLE_RANGE: Bounds1D = Bounds1D::new(370.0, 790.0);
 pub const BOUNDED_VISIBLE_RANGE: Bounds1D = Bounds1D::new(380.0, 780.0);
+// ISO 21348 UV-A
+pub const UVA_RANGE: Bounds1D = Bounds1D::new(315.0, 400.0);
+// CIE IR-A
+pub const NEAR_INFRARED_RANGE: Bounds1D = Bounds1D::new(780.0, 1400.0);
+// typical spectral sensitivity of a silicon photodiode / CMOS sensor without an IR cut filter
+pub const SILICON_SENSITIVITY_RANGE: Bounds1D = Bounds1D::new(350.0, 1100.0);
 
 pub type SingleWavelength = WavelengthEnergy<f32, f32>;
 pub type HeroWavelength = WavelengthEnergy<f32x4, f32x4>;
@@ -42,6 +58,126 @@ pub fn z_bar_f32x4(angstroms: f32x4) -> f32x4 {
         + gaussian_f32x4(angstroms, 0.681, 4590.0, 260.0, 138.0)
 }
 
+/// the range of wavelengths, in nanometers, over which the CIE 1931 color matching functions are tabulated.
+/// the gaussian fits above are treated as exactly 0 outside of this range by the bounded variants below.
+pub const CMF_SUPPORT: Bounds1D = Bounds1D::new(360.0, 830.0);
+
+// domain aware versions of the color matching functions.
+// note that these take angstroms like the functions above, while `support` is in nanometers.
+
+pub fn x_bar_bounded(angstroms: f32, support: Bounds1D) -> f32 {
+    if support.lower <= angstroms / 10.0 && angstroms / 10.0 <= support.upper {
+        x_bar(angstroms)
+    } else {
+        0.0
+    }
+}
+
+pub fn y_bar_bounded(angstroms: f32, support: Bounds1D) -> f32 {
+    if support.lower <= angstroms / 10.0 && angstroms / 10.0 <= support.upper {
+        y_bar(angstroms)
+    } else {
+        0.0
+    }
+}
+
+pub fn z_bar_bounded(angstroms: f32, support: Bounds1D) -> f32 {
+    if support.lower <= angstroms / 10.0 && angstroms / 10.0 <= support.upper {
+        z_bar(angstroms)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(feature="simdfloat_patch")]
+fn support_mask(angstroms: f32x4, support: Bounds1D) -> std::simd::mask32x4 {
+    let nanometers = angstroms / f32x4::splat(10.0);
+    nanometers.simd_ge(f32x4::splat(support.lower))
+        & nanometers.simd_le(f32x4::splat(support.upper))
+}
+
+#[cfg(feature="simdfloat_patch")]
+pub fn x_bar_bounded_f32x4(angstroms: f32x4, support: Bounds1D) -> f32x4 {
+    support_mask(angstroms, support).select(x_bar_f32x4(angstroms), f32x4::splat(0.0))
+}
+
+#[cfg(feature="simdfloat_patch")]
+pub fn y_bar_bounded_f32x4(angstroms: f32x4, support: Bounds1D) -> f32x4 {
+    support_mask(angstroms, support).select(y_bar_f32x4(angstroms), f32x4::splat(0.0))
+}
+
+#[cfg(feature="simdfloat_patch")]
+pub fn z_bar_bounded_f32x4(angstroms: f32x4, support: Bounds1D) -> f32x4 {
+    support_mask(angstroms, support).select(z_bar_f32x4(angstroms), f32x4::splat(0.0))
+}
+
+/// A range of wavelengths in nanometers. Unlike a plain `Bounds1D`, a `WavelengthRange` is guaranteed to be
+/// nonempty, finite, and strictly positive, and intersecting two ranges that don't overlap yields `None`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct WavelengthRange(Bounds1D);
+
+impl WavelengthRange {
+    pub const EXTENDED_VISIBLE: WavelengthRange = WavelengthRange(EXTENDED_VISIBLE_RANGE);
+    pub const VISIBLE: WavelengthRange = WavelengthRange(BOUNDED_VISIBLE_RANGE);
+    pub const UVA: WavelengthRange = WavelengthRange(UVA_RANGE);
+    pub const NEAR_INFRARED: WavelengthRange = WavelengthRange(NEAR_INFRARED_RANGE);
+    pub const SILICON_SENSITIVITY: WavelengthRange = WavelengthRange(SILICON_SENSITIVITY_RANGE);
+
+    pub fn new(lower: f32, upper: f32) -> Result<Self> {
+        let domain = Bounds1D::new(0.0, f32::INFINITY);
+        for value in [lower, upper] {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(MathError::OutOfDomain(value, domain));
+            }
+        }
+        if lower >= upper {
+            return Err(MathError::EmptyBounds);
+        }
+        Ok(WavelengthRange(Bounds1D::new(lower, upper)))
+    }
+    pub const fn bounds(&self) -> Bounds1D {
+        self.0
+    }
+    pub fn lower(&self) -> f32 {
+        self.0.lower
+    }
+    pub fn upper(&self) -> f32 {
+        self.0.upper
+    }
+    pub fn span(&self) -> f32 {
+        self.0.span()
+    }
+    pub fn contains(&self, lambda: f32) -> bool {
+        self.0.contains(&lambda)
+    }
+    /// returns None if the ranges do not overlap
+    pub fn intersection(&self, other: Self) -> Option<Self> {
+        let bounds = self.0.intersection(other.0);
+        WavelengthRange::new(bounds.lower, bounds.upper).ok()
+    }
+    pub fn union(&self, other: Self) -> Self {
+        WavelengthRange(self.0.union(other.0))
+    }
+    /// maps `sample` in [0, 1) to a wavelength within the range
+    pub fn sample(&self, sample: Sample1D) -> f32 {
+        self.0.sample(sample.x)
+    }
+}
+
+impl From<WavelengthRange> for Bounds1D {
+    fn from(range: WavelengthRange) -> Self {
+        range.0
+    }
+}
+
+impl TryFrom<Bounds1D> for WavelengthRange {
+    type Error = MathError;
+    fn try_from(bounds: Bounds1D) -> Result<Self> {
+        WavelengthRange::new(bounds.lower, bounds.upper)
+    }
+}
+
 // traits
 
 pub trait WavelengthEnergyTrait<L: Field, E: Field> {
@@ -49,6 +185,10 @@ pub trait WavelengthEnergyTrait<L: Field, E: Field> {
         WavelengthEnergy { lambda, energy }
     }
     fn new_from_range(sample: f32, bounds: Bounds1D) -> WavelengthEnergy<L, E>;
+    /// same as `new_from_range`, for the bounds of `range`
+    fn new_from_wavelength_range(sample: f32, range: WavelengthRange) -> WavelengthEnergy<L, E> {
+        Self::new_from_range(sample, range.into())
+    }
 }
 
 // does a WavelengthEnergy with L != E make any sense?
@@ -68,9 +208,9 @@ impl From<WavelengthEnergy<f32, f32>> for XYZColor {
     fn from(we: WavelengthEnergy<f32, f32>) -> Self {
         let angstroms = we.lambda * 10.0;
         XYZColor::new(
-            we.energy * x_bar(angstroms),
-            we.energy * y_bar(angstroms),
-            we.energy * z_bar(angstroms),
+            we.energy * x_bar_bounded(angstroms, CMF_SUPPORT),
+            we.energy * y_bar_bounded(angstroms, CMF_SUPPORT),
+            we.energy * z_bar_bounded(angstroms, CMF_SUPPORT),
         )
     }
 }
@@ -80,9 +220,9 @@ impl From<WavelengthEnergy<f32x4, f32x4>> for XYZColor {
     fn from(we: WavelengthEnergy<f32x4, f32x4>) -> Self {
         let angstroms = we.lambda * f32x4::splat(10.0);
         XYZColor::new(
-            (we.energy * x_bar_f32x4(angstroms)).reduce_sum(),
-            (we.energy * y_bar_f32x4(angstroms)).reduce_sum(),
-            (we.energy * z_bar_f32x4(angstroms)).reduce_sum(),
+            (we.energy * x_bar_bounded_f32x4(angstroms, CMF_SUPPORT)).reduce_sum(),
+            (we.energy * y_bar_bounded_f32x4(angstroms, CMF_SUPPORT)).reduce_sum(),
+            (we.energy * z_bar_bounded_f32x4(angstroms, CMF_SUPPORT)).reduce_sum(),
         )
     }
 }
@@ -96,16 +236,93 @@ impl WavelengthEnergyTrait<f32, f32> for WavelengthEnergy<f32, f32> {
     }
 }
 
-impl WavelengthEnergyTrait<f32x4, f32x4> for WavelengthEnergy<f32x4, f32x4> {
-    fn new_from_range(sample: f32, bounds: Bounds1D) -> WavelengthEnergy<f32x4, f32x4> {
+// hero wavelength sampling with any number of lanes, evenly spaced and rotated within the bounds
+impl<const N: usize> WavelengthEnergyTrait<Simd<f32, N>, Simd<f32, N>>
+    for WavelengthEnergy<Simd<f32, N>, Simd<f32, N>>
+{
+    fn new_from_range(
+        sample: f32,
+        bounds: Bounds1D,
+    ) -> WavelengthEnergy<Simd<f32, N>, Simd<f32, N>> {
         let hero = sample * bounds.span();
-        let delta = bounds.span() / 4.0;
-        let mult = f32x4::from_array([0.0, 1.0, 2.0, 3.0]);
+        let delta = bounds.span() / N as f32;
+        let mult = Simd::from_array(std::array::from_fn(|i| i as f32));
         let wavelengths =
-            f32x4::splat(bounds.lower) + (f32x4::splat(hero) + mult * f32x4::splat(delta));
-        let sub: f32x4 = wavelengths
-            .simd_gt(f32x4::splat(bounds.upper))
-            .select(f32x4::splat(bounds.span()), f32x4::splat(0.0));
-        HeroWavelength::new(wavelengths - sub, f32x4::splat(0.0))
+            Simd::splat(bounds.lower) + (Simd::splat(hero) + mult * Simd::splat(delta));
+        let sub: Simd<f32, N> = wavelengths
+            .simd_gt(Simd::splat(bounds.upper))
+            .select(Simd::splat(bounds.span()), Simd::splat(0.0));
+        Self::new(wavelengths - sub, Simd::splat(0.0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bounded_cmfs() {
+        // the gaussian fits are tiny but nonzero far outside of the visible range
+        assert!(x_bar(3000.0) > 0.0 || y_bar(9000.0) > 0.0);
+        assert_eq!(x_bar_bounded(3000.0, CMF_SUPPORT), 0.0);
+        assert_eq!(y_bar_bounded(9000.0, CMF_SUPPORT), 0.0);
+        assert_eq!(y_bar_bounded(5500.0, CMF_SUPPORT), y_bar(5500.0));
+    }
+
+    #[cfg(feature="simdfloat_patch")]
+    #[test]
+    fn test_bounded_cmfs_f32x4() {
+        let angstroms = f32x4::from_array([3000.0, 4500.0, 5500.0, 9000.0]);
+        let bounded = z_bar_bounded_f32x4(angstroms, CMF_SUPPORT);
+        assert_eq!(bounded[0], 0.0);
+        assert_eq!(bounded[1], z_bar_f32x4(angstroms)[1]);
+        assert_eq!(bounded[3], 0.0);
+
+        let xyz = XYZColor::from(HeroWavelength::new(
+            f32x4::from_array([300.0, 900.0, 1000.0, 2000.0]),
+            f32x4::splat(1.0),
+        ));
+        assert_eq!(xyz.0, f32x4::splat(0.0));
+    }
+
+    #[test]
+    fn test_wavelength_range() {
+        assert_eq!(
+            WavelengthRange::new(500.0, 400.0),
+            Err(MathError::EmptyBounds)
+        );
+        assert!(matches!(
+            WavelengthRange::new(-10.0, 400.0),
+            Err(MathError::OutOfDomain(..))
+        ));
+        assert!(WavelengthRange::new(400.0, f32::INFINITY).is_err());
+
+        let overlap = WavelengthRange::VISIBLE
+            .intersection(WavelengthRange::NEAR_INFRARED)
+            .is_none();
+        // visible and near infrared only share a single point
+        assert!(overlap);
+
+        let visible_uva = WavelengthRange::VISIBLE
+            .intersection(WavelengthRange::UVA)
+            .unwrap();
+        assert_eq!(visible_uva.bounds(), Bounds1D::new(380.0, 400.0));
+
+        assert_eq!(
+            WavelengthRange::try_from(SILICON_SENSITIVITY_RANGE),
+            Ok(WavelengthRange::SILICON_SENSITIVITY)
+        );
+        let lambda = WavelengthRange::VISIBLE.sample(Sample1D::new(0.5));
+        assert_eq!(lambda, 580.0);
+        let bounds: Bounds1D = WavelengthRange::VISIBLE.into();
+        assert_eq!(bounds, BOUNDED_VISIBLE_RANGE);
+
+        // ranges are accepted wherever wavelengths are sampled from bounds
+        let single = SingleWavelength::new_from_wavelength_range(0.0, WavelengthRange::UVA);
+        assert_eq!(single.lambda, WavelengthRange::UVA.lower());
+        let hero = HeroWavelength::new_from_wavelength_range(0.0, WavelengthRange::VISIBLE);
+        assert_eq!(hero.lambda[0], BOUNDED_VISIBLE_RANGE.lower);
+        let cdf = Curve::Const(1.0).to_cdf(WavelengthRange::NEAR_INFRARED, 10).unwrap();
+        assert_eq!(cdf.cdf_bounds(), Some(NEAR_INFRARED_RANGE));
     }
 }
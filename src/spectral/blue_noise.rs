@@ -0,0 +1,227 @@
+use crate::error::{MathError, Result};
+use crate::random::stream::StreamRng;
+use crate::sample::sequence::{GoldenRatioSequence, R2Sequence};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// per pixel offsets for the hero wavelength of each pixel.
+// with independent random offsets, the color noise of spectral rendering is white noise across the image.
+// offsets drawn from a blue noise mask instead put the error at high spatial frequencies, where it is less visible
+// and averages out under any filtering. the offset of each pixel is shifted by the golden ratio every frame,
+// such that each pixel also sees a low discrepancy sequence of offsets over time.
+
+fn temporal_shift(value: f32, frame: u32) -> f32 {
+    // the golden ratio sequence starting at the value, reduced in f64 such that late frames keep their fraction
+    GoldenRatioSequence::new(value).sequence(frame).x
+}
+
+/// an offset in [0, 1) for the hero wavelength of a pixel, for use as the sample of `HeroWavelength::new_from_range`.
+/// the spatial pattern is the R2 dither mask, which has a blue noise like spectrum without needing a precomputed table.
+pub fn wavelength_offset(pixel_x: u32, pixel_y: u32, frame: u32) -> f32 {
+    // the mask is frac(x / g + y / g^2), taking each term from the R2 sequence so that large pixel coordinates keep
+    // their fraction. the sum is reduced again by the temporal shift.
+    let r2 = R2Sequence::new((0.0, 0.0));
+    temporal_shift(r2.sequence(pixel_x).x + r2.sequence(pixel_y).y, frame)
+}
+
+/// A tileable blue noise mask, holding a value in [0, 1) per texel.
+/// values are evenly spread ranks, such that any threshold of the mask selects evenly spaced texels.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct BlueNoiseMask {
+    pub width: usize,
+    pub height: usize,
+    values: Vec<f32>,
+}
+
+impl BlueNoiseMask {
+    /// a mask from the ranks of a row major `width` by `height` texture, i.e. one loaded from a precomputed image.
+    /// fails with `UnsupportedLength` if `ranks` is not a permutation of 0..width * height.
+    pub fn from_ranks(width: usize, height: usize, ranks: &[usize]) -> Result<Self> {
+        let n = width * height;
+        if n == 0 || ranks.len() != n {
+            return Err(MathError::UnsupportedLength(ranks.len()));
+        }
+        let mut seen = vec![false; n];
+        for &rank in ranks {
+            if rank >= n || seen[rank] {
+                return Err(MathError::UnsupportedLength(ranks.len()));
+            }
+            seen[rank] = true;
+        }
+        Ok(BlueNoiseMask {
+            width,
+            height,
+            values: ranks
+                .iter()
+                .map(|&rank| (rank as f32 + 0.5) / n as f32)
+                .collect(),
+        })
+    }
+
+    /// generates a `size` by `size` mask with the void and cluster method (Ulichney 1993).
+    /// this is O(size^4), so it's meant for small masks, generated once and tiled.
+    pub fn generate(size: usize, seed: u64) -> Self {
+        let n = size * size;
+        assert!(n > 0, "blue noise mask size must be nonzero");
+        // a toroidal gaussian, indexed by the wrapped offset between two texels
+        let sigma = 1.5f32;
+        let kernel: Vec<f32> = (0..n)
+            .map(|i| {
+                let (dx, dy) = (i % size, i / size);
+                let dx = dx.min(size - dx) as f32;
+                let dy = dy.min(size - dy) as f32;
+                (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        let offset = |a: usize, b: usize| {
+            let dx = (a % size + size - b % size) % size;
+            let dy = (a / size + size - b / size) % size;
+            dx + dy * size
+        };
+        let update = |energy: &mut Vec<f32>, texel: usize, sign: f32| {
+            for (i, e) in energy.iter_mut().enumerate() {
+                *e += sign * kernel[offset(i, texel)];
+            }
+        };
+        let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+            (0..n)
+                .filter(|&i| pattern[i])
+                .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap()
+        };
+        let largest_void = |pattern: &[bool], energy: &[f32]| {
+            (0..n)
+                .filter(|&i| !pattern[i])
+                .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+                .unwrap()
+        };
+
+        // an initial random pattern covering a tenth of the texels
+        let mut rng = StreamRng::from_seed_u64(seed);
+        let mut pattern = vec![false; n];
+        let initial = (n / 10).max(1);
+        let mut count = 0;
+        while count < initial {
+            let i = (rng.next_f32() * n as f32) as usize % n;
+            if !pattern[i] {
+                pattern[i] = true;
+                count += 1;
+            }
+        }
+        let mut energy = vec![0.0; n];
+        for i in (0..n).filter(|&i| pattern[i]) {
+            update(&mut energy, i, 1.0);
+        }
+        // move points from the tightest cluster into the largest void until that no longer changes anything
+        if initial < n {
+            for _ in 0..n {
+                let cluster = tightest_cluster(&pattern, &energy);
+                pattern[cluster] = false;
+                update(&mut energy, cluster, -1.0);
+                let void = largest_void(&pattern, &energy);
+                pattern[void] = true;
+                update(&mut energy, void, 1.0);
+                if void == cluster {
+                    break;
+                }
+            }
+        }
+
+        let mut ranks = vec![0; n];
+        // rank the initial points by removing the tightest cluster, one at a time
+        let (mut removed, mut removed_energy) = (pattern.clone(), energy.clone());
+        for rank in (0..initial).rev() {
+            let cluster = tightest_cluster(&removed, &removed_energy);
+            removed[cluster] = false;
+            update(&mut removed_energy, cluster, -1.0);
+            ranks[cluster] = rank;
+        }
+        // then rank the rest by filling the largest void, one at a time
+        for rank in initial..n {
+            let void = largest_void(&pattern, &energy);
+            pattern[void] = true;
+            update(&mut energy, void, 1.0);
+            ranks[void] = rank;
+        }
+        BlueNoiseMask::from_ranks(size, size, &ranks)
+            .expect("void and cluster produces a permutation")
+    }
+
+    /// the value of the mask at a texel, tiling in both directions
+    pub fn value(&self, x: u32, y: u32) -> f32 {
+        self.values[(x as usize % self.width) + (y as usize % self.height) * self.width]
+    }
+
+    /// an offset in [0, 1) for the hero wavelength of a pixel, from the tiled mask shifted by the golden ratio every frame
+    pub fn wavelength_offset(&self, pixel_x: u32, pixel_y: u32, frame: u32) -> f32 {
+        temporal_shift(self.value(pixel_x, pixel_y), frame)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+    use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+    // the variance of the means of 4x4 blocks, which is 1 / 12 / 16 for white noise and much lower for blue noise
+    fn block_variance(size: u32, f: impl Fn(u32, u32) -> f32) -> f32 {
+        let means: Vec<f32> = (0..size / 4)
+            .flat_map(|by| (0..size / 4).map(move |bx| (bx, by)))
+            .map(|(bx, by)| {
+                (0..16)
+                    .map(|i| f(bx * 4 + i % 4, by * 4 + i / 4))
+                    .sum::<f32>()
+                    / 16.0
+            })
+            .collect();
+        let mean = means.iter().sum::<f32>() / means.len() as f32;
+        means.iter().map(|m| (m - mean).powi(2)).sum::<f32>() / means.len() as f32
+    }
+
+    #[test]
+    fn test_blue_noise_wavelength_offsets() {
+        let mask = BlueNoiseMask::generate(32, 1);
+        assert_eq!(mask, BlueNoiseMask::generate(32, 1));
+        // every rank is used once, so any threshold selects the expected fraction of texels
+        let mut values: Vec<f32> = (0..32 * 32).map(|i| mask.value(i % 32, i / 32)).collect();
+        values.sort_by(f32::total_cmp);
+        for (i, v) in values.iter().enumerate() {
+            assert!((v - (i as f32 + 0.5) / 1024.0).abs() < 1e-6);
+        }
+        assert_eq!(mask.value(3, 5), mask.value(35, 69));
+
+        let white = 1.0 / 12.0 / 16.0;
+        let r2 = block_variance(64, |x, y| wavelength_offset(x, y, 0));
+        let blue = block_variance(64, |x, y| mask.wavelength_offset(x, y, 0));
+        assert!(r2 < 0.25 * white, "{} {}", r2, white);
+        assert!(blue < 0.25 * white, "{} {}", blue, white);
+        let mut rng = StreamRng::from_seed_u64(2);
+        let noise: Vec<f32> = (0..64 * 64).map(|_| rng.next_f32()).collect();
+        let random = block_variance(64, |x, y| noise[(x + y * 64) as usize]);
+        assert!(random > 0.5 * white, "{} {}", random, white);
+
+        // over frames, each pixel sees evenly spread offsets
+        for (x, y) in [(0, 0), (17, 3), (1000, 2000)] {
+            let offsets: Vec<f32> = (0..64)
+                .map(|frame| wavelength_offset(x, y, frame))
+                .collect();
+            assert!(offsets.iter().all(|&v| (0.0..1.0).contains(&v)));
+            let mean = offsets.iter().sum::<f32>() / 64.0;
+            assert!((mean - 0.5).abs() < 0.02, "{}", mean);
+            assert!(offsets.windows(2).all(|w| w[0] != w[1]));
+        }
+        let hero =
+            HeroWavelength::new_from_range(wavelength_offset(5, 7, 3), BOUNDED_VISIBLE_RANGE);
+        assert!(BOUNDED_VISIBLE_RANGE.contains(&hero.lambda[0]));
+
+        assert!(BlueNoiseMask::from_ranks(2, 2, &[0, 1, 2, 3]).is_ok());
+        assert_eq!(
+            BlueNoiseMask::from_ranks(2, 2, &[0, 1, 1, 3]),
+            Err(MathError::UnsupportedLength(4))
+        );
+        assert!(BlueNoiseMask::from_ranks(2, 2, &[0, 1, 2]).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::prelude::*;
+use crate::spectral::{x_bar, y_bar, z_bar};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// the range of wavelengths, in nanometers, covered by `CIE_1931_TABLE`
+pub const CIE_1931_TABLE_RANGE: Bounds1D = Bounds1D::new(380.0, 780.0);
+const TABLE_STEP: f32 = 5.0;
+
+/// the CIE 1931 2 degree standard observer color matching functions, from 380nm to 780nm in 5nm increments.
+/// each entry holds x_bar, y_bar and z_bar.
+pub const CIE_1931_TABLE: [[f32; 3]; 81] = [
+    [0.001368, 0.000039, 0.00645],
+    [0.002236, 0.000064, 0.01055],
+    [0.004243, 0.00012, 0.02005],
+    [0.00765, 0.000217, 0.03621],
+    [0.01431, 0.000396, 0.06785],
+    [0.02319, 0.00064, 0.1102],
+    [0.04351, 0.00121, 0.2074],
+    [0.07763, 0.00218, 0.3713],
+    [0.13438, 0.004, 0.6456],
+    [0.21477, 0.0073, 1.03905],
+    [0.2839, 0.0116, 1.3856],
+    [0.3285, 0.01684, 1.62296],
+    [0.34828, 0.023, 1.74706],
+    [0.34806, 0.0298, 1.7826],
+    [0.3362, 0.038, 1.77211],
+    [0.3187, 0.048, 1.7441],
+    [0.2908, 0.06, 1.6692],
+    [0.2511, 0.0739, 1.5281],
+    [0.19536, 0.09098, 1.28764],
+    [0.1421, 0.1126, 1.0419],
+    [0.09564, 0.13902, 0.81295],
+    [0.05795, 0.1693, 0.6162],
+    [0.03201, 0.20802, 0.46518],
+    [0.0147, 0.2586, 0.3533],
+    [0.0049, 0.323, 0.272],
+    [0.0024, 0.4073, 0.2123],
+    [0.0093, 0.503, 0.1582],
+    [0.0291, 0.6082, 0.1117],
+    [0.06327, 0.71, 0.07825],
+    [0.1096, 0.7932, 0.05725],
+    [0.1655, 0.862, 0.04216],
+    [0.22575, 0.91485, 0.02984],
+    [0.2904, 0.954, 0.0203],
+    [0.3597, 0.9803, 0.0134],
+    [0.43345, 0.99495, 0.00875],
+    [0.51205, 1.0, 0.00575],
+    [0.5945, 0.995, 0.0039],
+    [0.6784, 0.9786, 0.00275],
+    [0.7621, 0.952, 0.0021],
+    [0.8425, 0.9154, 0.0018],
+    [0.9163, 0.87, 0.00165],
+    [0.9786, 0.8163, 0.0014],
+    [1.0263, 0.757, 0.0011],
+    [1.0567, 0.6949, 0.001],
+    [1.0622, 0.631, 0.0008],
+    [1.0456, 0.5668, 0.0006],
+    [1.0026, 0.503, 0.00034],
+    [0.9384, 0.4412, 0.00024],
+    [0.85445, 0.381, 0.00019],
+    [0.7514, 0.321, 0.0001],
+    [0.6424, 0.265, 0.00005],
+    [0.5419, 0.217, 0.00003],
+    [0.4479, 0.175, 0.00002],
+    [0.3608, 0.1382, 0.00001],
+    [0.2835, 0.107, 0.0],
+    [0.2187, 0.0816, 0.0],
+    [0.1649, 0.061, 0.0],
+    [0.1212, 0.04458, 0.0],
+    [0.0874, 0.032, 0.0],
+    [0.0636, 0.0232, 0.0],
+    [0.04677, 0.017, 0.0],
+    [0.0329, 0.01192, 0.0],
+    [0.0227, 0.00821, 0.0],
+    [0.01584, 0.005723, 0.0],
+    [0.011359, 0.004102, 0.0],
+    [0.008111, 0.002929, 0.0],
+    [0.00579, 0.002091, 0.0],
+    [0.004109, 0.001484, 0.0],
+    [0.002899, 0.001047, 0.0],
+    [0.002049, 0.00074, 0.0],
+    [0.00144, 0.00052, 0.0],
+    [0.001, 0.000361, 0.0],
+    [0.00069, 0.000249, 0.0],
+    [0.000476, 0.000172, 0.0],
+    [0.000332, 0.00012, 0.0],
+    [0.000235, 0.000085, 0.0],
+    [0.000166, 0.00006, 0.0],
+    [0.000117, 0.000042, 0.0],
+    [0.000083, 0.00003, 0.0],
+    [0.000059, 0.000021, 0.0],
+    [0.000042, 0.000015, 0.0],
+];
+
+/// linearly interpolates the tabulated color matching functions at `lambda` nanometers, returning 0 outside of the table
+pub fn cie_1931_tabulated(lambda: f32) -> [f32; 3] {
+    if !(CIE_1931_TABLE_RANGE.lower..=CIE_1931_TABLE_RANGE.upper).contains(&lambda) {
+        return [0.0; 3];
+    }
+    let t = (lambda - CIE_1931_TABLE_RANGE.lower) / TABLE_STEP;
+    let index = (t as usize).min(CIE_1931_TABLE.len() - 2);
+    let t = t - index as f32;
+    let (left, right) = (CIE_1931_TABLE[index], CIE_1931_TABLE[index + 1]);
+    [0, 1, 2].map(|i| left[i] + t * (right[i] - left[i]))
+}
+
+/// Selects the representation of the color matching functions used when integrating spectra to XYZ.
+/// `Fitted` evaluates the analytic multi-lobe gaussian fits (`x_bar`, `y_bar`, `z_bar`), which are cheap and smooth,
+/// while `Tabulated` interpolates the CIE data directly, which is slower but exact at the tabulated wavelengths.
+/// see `FitDeviation` for the difference between the two.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CMF {
+    #[default]
+    Fitted,
+    Tabulated,
+}
+
+/// the integral of the fitted `y_bar` over `CMF_SUPPORT`, in nanometers
+pub const FITTED_Y_BAR_INTEGRAL: f32 = 106.922_07;
+/// the integral of the linearly interpolated tabulated y_bar over `CIE_1931_TABLE_RANGE`, in nanometers
+pub const TABULATED_Y_BAR_INTEGRAL: f32 = 106.856_5;
+
+impl CMF {
+    /// the integral of y_bar over its support, i.e. the normalization factor that maps a spectrum that is
+    /// constant at 1 to a luminance Y of 1
+    pub fn y_bar_integral(&self) -> f32 {
+        match self {
+            CMF::Fitted => FITTED_Y_BAR_INTEGRAL,
+            CMF::Tabulated => TABULATED_Y_BAR_INTEGRAL,
+        }
+    }
+
+    /// evaluates x_bar, y_bar and z_bar at `lambda` nanometers
+    pub fn evaluate(&self, lambda: f32) -> [f32; 3] {
+        match self {
+            CMF::Fitted => {
+                let angstroms = lambda * 10.0;
+                [x_bar(angstroms), y_bar(angstroms), z_bar(angstroms)]
+            }
+            CMF::Tabulated => cie_1931_tabulated(lambda),
+        }
+    }
+}
+
+/// The deviation of the fitted color matching functions from the tabulated CIE data,
+/// measured at the tabulated wavelengths. each array holds the deviation of x_bar, y_bar and z_bar respectively.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FitDeviation {
+    pub max_absolute: [f32; 3],
+    pub mean_absolute: [f32; 3],
+    /// wavelength at which the maximum deviation occurs, in nanometers
+    pub max_wavelength: [f32; 3],
+}
+
+impl FitDeviation {
+    pub fn compute() -> Self {
+        let mut deviation = FitDeviation {
+            max_absolute: [0.0; 3],
+            mean_absolute: [0.0; 3],
+            max_wavelength: [CIE_1931_TABLE_RANGE.lower; 3],
+        };
+        for (i, tabulated) in CIE_1931_TABLE.iter().enumerate() {
+            let lambda = CIE_1931_TABLE_RANGE.lower + i as f32 * TABLE_STEP;
+            let fitted = CMF::Fitted.evaluate(lambda);
+            for channel in 0..3 {
+                let difference = (fitted[channel] - tabulated[channel]).abs();
+                deviation.mean_absolute[channel] += difference / CIE_1931_TABLE.len() as f32;
+                if difference > deviation.max_absolute[channel] {
+                    deviation.max_absolute[channel] = difference;
+                    deviation.max_wavelength[channel] = lambda;
+                }
+            }
+        }
+        deviation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tabulated_cmf() {
+        // an equal energy spectrum integrates to equal X, Y and Z
+        let sums = CIE_1931_TABLE.iter().fold([0.0f32; 3], |sum, row| {
+            [sum[0] + row[0], sum[1] + row[1], sum[2] + row[2]]
+        });
+        assert!((sums[0] - sums[1]).abs() < 1e-3 && (sums[2] - sums[1]).abs() < 1e-3);
+
+        assert_eq!(cie_1931_tabulated(555.0)[1], 1.0);
+        assert_eq!(cie_1931_tabulated(780.0), CIE_1931_TABLE[80]);
+        assert_eq!(cie_1931_tabulated(830.0), [0.0; 3]);
+        let between = cie_1931_tabulated(552.5);
+        assert!((between[1] - (0.99495 + 1.0) / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fit_deviation() {
+        let deviation = FitDeviation::compute();
+        for channel in 0..3 {
+            assert!(deviation.max_absolute[channel] < 0.03, "{:?}", deviation);
+            assert!(deviation.mean_absolute[channel] < 0.006, "{:?}", deviation);
+            assert!(deviation.mean_absolute[channel] <= deviation.max_absolute[channel]);
+        }
+    }
+}
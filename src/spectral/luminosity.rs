@@ -0,0 +1,240 @@
+use crate::photographic::MAX_LUMINOUS_EFFICACY;
+use crate::prelude::*;
+use crate::spectral::{y_bar_bounded, CMF_SUPPORT};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// luminous efficiency functions for low light vision. the photopic V(lambda) is y_bar,
+// the scotopic V'(lambda) describes vision by the rods alone below about 0.005 cd/m^2,
+// and the mesopic range in between is a blend of the two (CIE 191:2010).
+
+/// the range of wavelengths, in nanometers, over which V'(lambda) is tabulated
+pub const SCOTOPIC_TABLE_RANGE: Bounds1D = Bounds1D::new(380.0, 780.0);
+
+/// the CIE 1951 scotopic luminous efficiency function V'(lambda) at 10nm increments from 380 to 780 nm.
+/// it peaks at 507 nm, between the tabulated wavelengths.
+pub const SCOTOPIC_TABLE: [f32; 41] = [
+    0.000589,
+    0.002209,
+    0.00929,
+    0.03484,
+    0.0966,
+    0.1998,
+    0.3281,
+    0.455,
+    0.567,
+    0.676,
+    0.793,
+    0.904,
+    0.982,
+    0.997,
+    0.935,
+    0.811,
+    0.650,
+    0.481,
+    0.3288,
+    0.2076,
+    0.1212,
+    0.0655,
+    0.03315,
+    0.01593,
+    0.00737,
+    0.003335,
+    0.001497,
+    0.000677,
+    0.0003129,
+    0.000148,
+    0.0000715,
+    0.00003533,
+    0.0000178,
+    0.00000914,
+    0.00000478,
+    0.000002546,
+    0.000001379,
+    0.00000076,
+    0.000000425,
+    0.0000002413,
+    0.000000139,
+];
+
+/// the maximum scotopic luminous efficacy in lm / W, at 507 nm
+pub const SCOTOPIC_LUMINOUS_EFFICACY: f32 = 1700.0;
+/// V'(555 nm), which relates scotopic and photopic luminance in the mesopic formulas
+const SCOTOPIC_AT_555: f32 = 0.402;
+
+/// linearly interpolates V'(lambda) at `lambda` nanometers, returning 0 outside of the table
+pub fn scotopic_v_prime(lambda: f32) -> f32 {
+    if !(SCOTOPIC_TABLE_RANGE.lower..=SCOTOPIC_TABLE_RANGE.upper).contains(&lambda) {
+        return 0.0;
+    }
+    let t = (lambda - SCOTOPIC_TABLE_RANGE.lower) / 10.0;
+    let index = (t as usize).min(SCOTOPIC_TABLE.len() - 2);
+    let t = t - index as f32;
+    SCOTOPIC_TABLE[index] + t * (SCOTOPIC_TABLE[index + 1] - SCOTOPIC_TABLE[index])
+}
+
+fn photopic_v(lambda: f32) -> f32 {
+    y_bar_bounded(lambda * 10.0, CMF_SUPPORT)
+}
+
+/// the adaptation coefficient m of the CIE 191 mesopic system for a field with photopic luminance `photopic`
+/// and scotopic luminance `scotopic`, both in their own cd / m^2. m is 1 for photopic and 0 for scotopic vision.
+/// solved by fixed point iteration, which converges within a few steps.
+pub fn mesopic_adaptation(photopic: f32, scotopic: f32) -> f32 {
+    let mut m = 0.5;
+    for _ in 0..32 {
+        let luminance = mesopic_luminance(photopic, scotopic, m);
+        let next = if luminance <= 0.0 {
+            0.0
+        } else {
+            (0.7670 + 0.3334 * luminance.log10()).clamp(0.0, 1.0)
+        };
+        if Tolerance::DEFAULT.is_near_zero(next - m) {
+            return next;
+        }
+        m = next;
+    }
+    m
+}
+
+/// the mesopic luminance for adaptation coefficient `m`, from the photopic and scotopic luminances
+pub fn mesopic_luminance(photopic: f32, scotopic: f32, m: f32) -> f32 {
+    (m * photopic + (1.0 - m) * scotopic * SCOTOPIC_AT_555) / (m + (1.0 - m) * SCOTOPIC_AT_555)
+}
+
+/// Selects the luminous efficiency function for `Curve::convert_to_luminance`.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LuminanceMode {
+    /// V(lambda), i.e. y_bar, for daylight vision
+    Photopic,
+    /// V'(lambda), for vision in the dark
+    Scotopic,
+    /// the CIE 191 blend with a fixed adaptation coefficient in [0, 1]
+    Mesopic(f32),
+    /// the CIE 191 blend, with the adaptation coefficient solved from the spectrum itself
+    AdaptiveMesopic,
+}
+
+impl Curve {
+    /// the scotopic luminous efficiency function V'(lambda) as a curve
+    pub fn scotopic() -> Curve {
+        Curve::Tabulated {
+            signal: SCOTOPIC_TABLE
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (SCOTOPIC_TABLE_RANGE.lower + 10.0 * i as f32, *v))
+                .collect(),
+            mode: InterpolationMode::Linear,
+        }
+    }
+
+    /// the mesopic luminous efficiency function m V(lambda) + (1 - m) V'(lambda), normalized to a peak of 1,
+    /// tabulated at 5nm increments
+    pub fn mesopic(m: f32) -> Curve {
+        let signal: Vec<(f32, f32)> = (0..=80)
+            .map(|i| {
+                let lambda = SCOTOPIC_TABLE_RANGE.lower + 5.0 * i as f32;
+                (
+                    lambda,
+                    m * photopic_v(lambda) + (1.0 - m) * scotopic_v_prime(lambda),
+                )
+            })
+            .collect();
+        let peak = signal.iter().fold(0.0f32, |peak, (_, v)| peak.max(*v));
+        Curve::Tabulated {
+            signal: signal.into_iter().map(|(x, v)| (x, v / peak)).collect(),
+            mode: InterpolationMode::Linear,
+        }
+    }
+
+    /// integrates the curve as spectral radiance in W / (m^2 sr nm) to luminance in cd / m^2,
+    /// with the same rule as `convert_to_xyz`. scotopic luminance is in scotopic cd / m^2.
+    pub fn convert_to_luminance(
+        &self,
+        integration_bounds: Bounds1D,
+        step_size: f32,
+        mode: LuminanceMode,
+    ) -> f32 {
+        let iterations = (integration_bounds.span() / step_size) as usize;
+        let (mut photopic, mut scotopic) = (0.0, 0.0);
+        for i in 0..iterations {
+            let lambda = integration_bounds.lower + (i as f32) * step_size;
+            let value = self.evaluate_power(lambda) * step_size;
+            photopic += value * photopic_v(lambda);
+            scotopic += value * scotopic_v_prime(lambda);
+        }
+        let (photopic, scotopic) = (
+            MAX_LUMINOUS_EFFICACY * photopic,
+            SCOTOPIC_LUMINOUS_EFFICACY * scotopic,
+        );
+        match mode {
+            LuminanceMode::Photopic => photopic,
+            LuminanceMode::Scotopic => scotopic,
+            LuminanceMode::Mesopic(m) => mesopic_luminance(photopic, scotopic, m),
+            LuminanceMode::AdaptiveMesopic => {
+                mesopic_luminance(photopic, scotopic, mesopic_adaptation(photopic, scotopic))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scotopic_luminance() {
+        assert_eq!(scotopic_v_prime(510.0), 0.997);
+        assert_eq!(scotopic_v_prime(300.0), 0.0);
+        let peak = (500..520)
+            .map(|l| (l, scotopic_v_prime(l as f32)))
+            .fold((0, 0.0), |a, b| if b.1 > a.1 { b } else { a });
+        assert!((505..=510).contains(&peak.0));
+        assert!((scotopic_v_prime(555.0) - SCOTOPIC_AT_555).abs() < 2e-2);
+
+        // blue light is relatively brighter in the dark, the purkinje effect
+        let bounds = SCOTOPIC_TABLE_RANGE;
+        let blue = Curve::Exponential {
+            signal: vec![(470.0, 10.0, 10.0, 1e-3)],
+        };
+        let red = Curve::Exponential {
+            signal: vec![(620.0, 10.0, 10.0, 1e-3)],
+        };
+        let ratio = |curve: &Curve| {
+            curve.convert_to_luminance(bounds, 1.0, LuminanceMode::Scotopic)
+                / curve.convert_to_luminance(bounds, 1.0, LuminanceMode::Photopic)
+        };
+        assert!(ratio(&blue) > 10.0 * ratio(&red));
+
+        // mesopic luminance goes between the two, and equals photopic for bright light
+        let flat = Curve::Const(1e-5);
+        let photopic = flat.convert_to_luminance(bounds, 1.0, LuminanceMode::Photopic);
+        let scotopic = flat.convert_to_luminance(bounds, 1.0, LuminanceMode::Scotopic);
+        assert_eq!(
+            flat.convert_to_luminance(bounds, 1.0, LuminanceMode::Mesopic(1.0)),
+            photopic
+        );
+        assert!(
+            (flat.convert_to_luminance(bounds, 1.0, LuminanceMode::Mesopic(0.0)) - scotopic).abs()
+                < 1e-6 * scotopic
+        );
+        assert_eq!(mesopic_adaptation(10.0, 25.0), 1.0);
+        assert_eq!(mesopic_adaptation(0.001, 0.0025), 0.0);
+        let m = mesopic_adaptation(0.1, 0.25);
+        assert!(m > 0.0 && m < 1.0);
+        // m is a fixed point
+        let luminance = mesopic_luminance(0.1, 0.25, m);
+        assert!((0.7670 + 0.3334 * luminance.log10() - m).abs() < 1e-5);
+        let adaptive = flat.convert_to_luminance(bounds, 1.0, LuminanceMode::AdaptiveMesopic);
+        assert!(adaptive.is_finite() && adaptive > 0.0);
+
+        let mesopic = Curve::mesopic(0.5);
+        let max = (380..780)
+            .map(|l| mesopic.evaluate(l as f32))
+            .fold(0.0f32, f32::max);
+        assert!((max - 1.0).abs() < 1e-6);
+        assert_eq!(Curve::scotopic().evaluate(510.0), 0.997);
+    }
+}
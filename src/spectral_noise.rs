@@ -0,0 +1,163 @@
+use crate::prelude::*;
+use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Generates random, smooth reflectance spectra that are bounded within [0, 1],
+/// useful for fuzz testing spectral code paths and for generating varied test scenes.
+///
+/// spectra are built from a low order Fourier series in logit space, which is then mapped through a sigmoid.
+/// this keeps the result band limited and bounded without any clipping.
+/// linear interpolation is used between samples so that the bounds hold for the resulting curve as well.
+#[derive(Copy, Clone, Debug)]
+pub struct SpectrumNoise {
+    pub bounds: Bounds1D,
+    /// number of Fourier harmonics. more harmonics allow for more detail across the domain
+    pub harmonics: usize,
+    /// the median reflectance, in (0, 1)
+    pub median: f32,
+    /// standard deviation of the series in logit space. larger values produce more saturated spectra
+    pub amplitude: f32,
+    /// the amplitude of the kth harmonic is proportional to 1 / k^rolloff
+    pub rolloff: f32,
+    /// number of samples in the resulting `Curve::Linear`
+    pub resolution: usize,
+}
+
+impl Default for SpectrumNoise {
+    fn default() -> Self {
+        SpectrumNoise {
+            bounds: BOUNDED_VISIBLE_RANGE,
+            harmonics: 4,
+            median: 0.5,
+            amplitude: 1.5,
+            rolloff: 1.0,
+            resolution: 64,
+        }
+    }
+}
+
+impl SpectrumNoise {
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Curve {
+        debug_assert!(0.0 < self.median && self.median < 1.0);
+        let offset = (self.median / (1.0 - self.median)).ln();
+
+        // uniform in [-sqrt(3), sqrt(3)] has unit variance
+        let sqrt3 = 3.0f32.sqrt();
+        let mut coefficients = Vec::with_capacity(self.harmonics);
+        let mut variance = 0.0;
+        for k in 1..=self.harmonics {
+            let falloff = (k as f32).powf(-self.rolloff);
+            let a = rng.gen_range(-sqrt3..sqrt3) * falloff;
+            let b = rng.gen_range(-sqrt3..sqrt3) * falloff;
+            // each harmonic contributes (a^2 + b^2) / 2 on average over the domain
+            variance += falloff * falloff;
+            coefficients.push((a, b));
+        }
+        let normalization = if variance > 0.0 {
+            self.amplitude / variance.sqrt()
+        } else {
+            0.0
+        };
+        let phase = rng.gen_range(0.0..1.0f32);
+
+        Curve::from_function(
+            |lambda| {
+                let t = (lambda - self.bounds.lower) / self.bounds.span() + phase;
+                let mut series = 0.0;
+                for (i, (a, b)) in coefficients.iter().enumerate() {
+                    let (sin, cos) = ((i + 1) as f32 * PI * t).sin_cos();
+                    series += a * cos + b * sin;
+                }
+                sigmoid(offset + normalization * series)
+            },
+            self.resolution,
+            self.bounds,
+            InterpolationMode::Linear,
+        )
+    }
+
+    pub fn generate_seeded(&self, seed: u64) -> Curve {
+        self.generate(&mut StdRng::seed_from_u64(seed))
+    }
+}
+
+/// generates a random `Curve::Exponential` with `lobes` gaussian lobes within `bounds`, clamped to [0, 1].
+/// `width` bounds the standard deviations of each side of each lobe, in nanometers.
+pub fn random_gaussian_mixture<R: Rng + ?Sized>(
+    rng: &mut R,
+    bounds: Bounds1D,
+    lobes: usize,
+    width: Bounds1D,
+) -> Curve {
+    let signal = (0..lobes)
+        .map(|_| {
+            (
+                bounds.sample(rng.gen()),
+                width.sample(rng.gen()),
+                width.sample(rng.gen()),
+                // keep the total under 1 where lobes overlap
+                rng.gen_range(0.0..1.0) / lobes as f32,
+            )
+        })
+        .collect();
+    Curve::Exponential { signal }.clamped(0.0, 1.0)
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spectrum_noise_bounded_and_seeded() {
+        let generator = SpectrumNoise::default();
+        for seed in 0..20 {
+            let curve = generator.generate_seeded(seed);
+            let again = generator.generate_seeded(seed);
+            for i in 0..=100 {
+                let lambda = generator.bounds.lerp(i as f32 / 100.0);
+                let value = curve.evaluate(lambda);
+                assert!((0.0..=1.0).contains(&value), "{}", value);
+                assert_eq!(value, again.evaluate(lambda));
+            }
+        }
+    }
+
+    #[test]
+    fn test_spectrum_noise_statistics() {
+        let generator = SpectrumNoise {
+            median: 0.3,
+            ..SpectrumNoise::default()
+        };
+        let mut rng = StdRng::seed_from_u64(1234);
+        let mut values = Vec::new();
+        for _ in 0..200 {
+            let curve = generator.generate(&mut rng);
+            for i in 0..10 {
+                values.push(curve.evaluate(generator.bounds.lerp(i as f32 / 10.0)));
+            }
+        }
+        let median = crate::simd_util::median(&values).unwrap();
+        assert!((median - 0.3).abs() < 0.05, "{}", median);
+    }
+
+    #[test]
+    fn test_random_gaussian_mixture() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let curve = random_gaussian_mixture(
+            &mut rng,
+            BOUNDED_VISIBLE_RANGE,
+            3,
+            Bounds1D::new(10.0, 60.0),
+        );
+        for i in 0..=100 {
+            let value = curve.evaluate(BOUNDED_VISIBLE_RANGE.lerp(i as f32 / 100.0));
+            assert!((0.0..=1.0).contains(&value));
+        }
+    }
+}
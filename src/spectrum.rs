@@ -0,0 +1,164 @@
+use crate::prelude::*;
+
+pub mod field;
+pub mod sampled;
+
+// dynamic dispatch over spectra.
+// `SpectralPowerDistributionFunction` is generic over the field type, and a type usually implements it for both f32
+// and f32x4, so a `dyn SpectralPowerDistributionFunction<_>` can only ever expose one of them.
+// `Spectrum` is the scalar only, object safe core, which every scalar spectral power distribution implements
+// automatically. `dyn Spectrum` then implements the generic trait again, evaluating hero wavelengths lane by lane,
+// so that mixed collections of curves, CDFs and procedural spectra can be passed to generic code.
+
+/// Object safe interface for scalar spectral power distributions.
+/// method names differ from those of `SpectralPowerDistributionFunction` to avoid ambiguity,
+/// as every implementor of that trait (for f32) also implements this one.
+pub trait Spectrum {
+    /// range: [0, infinity)
+    fn power(&self, lambda: f32) -> f32;
+    /// range: [0, 1]
+    fn clamped_power(&self, lambda: f32) -> f32;
+    fn sample_wavelength(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>);
+}
+
+impl<S: SpectralPowerDistributionFunction<f32>> Spectrum for S {
+    fn power(&self, lambda: f32) -> f32 {
+        self.evaluate_power(lambda)
+    }
+    fn clamped_power(&self, lambda: f32) -> f32 {
+        self.evaluate_clamped(lambda)
+    }
+    fn sample_wavelength(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        self.sample_power_and_pdf(wavelength_range, sample)
+    }
+}
+
+impl SpectralPowerDistributionFunction<f32> for dyn Spectrum + '_ {
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        self.power(lambda)
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        self.clamped_power(lambda)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        self.sample_wavelength(wavelength_range, sample)
+    }
+}
+
+impl SpectralPowerDistributionFunction<f32x4> for dyn Spectrum + '_ {
+    fn evaluate_power(&self, lambda: f32x4) -> f32x4 {
+        f32x4::from_array(lambda.to_array().map(|l| self.power(l)))
+    }
+    fn evaluate_clamped(&self, lambda: f32x4) -> f32x4 {
+        f32x4::from_array(lambda.to_array().map(|l| self.clamped_power(l)))
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (HeroWavelength, PDF<f32x4, Uniform01>) {
+        // the lanes are stratified over the range, so importance sampling of the hero wavelength does not carry over
+        let ws = HeroWavelength::new_from_range(sample.x, wavelength_range);
+        (
+            ws.replace_energy(SpectralPowerDistributionFunction::<f32x4>::evaluate_power(
+                self, ws.lambda,
+            )),
+            PDF::new(f32x4::splat(1.0 / wavelength_range.span())),
+        )
+    }
+}
+
+impl SpectralPowerDistributionFunction<f32> for Box<dyn Spectrum + '_> {
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        (**self).power(lambda)
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        (**self).clamped_power(lambda)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        (**self).sample_wavelength(wavelength_range, sample)
+    }
+}
+
+/// A spectrum defined by an arbitrary function of wavelength, sampled uniformly
+#[derive(Copy, Clone)]
+pub struct ProceduralSpectrum<F: Fn(f32) -> f32>(pub F);
+
+impl<F: Fn(f32) -> f32> SpectralPowerDistributionFunction<f32> for ProceduralSpectrum<F> {
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        (self.0)(lambda).max(0.0)
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        (self.0)(lambda).clamp(0.0, 1.0)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        let ws = SingleWavelength::new_from_range(sample.x, wavelength_range);
+        (
+            ws.replace_energy(self.evaluate_power(ws.lambda)),
+            PDF::new(1.0 / wavelength_range.span()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+    fn total_power<S: SpectralPowerDistributionFunction<f32> + ?Sized>(spectrum: &S) -> f32 {
+        (380..780).map(|l| spectrum.evaluate_power(l as f32)).sum()
+    }
+
+    #[test]
+    fn test_dyn_spectrum() {
+        let curve = Curve::Linear {
+            signal: vec![0.0, 1.0, 2.0, 3.0],
+            bounds: Bounds1D::new(380.0, 780.0),
+            mode: InterpolationMode::Linear,
+        };
+        let cdf = curve.to_cdf(Bounds1D::new(380.0, 780.0), 100).unwrap();
+        let spectra: Vec<Box<dyn Spectrum>> = vec![
+            Box::new(Curve::Const(0.5)),
+            Box::new(cdf),
+            Box::new(ProceduralSpectrum(|lambda: f32| (lambda - 380.0) / 400.0)),
+        ];
+
+        assert_eq!(spectra[0].power(550.0), 0.5);
+        assert_eq!(spectra[1].power(580.0), curve.evaluate_power(580.0));
+        assert!((spectra[2].power(580.0) - 0.5).abs() < 1e-6);
+        assert_eq!(spectra[2].clamped_power(1000.0), 1.0);
+
+        // dyn spectra and boxes of them can be passed to generic code
+        assert_eq!(total_power(&*spectra[0]), 200.0);
+        assert_eq!(total_power(&spectra[0]), 200.0);
+
+        let lambdas = f32x4::from_array([400.0, 500.0, 600.0, 700.0]);
+        let hero: f32x4 = spectra[2].as_ref().evaluate_power(lambdas);
+        assert!((hero[2] - 0.55).abs() < 1e-6);
+        let (sampled, pdf): (HeroWavelength, _) = spectra[1]
+            .as_ref()
+            .sample_power_and_pdf(BOUNDED_VISIBLE_RANGE, Sample1D::new(0.3));
+        assert_eq!(sampled.energy[0], spectra[1].power(sampled.lambda[0]));
+        assert_eq!(pdf[0], 1.0 / 400.0);
+    }
+}
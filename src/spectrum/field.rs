@@ -0,0 +1,224 @@
+use crate::error::{MathError, Result};
+use crate::prelude::*;
+
+// spatially varying spectra, i.e. spectral textures.
+// a field maps surface coordinates (u, v) to a spectrum, which is returned by reference as a weighted blend of the
+// curves the field owns, such that looking up the spectrum at a point doesn't need to allocate or resample any curves.
+
+/// The spectrum at a point of a `SpectralField`, as a blend of the curves of the field.
+/// blends are evaluated by blending the values of each curve at a wavelength, not by blending the curves themselves.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CurveRef<'a> {
+    Single(&'a Curve),
+    /// (1 - t) * first + t * second
+    Blend(&'a Curve, &'a Curve, f32),
+    /// four curves with weights that sum to 1
+    Bilinear([&'a Curve; 4], [f32; 4]),
+}
+
+impl<'a> CurveRef<'a> {
+    pub fn evaluate(&self, lambda: f32) -> f32 {
+        match self {
+            CurveRef::Single(curve) => curve.evaluate(lambda),
+            CurveRef::Blend(first, second, t) => {
+                (1.0 - t) * first.evaluate(lambda) + t * second.evaluate(lambda)
+            }
+            CurveRef::Bilinear(curves, weights) => curves
+                .iter()
+                .zip(weights.iter())
+                .map(|(curve, weight)| weight * curve.evaluate(lambda))
+                .sum(),
+        }
+    }
+
+    /// evaluates each lane of a hero wavelength
+    pub fn evaluate_hero(&self, lambda: f32x4) -> f32x4 {
+        f32x4::from_array(lambda.to_array().map(|l| self.evaluate(l)))
+    }
+}
+
+impl<'a> SpectralPowerDistributionFunction<f32> for CurveRef<'a> {
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        self.evaluate(lambda).max(0.0)
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        self.evaluate(lambda).clamp(0.0, 1.0)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        if let CurveRef::Single(curve) = self {
+            return curve.sample_power_and_pdf(wavelength_range, sample);
+        }
+        let ws = SingleWavelength::new_from_range(sample.x, wavelength_range);
+        (
+            ws.replace_energy(self.evaluate_power(ws.lambda)),
+            PDF::new(1.0 / wavelength_range.span()),
+        )
+    }
+}
+
+/// A spectrum that varies over surface coordinates, i.e. a spectral texture.
+pub trait SpectralField {
+    /// the spectrum at (u, v)
+    fn curve_at(&self, u: f32, v: f32) -> CurveRef<'_>;
+
+    fn evaluate_at(&self, u: f32, v: f32, lambda: f32) -> f32 {
+        self.curve_at(u, v).evaluate(lambda)
+    }
+
+    fn evaluate_hero_at(&self, u: f32, v: f32, lambda: f32x4) -> f32x4 {
+        self.curve_at(u, v).evaluate_hero(lambda)
+    }
+}
+
+/// The same spectrum everywhere
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstantField(pub Curve);
+
+impl SpectralField for ConstantField {
+    fn curve_at(&self, _u: f32, _v: f32) -> CurveRef<'_> {
+        CurveRef::Single(&self.0)
+    }
+}
+
+/// Alternates between two spectra on a checkerboard with `frequency` squares per unit of u and v
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckerboardField {
+    pub even: Curve,
+    pub odd: Curve,
+    pub frequency: f32,
+}
+
+impl CheckerboardField {
+    pub fn new(even: Curve, odd: Curve, frequency: f32) -> Self {
+        CheckerboardField {
+            even,
+            odd,
+            frequency,
+        }
+    }
+}
+
+impl SpectralField for CheckerboardField {
+    fn curve_at(&self, u: f32, v: f32) -> CurveRef<'_> {
+        let parity = (u * self.frequency).floor() as i64 + (v * self.frequency).floor() as i64;
+        if parity.rem_euclid(2) == 0 {
+            CurveRef::Single(&self.even)
+        } else {
+            CurveRef::Single(&self.odd)
+        }
+    }
+}
+
+/// A `width` by `height` grid of spectra covering [0, 1) in u and v, like the texels of an image.
+/// each curve sits at the center of its cell, and values in between are bilinearly blended.
+/// coordinates outside of [0, 1) wrap around.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridField {
+    pub width: usize,
+    pub height: usize,
+    curves: Vec<Curve>,
+}
+
+impl GridField {
+    /// `curves` is in row major order, with v selecting the row.
+    /// fails with `UnsupportedLength` if there isn't exactly one curve per cell.
+    pub fn new(width: usize, height: usize, curves: Vec<Curve>) -> Result<Self> {
+        if width == 0 || height == 0 || curves.len() != width * height {
+            return Err(MathError::UnsupportedLength(curves.len()));
+        }
+        Ok(GridField {
+            width,
+            height,
+            curves,
+        })
+    }
+
+    pub fn curve(&self, x: usize, y: usize) -> &Curve {
+        &self.curves[x + y * self.width]
+    }
+}
+
+impl SpectralField for GridField {
+    fn curve_at(&self, u: f32, v: f32) -> CurveRef<'_> {
+        // offset by half a cell, such that cell centers land on integers
+        let (x, y) = (u * self.width as f32 - 0.5, v * self.height as f32 - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let wrap = |i: f32, n: usize| (i as i64).rem_euclid(n as i64) as usize;
+        let (x0, x1) = (wrap(x0, self.width), wrap(x0 + 1.0, self.width));
+        let (y0, y1) = (wrap(y0, self.height), wrap(y0 + 1.0, self.height));
+        CurveRef::Bilinear(
+            [
+                self.curve(x0, y0),
+                self.curve(x1, y0),
+                self.curve(x0, y1),
+                self.curve(x1, y1),
+            ],
+            [
+                (1.0 - tx) * (1.0 - ty),
+                tx * (1.0 - ty),
+                (1.0 - tx) * ty,
+                tx * ty,
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_spectral_fields() {
+        let red = Curve::Linear {
+            signal: vec![0.0, 1.0],
+            bounds: Bounds1D::new(400.0, 700.0),
+            mode: InterpolationMode::Linear,
+        };
+        let fields: Vec<Box<dyn SpectralField>> = vec![
+            Box::new(ConstantField(Curve::Const(0.25))),
+            Box::new(CheckerboardField::new(
+                Curve::Const(0.0),
+                Curve::Const(1.0),
+                4.0,
+            )),
+        ];
+        assert_eq!(fields[0].evaluate_at(0.3, 0.9, 550.0), 0.25);
+        let checker = &fields[1];
+        assert_eq!(checker.evaluate_at(0.1, 0.1, 550.0), 0.0);
+        assert_eq!(checker.evaluate_at(0.3, 0.1, 550.0), 1.0);
+        assert_eq!(checker.evaluate_at(0.3, 0.3, 550.0), 0.0);
+        // negative coordinates continue the pattern
+        assert_eq!(checker.evaluate_at(-0.1, 0.1, 550.0), 1.0);
+
+        let grid = GridField::new(2, 1, vec![Curve::Const(0.2), Curve::Const(0.6)]).unwrap();
+        // exactly the curve at a cell center, halfway between two centers, and wrapping at the edge
+        assert!((grid.evaluate_at(0.25, 0.5, 550.0) - 0.2).abs() < 1e-6);
+        assert!((grid.evaluate_at(0.5, 0.5, 550.0) - 0.4).abs() < 1e-6);
+        assert!((grid.evaluate_at(0.0, 0.5, 550.0) - 0.4).abs() < 1e-6);
+        assert!((grid.evaluate_at(1.25, 0.5, 550.0) - 0.2).abs() < 1e-6);
+
+        // blends of wavelength dependent curves are blends of their values
+        let grid = GridField::new(1, 2, vec![red.clone(), Curve::Const(1.0)]).unwrap();
+        let at = grid.curve_at(0.5, 0.5);
+        let lambda = f32x4::from_array([400.0, 500.0, 600.0, 700.0]);
+        let values = grid.evaluate_hero_at(0.5, 0.5, lambda);
+        for (l, value) in lambda.to_array().iter().zip(values.to_array().iter()) {
+            let expected = 0.5 * red.evaluate(*l) + 0.5;
+            assert!((value - expected).abs() < 1e-6, "{} {}", value, expected);
+            assert_eq!(at.evaluate_power(*l), *value);
+        }
+        // a blended spectrum can be used wherever a spectrum is expected
+        let spectrum: &dyn Spectrum = &at;
+        assert!(spectrum.power(550.0) > 0.5);
+
+        assert_eq!(
+            GridField::new(2, 2, vec![Curve::Const(0.0)]),
+            Err(MathError::UnsupportedLength(1))
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::prelude::*;
+use crate::spectral::{x_bar_bounded, y_bar_bounded, z_bar_bounded, CMF_SUPPORT};
+
+use std::ops::{AddAssign, MulAssign, Sub};
+use std::simd::cmp::SimdPartialEq;
+
+use super::Spectrum;
+
+/// A spectrum binned on a fixed wavelength grid, for renderers that carry whole spectra rather than hero wavelengths.
+/// `bounds` is split into 4 * `N` equal bins, stored as `N` chunks of 4, and each bin holds the value at its center.
+/// arithmetic is elementwise, and requires both operands to share the same bounds.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SampledSpectrum<const N: usize> {
+    pub bounds: Bounds1D,
+    pub values: [f32x4; N],
+}
+
+impl<const N: usize> SampledSpectrum<N> {
+    pub const BINS: usize = 4 * N;
+
+    pub fn new(bounds: Bounds1D, values: [f32x4; N]) -> Self {
+        debug_assert!(bounds.span() > 0.0);
+        SampledSpectrum { bounds, values }
+    }
+    pub fn splat(bounds: Bounds1D, value: f32) -> Self {
+        SampledSpectrum::new(bounds, [f32x4::splat(value); N])
+    }
+    pub fn zero(bounds: Bounds1D) -> Self {
+        SampledSpectrum::splat(bounds, 0.0)
+    }
+
+    /// samples `spectrum` at the center of each bin
+    pub fn from_spectrum(spectrum: &(impl Spectrum + ?Sized), bounds: Bounds1D) -> Self {
+        let mut sampled = SampledSpectrum::zero(bounds);
+        for i in 0..Self::BINS {
+            sampled.set(i, spectrum.power(sampled.wavelength(i)));
+        }
+        sampled
+    }
+
+    /// a tabulated curve through the bin centers, which is held constant beyond the first and last centers
+    pub fn to_curve(&self) -> Curve {
+        Curve::Tabulated {
+            signal: self.iter().collect(),
+            mode: InterpolationMode::Linear,
+        }
+    }
+
+    pub fn bin_width(&self) -> f32 {
+        self.bounds.span() / Self::BINS as f32
+    }
+    /// the wavelength at the center of bin `i`
+    pub fn wavelength(&self, i: usize) -> f32 {
+        self.bounds.lower + (i as f32 + 0.5) * self.bin_width()
+    }
+    /// the index of the bin containing `lambda`, if it lies within the bounds, including the upper end
+    pub fn bin(&self, lambda: f32) -> Option<usize> {
+        if !self.bounds.contains(&lambda) && lambda != self.bounds.upper {
+            return None;
+        }
+        Some((((lambda - self.bounds.lower) / self.bin_width()) as usize).min(Self::BINS - 1))
+    }
+
+    pub fn get(&self, i: usize) -> f32 {
+        self.values[i / 4][i % 4]
+    }
+    pub fn set(&mut self, i: usize, value: f32) {
+        self.values[i / 4][i % 4] = value;
+    }
+    /// (wavelength, value) pairs for each bin, in order
+    pub fn iter(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        (0..Self::BINS).map(move |i| (self.wavelength(i), self.get(i)))
+    }
+
+    pub fn map(&self, f: impl Fn(f32x4) -> f32x4) -> Self {
+        SampledSpectrum::new(self.bounds, self.values.map(f))
+    }
+    pub fn sum(&self) -> f32 {
+        self.values
+            .iter()
+            .fold(f32x4::splat(0.0), |acc, v| acc + v)
+            .reduce_sum()
+    }
+    pub fn average(&self) -> f32 {
+        self.sum() / Self::BINS as f32
+    }
+    pub fn max_value(&self) -> f32 {
+        self.values
+            .iter()
+            .fold(f32x4::splat(-f32::INFINITY), |acc, v| acc.simd_max(*v))
+            .reduce_max()
+    }
+    /// the integral over the bounds, treating each bin as constant
+    pub fn integral(&self) -> f32 {
+        self.sum() * self.bin_width()
+    }
+
+    /// integrates against the color matching functions with the midpoint rule, like `Curve::convert_to_xyz`
+    pub fn to_xyz(&self) -> XYZColor {
+        let mut xyz = f32x4::splat(0.0);
+        for (lambda, value) in self.iter() {
+            let angstroms = lambda * 10.0;
+            let cmf = f32x4::from_array([
+                x_bar_bounded(angstroms, CMF_SUPPORT),
+                y_bar_bounded(angstroms, CMF_SUPPORT),
+                z_bar_bounded(angstroms, CMF_SUPPORT),
+                0.0,
+            ]);
+            xyz += cmf * f32x4::splat(value);
+        }
+        XYZColor::from_raw(xyz * f32x4::splat(self.bin_width()))
+    }
+
+    fn zip(self, rhs: Self, f: impl Fn(f32x4, f32x4) -> f32x4) -> Self {
+        debug_assert!(self.bounds == rhs.bounds);
+        let mut values = self.values;
+        for (value, other) in values.iter_mut().zip(rhs.values) {
+            *value = f(*value, other);
+        }
+        SampledSpectrum::new(self.bounds, values)
+    }
+}
+
+impl<const N: usize> Add for SampledSpectrum<N> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| a + b)
+    }
+}
+
+impl<const N: usize> AddAssign for SampledSpectrum<N> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<const N: usize> Sub for SampledSpectrum<N> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| a - b)
+    }
+}
+
+impl<const N: usize> Mul for SampledSpectrum<N> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| a * b)
+    }
+}
+
+impl<const N: usize> MulAssign for SampledSpectrum<N> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const N: usize> Div for SampledSpectrum<N> {
+    type Output = Self;
+    /// elementwise division, where bins divided by 0 are 0
+    fn div(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| {
+            b.simd_eq(f32x4::splat(0.0))
+                .select(f32x4::splat(0.0), a / b)
+        })
+    }
+}
+
+impl<const N: usize> Mul<f32> for SampledSpectrum<N> {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self::Output {
+        self.map(|v| v * f32x4::splat(rhs))
+    }
+}
+
+impl<const N: usize> Div<f32> for SampledSpectrum<N> {
+    type Output = Self;
+    fn div(self, rhs: f32) -> Self::Output {
+        self.map(|v| v / f32x4::splat(rhs))
+    }
+}
+
+impl<const N: usize> SpectralPowerDistributionFunction<f32> for SampledSpectrum<N> {
+    /// the value of the bin containing `lambda`, or 0 outside of the bounds
+    fn evaluate_power(&self, lambda: f32) -> f32 {
+        self.bin(lambda).map_or(0.0, |i| self.get(i).max(0.0))
+    }
+    fn evaluate_clamped(&self, lambda: f32) -> f32 {
+        self.evaluate_power(lambda).min(1.0)
+    }
+    fn sample_power_and_pdf(
+        &self,
+        wavelength_range: Bounds1D,
+        sample: Sample1D,
+    ) -> (SingleWavelength, PDF<f32, Uniform01>) {
+        let ws = SingleWavelength::new_from_range(sample.x, wavelength_range);
+        (
+            ws.replace_energy(self.evaluate_power(ws.lambda)),
+            PDF::new(1.0 / wavelength_range.span()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::spectral::BOUNDED_VISIBLE_RANGE;
+
+    #[test]
+    fn test_sampled_spectrum() {
+        let blackbody = Curve::Blackbody {
+            temperature: 5000.0,
+            boost: 1.0,
+        };
+        let sampled = SampledSpectrum::<16>::from_spectrum(&blackbody, BOUNDED_VISIBLE_RANGE);
+        assert_eq!(SampledSpectrum::<16>::BINS, 64);
+        assert_eq!(sampled.get(10), blackbody.evaluate(sampled.wavelength(10)));
+        assert_eq!(sampled.bin(380.0), Some(0));
+        assert_eq!(sampled.bin(780.0), Some(63));
+        assert_eq!(sampled.evaluate_power(800.0), 0.0);
+
+        // integration matches the curve
+        let expected = blackbody.convert_to_xyz(BOUNDED_VISIBLE_RANGE, 1.0, false);
+        let xyz = sampled.to_xyz();
+        for (a, b) in [
+            (xyz.x(), expected.x()),
+            (xyz.y(), expected.y()),
+            (xyz.z(), expected.z()),
+        ] {
+            assert!((a - b).abs() < 1e-2 * b, "{} {}", a, b);
+        }
+        let integral = blackbody.evaluate_integral(BOUNDED_VISIBLE_RANGE, 4000, false);
+        assert!((sampled.integral() - integral).abs() < 1e-3 * integral);
+
+        // arithmetic is elementwise
+        let grey = SampledSpectrum::<16>::splat(BOUNDED_VISIBLE_RANGE, 0.5);
+        let product = sampled * grey + grey;
+        assert_eq!(product.get(30), sampled.get(30) * 0.5 + 0.5);
+        assert!((((product - grey) / grey).get(5) - sampled.get(5)).abs() < 1e-6);
+        assert_eq!(
+            (grey / SampledSpectrum::zero(BOUNDED_VISIBLE_RANGE)).max_value(),
+            0.0
+        );
+        assert!((grey.average() - 0.5).abs() < 1e-6);
+        assert!((sampled * 2.0).max_value() == 2.0 * sampled.max_value());
+
+        // round trips through a curve at the bin centers
+        let curve = sampled.to_curve();
+        let resampled = SampledSpectrum::<16>::from_spectrum(&curve, BOUNDED_VISIBLE_RANGE);
+        assert_eq!(resampled, sampled);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod partition;
+pub mod quadrature;
+pub mod statistics;
+
+// parameterizations of the unit sphere over the unit square, for tabulating and sampling spherical functions.
+// the lat-long mapping of `uv_to_direction` compresses texels towards the poles, such that its jacobian vanishes there.
+// the equal area mappings here have a constant jacobian of 4pi instead, so uniform texels subtend equal solid angles.
+
+/// maps uv in [0, 1]^2 to the unit sphere with a cylindrical equal area (lambert) projection.
+/// the azimuth follows `uv_to_direction`, while z is linear in v, going from +z at v = 0 to -z at v = 1.
+pub fn cylindrical_equal_area_to_direction(uv: (f32, f32)) -> Vec3 {
+    let azimuth = (uv.0 - 0.5) * 2.0 * PI;
+    let z = 1.0 - 2.0 * uv.1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let (sin, cos) = azimuth.sin_cos();
+    Vec3::new(r * cos, r * sin, z)
+}
+
+/// the inverse of `cylindrical_equal_area_to_direction`
+pub fn direction_to_cylindrical_equal_area(direction: Vec3) -> (f32, f32) {
+    let azimuth = direction.y().atan2(direction.x());
+    let u = azimuth / 2.0 / PI + 0.5;
+    let v = (1.0 - direction.z().clamp(-1.0, 1.0)) / 2.0;
+    (u, v)
+}
+
+/// maps uv in [0, 1]^2 to the unit sphere with Clarberg's octahedral equal area mapping.
+/// the square is folded like an octahedron, with +z at the center and -z at the corners,
+/// and neighbourhoods are continuous across its edges, unlike the seam of the cylindrical mapping.
+pub fn equal_area_square_to_sphere(uv: (f32, f32)) -> Vec3 {
+    let (u, v) = (2.0 * uv.0 - 1.0, 2.0 * uv.1 - 1.0);
+    let (up, vp) = (u.abs(), v.abs());
+    // signed distance from the diagonal edges of the inner diamond, which maps to the +z hemisphere
+    let signed_distance = 1.0 - (up + vp);
+    let r = 1.0 - signed_distance.abs();
+    let phi = if r == 0.0 { 1.0 } else { (vp - up) / r + 1.0 } * PI / 4.0;
+    let z = (1.0 - r * r).copysign(signed_distance);
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    let scale = r * (2.0 - r * r).max(0.0).sqrt();
+    Vec3::new(cos_phi.copysign(u) * scale, sin_phi.copysign(v) * scale, z)
+}
+
+/// the inverse of `equal_area_square_to_sphere`
+pub fn equal_area_sphere_to_square(direction: Vec3) -> (f32, f32) {
+    let (x, y, z) = (
+        direction.x().abs(),
+        direction.y().abs(),
+        direction.z().abs(),
+    );
+    let r = (1.0 - z).max(0.0).sqrt();
+    let (a, b) = (x.max(y), x.min(y));
+    let ratio = if a == 0.0 { 0.0 } else { b / a };
+    let mut phi = ratio.atan() * 2.0 / PI;
+    if x < y {
+        phi = 1.0 - phi;
+    }
+    let mut v = phi * r;
+    let mut u = r - v;
+    if direction.z() < 0.0 {
+        std::mem::swap(&mut u, &mut v);
+        u = 1.0 - u;
+        v = 1.0 - v;
+    }
+    let u = u.copysign(direction.x());
+    let v = v.copysign(direction.y());
+    ((u + 1.0) / 2.0, (v + 1.0) / 2.0)
+}
+
+/// Parameterizations of the sphere over uv in [0, 1]^2, i.e. for environment maps and other spherical tables
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SphereMapping {
+    /// `uv_to_direction`, which is not area preserving
+    LatLong,
+    CylindricalEqualArea,
+    /// Clarberg's octahedral mapping
+    EqualAreaOctahedral,
+}
+
+impl SphereMapping {
+    pub fn to_direction(&self, uv: (f32, f32)) -> Vec3 {
+        match self {
+            SphereMapping::LatLong => uv_to_direction(uv),
+            SphereMapping::CylindricalEqualArea => cylindrical_equal_area_to_direction(uv),
+            SphereMapping::EqualAreaOctahedral => equal_area_square_to_sphere(uv),
+        }
+    }
+    pub fn to_uv(&self, direction: Vec3) -> (f32, f32) {
+        match self {
+            SphereMapping::LatLong => direction_to_uv(direction),
+            SphereMapping::CylindricalEqualArea => direction_to_cylindrical_equal_area(direction),
+            SphereMapping::EqualAreaOctahedral => equal_area_sphere_to_square(direction),
+        }
+    }
+    /// the solid angle per unit uv area at `uv`, i.e. |d omega / du dv|
+    pub fn jacobian(&self, uv: (f32, f32)) -> f32 {
+        match self {
+            SphereMapping::LatLong => 2.0 * PI * PI * (PI * uv.1).sin().max(0.0),
+            SphereMapping::CylindricalEqualArea | SphereMapping::EqualAreaOctahedral => 4.0 * PI,
+        }
+    }
+    pub fn is_area_preserving(&self) -> bool {
+        !matches!(self, SphereMapping::LatLong)
+    }
+    /// converts a pdf wrt area in uv space to a pdf wrt solid angle. returns 0 where the mapping is degenerate.
+    pub fn pdf_to_solid_angle(&self, pdf: PDF<f32, Area>, uv: (f32, f32)) -> PDF<f32, SolidAngle> {
+        let jacobian = self.jacobian(uv);
+        if jacobian <= 0.0 {
+            return PDF::new(0.0);
+        }
+        PDF::new(*pdf / jacobian)
+    }
+    /// converts a pdf wrt solid angle to a pdf wrt area in uv space
+    pub fn pdf_to_area(&self, pdf: PDF<f32, SolidAngle>, uv: (f32, f32)) -> PDF<f32, Area> {
+        PDF::new(*pdf * self.jacobian(uv))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sphere_mappings() {
+        let mappings = [
+            SphereMapping::LatLong,
+            SphereMapping::CylindricalEqualArea,
+            SphereMapping::EqualAreaOctahedral,
+        ];
+        for mapping in mappings {
+            for _ in 0..1000 {
+                let direction = random_on_unit_sphere(Sample2D::new_random_sample());
+                let uv = mapping.to_uv(direction);
+                assert!((0.0..=1.0).contains(&uv.0) && (0.0..=1.0).contains(&uv.1));
+                let roundtrip = mapping.to_direction(uv);
+                assert!(
+                    (roundtrip - direction).norm() < 1e-3,
+                    "{:?} {:?}",
+                    mapping,
+                    uv
+                );
+                assert!((roundtrip.norm() - 1.0).abs() < 1e-4);
+            }
+        }
+
+        // the jacobians integrate to the area of the sphere
+        let n = 64;
+        for mapping in mappings {
+            let mut total = 0.0;
+            for j in 0..n {
+                for i in 0..n {
+                    let uv = ((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                    total += mapping.jacobian(uv) / (n * n) as f32;
+                }
+            }
+            assert!((total - 4.0 * PI).abs() < 1e-2, "{:?} {}", mapping, total);
+        }
+
+        // equal area: the fraction of uniform uv samples landing in the +z polar cap equals its solid angle fraction
+        let (cap, n) = (0.75, 256);
+        for mapping in mappings.iter().filter(|m| m.is_area_preserving()) {
+            let mut inside = 0;
+            for j in 0..n {
+                for i in 0..n {
+                    let uv = ((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                    if mapping.to_direction(uv).z() > cap {
+                        inside += 1;
+                    }
+                }
+            }
+            let fraction = inside as f32 / (n * n) as f32;
+            assert!(
+                (fraction - (1.0 - cap) / 2.0).abs() < 3e-3,
+                "{:?} {}",
+                mapping,
+                fraction
+            );
+        }
+
+        assert_eq!(equal_area_square_to_sphere((0.5, 0.5)), Vec3::Z);
+        assert_eq!(equal_area_square_to_sphere((0.0, 0.0)).z(), -1.0);
+        let pdf = SphereMapping::EqualAreaOctahedral.pdf_to_solid_angle(PDF::new(1.0), (0.3, 0.2));
+        assert_eq!(*pdf, 1.0 / (4.0 * PI));
+    }
+}
@@ -0,0 +1,236 @@
+use super::SphereMapping;
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// partitions of the sphere into cells of a regular grid over the uv square of a `SphereMapping`,
+// for indexing directional histograms and caches. with an area preserving mapping, every cell subtends 4pi / n cells.
+
+// subdivisions per axis when integrating over a cell, for centroids of cells with a varying jacobian
+const CENTROID_SUBDIVISIONS: usize = 16;
+
+/// A partition of the sphere into `resolution` x `resolution` cells, indexed in row major order over uv.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpherePartition {
+    pub mapping: SphereMapping,
+    pub resolution: usize,
+}
+
+impl SpherePartition {
+    pub fn new(mapping: SphereMapping, resolution: usize) -> Self {
+        debug_assert!(resolution > 0);
+        SpherePartition {
+            mapping,
+            resolution,
+        }
+    }
+    /// an equal area partition with octahedral cells, which are close to square everywhere
+    pub fn equal_area(resolution: usize) -> Self {
+        SpherePartition::new(SphereMapping::EqualAreaOctahedral, resolution)
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.resolution * self.resolution
+    }
+
+    /// the index of the cell containing `direction`
+    pub fn cell(&self, direction: Vec3) -> usize {
+        let (u, v) = self.mapping.to_uv(direction);
+        let quantize = |t: f32| ((t * self.resolution as f32) as usize).min(self.resolution - 1);
+        quantize(v) * self.resolution + quantize(u)
+    }
+
+    // the uv of the corner of `cell` with the smallest coordinates
+    fn cell_origin(&self, cell: usize) -> (f32, f32) {
+        let size = 1.0 / self.resolution as f32;
+        (
+            (cell % self.resolution) as f32 * size,
+            (cell / self.resolution) as f32 * size,
+        )
+    }
+
+    /// the solid angle subtended by `cell`
+    pub fn solid_angle(&self, cell: usize) -> f32 {
+        let n = self.resolution as f32;
+        match self.mapping {
+            // the jacobian 2pi^2 sin(pi v) integrates in closed form over the rows of the lat-long mapping
+            SphereMapping::LatLong => {
+                let (_, v) = self.cell_origin(cell);
+                2.0 * PI / n * ((PI * v).cos() - (PI * (v + 1.0 / n)).cos())
+            }
+            _ => 4.0 * PI / (n * n),
+        }
+    }
+
+    /// the direction at the center of `cell` in uv
+    pub fn center(&self, cell: usize) -> Vec3 {
+        let (u, v) = self.cell_origin(cell);
+        let half = 0.5 / self.resolution as f32;
+        self.mapping.to_direction((u + half, v + half))
+    }
+
+    /// the normalized mean direction over `cell`, weighted by solid angle.
+    /// unlike `center`, this is the direction that best represents the cell, i.e. for lobes fit to its contents.
+    pub fn centroid(&self, cell: usize) -> Vec3 {
+        let (u, v) = self.cell_origin(cell);
+        let step = 1.0 / (self.resolution * CENTROID_SUBDIVISIONS) as f32;
+        let mut sum = Vec3::ZERO;
+        for j in 0..CENTROID_SUBDIVISIONS {
+            for i in 0..CENTROID_SUBDIVISIONS {
+                let uv = (u + (i as f32 + 0.5) * step, v + (j as f32 + 0.5) * step);
+                sum = sum + self.mapping.to_direction(uv) * self.mapping.jacobian(uv);
+            }
+        }
+        sum.normalized()
+    }
+
+    /// samples a direction within `cell`, uniformly in uv, returning it with its pdf wrt solid angle
+    /// conditioned on the cell, i.e. 1 / solid angle for area preserving mappings
+    pub fn sample_cell(&self, cell: usize, sample: Sample2D) -> (Vec3, PDF<f32, SolidAngle>) {
+        let (u, v) = self.cell_origin(cell);
+        let n = self.resolution as f32;
+        let uv = (u + sample.x / n, v + sample.y / n);
+        let pdf = self.mapping.pdf_to_solid_angle(PDF::new(n * n), uv);
+        (self.mapping.to_direction(uv), pdf)
+    }
+}
+
+/// A set of cells of a `SpherePartition`, stored as a bitfield, i.e. for marking the directions
+/// in which a cache has valid entries or a light is visible.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirectionSet {
+    partition: SpherePartition,
+    bits: Vec<u64>,
+}
+
+impl DirectionSet {
+    pub fn new(partition: SpherePartition) -> Self {
+        DirectionSet {
+            partition,
+            bits: vec![0; partition.cell_count().div_ceil(64)],
+        }
+    }
+    pub fn partition(&self) -> SpherePartition {
+        self.partition
+    }
+
+    pub fn insert_cell(&mut self, cell: usize) {
+        self.bits[cell / 64] |= 1 << (cell % 64);
+    }
+    pub fn contains_cell(&self, cell: usize) -> bool {
+        self.bits[cell / 64] & (1 << (cell % 64)) != 0
+    }
+    /// inserts the cell containing `direction`
+    pub fn insert(&mut self, direction: Vec3) {
+        self.insert_cell(self.partition.cell(direction));
+    }
+    /// whether the cell containing `direction` is in the set
+    pub fn contains(&self, direction: Vec3) -> bool {
+        self.contains_cell(self.partition.cell(direction))
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bits.iter().all(|word| *word == 0)
+    }
+    /// the indices of the cells in the set, in increasing order
+    pub fn cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.partition.cell_count()).filter(move |cell| self.contains_cell(*cell))
+    }
+    /// the total solid angle of the cells in the set
+    pub fn solid_angle(&self) -> f32 {
+        self.cells()
+            .map(|cell| self.partition.solid_angle(cell))
+            .sum()
+    }
+
+    pub fn union(&self, other: &DirectionSet) -> DirectionSet {
+        debug_assert!(self.partition == other.partition);
+        DirectionSet {
+            partition: self.partition,
+            bits: self
+                .bits
+                .iter()
+                .zip(&other.bits)
+                .map(|(a, b)| a | b)
+                .collect(),
+        }
+    }
+    pub fn intersection(&self, other: &DirectionSet) -> DirectionSet {
+        debug_assert!(self.partition == other.partition);
+        DirectionSet {
+            partition: self.partition,
+            bits: self
+                .bits
+                .iter()
+                .zip(&other.bits)
+                .map(|(a, b)| a & b)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sphere_partition() {
+        for mapping in [
+            SphereMapping::LatLong,
+            SphereMapping::CylindricalEqualArea,
+            SphereMapping::EqualAreaOctahedral,
+        ] {
+            let partition = SpherePartition::new(mapping, 12);
+            let total: f32 = (0..partition.cell_count())
+                .map(|cell| partition.solid_angle(cell))
+                .sum();
+            assert!((total - 4.0 * PI).abs() < 1e-3, "{:?} {}", mapping, total);
+
+            for cell in [0, 17, 70, 143] {
+                // directions sampled within a cell map back to it, with the cell's uniform pdf
+                for sample in [Sample2D::new(0.5, 0.5), Sample2D::new(0.1, 0.9)] {
+                    let (direction, pdf) = partition.sample_cell(cell, sample);
+                    assert_eq!(partition.cell(direction), cell, "{:?}", mapping);
+                    if mapping != SphereMapping::LatLong {
+                        assert!((*pdf * partition.solid_angle(cell) - 1.0).abs() < 1e-4);
+                    }
+                }
+                assert_eq!(partition.cell(partition.center(cell)), cell);
+                assert_eq!(partition.cell(partition.centroid(cell)), cell);
+            }
+        }
+
+        // the centroid of a lat-long cell touching the pole is pulled away from it, towards the larger end of the cell
+        let lat_long = SpherePartition::new(SphereMapping::LatLong, 8);
+        let (center, centroid) = (lat_long.center(3), lat_long.centroid(3));
+        assert!(center.z().abs() > centroid.z().abs());
+
+        let partition = SpherePartition::equal_area(16);
+        let mut set = DirectionSet::new(partition);
+        assert!(set.is_empty());
+        set.insert(Vec3::Z);
+        set.insert(Vec3::Z);
+        set.insert(-Vec3::X);
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(Vec3::new(0.01, 0.0, 1.0).normalized()));
+        assert!(!set.contains(Vec3::Y));
+        assert!((set.solid_angle() - 2.0 * 4.0 * PI / 256.0).abs() < 1e-5);
+        let mut other = DirectionSet::new(partition);
+        other.insert(Vec3::Y);
+        other.insert(-Vec3::X);
+        assert_eq!(set.union(&other).len(), 3);
+        assert_eq!(
+            set.intersection(&other).cells().collect::<Vec<_>>(),
+            vec![partition.cell(-Vec3::X)]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::prelude::*;
+
+use std::collections::HashMap;
+
+// deterministic quadrature rules over the sphere, i.e. for preconvolving environment lighting
+// or testing directional integrals without monte carlo noise.
+
+/// `n` nearly evenly spaced directions on the unit sphere, spiralling from +z to -z along the golden angle.
+/// each direction represents an equal area of 4pi / n.
+pub fn fibonacci_sphere(n: usize) -> Vec<Vec3> {
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+    (0..n)
+        .map(|i| {
+            let z = 1.0 - (2 * i + 1) as f32 / n as f32;
+            let r = (1.0 - z * z).max(0.0).sqrt();
+            let (sin, cos) = (i as f32 * golden_angle).sin_cos();
+            Vec3::new(r * cos, r * sin, z)
+        })
+        .collect()
+}
+
+/// A fixed set of directions with weights, such that sum(f(direction) * weight) approximates the integral of f over solid angle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SphereQuadrature {
+    pub directions: Vec<Vec3>,
+    pub weights: Vec<f32>,
+}
+
+impl SphereQuadrature {
+    /// the directions of `fibonacci_sphere`, with equal weights
+    pub fn fibonacci(n: usize) -> Self {
+        SphereQuadrature {
+            directions: fibonacci_sphere(n),
+            weights: vec![4.0 * PI / n as f32; n],
+        }
+    }
+
+    /// a fibonacci spiral over the +z hemisphere, with equal weights summing to 2pi
+    pub fn fibonacci_hemisphere(n: usize) -> Self {
+        let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+        let directions = (0..n)
+            .map(|i| {
+                let z = 1.0 - (i as f32 + 0.5) / n as f32;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let (sin, cos) = (i as f32 * golden_angle).sin_cos();
+                Vec3::new(r * cos, r * sin, z)
+            })
+            .collect();
+        SphereQuadrature {
+            directions,
+            weights: vec![2.0 * PI / n as f32; n],
+        }
+    }
+
+    /// the vertices of an icosahedron with each face split into 4, `subdivisions` times, projected onto the sphere.
+    /// there are 10 * 4^subdivisions + 2 vertices, and each one is weighted by a third of the solid angle of the
+    /// spherical triangles around it, such that the weights sum to 4pi.
+    pub fn icosahedral(subdivisions: usize) -> Self {
+        let phi = (1.0 + 5.0f32.sqrt()) / 2.0;
+        let mut vertices: Vec<Vec3> = [
+            (-1.0, phi, 0.0),
+            (1.0, phi, 0.0),
+            (-1.0, -phi, 0.0),
+            (1.0, -phi, 0.0),
+            (0.0, -1.0, phi),
+            (0.0, 1.0, phi),
+            (0.0, -1.0, -phi),
+            (0.0, 1.0, -phi),
+            (phi, 0.0, -1.0),
+            (phi, 0.0, 1.0),
+            (-phi, 0.0, -1.0),
+            (-phi, 0.0, 1.0),
+        ]
+        .iter()
+        .map(|&(x, y, z)| Vec3::new(x, y, z).normalized())
+        .collect();
+        let mut faces: Vec<[usize; 3]> = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+        for _ in 0..subdivisions {
+            // midpoints are shared between the two faces on either side of an edge
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Vec3>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    vertices.push((vertices[a] + vertices[b]).normalized());
+                    vertices.len() - 1
+                })
+            };
+            faces = faces
+                .iter()
+                .flat_map(|&[a, b, c]| {
+                    let ab = midpoint(a, b, &mut vertices);
+                    let bc = midpoint(b, c, &mut vertices);
+                    let ca = midpoint(c, a, &mut vertices);
+                    [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+                })
+                .collect();
+        }
+        let mut weights = vec![0.0; vertices.len()];
+        for [a, b, c] in faces {
+            let (va, vb, vc) = (vertices[a], vertices[b], vertices[c]);
+            let solid_angle = spherical_triangle_solid_angle(va, vb, vc);
+            for vertex in [a, b, c] {
+                weights[vertex] += solid_angle / 3.0;
+            }
+        }
+        SphereQuadrature {
+            directions: vertices,
+            weights,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.directions.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.directions.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Vec3, f32)> + '_ {
+        self.directions
+            .iter()
+            .copied()
+            .zip(self.weights.iter().copied())
+    }
+    pub fn integrate(&self, f: impl Fn(Vec3) -> f32) -> f32 {
+        self.iter()
+            .map(|(direction, weight)| f(direction) * weight)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sphere_quadrature() {
+        for (subdivisions, count) in [(0, 12), (1, 42), (2, 162), (3, 642)] {
+            let rule = SphereQuadrature::icosahedral(subdivisions);
+            assert_eq!(rule.len(), count);
+            assert!((rule.integrate(|_| 1.0) - 4.0 * PI).abs() < 1e-4);
+            assert!(rule
+                .directions
+                .iter()
+                .all(|d| (d.norm() - 1.0).abs() < 1e-6));
+        }
+
+        // the integral of exp(z) over the sphere is 2pi (e - 1 / e), and of x^2 is 4pi / 3
+        let exp = 2.0 * PI * (1.0f32.exp() - (-1.0f32).exp());
+        for rule in [
+            SphereQuadrature::icosahedral(3),
+            SphereQuadrature::fibonacci(642),
+        ] {
+            assert!((rule.integrate(|d| d.z().exp()) - exp).abs() < 1e-2 * exp);
+            assert!((rule.integrate(|d| d.x() * d.x()) - 4.0 * PI / 3.0).abs() < 1e-2);
+            // odd functions cancel
+            assert!(rule.integrate(|d| d.y() * d.z() * d.z()).abs() < 1e-2);
+        }
+        let directions = fibonacci_sphere(1000);
+        assert_eq!(directions.len(), 1000);
+        let mean = directions.iter().fold(Vec3::ZERO, |acc, d| acc + *d) / 1000.0;
+        assert!(mean.norm() < 1e-3, "{:?}", mean);
+
+        // the cosine weighted hemisphere integrates to pi
+        let hemisphere = SphereQuadrature::fibonacci_hemisphere(500);
+        assert!(hemisphere.directions.iter().all(|d| d.z() > 0.0));
+        assert!((hemisphere.integrate(|d| d.z()) - PI).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::prelude::*;
+
+// summary statistics of weighted sets of unit directions, such as the photons or path vertices that land in a cell
+// of a guiding structure, for fitting von Mises-Fisher lobes and spherical gaussians to them.
+// the statistics are accumulated in one pass from the weighted first and second moments of the directions,
+// so that sets can be merged without revisiting their directions.
+
+/// Accumulated weighted moments of a set of unit directions
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DirectionStatistics {
+    total_weight: f32,
+    // sum of w d
+    resultant: Vec3,
+    // sum of w d d^T
+    second_moment: Matrix3x3,
+}
+
+impl DirectionStatistics {
+    pub fn new() -> Self {
+        DirectionStatistics {
+            total_weight: 0.0,
+            resultant: Vec3::ZERO,
+            second_moment: Matrix3x3::ZERO,
+        }
+    }
+
+    pub fn from_weighted(directions: impl IntoIterator<Item = (Vec3, f32)>) -> Self {
+        let mut statistics = DirectionStatistics::new();
+        for (direction, weight) in directions {
+            statistics.add(direction, weight);
+        }
+        statistics
+    }
+
+    /// adds `direction`, which should be normalized, with `weight`
+    pub fn add(&mut self, direction: Vec3, weight: f32) {
+        let weighted = direction * weight;
+        self.total_weight += weight;
+        self.resultant = self.resultant + weighted;
+        self.second_moment = self.second_moment
+            + Matrix3x3::from_columns(
+                weighted * direction.x(),
+                weighted * direction.y(),
+                weighted * direction.z(),
+            );
+    }
+
+    /// the statistics of the union of the two sets
+    pub fn merge(&self, other: &Self) -> Self {
+        DirectionStatistics {
+            total_weight: self.total_weight + other.total_weight,
+            resultant: self.resultant + other.resultant,
+            second_moment: self.second_moment + other.second_moment,
+        }
+    }
+
+    pub fn total_weight(&self) -> f32 {
+        self.total_weight
+    }
+
+    /// the weighted mean of the directions, which lies within the unit ball
+    pub fn mean(&self) -> Vec3 {
+        if self.total_weight > 0.0 {
+            self.resultant / self.total_weight
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    /// the normalized mean direction, or None if the directions cancel out or there are none
+    pub fn mean_direction(&self) -> Option<Vec3> {
+        let mean = self.mean();
+        let norm = mean.norm();
+        if norm > 0.0 && norm.is_finite() {
+            Some(mean / norm)
+        } else {
+            None
+        }
+    }
+
+    /// the length of the mean, in [0, 1]. 1 when all directions agree, and near 0 for directions spread over the sphere.
+    pub fn mean_resultant_length(&self) -> f32 {
+        self.mean().norm().min(1.0)
+    }
+
+    /// an estimate of the concentration kappa of the von Mises-Fisher distribution that the directions were drawn from.
+    /// see `vmf_concentration`.
+    pub fn concentration(&self) -> f32 {
+        vmf_concentration(self.mean_resultant_length())
+    }
+
+    /// the weighted covariance of the directions about their mean, E[d d^T] - E[d] E[d]^T
+    pub fn covariance(&self) -> Matrix3x3 {
+        if self.total_weight.is_nan() || self.total_weight <= 0.0 {
+            return Matrix3x3::ZERO;
+        }
+        let mean = self.mean();
+        let outer = Matrix3x3::from_columns(mean * mean.x(), mean * mean.y(), mean * mean.z());
+        self.second_moment * (1.0 / self.total_weight) + outer * -1.0
+    }
+
+    /// the variances of the directions along the principal axes of the covariance, in decreasing order,
+    /// and the axes as the columns of a matrix. for a rotationally symmetric lobe, the last axis is the mean direction,
+    /// and the ratio of the first two variances measures the anisotropy of the lobe.
+    pub fn principal_axes(&self) -> (Vec3, Matrix3x3) {
+        self.covariance().symmetric_eigen()
+    }
+}
+
+impl Default for DirectionStatistics {
+    fn default() -> Self {
+        DirectionStatistics::new()
+    }
+}
+
+/// the approximate maximum likelihood estimate of the von Mises-Fisher concentration on the sphere from the mean
+/// resultant length r, r (3 - r^2) / (1 - r^2), following Banerjee et al. (2005).
+/// grows without bound as r approaches 1, and returns infinity for r = 1.
+pub fn vmf_concentration(mean_resultant_length: f32) -> f32 {
+    let r = mean_resultant_length.clamp(0.0, 1.0);
+    if r >= 1.0 {
+        return f32::INFINITY;
+    }
+    r * (3.0 - r * r) / (1.0 - r * r)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_direction_statistics() {
+        // a von Mises-Fisher lobe, from a dense quadrature weighted by its density
+        let mu = Vec3::new(1.0, 2.0, -0.5).normalized();
+        let kappa = 10.0;
+        let directions = fibonacci_sphere(20000);
+        let statistics = DirectionStatistics::from_weighted(
+            directions
+                .iter()
+                .map(|d| (*d, (kappa * (*d * mu - 1.0)).exp())),
+        );
+        let mean_direction = statistics.mean_direction().unwrap();
+        assert!((mean_direction - mu).norm() < 1e-3);
+        // the mean resultant length of a vmf is coth(kappa) - 1 / kappa
+        let expected = 1.0 / kappa.tanh() - 1.0 / kappa;
+        assert!((statistics.mean_resultant_length() - expected).abs() < 1e-3);
+        let estimate = statistics.concentration();
+        assert!((estimate - kappa).abs() < 0.05 * kappa, "{}", estimate);
+
+        // the lobe is symmetric about its mean, which has the least variance
+        let (variances, axes) = statistics.principal_axes();
+        assert!((variances.x() - variances.y()).abs() < 1e-3 * variances.x());
+        assert!(variances.z() < 0.5 * variances.y());
+        assert!((axes.column(2) * mu).abs() > 0.999);
+        let covariance = statistics.covariance();
+        for i in 0..3 {
+            let axis = axes.column(i);
+            assert!((covariance * axis - axis * variances.as_array()[i]).norm() < 1e-4);
+        }
+
+        // a lobe stretched along x has its largest variance along x
+        let stretched = DirectionStatistics::from_weighted(directions.iter().map(|d| {
+            (
+                *d,
+                (-(d.y() * d.y()) * 40.0 - d.x() * d.x() * 4.0 + d.z() * 5.0).exp(),
+            )
+        }));
+        let (_, axes) = stretched.principal_axes();
+        assert!(axes.column(0).x().abs() > 0.99, "{:?}", axes);
+
+        // merging halves gives the statistics of the whole
+        let (a, b) = directions.split_at(7000);
+        let half = |set: &[Vec3]| {
+            DirectionStatistics::from_weighted(set.iter().map(|d| (*d, 1.0 + d.z())))
+        };
+        let merged = half(a).merge(&half(b));
+        let whole = half(&directions);
+        assert!((merged.mean() - whole.mean()).norm() < 1e-5);
+        assert!((merged.total_weight() - whole.total_weight()).abs() < 1e-2);
+
+        // uniform and empty sets have no mean direction
+        let uniform = DirectionStatistics::from_weighted(directions.iter().map(|d| (*d, 1.0)));
+        assert!(uniform.mean_resultant_length() < 1e-3);
+        assert!(uniform.concentration() < 1e-2);
+        assert_eq!(DirectionStatistics::new().mean_direction(), None);
+        assert_eq!(vmf_concentration(1.0), f32::INFINITY);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::prelude::*;
+use crate::Sidedness;
 
 // also known as an orthonormal basis.
 #[derive(Copy, Clone, Debug)]
@@ -69,4 +70,184 @@ impl TangentFrame {
             self.normal * (*v),
         )
     }
+
+    /// the frame around `to_new_normal` that is rotated from self by the smallest rotation taking the normal there,
+    /// such that the tangent doesn't twist about the normal. transporting a frame step by step along the normals of a
+    /// curve or path keeps anisotropy oriented consistently, where `from_normal` would pick unrelated tangents.
+    /// the handedness of the frame is kept. a normal flipped to its opposite is rotated about the tangent.
+    pub fn parallel_transport(&self, to_new_normal: Vec3) -> TangentFrame {
+        let normal = to_new_normal.normalized();
+        let axis = self.normal.cross(normal);
+        let cos = self.normal * normal;
+        let rotated = if !Tolerance::DEFAULT.is_near_zero(1.0 + cos) {
+            // rodrigues' rotation taking self.normal to normal, v cos + (k x v) + k (k . v) / (1 + cos) for k = n x n'
+            self.tangent * cos
+                + axis.cross(self.tangent)
+                + axis * ((axis * self.tangent) / (1.0 + cos))
+        } else {
+            self.tangent
+        };
+        // remove the drift that accumulates over many steps
+        let tangent = (rotated - normal * (normal * rotated)).normalized();
+        let handedness = (self.tangent.cross(self.bitangent) * self.normal).signum();
+        TangentFrame {
+            tangent,
+            bitangent: normal.cross(tangent) * handedness,
+            normal,
+        }
+    }
+
+    /// transports self along `normals` with `parallel_transport`, returning the frame at each normal
+    pub fn transport_along(&self, normals: impl IntoIterator<Item = Vec3>) -> Vec<TangentFrame> {
+        let mut frame = *self;
+        normals
+            .into_iter()
+            .map(|normal| {
+                frame = frame.parallel_transport(normal);
+                frame
+            })
+            .collect()
+    }
+}
+
+impl TangentFrame {
+    /// a frame around the shading normal that `resolve_normal` picks for `wo`, or None if the surface isn't visible from `wo`
+    pub fn from_sidedness(
+        sidedness: Sidedness,
+        geometric_normal: Vec3,
+        shading_normal: Vec3,
+        wo: Vec3,
+    ) -> Option<Self> {
+        resolve_normal(sidedness, geometric_normal, shading_normal, wo)
+            .map(|resolved| TangentFrame::from_normal(resolved.shading))
+    }
+}
+
+/// The normals of a surface interaction, oriented according to a `Sidedness`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ResolvedNormal {
+    pub geometric: Vec3,
+    pub shading: Vec3,
+    /// whether the normals were flipped, i.e. the back side of the surface is being shaded
+    pub flipped: bool,
+}
+
+impl Sidedness {
+    /// whether a surface with this sidedness can be seen from `wo`, which points away from the surface
+    pub fn is_visible(&self, geometric_normal: Vec3, wo: Vec3) -> bool {
+        let cos = geometric_normal * wo;
+        match self {
+            Sidedness::Forward => cos > 0.0,
+            Sidedness::Reverse => cos < 0.0,
+            Sidedness::Dual => cos != 0.0,
+        }
+    }
+}
+
+/// orients the normals of a surface interaction seen from `wo`, which points away from the surface.
+/// `Forward` surfaces are only visible from the side the geometric normal points to, `Reverse` surfaces only from the
+/// other side, and `Dual` surfaces from both. visible normals are flipped to face `wo`, and None is returned otherwise.
+/// the shading normal is first moved into the hemisphere of the geometric normal, such that both always agree.
+pub fn resolve_normal(
+    sidedness: Sidedness,
+    geometric_normal: Vec3,
+    shading_normal: Vec3,
+    wo: Vec3,
+) -> Option<ResolvedNormal> {
+    if !sidedness.is_visible(geometric_normal, wo) {
+        return None;
+    }
+    let shading = if shading_normal * geometric_normal < 0.0 {
+        -shading_normal
+    } else {
+        shading_normal
+    };
+    let flipped = geometric_normal * wo < 0.0;
+    let sign = if flipped { -1.0 } else { 1.0 };
+    Some(ResolvedNormal {
+        geometric: geometric_normal * sign,
+        shading: shading * sign,
+        flipped,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_resolve_normal() {
+        let geometric = Vec3::Z;
+        // an interpolated shading normal that has drifted below the geometric surface
+        let shading = Vec3::new(0.6, 0.0, -0.8);
+        let (above, below) = (Vec3::new(0.0, 0.6, 0.8), Vec3::new(0.0, 0.6, -0.8));
+
+        let front = resolve_normal(Sidedness::Forward, geometric, shading, above).unwrap();
+        assert_eq!(front.geometric, Vec3::Z);
+        assert_eq!(front.shading, Vec3::new(-0.6, 0.0, 0.8));
+        assert!(!front.flipped);
+        assert!(resolve_normal(Sidedness::Forward, geometric, shading, below).is_none());
+
+        assert!(resolve_normal(Sidedness::Reverse, geometric, shading, above).is_none());
+        let back = resolve_normal(Sidedness::Reverse, geometric, shading, below).unwrap();
+        assert_eq!(back.geometric, -Vec3::Z);
+        assert!(back.flipped);
+
+        // dual sided surfaces are visible from both sides, always facing wo
+        for wo in [above, below] {
+            let resolved = resolve_normal(Sidedness::Dual, geometric, shading, wo).unwrap();
+            assert!(resolved.geometric * wo > 0.0 && resolved.shading * resolved.geometric > 0.0);
+        }
+        // grazing directions don't see the surface at all
+        assert!(resolve_normal(Sidedness::Dual, geometric, shading, Vec3::X).is_none());
+
+        let frame =
+            TangentFrame::from_sidedness(Sidedness::Dual, geometric, shading, below).unwrap();
+        assert_eq!(frame.normal, back.shading);
+        assert!(frame.to_local(&below).z() > 0.0);
+    }
+
+    #[test]
+    fn test_parallel_transport() {
+        let frame = TangentFrame::from_normal(Vec3::Z);
+        // the minimal rotation from z to x is about y
+        let transported = frame.parallel_transport(Vec3::X);
+        assert!((transported.tangent - Vec3::new(0.0, 0.0, -1.0)).norm() < 1e-6);
+        assert!((transported.bitangent - frame.bitangent).norm() < 1e-6);
+        let same = frame.parallel_transport(Vec3::Z);
+        assert!((same.tangent - frame.tangent).norm() < 1e-6);
+        let flipped = frame.parallel_transport(-Vec3::Z);
+        assert!((flipped.tangent - frame.tangent).norm() < 1e-6);
+        assert!((flipped.bitangent * frame.bitangent + 1.0).abs() < 1e-6);
+
+        // around a loop of normals on a cone, the frame turns by the enclosed solid angle, 2pi (1 - cos theta).
+        // at 60 degrees that's a half turn, so the tangent comes back reversed
+        let theta = PI / 3.0;
+        let steps = 2000;
+        let normals = (0..=steps).map(|i| {
+            let phi = 2.0 * PI * i as f32 / steps as f32;
+            Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            )
+        });
+        let start = TangentFrame::from_normal(Vec3::new(theta.sin(), 0.0, theta.cos()));
+        let frames = start.transport_along(normals);
+        let end = frames[frames.len() - 1];
+        for frame in &frames {
+            assert!((frame.tangent * frame.normal).abs() < 1e-5);
+            assert!((frame.bitangent * frame.normal).abs() < 1e-5);
+            assert!((frame.tangent.norm() - 1.0).abs() < 1e-5);
+            // the handedness of from_normal is kept
+            assert!(frame.tangent.cross(frame.bitangent) * frame.normal > 0.99);
+        }
+        assert!((end.normal - start.normal).norm() < 1e-5);
+        assert!(
+            (end.tangent + start.tangent).norm() < 1e-2,
+            "{:?} {:?}",
+            end,
+            start
+        );
+    }
 }
@@ -0,0 +1,162 @@
+use crate::prelude::*;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A policy for comparing floats and rejecting near degenerate values, so that the numerics of a renderer
+/// can be tightened or relaxed in one place.
+/// two values are considered equal when any of the three criteria holds:
+/// their absolute difference is at most `abs`, their relative difference is at most `rel`,
+/// or they are at most `ulps` representable floats apart.
+/// `abs` also serves as the threshold below which a value counts as zero.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tolerance {
+    pub abs: f32,
+    pub rel: f32,
+    pub ulps: u32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::DEFAULT
+    }
+}
+
+// maps floats to integers such that adjacent floats map to adjacent integers, including across zero
+fn ordered_bits(x: f32) -> i64 {
+    let bits = x.to_bits() as i32;
+    if bits < 0 {
+        (i32::MIN - bits) as i64
+    } else {
+        bits as i64
+    }
+}
+
+impl Tolerance {
+    /// only values that are exactly equal compare equal, and only exact zeros count as zero
+    pub const EXACT: Tolerance = Tolerance::new(0.0, 0.0, 0);
+    pub const DEFAULT: Tolerance = Tolerance::new(1e-6, 1e-5, 4);
+    /// for values that went through long chains of arithmetic, i.e. transform round trips
+    pub const LOOSE: Tolerance = Tolerance::new(1e-4, 1e-3, 64);
+
+    pub const fn new(abs: f32, rel: f32, ulps: u32) -> Self {
+        Tolerance { abs, rel, ulps }
+    }
+
+    /// whether `a` and `b` are equal within the tolerance. NaNs never compare equal.
+    pub fn approx_eq(&self, a: f32, b: f32) -> bool {
+        if a == b {
+            return true;
+        }
+        if a.is_nan() || b.is_nan() || a.is_infinite() || b.is_infinite() {
+            return false;
+        }
+        let difference = (a - b).abs();
+        difference <= self.abs
+            || difference <= self.rel * a.abs().max(b.abs())
+            || (ordered_bits(a) - ordered_bits(b)).unsigned_abs() <= self.ulps as u64
+    }
+
+    /// whether every lane of `a` and `b` is equal within the tolerance
+    pub fn approx_eq_lanes(&self, a: f32x4, b: f32x4) -> bool {
+        (0..4).all(|i| self.approx_eq(a[i], b[i]))
+    }
+    pub fn approx_eq_vec3(&self, a: Vec3, b: Vec3) -> bool {
+        self.approx_eq(a.x(), b.x()) && self.approx_eq(a.y(), b.y()) && self.approx_eq(a.z(), b.z())
+    }
+    pub fn approx_eq_point3(&self, a: Point3, b: Point3) -> bool {
+        self.approx_eq_vec3(a - Point3::ORIGIN, b - Point3::ORIGIN)
+    }
+
+    /// whether `x` is zero within the absolute tolerance
+    pub fn is_near_zero(&self, x: f32) -> bool {
+        x.abs() <= self.abs
+    }
+    /// whether `x` is negligible compared to `scale`, the magnitude of the values it was computed from
+    pub fn is_negligible(&self, x: f32, scale: f32) -> bool {
+        x.abs() <= self.abs + self.rel * scale.abs()
+    }
+
+    /// whether `pdf` is too small or invalid to divide by, such that the sample should be rejected
+    pub fn rejects_pdf<M: Measure>(&self, pdf: PDF<f32, M>) -> bool {
+        !pdf.is_finite() || *pdf <= self.abs
+    }
+
+    /// whether the triangle `p0`, `p1`, `p2` is degenerate, i.e. its area is negligible
+    /// compared to the square of its longest edge, as for needles and collinear or coincident vertices.
+    /// only the relative tolerance applies, so that the test doesn't depend on the scale of the scene.
+    pub fn is_degenerate_triangle(&self, p0: Point3, p1: Point3, p2: Point3) -> bool {
+        let (e0, e1, e2) = (p1 - p0, p2 - p0, p2 - p1);
+        let twice_area = e0.cross(e1).norm();
+        let longest = e0
+            .norm_squared()
+            .max(e1.norm_squared())
+            .max(e2.norm_squared());
+        !twice_area.is_finite() || twice_area <= self.rel * longest
+    }
+
+    /// whether a matrix with determinant `determinant` and columns of lengths `column_norms` is singular.
+    /// the cube of the longest column bounds the determinant (hadamard's inequality),
+    /// so the relative tolerance applies to the volume the columns span compared to that bound,
+    /// which also catches columns that are negligible next to the others.
+    /// only the relative tolerance applies, so that well conditioned matrices of any scale are invertible.
+    pub fn is_singular(&self, determinant: f32, column_norms: [f32; 3]) -> bool {
+        let bound = column_norms[0]
+            .max(column_norms[1])
+            .max(column_norms[2])
+            .powi(3);
+        !determinant.is_finite() || determinant == 0.0 || determinant.abs() <= self.rel * bound
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tolerance() {
+        let tolerance = Tolerance::DEFAULT;
+        assert!(tolerance.approx_eq(1.0, 1.0 + 5e-6));
+        assert!(!tolerance.approx_eq(1.0, 1.001));
+        // relative for large values, absolute near zero, and ulps across zero
+        assert!(tolerance.approx_eq(1e6, 1e6 + 5.0));
+        assert!(tolerance.approx_eq(1e-8, -1e-8));
+        assert!(Tolerance::new(0.0, 0.0, 2).approx_eq(f32::from_bits(1), -f32::from_bits(1)));
+        assert!(!Tolerance::new(0.0, 0.0, 2).approx_eq(1.0, 1.0 + 4.0 * f32::EPSILON));
+        assert!(!tolerance.approx_eq(f32::NAN, f32::NAN));
+        assert!(tolerance.approx_eq(f32::INFINITY, f32::INFINITY));
+        assert!(!Tolerance::EXACT.approx_eq(1.0, 1.0 + f32::EPSILON));
+        assert!(tolerance.approx_eq_vec3(Vec3::new(1.0, 2.0, 3.0), Vec3::new(1.0, 2.0, 3.000001)));
+        assert!(tolerance.approx_eq_lanes(f32x4::splat(0.5), f32x4::splat(0.5 + 1e-7)));
+
+        assert!(tolerance.rejects_pdf(PDF::<f32, SolidAngle>::new(1e-9)));
+        assert!(!tolerance.rejects_pdf(PDF::<f32, SolidAngle>::new(0.1)));
+
+        let (a, b) = (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0));
+        assert!(!tolerance.is_degenerate_triangle(a, b, Point3::new(0.0, 1.0, 0.0)));
+        assert!(tolerance.is_degenerate_triangle(a, b, Point3::new(2.0, 1e-7, 0.0)));
+        assert!(tolerance.is_degenerate_triangle(a, b, b));
+        // scale invariant, so tiny but well shaped triangles are kept
+        assert!(!tolerance.is_degenerate_triangle(
+            a,
+            Point3::new(1e-4, 0.0, 0.0),
+            Point3::new(0.0, 1e-4, 0.0)
+        ));
+
+        // a nearly singular matrix is rejected by the default tolerance, but inverted exactly
+        let nearly = Matrix3x3::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 1e-7]]);
+        assert!(nearly.inverse().is_ok());
+        assert_eq!(
+            nearly.inverse_with_tolerance(tolerance),
+            Err(MathError::SingularMatrix)
+        );
+        assert!(Matrix3x3::IDENTITY
+            .inverse_with_tolerance(Tolerance::LOOSE)
+            .is_ok());
+        // small but well conditioned matrices are invertible
+        assert!(Matrix3x3::from_diagonal(Vec3::new(0.01, 0.01, 0.01))
+            .inverse_with_tolerance(tolerance)
+            .is_ok());
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     cmp::Ordering,
     fmt::Debug,
     ops::{AddAssign, MulAssign},
-    simd::f32x2,
+    simd::Simd,
 };
 
 // TODO: figure out if it's necessary to create a separate trait for the support of a measure,
@@ -33,10 +33,22 @@ impl Measure for SolidAngle {}
 pub struct ProjectedSolidAngle {}
 impl Measure for ProjectedSolidAngle {}
 
+// differential solid angle of a half vector (microfacet normal) wh, rather than of a scattered direction.
+// pdfs of sampled microfacet normals are wrt this measure, and converting them to the solid angle of the scattered direction
+// requires the jacobian |dwh / dwi| of the half vector mapping for reflection or refraction.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HalfVector {}
+impl Measure for HalfVector {}
+
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Area {}
 impl Measure for Area {}
 
+// differential length, along a ray for sampled distances in participating media, or along a 1D domain
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Length {}
+impl Measure for Length {}
+
 // basic measure
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Uniform01 {}
@@ -75,7 +87,7 @@ impl Abs for f32 {
     }
 }
 
-impl Abs for f32x4 {
+impl<const N: usize> Abs for Simd<f32, N> {
     #[inline(always)]
     fn abs(self) -> Self {
         // disambiguation needed because this method ^ and this method v share the same name
@@ -83,6 +95,24 @@ impl Abs for f32x4 {
     }
 }
 
+pub trait Sqrt {
+    fn sqrt(self) -> Self;
+}
+
+impl Sqrt for f32 {
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+}
+
+impl<const N: usize> Sqrt for Simd<f32, N> {
+    #[inline(always)]
+    fn sqrt(self) -> Self {
+        std::simd::StdFloat::sqrt(self)
+    }
+}
+
 pub trait TotalPartialOrd {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering>;
 }
@@ -93,7 +123,7 @@ impl TotalPartialOrd for f32 {
     }
 }
 
-impl TotalPartialOrd for f32x4 {
+impl<const N: usize> TotalPartialOrd for Simd<f32, N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         if self.eq(other) {
             Some(Ordering::Equal)
@@ -151,7 +181,7 @@ impl CheckNAN for f32 {
     }
 }
 
-impl CheckNAN for f32x4 {
+impl<const N: usize> CheckNAN for Simd<f32, N> {
     fn check_nan(&self) -> CheckResult {
         let mask = self.is_nan();
         if mask.all() {
@@ -174,7 +204,7 @@ impl CheckInf for f32 {
     }
 }
 
-impl CheckInf for f32x4 {
+impl<const N: usize> CheckInf for Simd<f32, N> {
     fn check_inf(&self) -> CheckResult {
         let mask = self.is_infinite();
         if mask.all() {
@@ -243,20 +273,21 @@ impl Field for f32 {
 }
 impl Scalar for f32 {}
 
-impl Field for f32x4 {
-    const ONE: Self = f32x4::from_array([1.0, 1.0, 1.0, 1.0]);
-    const ZERO: Self = f32x4::from_array([0.0, 0.0, 0.0, 0.0]);
+// implemented for every lane count, so that wider types such as PDF<f32x8, M> need no additional impls
+impl<const N: usize> Field for Simd<f32, N> {
+    const ONE: Self = Simd::from_array([1.0; N]);
+    const ZERO: Self = Simd::from_array([0.0; N]);
     #[inline(always)]
     fn max(&self, other: Self) -> Self {
-        f32x4::simd_max(*self, other)
+        self.simd_max(other)
     }
     #[inline(always)]
     fn min(&self, other: Self) -> Self {
-        f32x4::simd_min(*self, other)
+        self.simd_min(other)
     }
 }
 
-impl ToScalar<f32> for f32x4 {
+impl<const N: usize> ToScalar<f32> for Simd<f32, N> {
     #[inline(always)]
     fn to_scalar(&self) -> f32 {
         self[0]
@@ -270,10 +301,10 @@ impl ToScalar<f32> for f32 {
     }
 }
 
-impl FromScalar<f32> for f32x4 {
+impl<const N: usize> FromScalar<f32> for Simd<f32, N> {
     #[inline(always)]
-    fn from_scalar(v: f32) -> f32x4 {
-        f32x4::splat(v)
+    fn from_scalar(v: f32) -> Simd<f32, N> {
+        Simd::splat(v)
     }
 }
 impl FromScalar<f32> for f32 {
@@ -290,21 +321,11 @@ pub trait SimdFloatPatch {
 }
 
 #[cfg(feature = "simdfloat_patch")]
-impl SimdFloatPatch for f32x2 {
-    fn powf(mut self, power: f32x2) -> Self {
-        self[0] = self[0].powf(power[0]);
-        self[1] = self[1].powf(power[1]);
-        self
-    }
-}
-
-#[cfg(feature = "simdfloat_patch")]
-impl SimdFloatPatch for f32x4 {
-    fn powf(mut self, power: f32x4) -> Self {
-        self[0] = self[0].powf(power[0]);
-        self[1] = self[1].powf(power[1]);
-        self[2] = self[2].powf(power[2]);
-        self[3] = self[3].powf(power[3]);
+impl<const N: usize> SimdFloatPatch for Simd<f32, N> {
+    fn powf(mut self, power: Simd<f32, N>) -> Self {
+        for i in 0..N {
+            self[i] = self[i].powf(power[i]);
+        }
         self
     }
 }
@@ -445,4 +466,28 @@ mod test {
         // let variance = square_estimate - estimate * estimate;
         // println!("{:?}, var = {:?}", estimate, variance);
     }
+
+    #[test]
+    fn test_wide_fields() {
+        use std::simd::f32x8;
+
+        assert_eq!(<f32x8 as Field>::ONE, f32x8::splat(1.0));
+        let a = PDF::<f32x8, SolidAngle>::new(f32x8::splat(0.5));
+        let b = PDF::<f32x8, SolidAngle>::new(f32x8::splat(1.5));
+        assert_eq!(*(a + b), f32x8::splat(2.0));
+        assert_eq!(
+            TotalPartialOrd::partial_cmp(&*b, &*a),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            (f32x8::splat(1.0) / f32x8::splat(0.0)).check_inf(),
+            CheckResult::All
+        );
+
+        let bounds = Bounds1D::new(380.0, 780.0);
+        let wide = WavelengthEnergy::<f32x8, f32x8>::new_from_range(0.9, bounds);
+        let lambdas = wide.lambda.to_array();
+        assert!(lambdas.iter().all(|lambda| bounds.contains(lambda)));
+        assert!((lambdas[0] - 740.0).abs() < 1e-3 && (lambdas[1] - 390.0).abs() < 1e-3);
+    }
 }
@@ -1,8 +1,15 @@
+use crate::error::Result;
 use crate::prelude::*;
 
-use std::ops::IndexMut;
 use std::simd::{f32x16, simd_swizzle};
 
+pub mod animated;
+pub mod diagnostics;
+pub mod matrix3;
+
+/// A 4x4 matrix, stored in column major order, i.e. the entry at row i and column j is at index j * 4 + i.
+/// use the explicit `from_row_major` / `from_column_major` constructors when reading matrices from other sources,
+/// as file formats and apis disagree on the order.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Matrix4x4(f32x16);
 
@@ -10,12 +17,40 @@ impl Matrix4x4 {
     const I: Matrix4x4 = Matrix4x4(f32x16::from_array([
         1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
     ]));
+    /// from 16 entries listed row by row, i.e. with the translation of an affine transform at indices 3, 7 and 11
+    pub fn from_row_major(elements: [f32; 16]) -> Matrix4x4 {
+        Matrix4x4::from_column_major(elements).transpose()
+    }
+    /// from 16 entries listed column by column, i.e. with the translation of an affine transform at indices 12, 13 and 14
+    pub const fn from_column_major(elements: [f32; 16]) -> Matrix4x4 {
+        Matrix4x4(f32x16::from_array(elements))
+    }
+    pub fn to_row_major(&self) -> [f32; 16] {
+        self.transpose().0.to_array()
+    }
+    pub fn to_column_major(&self) -> [f32; 16] {
+        self.0.to_array()
+    }
     pub fn transpose(&self) -> Matrix4x4 {
         Matrix4x4(simd_swizzle!(
             self.0,
             [0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15]
         ))
     }
+    /// the affine transform that applies `linear` and then adds `translation`
+    pub fn from_affine(linear: Matrix3x3, translation: Vec3) -> Matrix4x4 {
+        let [c0, c1, c2, t] = [
+            linear.column(0),
+            linear.column(1),
+            linear.column(2),
+            translation,
+        ]
+        .map(|v| [v.x(), v.y(), v.z()]);
+        Matrix4x4::from_column_major([
+            c0[0], c0[1], c0[2], 0.0, c1[0], c1[1], c1[2], 0.0, c2[0], c2[1], c2[2], 0.0, t[0],
+            t[1], t[2], 1.0,
+        ])
+    }
 }
 
 impl Mul<Vec3> for Matrix4x4 {
@@ -133,11 +168,14 @@ impl Transform3 {
             reverse: Matrix4x4::I,
         }
     }
-    pub fn new_from_matrix(forward: nalgebra::Matrix4<f32>) -> Option<Self> {
-        forward.try_inverse().map(|inverse| Transform3 {
-            forward: Matrix4x4::from(forward),
-            reverse: Matrix4x4::from(inverse),
-        })
+    pub fn new_from_matrix(forward: nalgebra::Matrix4<f32>) -> Result<Self> {
+        forward
+            .try_inverse()
+            .map(|inverse| Transform3 {
+                forward: Matrix4x4::from(forward),
+                reverse: Matrix4x4::from(inverse),
+            })
+            .ok_or(MathError::SingularMatrix)
     }
 
     pub fn inverse(self) -> Transform3 {
@@ -171,6 +209,32 @@ impl Transform3 {
         Transform3::new_from_matrix(affine).expect("somehow, rotation matrix was not invertible")
     }
 
+    /// shears each axis by the other two, i.e. x' = x + xy * y + xz * z, y' = yx * x + y + yz * z and so on.
+    /// the inverse is computed in closed form from the adjugate of the shear matrix.
+    /// fails if the shears are large enough to collapse space onto a plane.
+    pub fn from_shear(xy: f32, xz: f32, yx: f32, yz: f32, zx: f32, zy: f32) -> Result<Self> {
+        let linear = Matrix3x3::new([[1.0, xy, xz], [yx, 1.0, yz], [zx, zy, 1.0]]);
+        let inverse = linear.inverse()?;
+        Ok(Transform3::new_from_raw(
+            Matrix4x4::from(linear),
+            Matrix4x4::from(inverse),
+        ))
+    }
+
+    /// mirrors across the plane through `plane_point` with normal `plane_normal`, which is its own inverse
+    pub fn from_reflection(plane_normal: Vec3, plane_point: Point3) -> Self {
+        let n = plane_normal.normalized();
+        // p' = p - 2 n (n . (p - p0)) = (I - 2 n n^T) p + 2 (n . p0) n
+        let householder = Matrix3x3::from_columns(
+            Vec3::X - n * (2.0 * n.x()),
+            Vec3::Y - n * (2.0 * n.y()),
+            Vec3::Z - n * (2.0 * n.z()),
+        );
+        let offset = n * (2.0 * (n * Vec3::from(plane_point)));
+        let matrix = Matrix4x4::from_affine(householder, offset);
+        Transform3::new_from_raw(matrix, matrix)
+    }
+
     // pub fn rotation(quaternion: f32x4) -> Self {
     //     let quat = nalgebra::Quaternion::new()
 
@@ -256,23 +320,16 @@ impl From<TangentFrame> for Transform3 {
 
 impl From<nalgebra::Matrix4<f32>> for Matrix4x4 {
     fn from(matrix: nalgebra::Matrix4<f32>) -> Self {
-        let vec: Vec<f32> = matrix.as_slice().to_owned();
-        let mut elements: f32x16 = f32x16::splat(0.0);
-        for (i, v) in vec.iter().enumerate() {
-            *elements.index_mut(i) = *v;
-        }
-        Matrix4x4(elements)
+        // nalgebra stores matrices in column major order as well
+        let mut elements = [0.0; 16];
+        elements.copy_from_slice(matrix.as_slice());
+        Matrix4x4::from_column_major(elements)
     }
 }
 
 impl From<Matrix4x4> for nalgebra::Matrix4<f32> {
     fn from(other: Matrix4x4) -> Self {
-        let [m11, m12, m13, m14, m21, m22, m23, m24, m31, m32, m33, m34, m41, m42, m43, m44]: [f32;
-            16] = other.0.into();
-        nalgebra::Matrix4::new(
-            m11, m12, m13, m14, m21, m22, m23, m24, m31, m32, m33, m34, m41, m42, m43, m44,
-        )
-        .transpose()
+        nalgebra::Matrix4::from_column_slice(&other.to_column_major())
     }
 }
 
@@ -286,6 +343,42 @@ impl Mul<Transform3> for Transform3 {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_matrix_layouts() {
+        let row_major: [f32; 16] = std::array::from_fn(|i| i as f32 + 1.0);
+        let matrix = Matrix4x4::from_row_major(row_major);
+        assert_eq!(matrix.to_row_major(), row_major);
+        assert_eq!(
+            Matrix4x4::from_column_major(matrix.to_column_major()),
+            matrix
+        );
+        assert_eq!(matrix.transpose().to_column_major(), row_major);
+
+        // nalgebra agrees on which entry is at which row and column
+        let na = nalgebra::Matrix4::from(matrix);
+        assert_eq!(na, nalgebra::Matrix4::from_row_slice(&row_major));
+        assert_eq!(na[(0, 3)], 4.0);
+        assert_eq!(Matrix4x4::from(na), matrix);
+
+        // the translation of a row major affine transform is in its last column
+        let translation = Matrix4x4::from_row_major([
+            1.0, 0.0, 0.0, 5.0, 0.0, 1.0, 0.0, 6.0, 0.0, 0.0, 1.0, 7.0, 0.0, 0.0, 0.0, 1.0,
+        ]);
+        assert_eq!(
+            translation * Point3::new(1.0, 1.0, 1.0),
+            Point3::new(6.0, 7.0, 8.0)
+        );
+        assert_eq!(translation * Vec3::X, Vec3::X);
+        assert_eq!(
+            translation,
+            Transform3::from_translation(Vec3::new(5.0, 6.0, 7.0)).forward
+        );
+        assert_eq!(
+            Matrix4x4::from_column_major(Matrix4x4::I.to_row_major()),
+            Matrix4x4::I
+        );
+    }
     #[test]
     fn test_transform() {
         let transform_translate = Transform3::from_translation(Vec3::new(1.0, 2.0, 0.0));
@@ -442,6 +535,16 @@ mod tests {
         println!("point  ts, {:?}", combination_ts.to_local(test_point));
     }
 
+    #[test]
+    fn test_singular_matrix() {
+        let singular =
+            nalgebra::Matrix4::new_nonuniform_scaling(&nalgebra::Vector3::new(1.0, 0.0, 1.0));
+        assert_eq!(
+            Transform3::new_from_matrix(singular),
+            Err(MathError::SingularMatrix)
+        );
+    }
+
     #[test]
     fn test_translate() {
         let n_translate =
@@ -466,4 +569,49 @@ mod tests {
         );
         println!("{:?} {:?}", result6, result7);
     }
+
+    #[test]
+    fn test_shear_and_reflection() {
+        let assert_identity = |transform: Transform3| {
+            let product = (transform.forward * transform.reverse).to_row_major();
+            let identity = Matrix4x4::I.to_row_major();
+            for (a, b) in product.iter().zip(identity.iter()) {
+                assert!((a - b).abs() < 1e-5, "{:?}", product);
+            }
+        };
+
+        let shear = Transform3::from_shear(0.5, -0.25, 0.0, 0.75, 0.1, 0.0).unwrap();
+        assert_identity(shear);
+        let p = shear.to_world(Point3::new(1.0, 2.0, 3.0));
+        assert!((p - Point3::new(1.25, 4.25, 3.1)).norm() < 1e-5, "{:?}", p);
+
+        // normals transformed by the inverse transpose stay perpendicular to sheared tangents
+        let (tangent, normal) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 2.0));
+        assert_eq!(tangent * normal, 0.0);
+        let normal_matrix = Matrix3x3::from(shear.reverse).transpose();
+        let sheared_tangent = shear.to_world(tangent);
+        assert!((sheared_tangent * (normal_matrix * normal)).abs() < 1e-5);
+        // without the inverse transpose they generally aren't
+        assert!((sheared_tangent * shear.to_world(normal)).abs() > 0.1);
+        let analytic = Matrix3x3::from(shear.forward).normal_matrix().unwrap() * normal;
+        assert!((analytic - normal_matrix * normal).norm() < 1e-5);
+
+        // shearing x entirely into y and y into x flattens the xy plane onto a line
+        assert_eq!(
+            Transform3::from_shear(1.0, 0.0, 1.0, 0.0, 0.0, 0.0),
+            Err(MathError::SingularMatrix)
+        );
+
+        let plane_point = Point3::new(0.0, 1.0, 0.0);
+        let mirror = Transform3::from_reflection(Vec3::new(0.0, 2.0, 0.0), plane_point);
+        assert_identity(mirror);
+        assert_eq!(mirror.forward, mirror.reverse);
+        // points on the plane are fixed, and points off of it swap sides
+        let on_plane = Point3::new(3.0, 1.0, -2.0);
+        assert!((mirror.to_world(on_plane) - on_plane).norm() < 1e-6);
+        let mirrored = mirror.to_world(Point3::new(1.0, 3.0, 1.0));
+        assert!((mirrored - Point3::new(1.0, -1.0, 1.0)).norm() < 1e-6);
+        assert!((mirror.to_world(Vec3::Y) + Vec3::Y).norm() < 1e-6);
+        assert!(Matrix3x3::from(mirror.forward).determinant() < 0.0);
+    }
 }
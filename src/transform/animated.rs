@@ -0,0 +1,196 @@
+use super::Transform3;
+use crate::error::Result;
+use crate::prelude::*;
+
+use nalgebra::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3};
+
+/// An affine transform split into translation * rotation * scale, where scale is a symmetric matrix
+/// that may contain shear. interpolating the parts separately avoids the shrinking and skewing that
+/// interpolating matrix entries directly causes with rotations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DecomposedTransform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Matrix3<f32>,
+}
+
+impl DecomposedTransform {
+    /// polar decomposition of the linear part of `transform`.
+    /// a transform with a reflection (negative determinant) gets a proper rotation and a negative definite scale.
+    pub fn new(transform: &Transform3) -> Self {
+        let matrix: Matrix4<f32> = transform.forward.into();
+        let translation = Vector3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+        let linear: Matrix3<f32> = matrix.fixed_view::<3, 3>(0, 0).into_owned();
+        let svd = linear.svd(true, true);
+        let mut rotation = svd.u.unwrap() * svd.v_t.unwrap();
+        if rotation.determinant() < 0.0 {
+            rotation = -rotation;
+        }
+        let scale = rotation.transpose() * linear;
+        DecomposedTransform {
+            translation,
+            rotation: UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(
+                rotation,
+            )),
+            scale,
+        }
+    }
+
+    /// lerps translation and scale, and slerps rotation along the shortest arc
+    pub fn interpolate(&self, other: &Self, t: f32) -> Self {
+        DecomposedTransform {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale * (1.0 - t) + other.scale * t,
+        }
+    }
+
+    pub fn to_transform(&self) -> Result<Transform3> {
+        let linear = self.rotation.to_rotation_matrix().into_inner() * self.scale;
+        let mut matrix = linear.to_homogeneous();
+        matrix
+            .fixed_view_mut::<3, 1>(0, 3)
+            .copy_from(&self.translation);
+        Transform3::new_from_matrix(matrix)
+    }
+}
+
+/// A transform that moves between `start` and `end` over `time_bounds`, for motion blur.
+/// the endpoints are decomposed once on construction, such that evaluating at a time only needs to interpolate and recompose.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimatedTransform {
+    pub start: Transform3,
+    pub end: Transform3,
+    pub time_bounds: Bounds1D,
+    decomposed: Option<(DecomposedTransform, DecomposedTransform)>,
+}
+
+impl AnimatedTransform {
+    /// fails with `SingularMatrix` if exactly one of the endpoints is a reflection,
+    /// since the animation between them would have to pass through a singular transform.
+    pub fn new(start: Transform3, end: Transform3, time_bounds: Bounds1D) -> Result<Self> {
+        if start == end {
+            return Ok(AnimatedTransform::fixed(start));
+        }
+        let (a, b) = (
+            DecomposedTransform::new(&start),
+            DecomposedTransform::new(&end),
+        );
+        if a.scale.determinant() * b.scale.determinant() <= 0.0 {
+            return Err(MathError::SingularMatrix);
+        }
+        Ok(AnimatedTransform {
+            start,
+            end,
+            time_bounds,
+            decomposed: Some((a, b)),
+        })
+    }
+
+    /// a transform that doesn't move
+    pub fn fixed(transform: Transform3) -> Self {
+        AnimatedTransform {
+            start: transform,
+            end: transform,
+            time_bounds: Bounds1D::new(0.0, 1.0),
+            decomposed: None,
+        }
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.decomposed.is_some()
+    }
+
+    /// the transform at `time`, which is clamped to `time_bounds`
+    pub fn interpolate(&self, time: f32) -> Transform3 {
+        let Some((a, b)) = &self.decomposed else {
+            return self.start;
+        };
+        let t = if self.time_bounds.span() > 0.0 {
+            ((time - self.time_bounds.lower) / self.time_bounds.span()).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if t == 0.0 {
+            return self.start;
+        }
+        if t == 1.0 {
+            return self.end;
+        }
+        // the scale of both endpoints is definite with the same sign, and so is every convex combination of them
+        a.interpolate(b, t)
+            .to_transform()
+            .expect("interpolated transform was singular")
+    }
+
+    /// transforms `ray` to world space using the transform at `ray.time`
+    pub fn transform_ray(&self, ray: Ray) -> Ray {
+        self.interpolate(ray.time).to_world(ray)
+    }
+
+    /// transforms `ray` to local space using the transform at `ray.time`
+    pub fn inverse_transform_ray(&self, ray: Ray) -> Ray {
+        self.interpolate(ray.time).to_local(ray)
+    }
+}
+
+impl From<Transform3> for AnimatedTransform {
+    fn from(transform: Transform3) -> Self {
+        AnimatedTransform::fixed(transform)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: Point3, b: Point3) {
+        assert!((a - b).norm() < 1e-4, "{:?} {:?}", a, b);
+    }
+
+    #[test]
+    fn test_animated_transform() {
+        let rotate = |degrees: f32| Transform3::from_axis_angle(Vec3::Z, degrees.to_radians());
+        let start = Transform3::new();
+        let end = Transform3::from_translation(Vec3::new(2.0, 0.0, 0.0)) * rotate(90.0);
+        let animated = AnimatedTransform::new(start, end, Bounds1D::new(1.0, 3.0)).unwrap();
+        assert!(animated.is_animated());
+
+        // halfway, the rotation is halfway around the arc rather than along the chord between the endpoints
+        let p = Point3::new(1.0, 0.0, 0.5);
+        let halfway = animated.interpolate(2.0);
+        let expected = |p: Point3| end.to_world(p);
+        let rotated = Transform3::from_translation(Vec3::new(1.0, 0.0, 0.0))
+            .to_world(rotate(45.0).to_world(p));
+        assert_close(halfway.to_world(p), rotated);
+        assert_close(halfway.to_local(halfway.to_world(p)), p);
+        assert_close(animated.interpolate(3.0).to_world(p), expected(p));
+        // times outside of the bounds are clamped
+        assert_close(animated.interpolate(10.0).to_world(p), expected(p));
+        assert_close(animated.interpolate(-10.0).to_world(p), p);
+
+        // rays are transformed at their own time
+        let ray = Ray::new_with_time(p, Vec3::X, 2.0);
+        let moved = animated.transform_ray(ray);
+        assert_close(moved.origin, rotated);
+        assert!((moved.direction - rotate(45.0).to_world(Vec3::X)).norm() < 1e-4);
+        assert_eq!(moved.time, 2.0);
+        assert_close(animated.inverse_transform_ray(moved).origin, p);
+
+        // a scale through a reflection keeps its sign, and a reflection can't animate into a proper transform
+        let mirror = |x: f32| Transform3::from_scale(Vec3::new(x, 1.0, 1.0));
+        let mirrored =
+            AnimatedTransform::new(mirror(-1.0), mirror(-2.0), Bounds1D::new(0.0, 1.0)).unwrap();
+        assert_close(
+            mirrored.interpolate(0.5).to_world(p),
+            Point3::new(-1.5, 0.0, 0.5),
+        );
+        assert_eq!(
+            AnimatedTransform::new(mirror(-1.0), mirror(1.0), Bounds1D::new(0.0, 1.0)),
+            Err(MathError::SingularMatrix)
+        );
+        let fixed = AnimatedTransform::from(end);
+        assert!(!fixed.is_animated());
+        assert_eq!(fixed.interpolate(0.3), end);
+    }
+}
@@ -0,0 +1,137 @@
+use super::{Matrix4x4, Transform3};
+use crate::prelude::*;
+
+/// Numerical diagnostics for a `Transform3`, used to detect badly scaled or nearly singular matrices
+/// before they show up as cracks or other artifacts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TransformDiagnostics {
+    /// largest distance between a point and its round trip through `to_world` and `to_local`
+    pub max_point_error: f32,
+    pub mean_point_error: f32,
+    /// largest round trip error of a unit direction
+    pub max_vector_error: f32,
+    /// 2-norm condition numbers of the linear (upper 3x3) parts of the forward and reverse matrices
+    pub forward_condition_number: f32,
+    pub reverse_condition_number: f32,
+    /// largest absolute entry of forward * reverse - I, which should be 0 for a consistent transform
+    pub inverse_residual: f32,
+}
+
+impl TransformDiagnostics {
+    /// whether every round trip error is below `tolerance` and the condition number is below `max_condition_number`
+    pub fn is_acceptable(&self, tolerance: f32, max_condition_number: f32) -> bool {
+        self.max_point_error <= tolerance
+            && self.max_vector_error <= tolerance
+            && self.forward_condition_number <= max_condition_number
+    }
+}
+
+/// ratio of the largest to the smallest singular value of the linear part of `matrix`.
+/// returns infinity if the linear part is singular.
+pub fn condition_number(matrix: Matrix4x4) -> f32 {
+    let matrix: nalgebra::Matrix4<f32> = matrix.into();
+    let linear: nalgebra::Matrix3<f32> = matrix.fixed_view::<3, 3>(0, 0).into_owned();
+    let singular_values = linear.singular_values();
+    let (min, max) = (singular_values.min(), singular_values.max());
+    if min > 0.0 {
+        max / min
+    } else {
+        f32::INFINITY
+    }
+}
+
+pub fn point_round_trip_error(transform: &Transform3, point: Point3) -> f32 {
+    (transform.to_local(transform.to_world(point)) - point).norm()
+}
+
+pub fn vector_round_trip_error(transform: &Transform3, vector: Vec3) -> f32 {
+    (transform.to_local(transform.to_world(vector)) - vector).norm()
+}
+
+fn inverse_residual(transform: &Transform3) -> f32 {
+    let product = (transform.forward * transform.reverse).0 - Matrix4x4::I.0;
+    SimdFloat::abs(product).reduce_max()
+}
+
+/// estimates the worst case round trip error of `transform` over the axis aligned box spanned by `lower` and `upper`,
+/// by testing the corners of the box and `samples` random points and directions drawn from `sampler`.
+pub fn diagnose<S: Sampler + ?Sized>(
+    transform: &Transform3,
+    lower: Point3,
+    upper: Point3,
+    samples: usize,
+    sampler: &mut S,
+) -> TransformDiagnostics {
+    let extent = upper - lower;
+    let mut max_point_error: f32 = 0.0;
+    let mut total_point_error = 0.0;
+    let mut count = 0;
+    let mut test_point = |point: Point3| {
+        let error = point_round_trip_error(transform, point);
+        max_point_error = max_point_error.max(error);
+        total_point_error += error;
+        count += 1;
+    };
+
+    for corner in 0..8 {
+        let select = |axis: usize| if corner & (1 << axis) != 0 { 1.0 } else { 0.0 };
+        test_point(
+            lower
+                + Vec3::new(
+                    select(0) * extent.x(),
+                    select(1) * extent.y(),
+                    select(2) * extent.z(),
+                ),
+        );
+    }
+    for _ in 0..samples {
+        let Sample3D { x, y, z } = sampler.draw_3d();
+        test_point(lower + Vec3::new(x * extent.x(), y * extent.y(), z * extent.z()));
+    }
+
+    let mut max_vector_error: f32 = 0.0;
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+        max_vector_error = max_vector_error.max(vector_round_trip_error(transform, axis));
+    }
+    for _ in 0..samples {
+        let direction = random_on_unit_sphere(sampler.draw_2d());
+        max_vector_error = max_vector_error.max(vector_round_trip_error(transform, direction));
+    }
+
+    TransformDiagnostics {
+        max_point_error,
+        mean_point_error: total_point_error / count as f32,
+        max_vector_error,
+        forward_condition_number: condition_number(transform.forward),
+        reverse_condition_number: condition_number(transform.reverse),
+        inverse_residual: inverse_residual(transform),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let mut sampler = RandomSampler::new();
+        let (lower, upper) = (
+            Point3::new(-10.0, -10.0, -10.0),
+            Point3::new(10.0, 10.0, 10.0),
+        );
+
+        let well_behaved = Transform3::from_stack(
+            Some(Transform3::from_scale(Vec3::new(2.0, 3.0, 4.0))),
+            Some(Transform3::from_axis_angle(Vec3::Z, PI / 4.0)),
+            Some(Transform3::from_translation(Vec3::new(1.0, 2.0, 3.0))),
+        );
+        let diagnostics = diagnose(&well_behaved, lower, upper, 100, &mut sampler);
+        assert!((diagnostics.forward_condition_number - 2.0).abs() < 1e-3);
+        assert!(diagnostics.is_acceptable(1e-4, 10.0), "{:?}", diagnostics);
+
+        let badly_scaled = Transform3::from_scale(Vec3::new(1e-4, 1.0, 1e4));
+        let diagnostics = diagnose(&badly_scaled, lower, upper, 100, &mut sampler);
+        assert!(diagnostics.forward_condition_number > 1e7);
+        assert!(!diagnostics.is_acceptable(1e-4, 1e6));
+    }
+}
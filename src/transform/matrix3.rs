@@ -0,0 +1,229 @@
+use super::Matrix4x4;
+use crate::error::Result;
+use crate::prelude::*;
+
+use std::simd::{f32x16, simd_swizzle};
+
+/// A 3x3 matrix for linear maps, i.e. rotations, normal transforms and changes of basis,
+/// stored as three columns with an unused fourth lane, such that each column has the layout of a `Vec3`.
+/// cheaper to store and apply than a `Matrix4x4` when translation and projection aren't needed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Matrix3x3([f32x4; 3]);
+
+impl Matrix3x3 {
+    pub const IDENTITY: Matrix3x3 = Matrix3x3::from_columns(Vec3::X, Vec3::Y, Vec3::Z);
+    pub const ZERO: Matrix3x3 = Matrix3x3::from_columns(Vec3::ZERO, Vec3::ZERO, Vec3::ZERO);
+
+    pub const fn from_columns(c0: Vec3, c1: Vec3, c2: Vec3) -> Self {
+        Matrix3x3([c0.0, c1.0, c2.0])
+    }
+    pub fn from_rows(r0: Vec3, r1: Vec3, r2: Vec3) -> Self {
+        Matrix3x3::from_columns(r0, r1, r2).transpose()
+    }
+    /// from the entries of each row, in order
+    pub fn new(rows: [[f32; 3]; 3]) -> Self {
+        let [r0, r1, r2] = rows.map(|[x, y, z]| Vec3::new(x, y, z));
+        Matrix3x3::from_rows(r0, r1, r2)
+    }
+    pub fn from_diagonal(diagonal: Vec3) -> Self {
+        Matrix3x3::from_columns(
+            Vec3::new(diagonal.x(), 0.0, 0.0),
+            Vec3::new(0.0, diagonal.y(), 0.0),
+            Vec3::new(0.0, 0.0, diagonal.z()),
+        )
+    }
+
+    pub fn column(&self, i: usize) -> Vec3 {
+        Vec3(self.0[i])
+    }
+    pub fn row(&self, i: usize) -> Vec3 {
+        Vec3::new(self.0[0][i], self.0[1][i], self.0[2][i])
+    }
+    /// the entry at `row` and `column`
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        self.0[column][row]
+    }
+
+    pub fn transpose(&self) -> Matrix3x3 {
+        Matrix3x3([self.row(0).0, self.row(1).0, self.row(2).0])
+    }
+    pub fn determinant(&self) -> f32 {
+        self.column(0) * self.column(1).cross(self.column(2))
+    }
+    pub fn inverse(&self) -> Result<Matrix3x3> {
+        self.inverse_with_tolerance(Tolerance::EXACT)
+    }
+    /// the inverse, treating the matrix as singular when `tolerance` considers its determinant negligible
+    pub fn inverse_with_tolerance(&self, tolerance: Tolerance) -> Result<Matrix3x3> {
+        let (c0, c1, c2) = (self.column(0), self.column(1), self.column(2));
+        // the rows of the inverse are the cross products of pairs of columns, scaled by 1 / det
+        let (r0, r1, r2) = (c1.cross(c2), c2.cross(c0), c0.cross(c1));
+        let determinant = c0 * r0;
+        if tolerance.is_singular(determinant, [c0.norm(), c1.norm(), c2.norm()]) {
+            return Err(MathError::SingularMatrix);
+        }
+        Ok(Matrix3x3::from_rows(r0, r1, r2) * (1.0 / determinant))
+    }
+    /// the inverse transpose, which transforms normals such that they stay perpendicular to transformed surfaces
+    pub fn normal_matrix(&self) -> Result<Matrix3x3> {
+        Ok(self.inverse()?.transpose())
+    }
+    /// the eigenvalues and eigenvectors of a symmetric matrix, with the eigenvalues in decreasing order
+    /// and the corresponding unit eigenvectors as the columns of the returned matrix.
+    /// only the lower triangle is read, so the result is meaningless for matrices that aren't symmetric.
+    pub fn symmetric_eigen(&self) -> (Vec3, Matrix3x3) {
+        let eigen = nalgebra::Matrix3::from(*self).symmetric_eigen();
+        let mut order = [0, 1, 2];
+        order.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+        let [a, b, c] = order.map(|i| {
+            let column = eigen.eigenvectors.column(i);
+            Vec3::new(column[0], column[1], column[2])
+        });
+        let values = order.map(|i| eigen.eigenvalues[i]);
+        (
+            Vec3::new(values[0], values[1], values[2]),
+            Matrix3x3::from_columns(a, b, c),
+        )
+    }
+}
+
+impl Default for Matrix3x3 {
+    fn default() -> Self {
+        Matrix3x3::IDENTITY
+    }
+}
+
+impl Mul<Vec3> for Matrix3x3 {
+    type Output = Vec3;
+    fn mul(self, rhs: Vec3) -> Self::Output {
+        let [c0, c1, c2] = self.0;
+        Vec3(c0 * f32x4::splat(rhs.x()) + c1 * f32x4::splat(rhs.y()) + c2 * f32x4::splat(rhs.z()))
+    }
+}
+
+impl Mul for Matrix3x3 {
+    type Output = Matrix3x3;
+    /// composes the two maps, such that (a * b) * v == a * (b * v)
+    fn mul(self, rhs: Matrix3x3) -> Self::Output {
+        Matrix3x3(rhs.0.map(|column| (self * Vec3(column)).0))
+    }
+}
+
+impl Mul<f32> for Matrix3x3 {
+    type Output = Matrix3x3;
+    fn mul(self, rhs: f32) -> Self::Output {
+        Matrix3x3(self.0.map(|column| column * f32x4::splat(rhs)))
+    }
+}
+
+impl Add for Matrix3x3 {
+    type Output = Matrix3x3;
+    fn add(self, rhs: Matrix3x3) -> Self::Output {
+        Matrix3x3([0, 1, 2].map(|i| self.0[i] + rhs.0[i]))
+    }
+}
+
+impl From<Matrix4x4> for Matrix3x3 {
+    /// the upper left 3x3 block, i.e. the linear part of an affine transform
+    fn from(matrix: Matrix4x4) -> Self {
+        let mask = f32x4::from_array([1.0, 1.0, 1.0, 0.0]);
+        Matrix3x3([
+            simd_swizzle!(matrix.0, [0, 1, 2, 3]) * mask,
+            simd_swizzle!(matrix.0, [4, 5, 6, 7]) * mask,
+            simd_swizzle!(matrix.0, [8, 9, 10, 11]) * mask,
+        ])
+    }
+}
+
+impl From<Matrix3x3> for Matrix4x4 {
+    fn from(matrix: Matrix3x3) -> Self {
+        let [c0, c1, c2] = matrix.0.map(|column| column.to_array());
+        Matrix4x4(f32x16::from_array([
+            c0[0], c0[1], c0[2], 0.0, c1[0], c1[1], c1[2], 0.0, c2[0], c2[1], c2[2], 0.0, 0.0, 0.0,
+            0.0, 1.0,
+        ]))
+    }
+}
+
+impl From<TangentFrame> for Matrix3x3 {
+    /// the matrix with the tangent, bitangent and normal as columns,
+    /// which maps local vectors to world space like `TangentFrame::to_world`. its transpose maps them back.
+    fn from(frame: TangentFrame) -> Self {
+        Matrix3x3::from_columns(frame.tangent, frame.bitangent, frame.normal)
+    }
+}
+
+impl From<Matrix3x3> for TangentFrame {
+    /// the frame spanned by the columns of an orthonormal matrix
+    fn from(matrix: Matrix3x3) -> Self {
+        TangentFrame::new(matrix.column(0), matrix.column(1), matrix.column(2))
+    }
+}
+
+impl From<nalgebra::Matrix3<f32>> for Matrix3x3 {
+    fn from(matrix: nalgebra::Matrix3<f32>) -> Self {
+        Matrix3x3::new([0, 1, 2].map(|i| [matrix[(i, 0)], matrix[(i, 1)], matrix[(i, 2)]]))
+    }
+}
+
+impl From<Matrix3x3> for nalgebra::Matrix3<f32> {
+    fn from(matrix: Matrix3x3) -> Self {
+        nalgebra::Matrix3::from_fn(|i, j| matrix.get(i, j))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: Matrix3x3, b: Matrix3x3) {
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!((a.get(i, j) - b.get(i, j)).abs() < 1e-5, "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_matrix3x3() {
+        let a = Matrix3x3::new([[2.0, -1.0, 0.5], [0.3, 1.5, -2.0], [1.0, 0.0, 3.0]]);
+        let b = Matrix3x3::new([[0.0, 1.0, 0.0], [-1.0, 0.2, 0.0], [0.4, 0.0, 1.0]]);
+        let (na, nb) = (nalgebra::Matrix3::from(a), nalgebra::Matrix3::from(b));
+        assert_eq!(na[(0, 1)], -1.0);
+        assert_close(a * b, Matrix3x3::from(na * nb));
+        assert!((a.determinant() - na.determinant()).abs() < 1e-5);
+        assert_close(
+            a.inverse().unwrap(),
+            Matrix3x3::from(na.try_inverse().unwrap()),
+        );
+        assert_close(a * a.inverse().unwrap(), Matrix3x3::IDENTITY);
+        assert_eq!(a.transpose().row(0), a.column(0));
+        assert_eq!(Matrix3x3::ZERO.inverse(), Err(MathError::SingularMatrix));
+
+        let v = Vec3::new(0.3, -1.0, 2.0);
+        assert!(((a * b) * v - a * (b * v)).norm() < 1e-5);
+
+        // the linear part of a transform acts on vectors like the full matrix
+        let transform = Transform3::from_axis_angle(Vec3::new(1.0, 1.0, 0.0).normalized(), 0.7)
+            * Transform3::from_scale(Vec3::new(1.0, 2.0, 3.0))
+            * Transform3::from_translation(Vec3::new(5.0, 0.0, 1.0));
+        let linear = Matrix3x3::from(transform.forward);
+        assert!((linear * v - transform.forward * v).norm() < 1e-5);
+        assert_eq!(
+            Matrix4x4::from(linear) * v,
+            Matrix4x4::from(Matrix3x3::from(Matrix4x4::from(linear))) * v
+        );
+
+        // normals stay perpendicular to transformed tangents
+        let (tangent, normal) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, -1.0, 0.5));
+        assert!((tangent * normal).abs() < 1e-6);
+        let transformed_normal = linear.normal_matrix().unwrap() * normal;
+        assert!(((linear * tangent) * transformed_normal).abs() < 1e-4);
+
+        let frame = TangentFrame::from_normal(Vec3::new(0.0, 0.6, 0.8));
+        let basis = Matrix3x3::from(frame);
+        assert!((basis * v - frame.to_world(&v)).norm() < 1e-6);
+        assert!((basis.transpose() * v - frame.to_local(&v)).norm() < 1e-6);
+        assert_eq!(TangentFrame::from(basis).normal, frame.normal);
+    }
+}
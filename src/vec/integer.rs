@@ -0,0 +1,262 @@
+use crate::prelude::*;
+
+use std::simd::{cmp::SimdOrd, i32x4, mask32x4, num::SimdUint, simd_swizzle, u32x4};
+
+// integer simd helpers for sorting and encoding, i.e. morton keys and quantized buffers.
+// morton keys interleave the bits of each coordinate, such that sorting by key orders points along a z-order curve.
+// 2d keys hold 16 bits per axis, and 3d keys hold 10 bits per axis.
+
+/// how lanes are rounded when converting floats to integers
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// rounds half away from zero
+    Nearest,
+    Floor,
+    Ceil,
+    Truncate,
+}
+
+impl Rounding {
+    pub fn apply(self, v: f32x4) -> f32x4 {
+        match self {
+            Rounding::Nearest => v.round(),
+            Rounding::Floor => v.floor(),
+            Rounding::Ceil => v.ceil(),
+            Rounding::Truncate => v.trunc(),
+        }
+    }
+}
+
+/// rounds each lane of `v` and clamps it to [`min`, `max`]. NaN lanes become `min`.
+pub fn f32x4_to_u32x4(v: f32x4, min: u32, max: u32, rounding: Rounding) -> u32x4 {
+    debug_assert!(min <= max);
+    // the cast saturates, and the final clamp catches bounds that aren't exactly representable as f32
+    let rounded = rounding
+        .apply(v)
+        .simd_clamp(f32x4::splat(min as f32), f32x4::splat(max as f32));
+    let converted = v
+        .is_nan()
+        .select(f32x4::splat(min as f32), rounded)
+        .cast::<u32>();
+    converted.simd_clamp(u32x4::splat(min), u32x4::splat(max))
+}
+
+/// rounds each lane of `v` and clamps it to [`min`, `max`]. NaN lanes become `min`.
+pub fn f32x4_to_i32x4(v: f32x4, min: i32, max: i32, rounding: Rounding) -> i32x4 {
+    debug_assert!(min <= max);
+    let rounded = rounding
+        .apply(v)
+        .simd_clamp(f32x4::splat(min as f32), f32x4::splat(max as f32));
+    let converted = v
+        .is_nan()
+        .select(f32x4::splat(min as f32), rounded)
+        .cast::<i32>();
+    converted.simd_clamp(i32x4::splat(min), i32x4::splat(max))
+}
+
+/// quantizes lanes in [0, 1] to `bits` bit integers, by splitting [0, 1] into 2^bits equally sized cells
+pub fn quantize_unit(v: f32x4, bits: u32) -> u32x4 {
+    debug_assert!((1..=24).contains(&bits));
+    let cells = (1u32 << bits) as f32;
+    f32x4_to_u32x4(v * f32x4::splat(cells), 0, (1 << bits) - 1, Rounding::Floor)
+}
+
+/// maps quantized lanes back to the centers of their cells in [0, 1]
+pub fn dequantize_unit(q: u32x4, bits: u32) -> f32x4 {
+    (q.cast::<f32>() + f32x4::splat(0.5)) / f32x4::splat((1u32 << bits) as f32)
+}
+
+/// a mask as lanes of all ones (true) or all zeros (false), for use with bitwise operations
+pub fn mask_to_u32x4(mask: mask32x4) -> u32x4 {
+    mask.select(u32x4::splat(u32::MAX), u32x4::splat(0))
+}
+
+/// the lanewise (min, max) of `a` and `b`, i.e. one layer of a sorting network
+pub fn compare_exchange(a: u32x4, b: u32x4) -> (u32x4, u32x4) {
+    (a.simd_min(b), a.simd_max(b))
+}
+
+/// sorts the lanes of `v` in ascending order
+pub fn sort_lanes(v: u32x4) -> u32x4 {
+    // compare pairs (0, 1) and (2, 3), then (0, 2) and (1, 3), then (1, 2)
+    let layer = |v: u32x4, partner: u32x4, keep_min: mask32x4| {
+        let (min, max) = compare_exchange(v, partner);
+        keep_min.select(min, max)
+    };
+    let v = layer(
+        v,
+        simd_swizzle!(v, [1, 0, 3, 2]),
+        mask32x4::from_array([true, false, true, false]),
+    );
+    let v = layer(
+        v,
+        simd_swizzle!(v, [2, 3, 0, 1]),
+        mask32x4::from_array([true, true, false, false]),
+    );
+    layer(
+        v,
+        simd_swizzle!(v, [0, 2, 1, 3]),
+        mask32x4::from_array([true, true, false, true]),
+    )
+}
+
+/// spreads the lower 16 bits of each lane to the even bits
+pub fn spread_bits_2(v: u32x4) -> u32x4 {
+    let mut v = v & u32x4::splat(0x0000ffff);
+    v = (v | (v << 8)) & u32x4::splat(0x00ff00ff);
+    v = (v | (v << 4)) & u32x4::splat(0x0f0f0f0f);
+    v = (v | (v << 2)) & u32x4::splat(0x33333333);
+    (v | (v << 1)) & u32x4::splat(0x55555555)
+}
+
+/// gathers the even bits of each lane into the lower 16 bits, the inverse of `spread_bits_2`
+pub fn compact_bits_2(v: u32x4) -> u32x4 {
+    let mut v = v & u32x4::splat(0x55555555);
+    v = (v | (v >> 1)) & u32x4::splat(0x33333333);
+    v = (v | (v >> 2)) & u32x4::splat(0x0f0f0f0f);
+    v = (v | (v >> 4)) & u32x4::splat(0x00ff00ff);
+    (v | (v >> 8)) & u32x4::splat(0x0000ffff)
+}
+
+/// spreads the lower 10 bits of each lane to every third bit
+pub fn spread_bits_3(v: u32x4) -> u32x4 {
+    let mut v = v & u32x4::splat(0x000003ff);
+    v = (v | (v << 16)) & u32x4::splat(0x030000ff);
+    v = (v | (v << 8)) & u32x4::splat(0x0300f00f);
+    v = (v | (v << 4)) & u32x4::splat(0x030c30c3);
+    (v | (v << 2)) & u32x4::splat(0x09249249)
+}
+
+/// gathers every third bit of each lane into the lower 10 bits, the inverse of `spread_bits_3`
+pub fn compact_bits_3(v: u32x4) -> u32x4 {
+    let mut v = v & u32x4::splat(0x09249249);
+    v = (v | (v >> 2)) & u32x4::splat(0x030c30c3);
+    v = (v | (v >> 4)) & u32x4::splat(0x0300f00f);
+    v = (v | (v >> 8)) & u32x4::splat(0x030000ff);
+    (v | (v >> 16)) & u32x4::splat(0x000003ff)
+}
+
+/// encodes four 2d morton keys at once, with x in the even bits and y in the odd bits
+pub fn morton_encode_2d(x: u32x4, y: u32x4) -> u32x4 {
+    spread_bits_2(x) | (spread_bits_2(y) << 1)
+}
+
+pub fn morton_decode_2d(keys: u32x4) -> (u32x4, u32x4) {
+    (compact_bits_2(keys), compact_bits_2(keys >> 1))
+}
+
+/// encodes four 3d morton keys at once, with x in bits 0, 3, 6, .., y in bits 1, 4, 7, .. and z in bits 2, 5, 8, ..
+pub fn morton_encode_3d(x: u32x4, y: u32x4, z: u32x4) -> u32x4 {
+    spread_bits_3(x) | (spread_bits_3(y) << 1) | (spread_bits_3(z) << 2)
+}
+
+pub fn morton_decode_3d(keys: u32x4) -> (u32x4, u32x4, u32x4) {
+    (
+        compact_bits_3(keys),
+        compact_bits_3(keys >> 1),
+        compact_bits_3(keys >> 2),
+    )
+}
+
+/// the 3d morton key of a single point, given as quantized x, y and z lanes. the fourth lane is ignored.
+pub fn morton_key_3d(q: u32x4) -> u32 {
+    let spread = spread_bits_3(q) << u32x4::from_array([0, 1, 2, 0]);
+    spread[0] | spread[1] | spread[2]
+}
+
+/// the 3d morton key of `p` relative to the box [`min`, `max`], with 10 bits per axis
+pub fn morton_key_point(p: Point3, min: Point3, max: Point3) -> u32 {
+    let extent = (max.0 - min.0).simd_max(f32x4::splat(f32::MIN_POSITIVE));
+    morton_key_3d(quantize_unit((p.0 - min.0) / extent, 10))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::simd::cmp::SimdPartialEq;
+
+    // interleaves bits one at a time
+    fn scalar_morton(coordinates: &[u32], bits: u32) -> u32 {
+        let mut key = 0;
+        for bit in 0..bits {
+            for (axis, c) in coordinates.iter().enumerate() {
+                key |= ((c >> bit) & 1) << (bit * coordinates.len() as u32 + axis as u32);
+            }
+        }
+        key
+    }
+
+    #[test]
+    fn test_integer_simd() {
+        let v = f32x4::from_array([-1.5, 2.5, 7.9, f32::NAN]);
+        assert_eq!(
+            f32x4_to_u32x4(v, 0, 5, Rounding::Nearest).to_array(),
+            [0, 3, 5, 0]
+        );
+        assert_eq!(
+            f32x4_to_i32x4(v, -10, 10, Rounding::Floor).to_array(),
+            [-2, 2, 7, -10]
+        );
+        assert_eq!(
+            f32x4_to_i32x4(v, -10, 10, Rounding::Ceil).to_array(),
+            [-1, 3, 8, -10]
+        );
+        assert_eq!(
+            f32x4_to_i32x4(v, -10, 10, Rounding::Truncate).to_array(),
+            [-1, 2, 7, -10]
+        );
+        let huge = f32x4::from_array([1e20, -1e20, 4294967295.0, 0.0]);
+        assert_eq!(
+            f32x4_to_u32x4(huge, 0, u32::MAX, Rounding::Nearest).to_array(),
+            [u32::MAX, 0, u32::MAX, 0]
+        );
+
+        let unit = f32x4::from_array([0.0, 0.3, 0.999, 1.0]);
+        let q = quantize_unit(unit, 10);
+        assert_eq!(q.to_array(), [0, 307, 1022, 1023]);
+        assert!(SimdFloat::abs(dequantize_unit(q, 10) - unit).reduce_max() <= 1.0 / 1024.0);
+
+        let a = u32x4::from_array([5, 1, 9, 9]);
+        let b = u32x4::from_array([3, 4, 9, 0]);
+        assert_eq!(mask_to_u32x4(a.simd_eq(b)).to_array(), [0, 0, u32::MAX, 0]);
+        assert_eq!(compare_exchange(a, b).0.to_array(), [3, 1, 9, 0]);
+        for values in [[4, 3, 2, 1], [2, 9, 2, 0], [7, 1, 8, 3], [0, 0, 1, 0]] {
+            let mut expected = values;
+            expected.sort();
+            assert_eq!(sort_lanes(u32x4::from_array(values)).to_array(), expected);
+        }
+
+        let mut state = 12345u32;
+        let mut next = || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            state
+        };
+        for _ in 0..64 {
+            let x = u32x4::from_array([next(), next(), next(), next()]);
+            let y = u32x4::from_array([next(), next(), next(), next()]);
+            let z = u32x4::from_array([next(), next(), next(), next()]);
+            let keys_2d = morton_encode_2d(x, y);
+            let keys_3d = morton_encode_3d(x, y, z);
+            for i in 0..4 {
+                assert_eq!(keys_2d[i], scalar_morton(&[x[i], y[i]], 16));
+                assert_eq!(keys_3d[i], scalar_morton(&[x[i], y[i], z[i]], 10));
+            }
+            let (dx, dy) = morton_decode_2d(keys_2d);
+            assert_eq!(
+                (dx, dy),
+                (x & u32x4::splat(0xffff), y & u32x4::splat(0xffff))
+            );
+            let mask = u32x4::splat(0x3ff);
+            assert_eq!(morton_decode_3d(keys_3d), (x & mask, y & mask, z & mask));
+            assert_eq!(
+                morton_key_3d(u32x4::from_array([x[0], y[0], z[0], 7])),
+                keys_3d[0]
+            );
+        }
+
+        let (min, max) = (Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        assert_eq!(morton_key_point(min, min, max), 0);
+        assert_eq!(morton_key_point(max, min, max), (1 << 30) - 1);
+    }
+}